@@ -0,0 +1,574 @@
+//! Interior mutability for GC objects.
+//!
+//! Strategies that trace the heap in pieces, such as generational and incremental strategies, must be told when a `Gc`
+//! is stored into an object that may already have been traced. The cells in this module are written through a [`Root`]
+//! of the object that contains them, and report every `Gc` in the new value to
+//! [`Collector::write_barrier`](crate::heap::Collector::write_barrier). Cells must be stored inline in that object, not
+//! behind another pointer such as a `Box`, so that the cell can check that it belongs to the given object.
+
+use core::{
+    cell::{Cell, OnceCell, UnsafeCell},
+    fmt, mem,
+    ops::{Deref, DerefMut},
+};
+
+use crate::{
+    heap::{GcStrategy, StrategyHandle},
+    ptr::GcPointee,
+    trace::{EdgeContext, Trace, TraceContext},
+    Root,
+};
+
+/// Asserts that `field` is part of the object rooted by `parent`. The object is only pinned during the check, rather
+/// than for the rest of the life of `parent` as by [`Root::get`].
+pub(crate) fn assert_within<S, P, F>(field: &F, parent: &Root<'_, S, P>)
+where
+    S: ?Sized + GcStrategy,
+    P: ?Sized + GcPointee,
+    F: ?Sized,
+{
+    let obj = parent.pin();
+    let start = (&*obj as *const P).cast::<u8>() as usize;
+    let end = start + mem::size_of_val(&*obj);
+    let addr = (field as *const F).cast::<u8>() as usize;
+    assert!(
+        start <= addr && addr + mem::size_of_val(field) <= end,
+        "cell is not part of the given parent object"
+    );
+}
+
+/// Reports every `Gc` in `value` to the write barrier, as having been stored into `parent`.
+pub(crate) fn write_barrier<S, P, T>(parent: &Root<'_, S, P>, value: &T)
+where
+    S: ?Sized + GcStrategy,
+    P: ?Sized + GcPointee,
+    T: ?Sized + Trace,
+{
+    if T::NEEDS_TRACE {
+        let (gc, handle) = (parent.gc, parent.handle.strategy_handle());
+        value.trace(&mut TraceContext::new(&mut |child| {
+            gc.write_barrier(handle, S::Handle::from_raw(child))
+        }));
+    }
+}
+
+/// A mutable memory location inside a GC object, which invokes the write barrier when written.
+///
+/// Like [`Cell`], a `GcCell` never hands out references to its contents. Values are copied out
+/// with [`GcCell::get`] or moved out with [`GcCell::replace`].
+pub struct GcCell<T: ?Sized> {
+    value: UnsafeCell<T>,
+}
+
+impl<T> GcCell<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+
+    /// Stores a value into the cell. `parent` is a root of the object containing the cell.
+    ///
+    /// # Panics
+    /// Panics if the cell is not part of `parent`.
+    pub fn set<S, P>(&self, parent: &Root<'_, S, P>, value: T)
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+        T: Trace,
+    {
+        drop(self.replace(parent, value));
+    }
+
+    /// Stores a value into the cell, returning the old value. `parent` is a root of the object containing the cell.
+    ///
+    /// # Panics
+    /// Panics if the cell is not part of `parent`.
+    pub fn replace<S, P>(&self, parent: &Root<'_, S, P>, value: T) -> T
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+        T: Trace,
+    {
+        assert_within(self, parent);
+        // SAFETY: the cell is not `Sync`, and never hands out references to its contents.
+        let old = unsafe { self.value.get().replace(value) };
+        // SAFETY: as above. Tracing only needs shared access.
+        write_barrier(parent, unsafe { &*self.value.get() });
+        old
+    }
+}
+
+impl<T: Copy> GcCell<T> {
+    pub fn get(&self) -> T {
+        // SAFETY: the cell is not `Sync`, and never hands out references to its contents.
+        unsafe { *self.value.get() }
+    }
+}
+
+impl<T: ?Sized> GcCell<T> {
+    /// Returns a mutable reference to the contents. This does not invoke the write barrier, as a cell that is
+    /// exclusively borrowed cannot be part of a GC object.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+}
+
+impl<T: Default> Default for GcCell<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Copy + fmt::Debug> fmt::Debug for GcCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GcCell")
+            .field("value", &self.get())
+            .finish()
+    }
+}
+
+/// SAFETY: the cell never hands out references to its contents, so no borrow of the contents can be invalidated by
+/// tracing. The concurrency caveats of [`Cell`] apply.
+unsafe impl<T: ?Sized + Trace> Trace for GcCell<T> {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        // SAFETY: see above
+        unsafe { (*self.value.get()).trace(ctx) }
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        self.get_mut().trace_edges(ctx);
+    }
+}
+
+/// The borrow state of a [`GcRefCell`] that is mutably borrowed.
+const WRITING: isize = -1;
+
+/// A mutable memory location inside a GC object with dynamically checked borrows, like
+/// [`RefCell`](core::cell::RefCell). Mutable borrows invoke the write barrier when they are released.
+///
+/// # Tracing Borrowed Data
+/// The GC traces the contents through the raw pointer of the cell, whatever their borrow state, so objects may be
+/// allocated and collections may run while a [`GcRef`] or [`GcRefMut`] is live. This is sound because tracing only
+/// reads the contents, and never overlaps with a write to them:
+/// - The cell is not `Sync`, so only the thread that borrowed it can write to the contents.
+/// - The heap only traces while that thread is inside it, e.g. allocating, collecting, or stopped at a safepoint,
+///   including when mark workers trace on other threads on its behalf. Every write through a [`GcRefMut`] has
+///   finished before tracing starts, and none starts before it ends.
+/// - A [`GcRefMut`] does not hold a `&mut T` itself. Each call to `deref_mut` reborrows a fresh `&mut T` from the raw
+///   pointer, so the reads of the GC do not invalidate a mutable reference that the borrow uses afterwards.
+///
+/// The GC may trace the contents between writes through a [`GcRefMut`], before the write barrier is invoked for
+/// them. Nothing stored in the meantime is missed, since the borrow roots the object containing the cell, so every
+/// collection traces the contents until the borrow is released.
+pub struct GcRefCell<T: ?Sized> {
+    /// The number of shared borrows, or `WRITING` if mutably borrowed.
+    borrow: Cell<isize>,
+    value: UnsafeCell<T>,
+}
+
+impl<T> GcRefCell<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            borrow: Cell::new(0),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: ?Sized> GcRefCell<T> {
+    /// Immutably borrows the contents.
+    ///
+    /// # Panics
+    /// Panics if the contents are mutably borrowed.
+    pub fn borrow(&self) -> GcRef<'_, T> {
+        self.try_borrow().expect("already mutably borrowed")
+    }
+
+    /// Immutably borrows the contents, if they are not mutably borrowed.
+    pub fn try_borrow(&self) -> Option<GcRef<'_, T>> {
+        let borrow = self.borrow.get();
+        if borrow < 0 || borrow == isize::MAX {
+            return None;
+        }
+        self.borrow.set(borrow + 1);
+        Some(GcRef { cell: self })
+    }
+
+    /// Mutably borrows the contents. `parent` is a root of the object containing the cell, which is passed to the
+    /// write barrier when the borrow is released.
+    ///
+    /// # Panics
+    /// Panics if the contents are borrowed, or if the cell is not part of `parent`.
+    pub fn borrow_mut<'a, S, P>(&'a self, parent: &'a Root<'a, S, P>) -> GcRefMut<'a, S, P, T>
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+        T: Trace,
+    {
+        self.try_borrow_mut(parent).expect("already borrowed")
+    }
+
+    /// Mutably borrows the contents, if they are not borrowed. See [`GcRefCell::borrow_mut`].
+    ///
+    /// # Panics
+    /// Panics if the cell is not part of `parent`.
+    pub fn try_borrow_mut<'a, S, P>(
+        &'a self,
+        parent: &'a Root<'a, S, P>,
+    ) -> Option<GcRefMut<'a, S, P, T>>
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+        T: Trace,
+    {
+        assert_within(self, parent);
+        if self.borrow.get() != 0 {
+            return None;
+        }
+        self.borrow.set(WRITING);
+        Some(GcRefMut { cell: self, parent })
+    }
+
+    /// Returns a mutable reference to the contents. This does not invoke the write barrier, as a cell that is
+    /// exclusively borrowed cannot be part of a GC object.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+}
+
+impl<T: Default> Default for GcRefCell<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for GcRefCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("GcRefCell");
+        match self.try_borrow() {
+            Some(value) => d.field("value", &&*value),
+            None => d.field("value", &format_args!("<borrowed>")),
+        };
+        d.finish()
+    }
+}
+
+/// SAFETY: see [Tracing Borrowed Data](GcRefCell#tracing-borrowed-data).
+unsafe impl<T: ?Sized + Trace> Trace for GcRefCell<T> {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        // The borrow flag is neither checked nor updated, since the contents are traced whatever their borrow state,
+        // and mark workers may trace the cell from several threads at once.
+        // SAFETY: tracing does not overlap with writes to the contents; see above.
+        unsafe { (*self.value.get()).trace(ctx) }
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        self.get_mut().trace_edges(ctx);
+    }
+}
+
+/// An immutable borrow of the contents of a [`GcRefCell`].
+pub struct GcRef<'a, T: ?Sized> {
+    cell: &'a GcRefCell<T>,
+}
+
+impl<T: ?Sized> Deref for GcRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: the contents are immutably borrowed
+        unsafe { &*self.cell.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for GcRef<'_, T> {
+    fn drop(&mut self) {
+        self.cell.borrow.set(self.cell.borrow.get() - 1);
+    }
+}
+
+/// A mutable borrow of the contents of a [`GcRefCell`]. The write barrier is invoked for the contents when the
+/// borrow is released.
+pub struct GcRefMut<'a, S: ?Sized + GcStrategy, P: ?Sized + GcPointee, T: ?Sized + Trace> {
+    cell: &'a GcRefCell<T>,
+    parent: &'a Root<'a, S, P>,
+}
+
+impl<S: ?Sized + GcStrategy, P: ?Sized + GcPointee, T: ?Sized + Trace> Deref
+    for GcRefMut<'_, S, P, T>
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: the contents are mutably borrowed
+        unsafe { &*self.cell.value.get() }
+    }
+}
+
+impl<S: ?Sized + GcStrategy, P: ?Sized + GcPointee, T: ?Sized + Trace> DerefMut
+    for GcRefMut<'_, S, P, T>
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: the contents are mutably borrowed. The reference is reborrowed from the raw pointer on each call,
+        // rather than stored, so that tracing may read the contents between calls.
+        unsafe { &mut *self.cell.value.get() }
+    }
+}
+
+/// Invokes the write barrier and releases the borrow.
+impl<S: ?Sized + GcStrategy, P: ?Sized + GcPointee, T: ?Sized + Trace> Drop
+    for GcRefMut<'_, S, P, T>
+{
+    fn drop(&mut self) {
+        write_barrier(self.parent, &**self);
+        self.cell.borrow.set(0);
+    }
+}
+
+/// A cell inside a GC object that can be written to only once, like [`OnceCell`]. The write barrier is invoked
+/// when the cell is initialized.
+pub struct GcOnceCell<T> {
+    inner: OnceCell<T>,
+}
+
+impl<T> GcOnceCell<T> {
+    pub const fn new() -> Self {
+        Self {
+            inner: OnceCell::new(),
+        }
+    }
+
+    pub fn get(&self) -> Option<&T> {
+        self.inner.get()
+    }
+
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        self.inner.get_mut()
+    }
+
+    pub fn into_inner(self) -> Option<T> {
+        self.inner.into_inner()
+    }
+
+    pub fn take(&mut self) -> Option<T> {
+        self.inner.take()
+    }
+}
+
+impl<T: Trace> GcOnceCell<T> {
+    /// Initializes the cell with `value`, returning it back if the cell is already initialized. `parent` is a root
+    /// of the object containing the cell.
+    ///
+    /// # Panics
+    /// Panics if the cell is not part of `parent`.
+    pub fn set<S, P>(&self, parent: &Root<'_, S, P>, value: T) -> Result<(), T>
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+    {
+        assert_within(self, parent);
+        self.inner.set(value)?;
+        write_barrier(parent, self.inner.get().unwrap());
+        Ok(())
+    }
+
+    /// Returns the contents of the cell, initializing it with `f` if it is uninitialized. `parent` is a root of the
+    /// object containing the cell.
+    ///
+    /// # Panics
+    /// Panics if the cell is uninitialized and not part of `parent`, or if `f` initializes the cell reentrantly.
+    pub fn get_or_init<S, P>(&self, parent: &Root<'_, S, P>, f: impl FnOnce() -> T) -> &T
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+    {
+        if let Some(value) = self.inner.get() {
+            return value;
+        }
+        assert_within(self, parent);
+        let value = self.inner.get_or_init(f);
+        write_barrier(parent, value);
+        value
+    }
+}
+
+impl<T> Default for GcOnceCell<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for GcOnceCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("GcOnceCell")
+            .field(&self.inner.get())
+            .finish()
+    }
+}
+
+/// SAFETY: see the implementation for [`OnceCell`].
+unsafe impl<T: Trace> Trace for GcOnceCell<T> {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        self.inner.trace(ctx);
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        self.inner.trace_edges(ctx);
+    }
+}
+
+/// A value inside a GC object that is computed on first access, like [`LazyCell`](core::cell::LazyCell). The write
+/// barrier is invoked when the value is computed.
+///
+/// The initializer is traced until the value has been computed, so a `GcLazy` is only a GC object if its initializer
+/// implements [`Trace`], such as a function pointer. Closures do not, as their captures are opaque.
+pub struct GcLazy<T, F = fn() -> T> {
+    cell: GcOnceCell<T>,
+    /// The initializer, until it is taken to compute the value. It is only accessed through a shared reference by
+    /// tracing, and mutably by [`GcLazy::force`] just to take it.
+    init: UnsafeCell<Option<F>>,
+}
+
+impl<T, F: FnOnce() -> T> GcLazy<T, F> {
+    pub const fn new(init: F) -> Self {
+        Self {
+            cell: GcOnceCell::new(),
+            init: UnsafeCell::new(Some(init)),
+        }
+    }
+
+    /// Returns the value if it has been computed.
+    pub fn get(&self) -> Option<&T> {
+        self.cell.get()
+    }
+}
+
+impl<T: Trace, F: FnOnce() -> T> GcLazy<T, F> {
+    /// Returns the value, computing it if it has not been computed yet. `parent` is a root of the object containing
+    /// this value.
+    ///
+    /// # Panics
+    /// Panics if the value has not been computed and this is not part of `parent`, or if a previous computation
+    /// panicked.
+    pub fn force<S, P>(&self, parent: &Root<'_, S, P>) -> &T
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+    {
+        // SAFETY: the lazy value is not `Sync`, and tracing cannot run while the initializer is taken
+        let init = || unsafe { (*self.init.get()).take() };
+        self.cell.get_or_init(parent, || match init() {
+            Some(init) => init(),
+            None => panic!("GcLazy instance has previously been poisoned"),
+        })
+    }
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for GcLazy<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_tuple("GcLazy");
+        match self.cell.get() {
+            Some(value) => d.field(value),
+            None => d.field(&format_args!("<uninit>")),
+        };
+        d.finish()
+    }
+}
+
+/// SAFETY: the computed value is traced as in [`GcOnceCell`], and the initializer until it is taken. The initializer
+/// is only taken on the owning thread, which does not run while the object is traced (see the notes on concurrency
+/// for [`OnceCell`]).
+unsafe impl<T: Trace, F: Trace> Trace for GcLazy<T, F> {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE || F::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        self.cell.trace(ctx);
+        // SAFETY: see above
+        unsafe { (*self.init.get()).trace(ctx) };
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        self.cell.trace_edges(ctx);
+        self.init.get_mut().trace_edges(ctx);
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::{
+        decorators::StressStrategy,
+        heap::{CollectOptions, CollectionKind},
+        strategies::{Generational, MarkSweep, Semispace},
+        Gc, GcHeap,
+    };
+    use alloc::vec::Vec;
+
+    #[test]
+    fn lazy_values_are_computed_once() {
+        let heap = GcHeap::new(MarkSweep::new());
+        let root = heap.alloc(GcLazy::<Option<Gc<u32>>>::new(|| None));
+        heap.collect();
+        assert_eq!(root.force(&root), &None);
+        assert_eq!(GcLazy::get(&root), Some(&None));
+    }
+
+    #[test]
+    fn checked_writes_leave_the_parent_unpinned() {
+        let heap = GcHeap::new(Semispace::new());
+        let root = heap.alloc(GcCell::new(1u32));
+        root.pin().set(&root, 2);
+        let addr = root.as_ptr();
+        heap.collect();
+        assert_ne!(root.as_ptr(), addr);
+        assert_eq!(root.pin().get(), 2);
+    }
+
+    #[test]
+    fn tracing_coexists_with_shared_borrows() {
+        let heap = GcHeap::new(MarkSweep::new());
+        let cell = heap.alloc(GcRefCell::new(Some(heap.alloc(1u32).gc())));
+        let _borrow = cell.borrow();
+        heap.collect();
+        assert_eq!(heap.stats().object_count, 2);
+    }
+
+    #[test]
+    fn tracing_reads_mutably_borrowed_cells() {
+        let heap = GcHeap::new(StressStrategy::new(Generational::new()));
+        let cell = heap.alloc(GcRefCell::new(Vec::new()));
+        let mut borrow = cell.borrow_mut(&cell);
+        for value in 0..10u32 {
+            // each allocation collects while the cell is mutably borrowed
+            let gc = heap.alloc(value).gc();
+            borrow.push(gc);
+        }
+        heap.collect_with(CollectOptions {
+            kind: CollectionKind::Minor,
+            compact: true,
+        });
+        drop(borrow);
+        heap.collect();
+        assert_eq!(heap.stats().object_count, 11);
+        // SAFETY: the values are read from an object that is rooted
+        let values = (cell.borrow().iter())
+            .map(|&gc| *unsafe { heap.root(gc) })
+            .collect::<Vec<_>>();
+        assert_eq!(values, (0..10).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,80 @@
+//! Callable GC objects.
+//!
+//! Rust closures cannot implement [`Trace`], as their captures are opaque. A [`GcClosure`] instead stores its
+//! captures as an explicit [`Trace`] value next to a plain function pointer, so the GC can trace any GC objects the
+//! closure captures. Closures with the same signature can be stored uniformly as `Gc<dyn GcFn<Args, Ret>>`, using
+//! [`gc_unsize!`](crate::gc_unsize).
+
+use core::ptr::NonNull;
+
+use crate::{
+    ptr::{DynMetadata, GcPointee},
+    trace::{EdgeContext, Trace, TraceContext},
+};
+
+/// A closure whose captured values are traced by the GC. `Args` is a tuple of the closure's arguments.
+pub struct GcClosure<C, Args, Ret> {
+    captures: C,
+    func: fn(&C, Args) -> Ret,
+}
+
+impl<C: Trace, Args, Ret> GcClosure<C, Args, Ret> {
+    /// Creates a closure from its captured values and the function that implements it. The function receives the
+    /// captured values as its first argument.
+    pub const fn new(captures: C, func: fn(&C, Args) -> Ret) -> Self {
+        Self { captures, func }
+    }
+
+    pub fn call(&self, args: Args) -> Ret {
+        (self.func)(&self.captures, args)
+    }
+
+    pub fn captures(&self) -> &C {
+        &self.captures
+    }
+}
+
+/// SAFETY: the function pointer stores nothing, so only the captures need to be traced.
+unsafe impl<C: Trace, Args, Ret> Trace for GcClosure<C, Args, Ret> {
+    const NEEDS_TRACE: bool = C::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        self.captures.trace(ctx);
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        self.captures.trace_edges(ctx);
+    }
+}
+
+/// A callable GC object. This is the type-erased form of a [`GcClosure`].
+pub trait GcFn<Args, Ret> {
+    fn call(&self, args: Args) -> Ret;
+}
+
+impl<C: Trace, Args, Ret> GcFn<Args, Ret> for GcClosure<C, Args, Ret> {
+    fn call(&self, args: Args) -> Ret {
+        GcClosure::call(self, args)
+    }
+}
+
+macro_rules! gc_fn_pointee {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            // SAFETY: `DynMetadata` preserves the address of the object.
+            unsafe impl<Args: 'static, Ret: 'static> GcPointee for $ty {
+                type Metadata = DynMetadata<Self>;
+
+                fn from_raw_parts(data: NonNull<()>, metadata: Self::Metadata) -> NonNull<Self> {
+                    metadata.project(data)
+                }
+            }
+        )*
+    };
+}
+
+gc_fn_pointee! {
+    dyn GcFn<Args, Ret>,
+    dyn GcFn<Args, Ret> + Send,
+    dyn GcFn<Args, Ret> + Send + Sync,
+}
@@ -0,0 +1,1055 @@
+//! Collections and strings stored in the GC heap.
+//!
+//! [`GcString`] is a rooted string allocated directly in the heap. The other collections in this module are stored
+//! inline in a GC object, like the cells in the [`cell`](crate::cell) module, and keep their contents in separate
+//! GC-allocated buffers. Methods that access the contents take a [`Root`] of the object containing the collection,
+//! which keeps the buffers alive and is passed to the write barrier. Methods that may need a larger buffer also take
+//! the heap to allocate it in.
+//!
+//! A collection must not be allocated in the heap before it is stored in its containing object, as its buffers would
+//! not be reachable in the meantime. Create collections empty, and reserve space once the containing object has been
+//! allocated.
+
+use core::{
+    borrow::Borrow,
+    cell::{Cell, UnsafeCell},
+    cmp::Ordering as CmpOrdering,
+    fmt,
+    hash::{BuildHasher, Hash, Hasher},
+    ops::{Add, Deref},
+    sync::atomic::AtomicPtr,
+};
+
+use crate::{
+    cell::{assert_within, write_barrier},
+    heap::GcStrategy,
+    ptr::GcPointee,
+    trace::{EdgeContext, Trace, TraceContext},
+    Gc, GcHeap, Root,
+};
+
+/// The smallest non-zero capacity of a buffer.
+const MIN_CAPACITY: usize = 4;
+
+/// Roots a buffer of a collection that is part of `parent`.
+///
+/// # Safety
+/// `buf` must have been read from a collection that is part of `parent`.
+unsafe fn root_buffer<'root, S, P, B>(parent: &Root<'root, S, P>, buf: Gc<B>) -> Root<'root, S, B>
+where
+    S: ?Sized + GcStrategy,
+    P: ?Sized + GcPointee,
+    B: ?Sized + GcPointee,
+{
+    // the buffer is reachable from the rooted parent, so it is alive
+    parent.gc.root(buf.strategy_handle());
+    Root {
+        handle: buf,
+        gc: parent.gc,
+        pinned: AtomicPtr::new(core::ptr::null_mut()),
+    }
+}
+
+/// Returns the capacity of a buffer that can hold at least `required` elements, given the current capacity.
+fn grow_capacity(capacity: usize, required: usize) -> usize {
+    required.max(capacity.saturating_mul(2)).max(MIN_CAPACITY)
+}
+
+/// An element of a buffer, which is empty if it is spare capacity.
+///
+/// Like [`GcCell`](crate::cell::GcCell), a slot never hands out references to its contents, so that the GC can
+/// trace it at any time.
+struct Slot<T>(UnsafeCell<Option<T>>);
+
+impl<T> Slot<T> {
+    const fn empty() -> Self {
+        Self(UnsafeCell::new(None))
+    }
+
+    fn replace(&self, value: Option<T>) -> Option<T> {
+        // SAFETY: slots are not `Sync`, and never hand out references to their contents.
+        unsafe { self.0.get().replace(value) }
+    }
+}
+
+impl<T: Copy> Slot<T> {
+    fn get(&self) -> Option<T> {
+        // SAFETY: as above
+        unsafe { *self.0.get() }
+    }
+}
+
+/// SAFETY: see [`GcCell`](crate::cell::GcCell).
+unsafe impl<T: Trace> Trace for Slot<T> {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        // SAFETY: see above
+        unsafe { (*self.0.get()).trace(ctx) }
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        self.0.get_mut().trace_edges(ctx);
+    }
+}
+
+/// A growable array stored inside a GC object, like a `Vec`.
+///
+/// The elements are stored in a GC-allocated buffer. When the buffer is full, a new buffer with at least twice the
+/// capacity is allocated and the elements are moved into it. The old buffer is left to the GC.
+///
+/// Like [`GcCell`](crate::cell::GcCell), a `GcVec` never hands out references to its elements. Elements are copied
+/// out with [`GcVec::get`], or moved out with [`GcVec::replace`] and [`GcVec::pop`].
+pub struct GcVec<T> {
+    buf: Cell<Option<Gc<[Slot<T>]>>>,
+    len: Cell<usize>,
+}
+
+impl<T> GcVec<T> {
+    /// Creates an empty vector. No buffer is allocated until elements are added.
+    pub const fn new() -> Self {
+        Self {
+            buf: Cell::new(None),
+            len: Cell::new(0),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len.get()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of elements the vector can hold without allocating a new buffer.
+    pub fn capacity(&self) -> usize {
+        self.buf.get().map_or(0, |buf| buf.metadata)
+    }
+}
+
+impl<T: Trace> GcVec<T> {
+    /// Roots the buffer of this vector.
+    ///
+    /// # Panics
+    /// Panics if the vector is not part of `parent`.
+    fn buffer<'root, S, P>(&self, parent: &Root<'root, S, P>) -> Option<Root<'root, S, [Slot<T>]>>
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+    {
+        assert_within(self, parent);
+        // SAFETY: the buffer was read from this vector
+        self.buf
+            .get()
+            .map(|buf| unsafe { root_buffer(parent, buf) })
+    }
+
+    /// Returns a copy of the element at `idx`, or `None` if it is out of bounds. `parent` is a root of the object
+    /// containing the vector.
+    ///
+    /// # Panics
+    /// Panics if the vector is not part of `parent`.
+    pub fn get<S, P>(&self, parent: &Root<'_, S, P>, idx: usize) -> Option<T>
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+        T: Copy,
+    {
+        let buf = self.buffer(parent)?;
+        if idx >= self.len.get() {
+            return None;
+        }
+        buf[idx].get()
+    }
+
+    /// Stores a value at `idx`. `parent` is a root of the object containing the vector.
+    ///
+    /// # Panics
+    /// Panics if `idx` is out of bounds, or if the vector is not part of `parent`.
+    pub fn set<S, P>(&self, parent: &Root<'_, S, P>, idx: usize, value: T)
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+    {
+        drop(self.replace(parent, idx, value));
+    }
+
+    /// Stores a value at `idx`, returning the old value. `parent` is a root of the object containing the vector.
+    ///
+    /// # Panics
+    /// Panics if `idx` is out of bounds, or if the vector is not part of `parent`.
+    pub fn replace<S, P>(&self, parent: &Root<'_, S, P>, idx: usize, value: T) -> T
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+    {
+        let buf = self.buffer(parent);
+        let len = self.len.get();
+        assert!(
+            idx < len,
+            "index out of bounds: the len is {len} but the index is {idx}"
+        );
+        // a non-empty vector has a buffer
+        let buf = buf.unwrap();
+        let old = buf[idx].replace(Some(value));
+        write_barrier(&buf, &buf[idx]);
+        // elements below the length are always present
+        old.unwrap()
+    }
+
+    /// Appends an element to the end of the vector, allocating a new buffer in `heap` if the vector is full.
+    /// `parent` is a root of the object containing the vector.
+    ///
+    /// # Panics
+    /// Panics if `parent` does not belong to `heap`, if the vector is not part of `parent`, or if the strategy does
+    /// not have enough heap space for a new buffer.
+    pub fn push<'lifetime, S, P>(
+        &self,
+        heap: &GcHeap<'lifetime, S>,
+        parent: &Root<'_, S, P>,
+        value: T,
+    ) where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+        T: Send + 'lifetime,
+    {
+        self.reserve(heap, parent, 1);
+        let len = self.len.get();
+        // the vector has spare capacity
+        let buf = self.buffer(parent).unwrap();
+        buf[len].replace(Some(value));
+        write_barrier(&buf, &buf[len]);
+        self.len.set(len + 1);
+    }
+
+    /// Removes the last element from the vector and returns it, or `None` if it is empty. `parent` is a root of the
+    /// object containing the vector.
+    ///
+    /// # Panics
+    /// Panics if the vector is not part of `parent`.
+    pub fn pop<S, P>(&self, parent: &Root<'_, S, P>) -> Option<T>
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+    {
+        let buf = self.buffer(parent)?;
+        let len = self.len.get().checked_sub(1)?;
+        self.len.set(len);
+        buf[len].replace(None)
+    }
+
+    /// Shortens the vector to `len` elements, dropping the rest. This has no effect if the vector is not longer than
+    /// `len`. The capacity of the vector is unchanged. `parent` is a root of the object containing the vector.
+    ///
+    /// # Panics
+    /// Panics if the vector is not part of `parent`.
+    pub fn truncate<S, P>(&self, parent: &Root<'_, S, P>, len: usize)
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+    {
+        let Some(buf) = self.buffer(parent) else {
+            return;
+        };
+        let old_len = self.len.get();
+        if len >= old_len {
+            return;
+        }
+        // update the length first, in case dropping an element accesses the vector
+        self.len.set(len);
+        for slot in &buf[len..old_len] {
+            drop(slot.replace(None));
+        }
+    }
+
+    /// Removes all elements from the vector. `parent` is a root of the object containing the vector.
+    ///
+    /// # Panics
+    /// Panics if the vector is not part of `parent`.
+    pub fn clear<S, P>(&self, parent: &Root<'_, S, P>)
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+    {
+        self.truncate(parent, 0);
+    }
+
+    /// Ensures the vector can hold at least `additional` more elements, allocating a new buffer in `heap` if
+    /// necessary. A new buffer has at least twice the capacity of the old one, so that repeated pushes take amortized
+    /// constant time. `parent` is a root of the object containing the vector.
+    ///
+    /// # Panics
+    /// Panics if `parent` does not belong to `heap`, if the vector is not part of `parent`, if the new capacity
+    /// overflows `usize`, or if the strategy does not have enough heap space for a new buffer.
+    pub fn reserve<'lifetime, S, P>(
+        &self,
+        heap: &GcHeap<'lifetime, S>,
+        parent: &Root<'_, S, P>,
+        additional: usize,
+    ) where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+        T: Send + 'lifetime,
+    {
+        assert!(
+            core::ptr::eq(parent.gc, &heap.strategy),
+            "parent does not belong to this heap"
+        );
+        let old = self.buffer(parent);
+        let len = self.len.get();
+        let required = len.checked_add(additional).expect("capacity overflow");
+        let capacity = self.capacity();
+        if required <= capacity {
+            return;
+        }
+        // the old buffer stays rooted while its elements are moved, in case allocating the new buffer collects
+        let new = heap.alloc_from_iter((0..grow_capacity(capacity, required)).map(
+            |idx| match &old {
+                Some(old) if idx < len => Slot(UnsafeCell::new(old[idx].replace(None))),
+                _ => Slot::empty(),
+            },
+        ));
+        self.buf.set(Some(new.gc()));
+        write_barrier(parent, &new.gc());
+    }
+}
+
+impl<T> Default for GcVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for GcVec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GcVec")
+            .field("len", &self.len())
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+// SAFETY: the buffer is only reachable through the vector, which is not `Sync`, so sending the vector sends the
+// elements along with it, the same as for `Vec`.
+unsafe impl<T: Send> Send for GcVec<T> {}
+
+/// SAFETY: the elements are traced as part of the buffer, which is a separate GC object.
+unsafe impl<T> Trace for GcVec<T> {
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        self.buf.trace(ctx);
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        self.buf.trace_edges(ctx);
+    }
+}
+
+/// A rooted string in the GC heap.
+///
+/// GC objects store strings as `Gc<str>`, which are allocated directly in the heap rather than behind a second
+/// allocation such as a `String`. A `GcString` is a root of such a string with the conveniences of an owned string:
+/// it dereferences to `str`, compares and hashes by contents, and can be concatenated with `+`, which allocates the
+/// result in the same heap.
+pub struct GcString<'root, S: ?Sized + GcStrategy> {
+    root: Root<'root, S, str>,
+}
+
+impl<'root, S: ?Sized + GcStrategy> GcString<'root, S> {
+    /// Allocates a copy of the given string.
+    pub fn new<'lifetime>(heap: &'root GcHeap<'lifetime, S>, value: &str) -> Self {
+        heap.alloc_str(value).into()
+    }
+
+    /// The GC handle of the string, which can be stored in GC objects.
+    pub fn gc(&self) -> Gc<str> {
+        self.root.gc()
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.root.get()
+    }
+
+    pub fn as_root(&self) -> &Root<'root, S, str> {
+        &self.root
+    }
+
+    pub fn into_root(self) -> Root<'root, S, str> {
+        self.root
+    }
+
+    /// Allocates the concatenation of this string and `other`.
+    pub fn concat(&self, other: &str) -> Self {
+        crate::alloc_concat(self.root.gc, &[self, other]).into()
+    }
+}
+
+impl<'root, S: ?Sized + GcStrategy> From<Root<'root, S, str>> for GcString<'root, S> {
+    fn from(root: Root<'root, S, str>) -> Self {
+        Self { root }
+    }
+}
+
+impl<S: ?Sized + GcStrategy> Deref for GcString<'_, S> {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl<S: ?Sized + GcStrategy> AsRef<str> for GcString<'_, S> {
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl<S: ?Sized + GcStrategy> Borrow<str> for GcString<'_, S> {
+    fn borrow(&self) -> &str {
+        self
+    }
+}
+
+impl<S: ?Sized + GcStrategy> Clone for GcString<'_, S> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+        }
+    }
+}
+
+impl<'root, S: ?Sized + GcStrategy> Add<&str> for GcString<'root, S> {
+    type Output = GcString<'root, S>;
+
+    fn add(self, rhs: &str) -> Self::Output {
+        self.concat(rhs)
+    }
+}
+
+impl<'root, S: ?Sized + GcStrategy> Add<&str> for &GcString<'root, S> {
+    type Output = GcString<'root, S>;
+
+    fn add(self, rhs: &str) -> Self::Output {
+        self.concat(rhs)
+    }
+}
+
+impl<S: ?Sized + GcStrategy> fmt::Debug for GcString<'_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl<S: ?Sized + GcStrategy> fmt::Display for GcString<'_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+/// Compares by contents. Use [`Gc::ptr_eq`] to compare by identity.
+impl<S: ?Sized + GcStrategy> PartialEq for GcString<'_, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl<S: ?Sized + GcStrategy> Eq for GcString<'_, S> {}
+
+impl<S: ?Sized + GcStrategy> PartialEq<str> for GcString<'_, S> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<S: ?Sized + GcStrategy> PartialEq<&str> for GcString<'_, S> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl<S: ?Sized + GcStrategy> PartialOrd for GcString<'_, S> {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S: ?Sized + GcStrategy> Ord for GcString<'_, S> {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+/// Hashes the contents, consistent with [`PartialEq`] and [`Borrow<str>`].
+impl<S: ?Sized + GcStrategy> Hash for GcString<'_, S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+/// Returns the number of buckets needed to hold `required` entries without exceeding the maximum load factor.
+fn buckets_for(required: usize) -> usize {
+    required
+        .checked_mul(4)
+        .and_then(|n| n.div_ceil(3).checked_next_power_of_two())
+        .expect("capacity overflow")
+        .max(MIN_CAPACITY)
+}
+
+/// Returns the number of entries a table with the given number of buckets can hold, which keeps at least a quarter
+/// of the buckets empty.
+const fn table_capacity(buckets: usize) -> usize {
+    buckets - buckets / 4
+}
+
+/// An entry of a [`GcHashMap`], along with the hash of its key.
+struct Entry<K, V> {
+    hash: u64,
+    key: K,
+    value: V,
+}
+
+/// SAFETY: every `Gc` in the key and value is visited. Keys are visited as fixed edges, so that a moving strategy
+/// does not change their hashes.
+unsafe impl<K: Trace, V: Trace> Trace for Entry<K, V> {
+    const NEEDS_TRACE: bool = K::NEEDS_TRACE || V::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        self.key.trace(ctx);
+        self.value.trace(ctx);
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        self.key.trace(ctx.fixed());
+        self.value.trace_edges(ctx);
+    }
+}
+
+/// The buckets of a [`GcHashMap`].
+type Table<K, V> = [Slot<Entry<K, V>>];
+
+impl<K: Copy, V> Slot<Entry<K, V>> {
+    /// Copies the hash and key out of an occupied bucket.
+    fn key(&self) -> Option<(u64, K)> {
+        // SAFETY: slots are not `Sync`, and the reference does not escape this call.
+        unsafe {
+            (*self.0.get())
+                .as_ref()
+                .map(|entry| (entry.hash, entry.key))
+        }
+    }
+}
+
+impl<K, V: Copy> Slot<Entry<K, V>> {
+    /// Copies the value out of an occupied bucket.
+    fn value(&self) -> Option<V> {
+        // SAFETY: as above
+        unsafe { (*self.0.get()).as_ref().map(|entry| entry.value) }
+    }
+}
+
+/// A hash map stored inside a GC object, like a `HashMap`.
+///
+/// The entries are stored in a GC-allocated table using open addressing with linear probing, so looking up a key
+/// does not follow any pointers other than the one to the table. When the table is more than three quarters full, a
+/// new table with at least twice as many buckets is allocated and the entries are moved into it. The old table is
+/// left to the GC.
+///
+/// Keys are copied out of the table to be hashed and compared, and values are copied out with [`GcHashMap::get`],
+/// so that user code never runs while the table is borrowed. Keys that are `Gc` values are hashed by identity, and
+/// their objects are never relocated by moving strategies while they are in the map.
+pub struct GcHashMap<K, V, H> {
+    buf: Cell<Option<Gc<Table<K, V>>>>,
+    len: Cell<usize>,
+    hasher: H,
+}
+
+impl<K, V, H> GcHashMap<K, V, H> {
+    /// Creates an empty map which uses the given hasher to hash keys. No table is allocated until entries are added.
+    pub const fn with_hasher(hasher: H) -> Self {
+        Self {
+            buf: Cell::new(None),
+            len: Cell::new(0),
+            hasher,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len.get()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of entries the map can hold without allocating a new table.
+    pub fn capacity(&self) -> usize {
+        self.buf.get().map_or(0, |buf| table_capacity(buf.metadata))
+    }
+
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K, V> GcHashMap<K, V, std::hash::RandomState> {
+    /// Creates an empty map with a randomly seeded hasher.
+    pub fn new() -> Self {
+        Self::with_hasher(Default::default())
+    }
+}
+
+impl<K, V, H> GcHashMap<K, V, H>
+where
+    K: Copy + Hash + Eq + Trace,
+    V: Trace,
+    H: BuildHasher,
+{
+    /// Roots the table of this map.
+    ///
+    /// # Panics
+    /// Panics if the map is not part of `parent`.
+    fn buffer<'root, S, P>(&self, parent: &Root<'root, S, P>) -> Option<Root<'root, S, Table<K, V>>>
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+    {
+        assert_within(self, parent);
+        // SAFETY: the table was read from this map
+        self.buf
+            .get()
+            .map(|buf| unsafe { root_buffer(parent, buf) })
+    }
+
+    /// Returns the bucket containing the given key.
+    fn find<Q>(&self, buf: &Table<K, V>, hash: u64, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq,
+    {
+        let mask = buf.len() - 1;
+        let mut idx = hash as usize & mask;
+        // the table always has an empty bucket, which ends the probe sequence
+        loop {
+            let (entry_hash, entry_key) = buf[idx].key()?;
+            if entry_hash == hash && entry_key.borrow() == key {
+                return Some(idx);
+            }
+            idx = (idx + 1) & mask;
+        }
+    }
+
+    /// Returns the first empty bucket in the probe sequence for `hash`.
+    fn find_empty(buf: &Table<K, V>, hash: u64) -> usize {
+        let mask = buf.len() - 1;
+        let mut idx = hash as usize & mask;
+        while buf[idx].key().is_some() {
+            idx = (idx + 1) & mask;
+        }
+        idx
+    }
+
+    /// Returns a copy of the value for the given key. `parent` is a root of the object containing the map.
+    ///
+    /// # Panics
+    /// Panics if the map is not part of `parent`.
+    pub fn get<S, P, Q>(&self, parent: &Root<'_, S, P>, key: &Q) -> Option<V>
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+        V: Copy,
+    {
+        let buf = self.buffer(parent)?;
+        let idx = self.find(&buf, self.hasher.hash_one(key), key)?;
+        buf[idx].value()
+    }
+
+    /// Returns whether the map contains the given key. `parent` is a root of the object containing the map.
+    ///
+    /// # Panics
+    /// Panics if the map is not part of `parent`.
+    pub fn contains_key<S, P, Q>(&self, parent: &Root<'_, S, P>, key: &Q) -> bool
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.buffer(parent)
+            .is_some_and(|buf| self.find(&buf, self.hasher.hash_one(key), key).is_some())
+    }
+
+    /// Inserts an entry into the map, allocating a new table in `heap` if the map is full. If the map already
+    /// contains the key, the value is replaced and the old value is returned. `parent` is a root of the object
+    /// containing the map.
+    ///
+    /// # Panics
+    /// Panics if `parent` does not belong to `heap`, if the map is not part of `parent`, or if the strategy does not
+    /// have enough heap space for a new table.
+    pub fn insert<'lifetime, S, P>(
+        &self,
+        heap: &GcHeap<'lifetime, S>,
+        parent: &Root<'_, S, P>,
+        key: K,
+        value: V,
+    ) -> Option<V>
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+        K: Send + 'lifetime,
+        V: Send + 'lifetime,
+    {
+        let hash = self.hasher.hash_one(key);
+        if let Some(buf) = self.buffer(parent) {
+            if let Some(idx) = self.find(&buf, hash, &key) {
+                let old = buf[idx].replace(Some(Entry { hash, key, value }));
+                write_barrier(&buf, &buf[idx]);
+                return old.map(|entry| entry.value);
+            }
+        }
+        self.reserve(heap, parent, 1);
+        // the map has spare capacity
+        let buf = self.buffer(parent).unwrap();
+        let idx = Self::find_empty(&buf, hash);
+        buf[idx].replace(Some(Entry { hash, key, value }));
+        write_barrier(&buf, &buf[idx]);
+        self.len.set(self.len.get() + 1);
+        None
+    }
+
+    /// Removes the entry for the given key from the map, returning its value. `parent` is a root of the object
+    /// containing the map.
+    ///
+    /// # Panics
+    /// Panics if the map is not part of `parent`.
+    pub fn remove<S, P, Q>(&self, parent: &Root<'_, S, P>, key: &Q) -> Option<V>
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        let buf = self.buffer(parent)?;
+        let idx = self.find(&buf, self.hasher.hash_one(key), key)?;
+        let entry = buf[idx].replace(None);
+        self.len.set(self.len.get() - 1);
+        // shift later entries in the probe sequence back, so that it has no gaps
+        let mask = buf.len() - 1;
+        let mut hole = idx;
+        let mut next = (idx + 1) & mask;
+        while let Some((hash, _)) = buf[next].key() {
+            let home = hash as usize & mask;
+            if next.wrapping_sub(home) & mask >= next.wrapping_sub(hole) & mask {
+                buf[hole].replace(buf[next].replace(None));
+                write_barrier(&buf, &buf[hole]);
+                hole = next;
+            }
+            next = (next + 1) & mask;
+        }
+        entry.map(|entry| entry.value)
+    }
+
+    /// Removes all entries from the map. The capacity of the map is unchanged. `parent` is a root of the object
+    /// containing the map.
+    ///
+    /// # Panics
+    /// Panics if the map is not part of `parent`.
+    pub fn clear<S, P>(&self, parent: &Root<'_, S, P>)
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+    {
+        let Some(buf) = self.buffer(parent) else {
+            return;
+        };
+        // update the length first, in case dropping an entry accesses the map
+        self.len.set(0);
+        for slot in buf.iter() {
+            drop(slot.replace(None));
+        }
+    }
+
+    /// Ensures the map can hold at least `additional` more entries, allocating a new table in `heap` if necessary.
+    /// A new table has at least twice as many buckets as the old one, so that repeated insertions take amortized
+    /// constant time. `parent` is a root of the object containing the map.
+    ///
+    /// # Panics
+    /// Panics if `parent` does not belong to `heap`, if the map is not part of `parent`, if the new capacity
+    /// overflows `usize`, or if the strategy does not have enough heap space for a new table.
+    pub fn reserve<'lifetime, S, P>(
+        &self,
+        heap: &GcHeap<'lifetime, S>,
+        parent: &Root<'_, S, P>,
+        additional: usize,
+    ) where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+        K: Send + 'lifetime,
+        V: Send + 'lifetime,
+    {
+        assert!(
+            core::ptr::eq(parent.gc, &heap.strategy),
+            "parent does not belong to this heap"
+        );
+        let old = self.buffer(parent);
+        let required = self
+            .len
+            .get()
+            .checked_add(additional)
+            .expect("capacity overflow");
+        if required <= self.capacity() {
+            return;
+        }
+        let buckets = self.buf.get().map_or(0, |buf| buf.metadata);
+        let buckets = buckets_for(required).max(buckets.saturating_mul(2));
+        // the old table stays rooted while its entries are moved, in case allocating the new table collects
+        let new = heap.alloc_from_iter((0..buckets).map(|_| Slot::empty()));
+        for slot in old.iter().flat_map(|old| old.iter()) {
+            if let Some(entry) = slot.replace(None) {
+                let idx = Self::find_empty(&new, entry.hash);
+                new[idx].replace(Some(entry));
+                write_barrier(&new, &new[idx]);
+            }
+        }
+        self.buf.set(Some(new.gc()));
+        write_barrier(parent, &new.gc());
+    }
+
+    /// Iterates over copies of the entries of the map, in no particular order. `parent` is a root of the object
+    /// containing the map.
+    ///
+    /// If the map is modified during iteration, entries may be skipped or visited more than once.
+    ///
+    /// # Panics
+    /// Panics if the map is not part of `parent`.
+    pub fn iter<'root, S, P>(&self, parent: &Root<'root, S, P>) -> GcHashMapIter<'root, S, K, V>
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+        V: Copy,
+    {
+        GcHashMapIter {
+            buf: self.buffer(parent),
+            idx: 0,
+        }
+    }
+}
+
+impl<K, V, H: Default> Default for GcHashMap<K, V, H> {
+    fn default() -> Self {
+        Self::with_hasher(H::default())
+    }
+}
+
+impl<K, V, H> fmt::Debug for GcHashMap<K, V, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GcHashMap")
+            .field("len", &self.len())
+            .field("capacity", &self.capacity())
+            .finish_non_exhaustive()
+    }
+}
+
+// SAFETY: see `GcVec`
+unsafe impl<K: Send, V: Send, H: Send> Send for GcHashMap<K, V, H> {}
+
+/// SAFETY: the entries are traced as part of the table, which is a separate GC object. The hasher cannot contain GC
+/// objects, as it is not `Trace`.
+unsafe impl<K, V, H> Trace for GcHashMap<K, V, H> {
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        self.buf.trace(ctx);
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        self.buf.trace_edges(ctx);
+    }
+}
+
+/// An iterator over copies of the entries of a [`GcHashMap`], created by [`GcHashMap::iter`]. The table is kept
+/// rooted and pinned for as long as the iterator exists.
+pub struct GcHashMapIter<'root, S: ?Sized + GcStrategy, K, V> {
+    buf: Option<Root<'root, S, Table<K, V>>>,
+    idx: usize,
+}
+
+impl<S: ?Sized + GcStrategy, K: Copy, V: Copy> Iterator for GcHashMapIter<'_, S, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let buf = self.buf.as_ref()?;
+        while let Some(slot) = buf.get().get(self.idx) {
+            self.idx += 1;
+            if let (Some((_, key)), Some(value)) = (slot.key(), slot.value()) {
+                return Some((key, value));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::{
+        decorators::StressStrategy,
+        heap::{CollectOptions, CollectionKind},
+        strategies::{Generational, MarkSweep, Semispace},
+    };
+    use alloc::vec::Vec;
+    use core::hash::BuildHasherDefault;
+
+    /// A simple FNV-1a hasher, as the tests may run without `std`.
+    #[derive(Default)]
+    struct Fnv(u64);
+
+    impl Hasher for Fnv {
+        fn finish(&self) -> u64 {
+            self.0
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            for &byte in bytes {
+                self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x100_0000_01b3);
+            }
+        }
+    }
+
+    type FnvBuildHasher = BuildHasherDefault<Fnv>;
+
+    /// Copies the values out of a vector of `Gc<u32>`.
+    fn values<S: ?Sized + GcStrategy>(
+        heap: &GcHeap<'_, S>,
+        vec: &Root<'_, S, GcVec<Gc<u32>>>,
+    ) -> Vec<u32> {
+        (0..vec.len())
+            // SAFETY: the elements are read from an object that is rooted
+            .map(|idx| *unsafe { heap.root(vec.get().get(vec, idx).unwrap()) })
+            .collect()
+    }
+
+    #[test]
+    fn vectors_survive_collections() {
+        let heap = GcHeap::new(StressStrategy::new(Generational::new()));
+        let vec = heap.alloc(GcVec::new());
+        for value in 0..20u32 {
+            // each allocation collects, including the one that grows the buffer
+            let value = heap.alloc(value);
+            vec.push(&heap, &vec, value.gc());
+        }
+        assert_eq!(values(&heap, &vec), (0..20).collect::<Vec<_>>());
+
+        let last = vec.pop(&vec).unwrap();
+        // SAFETY: nothing has been allocated since the value was removed
+        assert_eq!(*unsafe { heap.root(last) }, 19);
+        vec.truncate(&vec, 10);
+        vec.reserve(&heap, &vec, 100);
+        assert!(vec.capacity() >= 110);
+        assert_eq!(values(&heap, &vec), (0..10).collect::<Vec<_>>());
+
+        heap.collect();
+        // the vector, its buffer, and the values still in it
+        assert_eq!(heap.stats().object_count, 12);
+        vec.clear(&vec);
+        assert_eq!(vec.pop(&vec), None);
+        heap.collect();
+        assert_eq!(heap.stats().object_count, 2);
+    }
+
+    #[test]
+    fn vector_stores_invoke_the_write_barrier() {
+        let heap = GcHeap::new(Generational::new());
+        let vec = heap.alloc(GcVec::new());
+        vec.reserve(&heap, &vec, 4);
+        let minor = CollectOptions {
+            kind: CollectionKind::Minor,
+            ..CollectOptions::default()
+        };
+        // promote the vector and its buffer
+        for _ in 0..3 {
+            heap.collect_with(minor);
+        }
+        vec.push(&heap, &vec, heap.alloc(1u32).gc());
+        vec.push(&heap, &vec, heap.alloc(2u32).gc());
+        vec.set(&vec, 0, heap.alloc(3u32).gc());
+        // the young values are only reachable from the old buffer, and the overwritten one is garbage
+        heap.collect_with(minor);
+        assert_eq!(heap.stats().object_count, 4);
+        assert_eq!(values(&heap, &vec), [3, 2]);
+    }
+
+    #[test]
+    fn strings_compare_by_contents() {
+        let heap = GcHeap::new(Semispace::new());
+        let hello = GcString::new(&heap, "hello");
+        let greeting = &hello + ", " + "world";
+        let copy = GcString::new(&heap, "hello, world");
+        heap.collect();
+        assert_eq!(hello, "hello");
+        assert_eq!(greeting.as_str(), "hello, world");
+        assert_eq!(greeting, copy);
+        assert!(!Gc::ptr_eq(greeting.gc(), copy.gc()));
+        assert!(hello < greeting);
+        let hasher = FnvBuildHasher::default();
+        assert_eq!(hasher.hash_one(&greeting), hasher.hash_one(&copy));
+        assert_eq!(hasher.hash_one(&greeting), hasher.hash_one("hello, world"));
+        // the intermediate concatenation is garbage
+        assert_eq!(heap.stats().object_count, 3);
+    }
+
+    #[test]
+    fn maps_insert_remove_and_grow() {
+        let heap = GcHeap::new(MarkSweep::new());
+        let map = heap.alloc(GcHashMap::<u32, u32, FnvBuildHasher>::default());
+        for key in 0..100 {
+            assert_eq!(map.insert(&heap, &map, key, key), None);
+        }
+        assert_eq!(map.len(), 100);
+        assert!(map.capacity() >= 100);
+        for key in 0..100 {
+            assert_eq!(map.insert(&heap, &map, key, key * 2), Some(key));
+        }
+        for key in (0..100).step_by(2) {
+            assert_eq!(map.remove(&map, &key), Some(key * 2));
+        }
+        assert_eq!(map.remove(&map, &0), None);
+        assert_eq!(map.len(), 50);
+        for key in 0..100 {
+            let expected = (key % 2 == 1).then_some(key * 2);
+            assert_eq!(map.get().get(&map, &key), expected);
+            assert_eq!(map.contains_key(&map, &key), expected.is_some());
+        }
+        let mut entries = map.iter(&map).collect::<Vec<_>>();
+        entries.sort_unstable();
+        let expected = (1..100).step_by(2).map(|key| (key, key * 2));
+        assert_eq!(entries, expected.collect::<Vec<_>>());
+
+        let capacity = map.capacity();
+        map.clear(&map);
+        assert!(map.is_empty());
+        assert_eq!(map.iter(&map).next(), None);
+        assert_eq!(map.capacity(), capacity);
+    }
+
+    #[test]
+    fn map_lookups_survive_moving_collections() {
+        let heap = GcHeap::new(Semispace::new());
+        let map = heap.alloc(GcHashMap::<Gc<u32>, Gc<u32>, FnvBuildHasher>::default());
+        let keys = (0..10).map(|key| heap.alloc(key)).collect::<Vec<_>>();
+        for (key, value) in keys.iter().zip(100..) {
+            let value = heap.alloc(value);
+            // access the map through short-lived pins, so that it can be moved
+            map.pin().insert(&heap, &map, key.gc(), value.gc());
+        }
+        let addr = map.as_ptr();
+        heap.collect();
+        assert_ne!(map.as_ptr(), addr);
+        for (key, expected) in keys.iter().zip(100..) {
+            let value = map.pin().get(&map, &key.gc()).unwrap();
+            // SAFETY: the value is read from an object that is rooted
+            assert_eq!(*unsafe { heap.root(value) }, expected);
+        }
+        assert_eq!(map.pin().len(), 10);
+    }
+}
@@ -0,0 +1,1637 @@
+//! Strategies that wrap other strategies.
+//!
+//! Each decorator implements [`GcStrategy`] by passing every operation through to the strategy it wraps, and adds
+//! some behavior on the side. They compose, so e.g. a `StatsStrategy<ValidatingStrategy<S>>` both counts and checks
+//! the operations of `S`.
+//!
+//! - [`StatsStrategy`] counts operations, which is useful for benchmarks and for tests that assert that an
+//!   operation was (or was not) performed.
+//! - [`LoggingStrategy`] reports operations to a callback as [`StrategyEvent`]s, which is useful for tracing the
+//!   behavior of a program or of a strategy under development.
+//! - [`ValidatingStrategy`] checks that the handles and addresses that cross the strategy boundary are sane, and
+//!   panics with a description of the problem if not. This catches bugs in strategies early, before they corrupt
+//!   memory.
+//! - [`DebugStrategy`] tracks the lifecycle state, root count, and pin count of every object, and panics as soon as
+//!   the heap or the strategy violates the object lifecycle. This is more thorough than [`ValidatingStrategy`], but
+//!   keeps a table of all objects, so it is meant for tests and debug builds.
+//! - [`StressStrategy`] collects before every allocation, so that missing roots and write barriers cause failures
+//!   right away instead of once in a while.
+
+#[cfg(feature = "alloc")]
+use alloc::collections::BTreeMap;
+use core::{
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    heap::{
+        AllocHint, CollectOptions, CollectionKind, CollectionStats, Collector, FreshAllocation,
+        GcAllocator, GcStrategy, GcVtable, HeapStats, MemoryPressure, RelocationListener,
+        RootManager, ShrinkStats, StepBudget, StepResult, ThreadToken,
+    },
+    trace::RootProvider,
+};
+#[cfg(feature = "alloc")]
+use crate::{
+    heap::{Handle, StrategyHandle},
+    sync::GcMutex,
+};
+
+/// A strategy that counts the operations performed on another strategy.
+///
+/// The counters are updated with relaxed atomics, so they are exact once the heap is quiescent, but may lag behind
+/// while other threads use it.
+#[derive(Debug, Default)]
+pub struct StatsStrategy<S> {
+    strategy: S,
+    allocations: AtomicUsize,
+    failed_allocations: AtomicUsize,
+    bytes_allocated: AtomicUsize,
+    roots: AtomicUsize,
+    unroots: AtomicUsize,
+    pins: AtomicUsize,
+    unpins: AtomicUsize,
+    write_barriers: AtomicUsize,
+    collections: AtomicUsize,
+}
+
+/// A snapshot of the counters of a [`StatsStrategy`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct StrategyCounters {
+    /// The number of successful allocations.
+    pub allocations: usize,
+    /// The number of allocations that returned an error.
+    pub failed_allocations: usize,
+    /// The total size of all successful allocations, in bytes.
+    pub bytes_allocated: usize,
+    /// The number of times an object was rooted, including by allocation and by rooting in bulk.
+    pub roots: usize,
+    /// The number of times an object was unrooted, including by unrooting in bulk.
+    pub unroots: usize,
+    /// The number of times an object was pinned, including by pinning in bulk.
+    pub pins: usize,
+    /// The number of times an object was unpinned, including by unpinning in bulk.
+    pub unpins: usize,
+    /// The number of write barriers.
+    pub write_barriers: usize,
+    /// The number of explicit collections, counting each step of an incremental collection.
+    pub collections: usize,
+}
+
+impl<S: GcStrategy> StatsStrategy<S> {
+    /// Counts the operations performed on `strategy`.
+    pub fn new(strategy: S) -> Self {
+        Self {
+            strategy,
+            allocations: AtomicUsize::new(0),
+            failed_allocations: AtomicUsize::new(0),
+            bytes_allocated: AtomicUsize::new(0),
+            roots: AtomicUsize::new(0),
+            unroots: AtomicUsize::new(0),
+            pins: AtomicUsize::new(0),
+            unpins: AtomicUsize::new(0),
+            write_barriers: AtomicUsize::new(0),
+            collections: AtomicUsize::new(0),
+        }
+    }
+
+    /// The wrapped strategy.
+    pub fn strategy(&self) -> &S {
+        &self.strategy
+    }
+
+    /// Returns the current values of the counters.
+    pub fn counters(&self) -> StrategyCounters {
+        let load = |counter: &AtomicUsize| counter.load(Ordering::Relaxed);
+        StrategyCounters {
+            allocations: load(&self.allocations),
+            failed_allocations: load(&self.failed_allocations),
+            bytes_allocated: load(&self.bytes_allocated),
+            roots: load(&self.roots),
+            unroots: load(&self.unroots),
+            pins: load(&self.pins),
+            unpins: load(&self.unpins),
+            write_barriers: load(&self.write_barriers),
+            collections: load(&self.collections),
+        }
+    }
+
+    /// Resets all counters to zero.
+    pub fn reset(&self) {
+        for counter in [
+            &self.allocations,
+            &self.failed_allocations,
+            &self.bytes_allocated,
+            &self.roots,
+            &self.unroots,
+            &self.pins,
+            &self.unpins,
+            &self.write_barriers,
+            &self.collections,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Counts the outcome of an allocation. Fresh allocations are rooted, so they count as roots too.
+    fn count_allocation<E>(
+        &self,
+        vtable: &GcVtable,
+        len: usize,
+        result: Result<FreshAllocation<S::Handle>, E>,
+    ) -> Result<FreshAllocation<S::Handle>, E> {
+        if result.is_ok() {
+            let size = vtable.layout(len).map_or(0, |layout| layout.size());
+            self.allocations.fetch_add(1, Ordering::Relaxed);
+            self.bytes_allocated.fetch_add(size, Ordering::Relaxed);
+            self.roots.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.failed_allocations.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+// SAFETY: every method is passed through to the wrapped strategy.
+unsafe impl<S: GcStrategy> GcAllocator for StatsStrategy<S> {
+    type Handle = S::Handle;
+    type Error = S::Error;
+
+    fn allocate(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+    ) -> Result<FreshAllocation<Self::Handle>, Self::Error> {
+        self.count_allocation(vtable, len, self.strategy.allocate(vtable, len))
+    }
+
+    fn allocate_with_hint(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+        hint: AllocHint,
+    ) -> Result<FreshAllocation<Self::Handle>, Self::Error> {
+        self.count_allocation(
+            vtable,
+            len,
+            self.strategy.allocate_with_hint(vtable, len, hint),
+        )
+    }
+
+    unsafe fn allocate_for_thread(
+        &self,
+        token: ThreadToken,
+        vtable: &'static GcVtable,
+        len: usize,
+        hint: AllocHint,
+    ) -> Result<FreshAllocation<Self::Handle>, Self::Error> {
+        // SAFETY: caller
+        let result = unsafe { self.strategy.allocate_for_thread(token, vtable, len, hint) };
+        self.count_allocation(vtable, len, result)
+    }
+
+    fn pin(&self, obj: S::Handle) -> *const () {
+        self.pins.fetch_add(1, Ordering::Relaxed);
+        self.strategy.pin(obj)
+    }
+
+    fn unpin(&self, obj: S::Handle) {
+        self.unpins.fetch_add(1, Ordering::Relaxed);
+        self.strategy.unpin(obj)
+    }
+
+    fn pin_all(&self, objs: &[S::Handle], addrs: &mut [*const ()]) {
+        self.pins.fetch_add(objs.len(), Ordering::Relaxed);
+        self.strategy.pin_all(objs, addrs)
+    }
+
+    fn unpin_all(&self, objs: &[S::Handle]) {
+        self.unpins.fetch_add(objs.len(), Ordering::Relaxed);
+        self.strategy.unpin_all(objs)
+    }
+
+    delegate! { strategy =>
+        fn register_thread(&self) -> ThreadToken;
+        unsafe fn unregister_thread(&self, token: ThreadToken);
+        unsafe fn set_initialized(&self, obj: S::Handle);
+        unsafe fn set_finalized(&self, obj: S::Handle);
+        unsafe fn reclaim(&self, obj: S::Handle);
+        fn resolve(&self, obj: S::Handle) -> *const ();
+        fn pin_long_lived(&self, obj: S::Handle) -> *const ();
+        fn unpin_long_lived(&self, obj: S::Handle);
+        fn shrink(&self) -> ShrinkStats;
+        fn contains(&self, obj: S::Handle) -> bool;
+        fn vtable(&self, obj: S::Handle) -> &'static GcVtable;
+    }
+}
+
+// SAFETY: every method is passed through to the wrapped strategy.
+unsafe impl<S: GcStrategy> RootManager<S::Handle> for StatsStrategy<S> {
+    fn root(&self, obj: S::Handle) {
+        self.roots.fetch_add(1, Ordering::Relaxed);
+        self.strategy.root(obj)
+    }
+
+    fn unroot(&self, obj: S::Handle) {
+        self.unroots.fetch_add(1, Ordering::Relaxed);
+        self.strategy.unroot(obj)
+    }
+
+    fn root_all(&self, objs: &[S::Handle]) {
+        self.roots.fetch_add(objs.len(), Ordering::Relaxed);
+        self.strategy.root_all(objs)
+    }
+
+    fn unroot_all(&self, objs: &[S::Handle]) {
+        self.unroots.fetch_add(objs.len(), Ordering::Relaxed);
+        self.strategy.unroot_all(objs)
+    }
+
+    delegate! { strategy =>
+        fn freeze(&self, obj: S::Handle);
+        unsafe fn register_root_provider(&self, provider: &dyn RootProvider);
+        unsafe fn unregister_root_provider(&self, provider: &dyn RootProvider);
+    }
+}
+
+// SAFETY: every method is passed through to the wrapped strategy.
+unsafe impl<S: GcStrategy> Collector<S::Handle> for StatsStrategy<S> {
+    fn write_barrier(&self, parent: S::Handle, child: S::Handle) {
+        self.write_barriers.fetch_add(1, Ordering::Relaxed);
+        self.strategy.write_barrier(parent, child)
+    }
+
+    fn collect(&self, options: CollectOptions) -> CollectionStats {
+        self.collections.fetch_add(1, Ordering::Relaxed);
+        self.strategy.collect(options)
+    }
+
+    fn collect_step(&self, budget: StepBudget) -> StepResult {
+        self.collections.fetch_add(1, Ordering::Relaxed);
+        self.strategy.collect_step(budget)
+    }
+
+    delegate! { strategy =>
+        fn allocate_weak(&self, target: S::Handle) -> Option<S::Handle>;
+        unsafe fn upgrade_weak(&self, cell: S::Handle) -> Option<S::Handle>;
+        fn allocate_ephemeron(&self, key: S::Handle, value: S::Handle) -> Option<S::Handle>;
+        unsafe fn upgrade_ephemeron(&self, cell: S::Handle) -> Option<(S::Handle, S::Handle)>;
+        fn poll_safepoint(&self);
+        fn enter_safepoint(&self);
+        fn leave_safepoint(&self);
+        fn memory_pressure(&self, level: MemoryPressure);
+        unsafe fn register_relocation_listener(&self, listener: &dyn RelocationListener);
+        unsafe fn unregister_relocation_listener(&self, listener: &dyn RelocationListener);
+        fn age(&self, obj: S::Handle) -> Option<u32>;
+        fn promote(&self, obj: S::Handle);
+        fn stats(&self) -> HeapStats;
+    }
+}
+
+/// An operation performed on a [`LoggingStrategy`]. Events for operations that return a value are reported after
+/// the operation, and carry its result; other events are reported before the operation.
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub enum StrategyEvent<'a, H> {
+    /// An object of type `type_name` and `size` bytes was allocated, or failed to be allocated if `handle` is None.
+    Allocate {
+        type_name: &'static str,
+        size: usize,
+        hint: AllocHint,
+        handle: Option<H>,
+    },
+    /// A thread was registered as a mutator.
+    RegisterThread(ThreadToken),
+    /// A mutator thread is about to be unregistered.
+    UnregisterThread(ThreadToken),
+    /// An object is about to be marked initialized.
+    Initialize(H),
+    /// An object is about to be marked finalized.
+    Finalize(H),
+    /// An object is about to be reclaimed.
+    Reclaim(H),
+    /// An object is about to be rooted.
+    Root(H),
+    /// An object is about to be unrooted.
+    Unroot(H),
+    /// Objects are about to be rooted in bulk.
+    RootAll(&'a [H]),
+    /// Objects are about to be unrooted in bulk.
+    UnrootAll(&'a [H]),
+    /// An object is about to be frozen.
+    Freeze(H),
+    /// An object was pinned at the given address.
+    Pin(H, *const ()),
+    /// An object is about to be unpinned.
+    Unpin(H),
+    /// Objects were pinned in bulk at the given addresses.
+    PinAll(&'a [H], &'a [*const ()]),
+    /// Objects are about to be unpinned in bulk.
+    UnpinAll(&'a [H]),
+    /// A write barrier is about to run for a store of `child` into `parent`.
+    WriteBarrier { parent: H, child: H },
+    /// A collection finished.
+    Collect {
+        options: CollectOptions,
+        stats: CollectionStats,
+    },
+    /// A step of an incremental collection finished.
+    CollectStep {
+        budget: StepBudget,
+        result: StepResult,
+    },
+    /// The strategy is about to be notified of memory pressure.
+    MemoryPressure(MemoryPressure),
+    /// The strategy released memory.
+    Shrink(ShrinkStats),
+    /// An object is about to be promoted.
+    Promote(H),
+}
+
+/// A strategy that reports the operations performed on another strategy to a callback.
+///
+/// The callback is invoked on the thread performing the operation, while the operation is in progress, so it must
+/// not use the heap. Frequent operations such as [`GcAllocator::resolve`] and safepoint polls are not reported.
+pub struct LoggingStrategy<S, F> {
+    strategy: S,
+    log: F,
+}
+
+impl<S: GcStrategy, F: Fn(StrategyEvent<'_, S::Handle>)> LoggingStrategy<S, F> {
+    /// Reports the operations performed on `strategy` to `log`.
+    pub fn new(strategy: S, log: F) -> Self {
+        Self { strategy, log }
+    }
+
+    /// The wrapped strategy.
+    pub fn strategy(&self) -> &S {
+        &self.strategy
+    }
+
+    /// Reports the outcome of an allocation.
+    fn log_allocation<E>(
+        &self,
+        vtable: &GcVtable,
+        len: usize,
+        hint: AllocHint,
+        result: Result<FreshAllocation<S::Handle>, E>,
+    ) -> Result<FreshAllocation<S::Handle>, E> {
+        (self.log)(StrategyEvent::Allocate {
+            type_name: vtable.type_name(),
+            size: vtable.layout(len).map_or(0, |layout| layout.size()),
+            hint,
+            handle: result.as_ref().ok().map(|fresh| fresh.handle),
+        });
+        result
+    }
+}
+
+impl<S: fmt::Debug, F> fmt::Debug for LoggingStrategy<S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoggingStrategy")
+            .field("strategy", &self.strategy)
+            .finish_non_exhaustive()
+    }
+}
+
+// SAFETY: every method is passed through to the wrapped strategy.
+unsafe impl<S: GcStrategy, F: Fn(StrategyEvent<'_, S::Handle>)> GcAllocator
+    for LoggingStrategy<S, F>
+{
+    type Handle = S::Handle;
+    type Error = S::Error;
+
+    fn allocate(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+    ) -> Result<FreshAllocation<Self::Handle>, Self::Error> {
+        let result = self.strategy.allocate(vtable, len);
+        self.log_allocation(vtable, len, AllocHint::Normal, result)
+    }
+
+    fn allocate_with_hint(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+        hint: AllocHint,
+    ) -> Result<FreshAllocation<Self::Handle>, Self::Error> {
+        let result = self.strategy.allocate_with_hint(vtable, len, hint);
+        self.log_allocation(vtable, len, hint, result)
+    }
+
+    fn register_thread(&self) -> ThreadToken {
+        let token = self.strategy.register_thread();
+        (self.log)(StrategyEvent::RegisterThread(token));
+        token
+    }
+
+    unsafe fn unregister_thread(&self, token: ThreadToken) {
+        (self.log)(StrategyEvent::UnregisterThread(token));
+        // SAFETY: caller
+        unsafe { self.strategy.unregister_thread(token) }
+    }
+
+    unsafe fn allocate_for_thread(
+        &self,
+        token: ThreadToken,
+        vtable: &'static GcVtable,
+        len: usize,
+        hint: AllocHint,
+    ) -> Result<FreshAllocation<Self::Handle>, Self::Error> {
+        // SAFETY: caller
+        let result = unsafe { self.strategy.allocate_for_thread(token, vtable, len, hint) };
+        self.log_allocation(vtable, len, hint, result)
+    }
+
+    unsafe fn set_initialized(&self, obj: S::Handle) {
+        (self.log)(StrategyEvent::Initialize(obj));
+        // SAFETY: caller
+        unsafe { self.strategy.set_initialized(obj) }
+    }
+
+    unsafe fn set_finalized(&self, obj: S::Handle) {
+        (self.log)(StrategyEvent::Finalize(obj));
+        // SAFETY: caller
+        unsafe { self.strategy.set_finalized(obj) }
+    }
+
+    unsafe fn reclaim(&self, obj: S::Handle) {
+        (self.log)(StrategyEvent::Reclaim(obj));
+        // SAFETY: caller
+        unsafe { self.strategy.reclaim(obj) }
+    }
+
+    fn pin(&self, obj: S::Handle) -> *const () {
+        let addr = self.strategy.pin(obj);
+        (self.log)(StrategyEvent::Pin(obj, addr));
+        addr
+    }
+
+    fn unpin(&self, obj: S::Handle) {
+        (self.log)(StrategyEvent::Unpin(obj));
+        self.strategy.unpin(obj)
+    }
+
+    fn pin_all(&self, objs: &[S::Handle], addrs: &mut [*const ()]) {
+        self.strategy.pin_all(objs, addrs);
+        (self.log)(StrategyEvent::PinAll(objs, addrs));
+    }
+
+    fn unpin_all(&self, objs: &[S::Handle]) {
+        (self.log)(StrategyEvent::UnpinAll(objs));
+        self.strategy.unpin_all(objs)
+    }
+
+    fn shrink(&self) -> ShrinkStats {
+        let stats = self.strategy.shrink();
+        (self.log)(StrategyEvent::Shrink(stats));
+        stats
+    }
+
+    delegate! { strategy =>
+        fn resolve(&self, obj: S::Handle) -> *const ();
+        fn pin_long_lived(&self, obj: S::Handle) -> *const ();
+        fn unpin_long_lived(&self, obj: S::Handle);
+        fn contains(&self, obj: S::Handle) -> bool;
+        fn vtable(&self, obj: S::Handle) -> &'static GcVtable;
+    }
+}
+
+// SAFETY: every method is passed through to the wrapped strategy.
+unsafe impl<S: GcStrategy, F: Fn(StrategyEvent<'_, S::Handle>)> RootManager<S::Handle>
+    for LoggingStrategy<S, F>
+{
+    fn root(&self, obj: S::Handle) {
+        (self.log)(StrategyEvent::Root(obj));
+        self.strategy.root(obj)
+    }
+
+    fn unroot(&self, obj: S::Handle) {
+        (self.log)(StrategyEvent::Unroot(obj));
+        self.strategy.unroot(obj)
+    }
+
+    fn root_all(&self, objs: &[S::Handle]) {
+        (self.log)(StrategyEvent::RootAll(objs));
+        self.strategy.root_all(objs)
+    }
+
+    fn unroot_all(&self, objs: &[S::Handle]) {
+        (self.log)(StrategyEvent::UnrootAll(objs));
+        self.strategy.unroot_all(objs)
+    }
+
+    fn freeze(&self, obj: S::Handle) {
+        (self.log)(StrategyEvent::Freeze(obj));
+        self.strategy.freeze(obj)
+    }
+
+    delegate! { strategy =>
+        unsafe fn register_root_provider(&self, provider: &dyn RootProvider);
+        unsafe fn unregister_root_provider(&self, provider: &dyn RootProvider);
+    }
+}
+
+// SAFETY: every method is passed through to the wrapped strategy.
+unsafe impl<S: GcStrategy, F: Fn(StrategyEvent<'_, S::Handle>)> Collector<S::Handle>
+    for LoggingStrategy<S, F>
+{
+    fn write_barrier(&self, parent: S::Handle, child: S::Handle) {
+        (self.log)(StrategyEvent::WriteBarrier { parent, child });
+        self.strategy.write_barrier(parent, child)
+    }
+
+    fn collect(&self, options: CollectOptions) -> CollectionStats {
+        let stats = self.strategy.collect(options);
+        (self.log)(StrategyEvent::Collect { options, stats });
+        stats
+    }
+
+    fn collect_step(&self, budget: StepBudget) -> StepResult {
+        let result = self.strategy.collect_step(budget);
+        (self.log)(StrategyEvent::CollectStep { budget, result });
+        result
+    }
+
+    fn memory_pressure(&self, level: MemoryPressure) {
+        (self.log)(StrategyEvent::MemoryPressure(level));
+        self.strategy.memory_pressure(level)
+    }
+
+    fn promote(&self, obj: S::Handle) {
+        (self.log)(StrategyEvent::Promote(obj));
+        self.strategy.promote(obj)
+    }
+
+    delegate! { strategy =>
+        fn allocate_weak(&self, target: S::Handle) -> Option<S::Handle>;
+        unsafe fn upgrade_weak(&self, cell: S::Handle) -> Option<S::Handle>;
+        fn allocate_ephemeron(&self, key: S::Handle, value: S::Handle) -> Option<S::Handle>;
+        unsafe fn upgrade_ephemeron(&self, cell: S::Handle) -> Option<(S::Handle, S::Handle)>;
+        fn poll_safepoint(&self);
+        fn enter_safepoint(&self);
+        fn leave_safepoint(&self);
+        unsafe fn register_relocation_listener(&self, listener: &dyn RelocationListener);
+        unsafe fn unregister_relocation_listener(&self, listener: &dyn RelocationListener);
+        fn age(&self, obj: S::Handle) -> Option<u32>;
+        fn stats(&self) -> HeapStats;
+    }
+}
+
+/// A strategy that checks the handles and addresses passed to and returned from another strategy.
+///
+/// Every handle passed in must belong to the strategy according to [`GcAllocator::contains`], and every address
+/// returned must be non-null and suitably aligned for the object. Violations panic. The checks query the wrapped
+/// strategy, so they are only as reliable as its [`contains`](GcAllocator::contains) and
+/// [`vtable`](GcAllocator::vtable), and they make every operation slower; this decorator is meant for debugging.
+#[derive(Debug, Default)]
+pub struct ValidatingStrategy<S> {
+    strategy: S,
+}
+
+impl<S: GcStrategy> ValidatingStrategy<S> {
+    /// Checks the operations performed on `strategy`.
+    pub fn new(strategy: S) -> Self {
+        Self { strategy }
+    }
+
+    /// The wrapped strategy.
+    pub fn strategy(&self) -> &S {
+        &self.strategy
+    }
+
+    /// Checks that `obj` belongs to the wrapped strategy.
+    #[track_caller]
+    fn check_handle(&self, op: &str, obj: S::Handle) {
+        assert!(
+            self.strategy.contains(obj),
+            "{op}: handle {obj:?} does not belong to this strategy"
+        );
+    }
+
+    /// Checks that `addr` is a valid address for an object with the given vtable.
+    #[track_caller]
+    fn check_addr(&self, op: &str, obj: S::Handle, vtable: &GcVtable, addr: *const ()) {
+        assert!(!addr.is_null(), "{op}: null address for handle {obj:?}");
+        let align = vtable.layout(0).map_or(1, |layout| layout.align());
+        assert!(
+            (addr as usize).is_multiple_of(align),
+            "{op}: address {addr:?} for handle {obj:?} is not aligned to {align}"
+        );
+    }
+
+    /// Checks a fresh allocation.
+    #[track_caller]
+    fn check_allocation<E>(
+        &self,
+        vtable: &GcVtable,
+        result: Result<FreshAllocation<S::Handle>, E>,
+    ) -> Result<FreshAllocation<S::Handle>, E> {
+        if let Ok(fresh) = &result {
+            self.check_handle("allocate", fresh.handle);
+            self.check_addr("allocate", fresh.handle, vtable, fresh.ptr);
+        }
+        result
+    }
+
+    /// Checks that `obj` belongs to the wrapped strategy, then checks the address returned by `f`.
+    #[track_caller]
+    fn check_resolved(&self, op: &str, obj: S::Handle, f: impl FnOnce() -> *const ()) -> *const () {
+        self.check_handle(op, obj);
+        let addr = f();
+        self.check_addr(op, obj, self.strategy.vtable(obj), addr);
+        addr
+    }
+}
+
+// SAFETY: every method is passed through to the wrapped strategy, after checks that panic on failure.
+unsafe impl<S: GcStrategy> GcAllocator for ValidatingStrategy<S> {
+    type Handle = S::Handle;
+    type Error = S::Error;
+
+    fn allocate(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+    ) -> Result<FreshAllocation<Self::Handle>, Self::Error> {
+        self.check_allocation(vtable, self.strategy.allocate(vtable, len))
+    }
+
+    fn allocate_with_hint(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+        hint: AllocHint,
+    ) -> Result<FreshAllocation<Self::Handle>, Self::Error> {
+        self.check_allocation(vtable, self.strategy.allocate_with_hint(vtable, len, hint))
+    }
+
+    unsafe fn allocate_for_thread(
+        &self,
+        token: ThreadToken,
+        vtable: &'static GcVtable,
+        len: usize,
+        hint: AllocHint,
+    ) -> Result<FreshAllocation<Self::Handle>, Self::Error> {
+        // SAFETY: caller
+        let result = unsafe { self.strategy.allocate_for_thread(token, vtable, len, hint) };
+        self.check_allocation(vtable, result)
+    }
+
+    unsafe fn set_initialized(&self, obj: S::Handle) {
+        self.check_handle("set_initialized", obj);
+        // SAFETY: caller
+        unsafe { self.strategy.set_initialized(obj) }
+    }
+
+    unsafe fn set_finalized(&self, obj: S::Handle) {
+        self.check_handle("set_finalized", obj);
+        // SAFETY: caller
+        unsafe { self.strategy.set_finalized(obj) }
+    }
+
+    unsafe fn reclaim(&self, obj: S::Handle) {
+        self.check_handle("reclaim", obj);
+        // SAFETY: caller
+        unsafe { self.strategy.reclaim(obj) }
+    }
+
+    fn pin(&self, obj: S::Handle) -> *const () {
+        self.check_resolved("pin", obj, || self.strategy.pin(obj))
+    }
+
+    fn unpin(&self, obj: S::Handle) {
+        self.check_handle("unpin", obj);
+        self.strategy.unpin(obj)
+    }
+
+    fn pin_all(&self, objs: &[S::Handle], addrs: &mut [*const ()]) {
+        assert!(
+            addrs.len() >= objs.len(),
+            "pin_all: {} addresses for {} handles",
+            addrs.len(),
+            objs.len()
+        );
+        for &obj in objs {
+            self.check_handle("pin_all", obj);
+        }
+        self.strategy.pin_all(objs, addrs);
+        for (&obj, &addr) in objs.iter().zip(addrs.iter()) {
+            self.check_addr("pin_all", obj, self.strategy.vtable(obj), addr);
+        }
+    }
+
+    fn unpin_all(&self, objs: &[S::Handle]) {
+        for &obj in objs {
+            self.check_handle("unpin_all", obj);
+        }
+        self.strategy.unpin_all(objs)
+    }
+
+    fn resolve(&self, obj: S::Handle) -> *const () {
+        self.check_resolved("resolve", obj, || self.strategy.resolve(obj))
+    }
+
+    fn pin_long_lived(&self, obj: S::Handle) -> *const () {
+        self.check_resolved("pin_long_lived", obj, || self.strategy.pin_long_lived(obj))
+    }
+
+    fn unpin_long_lived(&self, obj: S::Handle) {
+        self.check_handle("unpin_long_lived", obj);
+        self.strategy.unpin_long_lived(obj)
+    }
+
+    fn vtable(&self, obj: S::Handle) -> &'static GcVtable {
+        self.check_handle("vtable", obj);
+        self.strategy.vtable(obj)
+    }
+
+    delegate! { strategy =>
+        fn register_thread(&self) -> ThreadToken;
+        unsafe fn unregister_thread(&self, token: ThreadToken);
+        fn shrink(&self) -> ShrinkStats;
+        fn contains(&self, obj: S::Handle) -> bool;
+    }
+}
+
+// SAFETY: every method is passed through to the wrapped strategy, after checks that panic on failure.
+unsafe impl<S: GcStrategy> RootManager<S::Handle> for ValidatingStrategy<S> {
+    fn root(&self, obj: S::Handle) {
+        self.check_handle("root", obj);
+        self.strategy.root(obj)
+    }
+
+    fn unroot(&self, obj: S::Handle) {
+        self.check_handle("unroot", obj);
+        self.strategy.unroot(obj)
+    }
+
+    fn root_all(&self, objs: &[S::Handle]) {
+        for &obj in objs {
+            self.check_handle("root_all", obj);
+        }
+        self.strategy.root_all(objs)
+    }
+
+    fn unroot_all(&self, objs: &[S::Handle]) {
+        for &obj in objs {
+            self.check_handle("unroot_all", obj);
+        }
+        self.strategy.unroot_all(objs)
+    }
+
+    fn freeze(&self, obj: S::Handle) {
+        self.check_handle("freeze", obj);
+        self.strategy.freeze(obj)
+    }
+
+    delegate! { strategy =>
+        unsafe fn register_root_provider(&self, provider: &dyn RootProvider);
+        unsafe fn unregister_root_provider(&self, provider: &dyn RootProvider);
+    }
+}
+
+// SAFETY: every method is passed through to the wrapped strategy, after checks that panic on failure.
+unsafe impl<S: GcStrategy> Collector<S::Handle> for ValidatingStrategy<S> {
+    fn write_barrier(&self, parent: S::Handle, child: S::Handle) {
+        self.check_handle("write_barrier", parent);
+        self.check_handle("write_barrier", child);
+        self.strategy.write_barrier(parent, child)
+    }
+
+    fn allocate_weak(&self, target: S::Handle) -> Option<S::Handle> {
+        self.check_handle("allocate_weak", target);
+        let cell = self.strategy.allocate_weak(target)?;
+        self.check_handle("allocate_weak", cell);
+        Some(cell)
+    }
+
+    unsafe fn upgrade_weak(&self, cell: S::Handle) -> Option<S::Handle> {
+        self.check_handle("upgrade_weak", cell);
+        // SAFETY: caller
+        let target = unsafe { self.strategy.upgrade_weak(cell) }?;
+        self.check_handle("upgrade_weak", target);
+        Some(target)
+    }
+
+    fn allocate_ephemeron(&self, key: S::Handle, value: S::Handle) -> Option<S::Handle> {
+        self.check_handle("allocate_ephemeron", key);
+        self.check_handle("allocate_ephemeron", value);
+        let cell = self.strategy.allocate_ephemeron(key, value)?;
+        self.check_handle("allocate_ephemeron", cell);
+        Some(cell)
+    }
+
+    unsafe fn upgrade_ephemeron(&self, cell: S::Handle) -> Option<(S::Handle, S::Handle)> {
+        self.check_handle("upgrade_ephemeron", cell);
+        // SAFETY: caller
+        let (key, value) = unsafe { self.strategy.upgrade_ephemeron(cell) }?;
+        self.check_handle("upgrade_ephemeron", key);
+        self.check_handle("upgrade_ephemeron", value);
+        Some((key, value))
+    }
+
+    fn age(&self, obj: S::Handle) -> Option<u32> {
+        self.check_handle("age", obj);
+        self.strategy.age(obj)
+    }
+
+    fn promote(&self, obj: S::Handle) {
+        self.check_handle("promote", obj);
+        self.strategy.promote(obj)
+    }
+
+    delegate! { strategy =>
+        fn poll_safepoint(&self);
+        fn enter_safepoint(&self);
+        fn leave_safepoint(&self);
+        fn collect(&self, options: CollectOptions) -> CollectionStats;
+        fn collect_step(&self, budget: StepBudget) -> StepResult;
+        fn memory_pressure(&self, level: MemoryPressure);
+        unsafe fn register_relocation_listener(&self, listener: &dyn RelocationListener);
+        unsafe fn unregister_relocation_listener(&self, listener: &dyn RelocationListener);
+        fn stats(&self) -> HeapStats;
+    }
+}
+
+/// The lifecycle state of a GC object. See [`GcStrategy`] for the lifecycle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LifecycleState {
+    Uninitialized,
+    Initialized,
+    Finalized,
+}
+
+/// The state of a GC object as tracked by a [`DebugStrategy`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct ObjectState {
+    pub lifecycle: LifecycleState,
+    /// The number of times the object is currently rooted, not counting [freezing](RootManager::freeze).
+    pub roots: usize,
+    /// The number of times the object is currently pinned, including long-lived pins.
+    pub pins: usize,
+    /// Whether the object has been frozen, and so can never be reclaimed.
+    pub frozen: bool,
+}
+
+#[cfg(feature = "alloc")]
+impl ObjectState {
+    /// The state of a fresh allocation, which is rooted and pinned.
+    const FRESH: Self = Self {
+        lifecycle: LifecycleState::Uninitialized,
+        roots: 1,
+        pins: 1,
+        frozen: false,
+    };
+
+    /// Whether the object must not be reclaimed.
+    fn is_held(&self) -> bool {
+        self.roots > 0 || self.pins > 0 || self.frozen
+    }
+
+    /// Returns a description of the violation if the object is Finalized, or if it is an abandoned allocation: one
+    /// that is Uninitialized without a root or pin to initialize it through.
+    fn check_live(&self) -> Result<(), &'static str> {
+        match self.lifecycle {
+            LifecycleState::Finalized => Err("is Finalized"),
+            LifecycleState::Uninitialized if !self.is_held() => {
+                Err("is an abandoned Uninitialized allocation")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A strategy that tracks the state of every object of another strategy, and panics as soon as the object lifecycle
+/// is violated. The panic message names the operation, the handle, and the state of the object at that point.
+///
+/// Violations include initializing an object twice, finalizing an object that is not Initialized, unrooting or
+/// unpinning an object more times than it was rooted or pinned, using an object after it was finalized or reclaimed,
+/// reclaiming an object that is still rooted, and a collection reclaiming an object that is still rooted. Objects
+/// that a collection reclaims are forgotten afterwards, so their handles may be reused.
+///
+/// The wrapped strategy traces objects itself, so this decorator cannot see a trace of an Uninitialized object
+/// directly. Instead, it catches the heap making one traceable: an allocation abandoned while Uninitialized must not
+/// be rooted, pinned, or stored in another object, where the next collection would reach it and trace it.
+///
+/// Every operation locks the object table, so this decorator serializes the heap and makes it considerably slower.
+#[cfg(feature = "alloc")]
+pub struct DebugStrategy<S> {
+    strategy: S,
+    objects: GcMutex<BTreeMap<Handle, ObjectState>>,
+}
+
+#[cfg(feature = "alloc")]
+impl<S: GcStrategy> DebugStrategy<S> {
+    /// Tracks the objects of `strategy`.
+    pub fn new(strategy: S) -> Self {
+        Self {
+            strategy,
+            objects: GcMutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// The wrapped strategy.
+    pub fn strategy(&self) -> &S {
+        &self.strategy
+    }
+
+    /// Returns the tracked state of the given object, or None if it is not a live object of the strategy.
+    pub fn state(&self, obj: S::Handle) -> Option<ObjectState> {
+        self.objects.lock().get(&obj.into_raw()).copied()
+    }
+
+    /// The number of live objects being tracked.
+    pub fn object_count(&self) -> usize {
+        self.objects.lock().len()
+    }
+
+    /// Applies a state transition to `obj`, panicking if the object is unknown or the transition is invalid.
+    #[track_caller]
+    fn transition(
+        &self,
+        op: &str,
+        obj: S::Handle,
+        f: impl FnOnce(&mut ObjectState) -> Result<(), &'static str>,
+    ) {
+        let mut objects = self.objects.lock();
+        let Some(state) = objects.get_mut(&obj.into_raw()) else {
+            drop(objects);
+            panic!("{op}: {obj:?} is not a live object of this strategy");
+        };
+        let before = *state;
+        if let Err(violation) = f(state) {
+            drop(objects);
+            panic!("{op}: {obj:?} {violation} (state: {before:?})");
+        }
+    }
+
+    /// Starts tracking a fresh object.
+    #[track_caller]
+    fn track(&self, op: &str, obj: S::Handle, state: ObjectState) {
+        let mut objects = self.objects.lock();
+        // handles of unreachable objects may be reused, even if no collection was requested
+        if let Some(old) = objects.insert(obj.into_raw(), state) {
+            if old.is_held() {
+                drop(objects);
+                panic!(
+                    "{op}: the strategy returned {obj:?}, which is still in use (state: {old:?})"
+                );
+            }
+        }
+    }
+
+    /// Tracks the outcome of an allocation.
+    #[track_caller]
+    fn track_allocation<E>(
+        &self,
+        result: Result<FreshAllocation<S::Handle>, E>,
+    ) -> Result<FreshAllocation<S::Handle>, E> {
+        if let Ok(fresh) = &result {
+            self.track("allocate", fresh.handle, ObjectState::FRESH);
+        }
+        result
+    }
+
+    /// Forgets the objects that the strategy has reclaimed, panicking if any of them was still in use.
+    #[track_caller]
+    fn sweep(&self, op: &str) {
+        let mut objects = self.objects.lock();
+        let mut reclaimed = None;
+        objects.retain(|&handle, state| {
+            let live = self.strategy.contains(S::Handle::from_raw(handle));
+            if !live && state.is_held() {
+                reclaimed.get_or_insert((handle, *state));
+            }
+            live
+        });
+        drop(objects);
+        if let Some((handle, state)) = reclaimed {
+            panic!(
+                "{op}: the strategy reclaimed {handle}, which is still in use (state: {state:?})"
+            );
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S: fmt::Debug> fmt::Debug for DebugStrategy<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DebugStrategy")
+            .field("strategy", &self.strategy)
+            .field("objects", &self.objects)
+            .finish()
+    }
+}
+
+// SAFETY: every method is passed through to the wrapped strategy, after checks that panic on failure.
+#[cfg(feature = "alloc")]
+unsafe impl<S: GcStrategy> GcAllocator for DebugStrategy<S> {
+    type Handle = S::Handle;
+    type Error = S::Error;
+
+    fn allocate(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+    ) -> Result<FreshAllocation<Self::Handle>, Self::Error> {
+        self.track_allocation(self.strategy.allocate(vtable, len))
+    }
+
+    fn allocate_with_hint(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+        hint: AllocHint,
+    ) -> Result<FreshAllocation<Self::Handle>, Self::Error> {
+        self.track_allocation(self.strategy.allocate_with_hint(vtable, len, hint))
+    }
+
+    unsafe fn allocate_for_thread(
+        &self,
+        token: ThreadToken,
+        vtable: &'static GcVtable,
+        len: usize,
+        hint: AllocHint,
+    ) -> Result<FreshAllocation<Self::Handle>, Self::Error> {
+        // SAFETY: caller
+        self.track_allocation(unsafe {
+            self.strategy.allocate_for_thread(token, vtable, len, hint)
+        })
+    }
+
+    unsafe fn set_initialized(&self, obj: S::Handle) {
+        self.transition("set_initialized", obj, |state| {
+            if state.lifecycle != LifecycleState::Uninitialized {
+                return Err("is not Uninitialized");
+            }
+            if state.pins == 0 {
+                return Err("is not pinned, though fresh allocations are");
+            }
+            state.lifecycle = LifecycleState::Initialized;
+            state.pins -= 1;
+            Ok(())
+        });
+        // SAFETY: caller
+        unsafe { self.strategy.set_initialized(obj) }
+    }
+
+    unsafe fn set_finalized(&self, obj: S::Handle) {
+        self.transition("set_finalized", obj, |state| {
+            if state.lifecycle != LifecycleState::Initialized {
+                return Err("is not Initialized");
+            }
+            state.lifecycle = LifecycleState::Finalized;
+            Ok(())
+        });
+        // SAFETY: caller
+        unsafe { self.strategy.set_finalized(obj) }
+    }
+
+    unsafe fn reclaim(&self, obj: S::Handle) {
+        self.transition("reclaim", obj, |state| {
+            if state.lifecycle != LifecycleState::Initialized {
+                return Err("is not Initialized");
+            }
+            if state.roots > 0 || state.frozen {
+                return Err("is still rooted");
+            }
+            if state.pins != 1 {
+                return Err("is not pinned exactly once");
+            }
+            Ok(())
+        });
+        self.objects.lock().remove(&obj.into_raw());
+        // SAFETY: caller
+        unsafe { self.strategy.reclaim(obj) }
+    }
+
+    fn pin(&self, obj: S::Handle) -> *const () {
+        self.transition("pin", obj, |state| {
+            state.check_live()?;
+            state.pins += 1;
+            Ok(())
+        });
+        self.strategy.pin(obj)
+    }
+
+    fn unpin(&self, obj: S::Handle) {
+        self.transition("unpin", obj, |state| {
+            state.pins = state.pins.checked_sub(1).ok_or("is not pinned")?;
+            Ok(())
+        });
+        self.strategy.unpin(obj)
+    }
+
+    fn pin_all(&self, objs: &[S::Handle], addrs: &mut [*const ()]) {
+        for &obj in objs {
+            self.transition("pin_all", obj, |state| {
+                state.check_live()?;
+                state.pins += 1;
+                Ok(())
+            });
+        }
+        self.strategy.pin_all(objs, addrs)
+    }
+
+    fn unpin_all(&self, objs: &[S::Handle]) {
+        for &obj in objs {
+            self.transition("unpin_all", obj, |state| {
+                state.pins = state.pins.checked_sub(1).ok_or("is not pinned")?;
+                Ok(())
+            });
+        }
+        self.strategy.unpin_all(objs)
+    }
+
+    fn resolve(&self, obj: S::Handle) -> *const () {
+        self.transition("resolve", obj, |state| state.check_live());
+        self.strategy.resolve(obj)
+    }
+
+    fn pin_long_lived(&self, obj: S::Handle) -> *const () {
+        self.transition("pin_long_lived", obj, |state| {
+            state.check_live()?;
+            state.pins += 1;
+            Ok(())
+        });
+        self.strategy.pin_long_lived(obj)
+    }
+
+    fn unpin_long_lived(&self, obj: S::Handle) {
+        self.transition("unpin_long_lived", obj, |state| {
+            state.pins = state.pins.checked_sub(1).ok_or("is not pinned")?;
+            Ok(())
+        });
+        self.strategy.unpin_long_lived(obj)
+    }
+
+    fn shrink(&self) -> ShrinkStats {
+        let stats = self.strategy.shrink();
+        self.sweep("shrink");
+        stats
+    }
+
+    fn vtable(&self, obj: S::Handle) -> &'static GcVtable {
+        self.transition("vtable", obj, |_| Ok(()));
+        self.strategy.vtable(obj)
+    }
+
+    delegate! { strategy =>
+        fn register_thread(&self) -> ThreadToken;
+        unsafe fn unregister_thread(&self, token: ThreadToken);
+        fn contains(&self, obj: S::Handle) -> bool;
+    }
+}
+
+// SAFETY: every method is passed through to the wrapped strategy, after checks that panic on failure.
+#[cfg(feature = "alloc")]
+unsafe impl<S: GcStrategy> RootManager<S::Handle> for DebugStrategy<S> {
+    fn root(&self, obj: S::Handle) {
+        self.transition("root", obj, |state| {
+            state.check_live()?;
+            state.roots += 1;
+            Ok(())
+        });
+        self.strategy.root(obj)
+    }
+
+    fn unroot(&self, obj: S::Handle) {
+        self.transition("unroot", obj, |state| {
+            state.roots = state.roots.checked_sub(1).ok_or("is not rooted")?;
+            Ok(())
+        });
+        self.strategy.unroot(obj)
+    }
+
+    fn root_all(&self, objs: &[S::Handle]) {
+        for &obj in objs {
+            self.transition("root_all", obj, |state| {
+                state.check_live()?;
+                state.roots += 1;
+                Ok(())
+            });
+        }
+        self.strategy.root_all(objs)
+    }
+
+    fn unroot_all(&self, objs: &[S::Handle]) {
+        for &obj in objs {
+            self.transition("unroot_all", obj, |state| {
+                state.roots = state.roots.checked_sub(1).ok_or("is not rooted")?;
+                Ok(())
+            });
+        }
+        self.strategy.unroot_all(objs)
+    }
+
+    fn freeze(&self, obj: S::Handle) {
+        self.transition("freeze", obj, |state| {
+            state.check_live()?;
+            state.frozen = true;
+            Ok(())
+        });
+        self.strategy.freeze(obj)
+    }
+
+    delegate! { strategy =>
+        unsafe fn register_root_provider(&self, provider: &dyn RootProvider);
+        unsafe fn unregister_root_provider(&self, provider: &dyn RootProvider);
+    }
+}
+
+// SAFETY: every method is passed through to the wrapped strategy, after checks that panic on failure.
+#[cfg(feature = "alloc")]
+unsafe impl<S: GcStrategy> Collector<S::Handle> for DebugStrategy<S> {
+    fn write_barrier(&self, parent: S::Handle, child: S::Handle) {
+        self.transition("write_barrier", parent, |state| state.check_live());
+        self.transition("write_barrier", child, |state| state.check_live());
+        self.strategy.write_barrier(parent, child)
+    }
+
+    fn allocate_weak(&self, target: S::Handle) -> Option<S::Handle> {
+        self.transition("allocate_weak", target, |state| state.check_live());
+        let cell = self.strategy.allocate_weak(target)?;
+        self.track(
+            "allocate_weak",
+            cell,
+            ObjectState {
+                lifecycle: LifecycleState::Initialized,
+                pins: 0,
+                ..ObjectState::FRESH
+            },
+        );
+        Some(cell)
+    }
+
+    unsafe fn upgrade_weak(&self, cell: S::Handle) -> Option<S::Handle> {
+        self.transition("upgrade_weak", cell, |state| state.check_live());
+        // SAFETY: caller
+        let target = unsafe { self.strategy.upgrade_weak(cell) }?;
+        self.transition("upgrade_weak", target, |state| {
+            state.check_live()?;
+            state.roots += 1;
+            Ok(())
+        });
+        Some(target)
+    }
+
+    fn allocate_ephemeron(&self, key: S::Handle, value: S::Handle) -> Option<S::Handle> {
+        self.transition("allocate_ephemeron", key, |state| state.check_live());
+        self.transition("allocate_ephemeron", value, |state| state.check_live());
+        let cell = self.strategy.allocate_ephemeron(key, value)?;
+        self.track(
+            "allocate_ephemeron",
+            cell,
+            ObjectState {
+                lifecycle: LifecycleState::Initialized,
+                pins: 0,
+                ..ObjectState::FRESH
+            },
+        );
+        Some(cell)
+    }
+
+    unsafe fn upgrade_ephemeron(&self, cell: S::Handle) -> Option<(S::Handle, S::Handle)> {
+        self.transition("upgrade_ephemeron", cell, |state| state.check_live());
+        // SAFETY: caller
+        let (key, value) = unsafe { self.strategy.upgrade_ephemeron(cell) }?;
+        for obj in [key, value] {
+            self.transition("upgrade_ephemeron", obj, |state| {
+                state.check_live()?;
+                state.roots += 1;
+                Ok(())
+            });
+        }
+        Some((key, value))
+    }
+
+    fn collect(&self, options: CollectOptions) -> CollectionStats {
+        let stats = self.strategy.collect(options);
+        self.sweep("collect");
+        stats
+    }
+
+    fn collect_step(&self, budget: StepBudget) -> StepResult {
+        let result = self.strategy.collect_step(budget);
+        self.sweep("collect_step");
+        result
+    }
+
+    fn age(&self, obj: S::Handle) -> Option<u32> {
+        self.transition("age", obj, |state| state.check_live());
+        self.strategy.age(obj)
+    }
+
+    fn promote(&self, obj: S::Handle) {
+        self.transition("promote", obj, |state| state.check_live());
+        self.strategy.promote(obj)
+    }
+
+    delegate! { strategy =>
+        fn poll_safepoint(&self);
+        fn enter_safepoint(&self);
+        fn leave_safepoint(&self);
+        fn memory_pressure(&self, level: MemoryPressure);
+        unsafe fn register_relocation_listener(&self, listener: &dyn RelocationListener);
+        unsafe fn unregister_relocation_listener(&self, listener: &dyn RelocationListener);
+        fn stats(&self) -> HeapStats;
+    }
+}
+
+/// A strategy that runs a full, compacting collection before allocating in another strategy.
+///
+/// Collecting as often as possible makes bugs that depend on when collections happen show up right away: an object
+/// that is not rooted across an allocation is reclaimed or moved by it, and an edge stored without a write barrier
+/// is lost by the next collection of a strategy that relies on barriers. Every collection takes time in proportion to
+/// the heap, so this makes allocation very slow; this decorator is meant for tests. To keep larger tests fast,
+/// [`StressStrategy::with_interval`] collects before every few allocations instead.
+#[derive(Debug)]
+pub struct StressStrategy<S> {
+    strategy: S,
+    interval: usize,
+    allocations: AtomicUsize,
+}
+
+impl<S: GcStrategy> StressStrategy<S> {
+    /// Collects `strategy` before every allocation.
+    pub fn new(strategy: S) -> Self {
+        Self::with_interval(strategy, 1)
+    }
+
+    /// Collects `strategy` before every `interval`th allocation, starting with the first.
+    ///
+    /// # Panics
+    /// Panics if `interval` is zero.
+    pub fn with_interval(strategy: S, interval: usize) -> Self {
+        assert!(interval > 0, "collection interval is zero");
+        Self {
+            strategy,
+            interval,
+            allocations: AtomicUsize::new(0),
+        }
+    }
+
+    /// The wrapped strategy.
+    pub fn strategy(&self) -> &S {
+        &self.strategy
+    }
+
+    /// Collects the wrapped strategy if this allocation is due for a collection.
+    fn stress(&self) {
+        if self
+            .allocations
+            .fetch_add(1, Ordering::Relaxed)
+            .is_multiple_of(self.interval)
+        {
+            self.strategy.collect(CollectOptions {
+                kind: CollectionKind::Full,
+                compact: true,
+            });
+        }
+    }
+}
+
+impl<S: GcStrategy + Default> Default for StressStrategy<S> {
+    fn default() -> Self {
+        Self::new(S::default())
+    }
+}
+
+// SAFETY: every method is passed through to the wrapped strategy, after a collection requested through its public
+// interface.
+unsafe impl<S: GcStrategy> GcAllocator for StressStrategy<S> {
+    type Handle = S::Handle;
+    type Error = S::Error;
+
+    fn allocate(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+    ) -> Result<FreshAllocation<Self::Handle>, Self::Error> {
+        self.stress();
+        self.strategy.allocate(vtable, len)
+    }
+
+    fn allocate_with_hint(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+        hint: AllocHint,
+    ) -> Result<FreshAllocation<Self::Handle>, Self::Error> {
+        self.stress();
+        self.strategy.allocate_with_hint(vtable, len, hint)
+    }
+
+    unsafe fn allocate_for_thread(
+        &self,
+        token: ThreadToken,
+        vtable: &'static GcVtable,
+        len: usize,
+        hint: AllocHint,
+    ) -> Result<FreshAllocation<Self::Handle>, Self::Error> {
+        self.stress();
+        // SAFETY: caller
+        unsafe { self.strategy.allocate_for_thread(token, vtable, len, hint) }
+    }
+
+    delegate! { strategy =>
+        fn register_thread(&self) -> ThreadToken;
+        unsafe fn unregister_thread(&self, token: ThreadToken);
+        unsafe fn set_initialized(&self, obj: S::Handle);
+        unsafe fn set_finalized(&self, obj: S::Handle);
+        unsafe fn reclaim(&self, obj: S::Handle);
+        fn pin(&self, obj: S::Handle) -> *const ();
+        fn unpin(&self, obj: S::Handle);
+        fn pin_all(&self, objs: &[S::Handle], addrs: &mut [*const ()]);
+        fn unpin_all(&self, objs: &[S::Handle]);
+        fn resolve(&self, obj: S::Handle) -> *const ();
+        fn pin_long_lived(&self, obj: S::Handle) -> *const ();
+        fn unpin_long_lived(&self, obj: S::Handle);
+        fn shrink(&self) -> ShrinkStats;
+        fn contains(&self, obj: S::Handle) -> bool;
+        fn vtable(&self, obj: S::Handle) -> &'static GcVtable;
+    }
+}
+
+// SAFETY: every method is passed through to the wrapped strategy.
+unsafe impl<S: GcStrategy> RootManager<S::Handle> for StressStrategy<S> {
+    delegate! { strategy =>
+        fn root(&self, obj: S::Handle);
+        fn unroot(&self, obj: S::Handle);
+        fn root_all(&self, objs: &[S::Handle]);
+        fn unroot_all(&self, objs: &[S::Handle]);
+        fn freeze(&self, obj: S::Handle);
+        unsafe fn register_root_provider(&self, provider: &dyn RootProvider);
+        unsafe fn unregister_root_provider(&self, provider: &dyn RootProvider);
+    }
+}
+
+// SAFETY: every method is passed through to the wrapped strategy.
+unsafe impl<S: GcStrategy> Collector<S::Handle> for StressStrategy<S> {
+    delegate! { strategy =>
+        fn write_barrier(&self, parent: S::Handle, child: S::Handle);
+        fn allocate_weak(&self, target: S::Handle) -> Option<S::Handle>;
+        unsafe fn upgrade_weak(&self, cell: S::Handle) -> Option<S::Handle>;
+        fn allocate_ephemeron(&self, key: S::Handle, value: S::Handle) -> Option<S::Handle>;
+        unsafe fn upgrade_ephemeron(&self, cell: S::Handle) -> Option<(S::Handle, S::Handle)>;
+        fn poll_safepoint(&self);
+        fn enter_safepoint(&self);
+        fn leave_safepoint(&self);
+        fn collect(&self, options: CollectOptions) -> CollectionStats;
+        fn collect_step(&self, budget: StepBudget) -> StepResult;
+        fn memory_pressure(&self, level: MemoryPressure);
+        unsafe fn register_relocation_listener(&self, listener: &dyn RelocationListener);
+        unsafe fn unregister_relocation_listener(&self, listener: &dyn RelocationListener);
+        fn age(&self, obj: S::Handle) -> Option<u32>;
+        fn promote(&self, obj: S::Handle);
+        fn stats(&self) -> HeapStats;
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+
+    use super::*;
+    use crate::{
+        cell::GcCell,
+        strategies::{Generational, MarkSweep, Semispace},
+        testing, GcHeap,
+    };
+
+    #[test]
+    fn stats_conformance() {
+        testing::run_all(|| StatsStrategy::new(Semispace::new()));
+    }
+
+    #[test]
+    fn logging_conformance() {
+        testing::run_all(|| LoggingStrategy::new(Semispace::new(), |_| {}));
+    }
+
+    #[test]
+    fn logging_reports_operations() {
+        let events = RefCell::new(Vec::new());
+        let heap = GcHeap::new(LoggingStrategy::new(MarkSweep::new(), |event| {
+            let event = match event {
+                StrategyEvent::Allocate {
+                    type_name,
+                    size,
+                    handle,
+                    ..
+                } => (type_name, size, handle),
+                StrategyEvent::Initialize(handle) => ("initialize", 0, Some(handle)),
+                StrategyEvent::Unroot(handle) => ("unroot", 0, Some(handle)),
+                StrategyEvent::Collect { stats, .. } => ("collect", stats.objects_reclaimed, None),
+                _ => ("other", 0, None),
+            };
+            events.borrow_mut().push(event);
+        }));
+        let handle = heap.alloc(1u32).gc().strategy_handle();
+        heap.collect();
+        assert_eq!(
+            *events.borrow(),
+            [
+                ("u32", 4, Some(handle)),
+                ("initialize", 0, Some(handle)),
+                ("unroot", 0, Some(handle)),
+                ("collect", 1, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn validating_conformance() {
+        testing::run_all(|| ValidatingStrategy::new(MarkSweep::new()));
+        testing::run_all(|| ValidatingStrategy::new(Semispace::new()));
+    }
+
+    #[test]
+    #[should_panic = "pin: handle"]
+    fn validating_catches_stale_handles() {
+        let heap = GcHeap::new(ValidatingStrategy::new(MarkSweep::new()));
+        let gc = heap.alloc(1u32).gc();
+        heap.collect();
+        heap.strategy().pin(gc.strategy_handle());
+    }
+
+    #[test]
+    fn debug_conformance() {
+        testing::run_all(|| DebugStrategy::new(MarkSweep::new()));
+        testing::run_all(|| DebugStrategy::new(Generational::new()));
+    }
+
+    #[test]
+    #[should_panic = "unroot: "]
+    fn debug_catches_unrooting_without_a_root() {
+        let heap = GcHeap::new(DebugStrategy::new(MarkSweep::new()));
+        let gc = heap.alloc(1u32).gc();
+        heap.strategy().unroot(gc.strategy_handle());
+    }
+
+    #[test]
+    #[should_panic = "is not a live object of this strategy"]
+    fn debug_catches_use_after_reclaim() {
+        let heap = GcHeap::new(DebugStrategy::new(MarkSweep::new()));
+        let gc = heap.alloc(1u32).gc();
+        heap.collect();
+        heap.strategy().root(gc.strategy_handle());
+    }
+
+    #[test]
+    #[should_panic = "is an abandoned Uninitialized allocation"]
+    fn debug_catches_reachable_uninitialized_objects() {
+        let heap = GcHeap::new(DebugStrategy::new(MarkSweep::new()));
+        let holder = heap.alloc(GcCell::new(None));
+        // the allocation is abandoned along with its root
+        let gc = heap.alloc_uninit::<u32>().gc();
+        holder.set(&holder, Some(gc));
+    }
+
+    #[test]
+    fn stress_conformance() {
+        testing::run_all(|| StressStrategy::new(MarkSweep::new()));
+        testing::run_all(|| StressStrategy::new(Semispace::new()));
+        testing::run_all(|| StressStrategy::with_interval(Generational::new(), 3));
+        testing::run_all(|| {
+            DebugStrategy::new(ValidatingStrategy::new(StressStrategy::new(
+                Semispace::new(),
+            )))
+        });
+    }
+
+    #[test]
+    fn stress_collects_before_allocating() {
+        let heap = GcHeap::new(StressStrategy::new(StatsStrategy::new(MarkSweep::new())));
+        let root = heap.alloc(1u32);
+        for value in 0..10u32 {
+            heap.alloc(value);
+        }
+        let counters = heap.strategy().strategy().counters();
+        assert_eq!(counters.collections, 11);
+        assert_eq!(heap.stats().object_count, 2);
+        assert_eq!(*root, 1);
+
+        let heap = GcHeap::new(StressStrategy::with_interval(
+            StatsStrategy::new(MarkSweep::new()),
+            4,
+        ));
+        for value in 0..10u32 {
+            heap.alloc(value);
+        }
+        // before the first, fifth, and ninth allocations
+        assert_eq!(heap.strategy().strategy().counters().collections, 3);
+    }
+
+    #[test]
+    #[should_panic = "collection interval is zero"]
+    fn stress_interval_must_not_be_zero() {
+        StressStrategy::with_interval(MarkSweep::new(), 0);
+    }
+}
@@ -1,31 +1,500 @@
-use core::{ptr::NonNull, alloc::Layout};
+use core::{
+    ptr::NonNull, alloc::Layout, num::NonZeroUsize, any::{type_name, TypeId}, marker::PhantomData, fmt, time::Duration,
+};
 
-use crate::trace::{TraceContext, Trace};
+use crate::{ptr::{GcPointee, HeaderSlice}, trace::{EdgeContext, RootProvider, TraceContext, Trace}};
 
 
 
 /// Functionality implemented by individual GC objects. This includes the finalizer and tracing methods.
 pub struct GcVtable {
-    /// The size and alignment of the GC allocation.
+    /// The size and alignment of the GC allocation, or of each element for slice types.
     layout: Layout,
-    /// Marking functionality for a GC type.
+    /// Whether the GC type is a slice, whose length is chosen at allocation time.
+    is_slice: bool,
+    /// The layout of the fields that precede the elements of a slice type, see [`HeaderSlice`]. This is empty for
+    /// plain slices, and unused for other types.
+    header: Layout,
+    /// Marking functionality for a GC type. The second parameter is the length of slice types.
     /// # Safety
     /// This function must be called on a value of compatible that is valid for shared access.
-    trace: unsafe fn(NonNull<()>, &TraceContext<'_>),
+    trace: unsafe fn(NonNull<()>, usize, &mut TraceContext<'_>),
+    /// Edge updating functionality for a GC type. The second parameter is the length of slice types. If this is
+    /// None, every edge is fixed, and visited with the trace function.
+    /// # Safety
+    /// This function must be called on a value of compatible type that is valid for exclusive access.
+    trace_edges: Option<unsafe fn(NonNull<()>, usize, &mut EdgeContext<'_>)>,
+    /// Whether the trace function needs to be called at all. See [`Trace::NEEDS_TRACE`].
+    needs_trace: bool,
+    /// The drop glue of the GC type, or None if it has none. The second parameter is the length of slice types.
+    /// # Safety
+    /// This function must be called on a value of compatible type that is valid for exclusive access, and the value
+    /// must not be used afterwards.
+    finalize: Option<unsafe fn(NonNull<()>, usize)>,
+    /// The type ID of the GC type. This is a function because GC types need not be `'static`.
+    type_id: fn() -> TypeId,
+    /// The name of the GC type, for diagnostics.
+    type_name: TypeName,
+    /// Whether the GC type may only be finalized on the thread that allocated it.
+    is_local: bool,
+}
+
+impl fmt::Debug for GcVtable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GcVtable")
+            .field("type_name", &self.type_name())
+            .field("layout", &self.layout)
+            .field("is_slice", &self.is_slice)
+            .field("needs_trace", &self.needs_trace)
+            .field("needs_finalize", &self.needs_finalize())
+            .field("is_local", &self.is_local)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The name of a GC type, which is computed at run time for Rust types as `type_name` is not yet `const`.
+#[derive(Clone, Copy)]
+enum TypeName {
+    Static(fn() -> &'static str),
+    Dynamic(&'static str),
+}
+
+/// A builder for the vtable of a type defined at run time, created by [`GcVtable::builder`]. Every method is
+/// `const`, so the vtables of types known ahead of time can be built in a `static`. Others must be leaked, as
+/// allocation requires a `&'static GcVtable`.
+#[derive(Debug)]
+pub struct GcVtableBuilder {
+    vtable: GcVtable,
+}
+
+impl GcVtableBuilder {
+    /// Makes the type a slice type, whose objects consist of the fields given by the layout passed to
+    /// [`GcVtable::builder`], followed by a number of elements with the given layout that is chosen at allocation
+    /// time.
+    pub const fn elements(mut self, element: Layout) -> Self {
+        self.vtable.header = self.vtable.layout;
+        self.vtable.layout = element;
+        self.vtable.is_slice = true;
+        self
+    }
+
+    /// Sets the function that marks the GC objects directly reachable from an object, see [`GcVtable::trace`]. The
+    /// second parameter is the length of slice types. Without one, objects of the type contain no GC objects.
+    pub const fn trace(mut self, trace: unsafe fn(NonNull<()>, usize, &mut TraceContext<'_>)) -> Self {
+        self.vtable.trace = trace;
+        self.vtable.needs_trace = true;
+        self
+    }
+
+    /// Sets the function that visits the GC handles of an object so that they can be updated in place, see
+    /// [`GcVtable::trace_edges`]. It must visit the same edges as the trace function. Without one, every edge is
+    /// visited as fixed, so a moving strategy cannot relocate the objects the type refers to.
+    pub const fn trace_edges(mut self, trace_edges: unsafe fn(NonNull<()>, usize, &mut EdgeContext<'_>)) -> Self {
+        self.vtable.trace_edges = Some(trace_edges);
+        self
+    }
+
+    /// Sets the finalizer of the type, see [`GcVtable::finalize`]. The second parameter is the length of slice
+    /// types. Without one, objects of the type are reclaimed without being finalized.
+    pub const fn finalize(mut self, finalize: unsafe fn(NonNull<()>, usize)) -> Self {
+        self.vtable.finalize = Some(finalize);
+        self
+    }
+
+    /// Marks objects of the type as thread-local, see [`GcVtable::is_local`].
+    pub const fn local(mut self) -> Self {
+        self.vtable.is_local = true;
+        self
+    }
+
+    /// Returns the vtable, which reports the name passed to [`GcVtable::builder`] and the type ID of
+    /// [`DynamicObject`]. This never panics, as any combination of the settings above makes a valid vtable. It is up to
+    /// the functions that were set to uphold the contracts of [`GcVtable::trace`], [`GcVtable::trace_edges`], and
+    /// [`GcVtable::finalize`], which is why allocating with the vtable is `unsafe` (see
+    /// [`GcHeap::alloc_dynamic`](crate::GcHeap::alloc_dynamic)).
+    pub const fn build(self) -> GcVtable {
+        self.vtable
+    }
+}
+
+/// The type of objects allocated with a vtable from [`GcVtable::builder`], whose type is not known to Rust. The
+/// contents of such objects are accessed through their address, e.g. with [`Root::as_ptr`](crate::Root::as_ptr).
+/// Their vtables report the type ID of `DynamicObject`.
+#[repr(C)]
+pub struct DynamicObject {
+    _private: [u8; 0],
+}
+
+impl fmt::Debug for DynamicObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DynamicObject")
+    }
+}
+
+/// Returns the type ID of `T`, which need not be `'static`. Lifetimes are erased from type IDs, so `T` has the same
+/// type ID as `T` with all its lifetimes replaced by `'static`.
+///
+/// This is only used for [`GcVtable::type_id`], because GC types need not be `'static`. A matching type ID therefore
+/// only proves that an object is of some type `U: 'static` if the object's type is known to be `'static` as well, as
+/// for objects behind a `Gc<dyn Any>`: otherwise it may be `U` with shorter lifetimes.
+fn type_id_of<T: ?Sized>() -> TypeId {
+    trait NonStaticAny {
+        fn type_id(&self) -> TypeId where Self: 'static;
+    }
+
+    impl<T: ?Sized> NonStaticAny for PhantomData<T> {
+        fn type_id(&self) -> TypeId where Self: 'static {
+            TypeId::of::<T>()
+        }
+    }
+
+    let phantom = PhantomData::<T>;
+    let phantom: &dyn NonStaticAny = &phantom;
+    // SAFETY: extending the lifetime bound of the trait object does not extend the lifetime of any data, as a
+    // `PhantomData` holds none, and the method only passes `T` to `TypeId::of`, which requires `T: 'static` but does
+    // not depend on the lifetimes in `T`.
+    let phantom = unsafe { core::mem::transmute::<&dyn NonStaticAny, &(dyn NonStaticAny + 'static)>(phantom) };
+    phantom.type_id()
 }
 
 impl GcVtable {
-    pub const fn for_type<T: Trace>() -> &'static Self {
+    const fn sized<T: Trace>(is_local: bool) -> Self {
+        Self {
+            layout: Layout::new::<T>(),
+            is_slice: false,
+            header: Layout::new::<()>(),
+            trace: |ptr, _, ctx| /* Safety: caller */ unsafe { ptr.cast::<T>().as_ref().trace(ctx) },
+            trace_edges: Some(|ptr, _, ctx| /* Safety: caller */ unsafe { ptr.cast::<T>().as_mut().trace_edges(ctx) }),
+            needs_trace: T::NEEDS_TRACE,
+            finalize: if core::mem::needs_drop::<T>() {
+                Some(|ptr, _| /* Safety: caller */ unsafe { ptr.cast::<T>().drop_in_place() })
+            } else {
+                None
+            },
+            type_id: type_id_of::<T>,
+            type_name: TypeName::Static(type_name::<T>),
+            is_local,
+        }
+    }
+
+    pub const fn for_type<T: Trace + Send>() -> &'static Self {
+        const { &Self::sized::<T>(false) }
+    }
+
+    /// The vtable for a type that is not `Send`, whose objects must be finalized on the thread that allocated them.
+    /// See [`GcVtable::is_local`].
+    pub const fn for_local_type<T: Trace>() -> &'static Self {
+        const { &Self::sized::<T>(true) }
+    }
+
+    pub const fn for_slice<T: Trace + Send>() -> &'static Self {
+        const {
+            &Self {
+                layout: Layout::new::<T>(),
+                is_slice: true,
+                header: Layout::new::<()>(),
+                trace: |ptr, len, ctx| /* Safety: caller */ unsafe {
+                    NonNull::slice_from_raw_parts(ptr.cast::<T>(), len).as_ref().trace(ctx)
+                },
+                trace_edges: Some(|ptr, len, ctx| /* Safety: caller */ unsafe {
+                    NonNull::slice_from_raw_parts(ptr.cast::<T>(), len).as_mut().trace_edges(ctx)
+                }),
+                needs_trace: T::NEEDS_TRACE,
+                finalize: if core::mem::needs_drop::<T>() {
+                    Some(|ptr, len| /* Safety: caller */ unsafe {
+                        NonNull::slice_from_raw_parts(ptr.cast::<T>(), len).drop_in_place()
+                    })
+                } else {
+                    None
+                },
+                type_id: type_id_of::<[T]>,
+                type_name: TypeName::Static(type_name::<[T]>),
+                is_local: false,
+            }
+        }
+    }
+
+    /// The vtable for a [`HeaderSlice`], whose number of elements is chosen at allocation time.
+    pub const fn for_header_slice<H: Trace + Send, T: Trace + Send>() -> &'static Self {
         const {
             &Self {
                 layout: Layout::new::<T>(),
-                trace: |ptr, ctx| /* Safety: caller */ unsafe { ptr.cast::<T>().as_ref().trace(ctx) },
+                is_slice: true,
+                header: Layout::new::<H>(),
+                trace: |ptr, len, ctx| /* Safety: caller */ unsafe {
+                    HeaderSlice::<H, T>::from_raw_parts(ptr, len).as_ref().trace(ctx)
+                },
+                trace_edges: Some(|ptr, len, ctx| /* Safety: caller */ unsafe {
+                    HeaderSlice::<H, T>::from_raw_parts(ptr, len).as_mut().trace_edges(ctx)
+                }),
+                needs_trace: H::NEEDS_TRACE || T::NEEDS_TRACE,
+                finalize: if core::mem::needs_drop::<HeaderSlice<H, T>>() {
+                    Some(|ptr, len| /* Safety: caller */ unsafe {
+                        HeaderSlice::<H, T>::from_raw_parts(ptr, len).drop_in_place()
+                    })
+                } else {
+                    None
+                },
+                type_id: type_id_of::<HeaderSlice<H, T>>,
+                type_name: TypeName::Static(type_name::<HeaderSlice<H, T>>),
+                is_local: false,
             }
         }
     }
+
+    pub const fn for_str() -> &'static Self {
+        const {
+            &Self {
+                layout: Layout::new::<u8>(),
+                is_slice: true,
+                header: Layout::new::<()>(),
+                trace: |_, _, _| {},
+                trace_edges: None,
+                needs_trace: false,
+                finalize: None,
+                type_id: type_id_of::<str>,
+                type_name: TypeName::Static(type_name::<str>),
+                is_local: false,
+            }
+        }
+    }
+
+    /// Starts building a vtable for a type defined at run time, such as a class of an interpreted language, whose
+    /// objects have the given name and layout. Allocate objects of such types with
+    /// [`GcHeap::alloc_dynamic`](crate::GcHeap::alloc_dynamic).
+    ///
+    /// The functions of the vtable cannot capture any state, so per-type data such as a table of field offsets is
+    /// typically reached through the object itself, e.g. through a pointer to its class in its first field.
+    pub const fn builder(type_name: &'static str, layout: Layout) -> GcVtableBuilder {
+        GcVtableBuilder {
+            vtable: Self {
+                layout,
+                is_slice: false,
+                header: Layout::new::<()>(),
+                trace: |_, _, _| {},
+                trace_edges: None,
+                needs_trace: false,
+                finalize: None,
+                type_id: type_id_of::<DynamicObject>,
+                type_name: TypeName::Dynamic(type_name),
+                is_local: false,
+            },
+        }
+    }
+
+    /// The size and alignment of GC objects using this vtable, given the length of the object if it is a slice.
+    /// Returns `None` if the size of the slice overflows.
+    ///
+    /// The layout is computed at run time for slice types, which include strings and [`HeaderSlice`]s, so a single
+    /// vtable serves objects of many different sizes.
+    pub const fn layout(&self, len: usize) -> Option<Layout> {
+        if !self.is_slice {
+            return Some(self.layout);
+        }
+        let Some(size) = self.layout.size().checked_mul(len) else {
+            return None;
+        };
+        let Ok(elements) = Layout::from_size_align(size, self.layout.align()) else {
+            return None;
+        };
+        match self.header.extend(elements) {
+            Ok((layout, _)) => Some(layout.pad_to_align()),
+            Err(_) => None,
+        }
+    }
+
+    /// Whether GC objects using this vtable are slices, whose length is chosen when they are allocated.
+    pub const fn is_slice(&self) -> bool {
+        self.is_slice
+    }
+
+    /// Whether GC objects using this vtable may contain other GC objects. If this is `false`, the strategy may skip
+    /// tracing them entirely.
+    pub const fn needs_trace(&self) -> bool {
+        self.needs_trace
+    }
+
+    /// Whether GC objects using this vtable have a finalizer, i.e. drop glue. If this is `false`,
+    /// [`GcVtable::finalize`] does nothing, and the strategy may reclaim unreachable objects without finalizing them.
+    pub const fn needs_finalize(&self) -> bool {
+        self.finalize.is_some()
+    }
+
+    /// Whether GC objects using this vtable are thread-local, as their type is not `Send`. The strategy must finalize
+    /// thread-local objects on the thread that allocated them, e.g. by queueing them for that thread to finalize at
+    /// its next safepoint. If that thread has exited, or the heap is destroyed on another thread, the objects must be
+    /// leaked instead.
+    pub const fn is_local(&self) -> bool {
+        self.is_local
+    }
+
+    /// The type ID of the type this vtable was created for. Lifetimes are erased, so types that differ only in
+    /// their lifetimes have the same type ID, and a matching type ID only proves that an object is of a `'static` type
+    /// `U` if the object's type is known to be `'static`.
+    pub fn type_id(&self) -> TypeId {
+        (self.type_id)()
+    }
+
+    /// The name of the type this vtable was created for, as given by [`core::any::type_name`]. This is meant for
+    /// diagnostics such as heap dumps, and is not guaranteed to be unique or stable across compiler versions.
+    pub fn type_name(&self) -> &'static str {
+        match self.type_name {
+            TypeName::Static(type_name) => type_name(),
+            TypeName::Dynamic(type_name) => type_name,
+        }
+    }
+
+    /// Mark all GC objects directly reachable from the given GC object.
+    ///
+    /// # Safety
+    /// `ptr` must point to an initialized value of the type this vtable was created for, and the value must be
+    /// valid for shared access. For slice types, `len` must be the length the object was allocated with.
+    pub unsafe fn trace(&self, ptr: NonNull<()>, len: usize, ctx: &mut TraceContext<'_>) {
+        // SAFETY: caller
+        unsafe { (self.trace)(ptr, len, ctx) }
+    }
+
+    /// Visit all GC handles directly reachable from the given GC object, allowing them to be updated in place.
+    ///
+    /// # Safety
+    /// `ptr` must point to an initialized value of the type this vtable was created for, and the value must be
+    /// valid for exclusive access. For slice types, `len` must be the length the object was allocated with.
+    pub unsafe fn trace_edges(&self, ptr: NonNull<()>, len: usize, ctx: &mut EdgeContext<'_>) {
+        match self.trace_edges {
+            // SAFETY: caller
+            Some(trace_edges) => unsafe { trace_edges(ptr, len, ctx) },
+            // SAFETY: caller; exclusive access implies shared access
+            None => unsafe { (self.trace)(ptr, len, ctx.fixed()) },
+        }
+    }
+
+    /// Runs the finalizer of the given GC object, dropping its value in place. See [`GcAllocator::set_finalized`]
+    /// for when strategies call this.
+    ///
+    /// # Safety
+    /// `ptr` must point to an initialized value of the type this vtable was created for, and the value must be
+    /// valid for exclusive access. For slice types, `len` must be the length the object was allocated with. The
+    /// value must not be traced or otherwise used afterwards, and for [local](GcVtable::is_local) types this must be
+    /// called on the thread that allocated the object.
+    pub unsafe fn finalize(&self, ptr: NonNull<()>, len: usize) {
+        if let Some(finalize) = self.finalize {
+            // SAFETY: caller
+            unsafe { finalize(ptr, len) }
+        }
+    }
+}
+
+/// An opaque reference to a GC allocation, in the type-erased form stored in a [`Gc`](crate::Gc) and passed to
+/// [`GcVisitor`](crate::trace::GcVisitor)s. Handles identify allocations, and are not necessarily addresses.
+///
+/// Handles are never zero, so that `Option<Gc<T>>` is the same size as `Gc<T>`. Strategies that use addresses as
+/// handles get this for free; table-based strategies must offset their indices, e.g. by storing `index + 1`.
+pub type Handle = NonZeroUsize;
+
+/// The handle type of a strategy, see [`GcAllocator::Handle`].
+///
+/// A [`Gc`](crate::Gc) does not know which strategy its object was allocated by, so strategy handles are stored as
+/// a raw [`Handle`]. A strategy handle must therefore fit in a non-zero word, e.g. an address, or a table index
+/// packed together with a generation count as in [`GenerationalHandle`].
+pub trait StrategyHandle: Copy + Eq + fmt::Debug {
+    /// Converts this handle into its raw form. Distinct handles must have distinct raw forms.
+    fn into_raw(self) -> Handle;
+
+    /// Converts a raw handle back into a strategy handle. `raw` is normally a value returned by
+    /// [`StrategyHandle::into_raw`], but handles passed to the wrong heap may be arbitrary, in which case this may
+    /// panic or return any handle.
+    fn from_raw(raw: Handle) -> Self;
+}
+
+impl StrategyHandle for Handle {
+    fn into_raw(self) -> Handle {
+        self
+    }
+
+    fn from_raw(raw: Handle) -> Self {
+        raw
+    }
+}
+
+/// A strategy handle made of a table index and a generation count, for strategies that keep their objects in
+/// slots which are reused after reclamation.
+///
+/// The strategy stores the current generation of each slot, and bumps it whenever the slot's object is reclaimed.
+/// A handle to a reclaimed object then carries an old generation, so its use can be detected by comparing the
+/// handle's generation with the slot's using [`GenerationalHandle::check`], instead of silently aliasing whichever
+/// object occupies the slot now. The generation wraps around, so detection is certain only for handles that are
+/// less than [`GenerationalHandle::MAX_GENERATION`] reuses out of date.
+///
+/// The generation is stored in the low [`GenerationalHandle::GENERATION_BITS`] bits of the raw handle, and the index
+/// plus one in the rest.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GenerationalHandle(Handle);
+
+impl GenerationalHandle {
+    /// The number of bits used for the generation: 16 on 64-bit targets, and 8 on 32-bit targets.
+    pub const GENERATION_BITS: u32 = usize::BITS / 4;
+    /// The largest generation, after which generations wrap around to zero.
+    pub const MAX_GENERATION: u32 = (1 << Self::GENERATION_BITS) - 1;
+    /// The largest index a handle can hold.
+    pub const MAX_INDEX: usize = (usize::MAX >> Self::GENERATION_BITS) - 1;
+
+    /// Creates a handle for the given slot index and generation. Generations above
+    /// [`GenerationalHandle::MAX_GENERATION`] wrap around.
+    ///
+    /// # Panics
+    /// Panics if `index` is greater than [`GenerationalHandle::MAX_INDEX`].
+    pub const fn new(index: usize, generation: u32) -> Self {
+        assert!(index <= Self::MAX_INDEX, "slot index out of range");
+        let raw = (index + 1) << Self::GENERATION_BITS | (generation & Self::MAX_GENERATION) as usize;
+        match Handle::new(raw) {
+            Some(raw) => Self(raw),
+            None => unreachable!(),
+        }
+    }
+
+    /// The slot index of this handle.
+    pub const fn index(self) -> usize {
+        (self.0.get() >> Self::GENERATION_BITS) - 1
+    }
+
+    /// The generation of this handle.
+    pub const fn generation(self) -> u32 {
+        (self.0.get() & Self::MAX_GENERATION as usize) as u32
+    }
+
+    /// Returns the handle for the next object in the same slot, which has the next generation.
+    pub const fn next_generation(self) -> Self {
+        Self::new(self.index(), self.generation().wrapping_add(1))
+    }
+
+    /// Checks that this handle is not stale, given the current generation of its slot, which is wrapped around as in
+    /// [`GenerationalHandle::new`]. Strategies call this whenever they look up a handle.
+    ///
+    /// # Panics
+    /// Panics if debug assertions are enabled and the generations differ. Otherwise, this does nothing.
+    #[track_caller]
+    pub fn check(self, current: u32) {
+        if cfg!(debug_assertions) && self.generation() != current & Self::MAX_GENERATION {
+            panic!("stale handle {self:?}: the object in slot {} has generation {current}", self.index());
+        }
+    }
 }
 
-pub type Handle = usize;
+impl fmt::Debug for GenerationalHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}v{}", self.index(), self.generation())
+    }
+}
+
+impl StrategyHandle for GenerationalHandle {
+    fn into_raw(self) -> Handle {
+        self.0
+    }
+
+    fn from_raw(raw: Handle) -> Self {
+        Self(raw)
+    }
+}
 
 /// A GC strategy. This trait defines how to allocate and manage a GC heap and the objects contained within it.
 ///
@@ -47,48 +516,633 @@ pub type Handle = usize;
 /// In general, the GC heap should not directly access the values it holds, except to invoke methods of the [`Trace`] trait.
 /// This includes the value's drop glue. Dropping should be handled using the object's associated finalization queue, if any.
 ///
+/// # Object Identity
+/// The handle of a GC object identifies it for its entire lifetime, including while it is being moved by a compacting
+/// GC. Two live objects never share a handle, though the handle of a reclaimed object may be reused for a new one.
+/// Object addresses, on the other hand, are only stable while the object is pinned, so identity must always be
+/// determined by comparing handles. Strategies that reuse handles can detect the use of stale ones with a
+/// [`GenerationalHandle`].
+///
 /// # Heap Ownership
 /// The GC heap uniquely owns and manages all GC objects. When the heap is destroyed, all GC allocations controlled by the
 /// heap must also be destroyed. It is not permissible to share GC allocations among several heaps or store GC allocations
 /// in borrowed storage. At the very least, implementations must not invoke [`Trace::trace`] on GC objects allocated
 /// within the heap after the heap has been destroyed.
 ///
+/// # Composition
+/// The functionality of a strategy is split among three traits, so that each part can be implemented and reused on its
+/// own: [`GcAllocator`] manages the memory of GC allocations, [`RootManager`] keeps track of roots, and [`Collector`]
+/// determines which allocations are reachable and reclaims the rest. `GcStrategy` is implemented for every type that
+/// implements all three with the same handle type.
+pub trait GcStrategy:
+    GcAllocator + RootManager<<Self as GcAllocator>::Handle> + Collector<<Self as GcAllocator>::Handle>
+{
+}
+
+impl<S: ?Sized + GcAllocator + RootManager<S::Handle> + Collector<S::Handle>> GcStrategy for S {}
+
+/// The part of a [`GcStrategy`] that manages the memory of GC allocations: allocating, pinning, and reclaiming them.
+///
 /// # Safety
-/// Implementations of this trait must uphold the contracts of all defined methods, as well as the trait documentation.
-pub unsafe trait GcStrategy {
+/// Implementations of this trait must uphold the contracts of all defined methods, as well as the documentation of
+/// [`GcStrategy`].
+pub unsafe trait GcAllocator {
+    /// The handle type of this strategy, which identifies its GC allocations. Use [`Handle`] itself if the
+    /// strategy has no need for a different type.
+    type Handle: StrategyHandle;
+
+    /// The error returned when an allocation fails. Use [`AllocError`] itself if the strategy has no need to report
+    /// more detail. Invalid layouts are detected by the heap before calling the strategy, and are reported by
+    /// converting [`AllocError::InvalidLayout`] into this type.
+    type Error: From<AllocError> + fmt::Debug + fmt::Display;
+
     /// Allocate memory on the GC heap for a GC node with the given vtable. The returned GC allocation is rooted and
     /// pinned, and in an Uninitialized state in preparation for a value to be written.
     ///
-    /// Returns None if there is not enough heap space for the node, otherwise returns a handle representing the node.
-    fn allocate(&self, vtable: &'static GcVtable) -> Option<FreshAllocation>;
+    /// The size of the allocation is given by [`GcVtable::layout`] for `len`, which is guaranteed not to overflow.
+    /// If the vtable is for a slice type, the size is chosen at run time, and the strategy must remember `len` and
+    /// pass it to the vtable's tracing methods. Otherwise, `len` is always 1.
+    ///
+    /// Returns an error if the node cannot be allocated, e.g. [`AllocError::OutOfMemory`] if there is not enough heap
+    /// space for it. Otherwise, returns a handle representing the node.
+    ///
+    /// If the allocation is unpinned and unrooted while still Uninitialized, it is abandoned. The strategy must not
+    /// trace or finalize it, but may reclaim its memory.
+    fn allocate(&self, vtable: &'static GcVtable, len: usize) -> Result<FreshAllocation<Self::Handle>, Self::Error>;
+
+    /// Allocates memory on the GC heap as [`GcAllocator::allocate`], taking into account a hint about how the
+    /// allocation will be used. Hints never change the semantics of the allocation, so strategies may ignore them.
+    /// The default implementation calls [`GcAllocator::allocate`].
+    fn allocate_with_hint(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+        hint: AllocHint,
+    ) -> Result<FreshAllocation<Self::Handle>, Self::Error> {
+        let _ = hint;
+        self.allocate(vtable, len)
+    }
+
+    /// Registers the calling thread as a mutator of the heap, returning a token that identifies it. Strategies that
+    /// keep per-thread state, such as thread-local allocation buffers or the set of threads to stop at a safepoint,
+    /// set it up here. The default implementation returns a token with an ID of 0.
+    fn register_thread(&self) -> ThreadToken {
+        ThreadToken::new(0)
+    }
+
+    /// Unregisters a thread registered with [`GcAllocator::register_thread`], releasing its per-thread state. The
+    /// default implementation does nothing.
+    ///
+    /// # Safety
+    /// `token` must have been returned by [`GcAllocator::register_thread`] on the calling thread, and must not have
+    /// been unregistered already.
+    unsafe fn unregister_thread(&self, token: ThreadToken) {
+        let _ = token;
+    }
+
+    /// Allocates memory on the GC heap as [`GcAllocator::allocate_with_hint`], on behalf of a registered thread.
+    /// Strategies may serve the allocation from the thread's own state without synchronization. The default
+    /// implementation calls [`GcAllocator::allocate_with_hint`].
+    ///
+    /// # Safety
+    /// `token` must have been returned by [`GcAllocator::register_thread`] on the calling thread, and must not have
+    /// been unregistered yet.
+    unsafe fn allocate_for_thread(
+        &self,
+        token: ThreadToken,
+        vtable: &'static GcVtable,
+        len: usize,
+        hint: AllocHint,
+    ) -> Result<FreshAllocation<Self::Handle>, Self::Error> {
+        let _ = token;
+        self.allocate_with_hint(vtable, len, hint)
+    }
 
     /// Marks the given GC allocation initialized. This unpins the allocation (but keeps it rooted) and sets its state
     /// to Initialized.
     ///
     /// # Safety
-    /// The GC allocation must be in the Uninitialized state (that is, having just been returned from [`GcStrategy::allocate`]).
-    unsafe fn set_initialized(&self, obj: Handle);
+    /// The GC allocation must be in the Uninitialized state (that is, having just been returned from [`GcAllocator::allocate`]).
+    unsafe fn set_initialized(&self, obj: Self::Handle);
 
     /// Marks the given GC allocation as finalized. This notifies the GC that the allocation may be reclaimed.
     ///
+    /// Strategies finalize unreachable Initialized objects in one of two ways. If the object is registered with a
+    /// finalization queue, the strategy passes it to the queue, which runs the finalizer and then calls this method.
+    /// Otherwise, the strategy runs the finalizer itself with [`GcVtable::finalize`], after the collection that found
+    /// the object unreachable has finished tracing, and then treats the object as Finalized without calling this
+    /// method. Either way, the finalizer runs exactly once, before the memory of the object is reclaimed. It never
+    /// runs for Uninitialized objects, or for objects passed to [`GcAllocator::reclaim`], whose values have already
+    /// been dropped.
+    ///
     /// # Safety
     /// The GC allocation must have been previously determined to be finalizable (e.g. by being passed to a finalization queue).
-    unsafe fn set_finalized(&self, obj: Handle);
+    unsafe fn set_finalized(&self, obj: Self::Handle);
 
-    fn pin(&self, obj: Handle) -> *const ();
+    /// Reclaims the given GC allocation immediately, rather than waiting for a collection to find it unreachable.
+    /// The value has already been dropped by the caller, so the strategy must not trace the allocation again or pass
+    /// it to a finalization queue. The allocation is pinned exactly once by the caller, which the strategy releases
+    /// along with the allocation.
+    ///
+    /// The strategy must clear the weak cells referring to the allocation, and the ephemerons whose key or value it
+    /// is, before this method returns, so that upgrading them afterwards returns None rather than a handle to a
+    /// dropped value, or to whatever object reuses its memory.
+    ///
+    /// The default implementation unpins the allocation and marks it Finalized, leaving its memory to be reclaimed by
+    /// the next collection. It does not clear weak cells or ephemerons, so strategies that support them must override
+    /// this method.
+    ///
+    /// # Safety
+    /// The GC allocation must be Initialized, but its value must have been dropped. It must not be rooted, must not be
+    /// pinned other than by the caller, and must not be reachable from any other GC allocation.
+    unsafe fn reclaim(&self, obj: Self::Handle) {
+        self.unpin(obj);
+        // SAFETY: the allocation is unreachable and its value has been dropped
+        unsafe { self.set_finalized(obj) };
+    }
 
-    fn unpin(&self, obj: Handle);
+    /// Pins the given GC allocation, returning its address. The allocation will not be moved until it is unpinned.
+    /// Pins nest: an allocation that has been pinned several times stays pinned until it has been unpinned as many
+    /// times. The returned address must not be null.
+    fn pin(&self, obj: Self::Handle) -> *const ();
+
+    /// Removes a pin from the given GC allocation.
+    fn unpin(&self, obj: Self::Handle);
+
+    /// Pins each of the given GC allocations as [`GcAllocator::pin`], writing their addresses to the corresponding
+    /// elements of `addrs`, which has the same length as `objs`. Strategies may override this to pin a batch of
+    /// allocations with a single synchronized operation. The default implementation pins each allocation in turn.
+    fn pin_all(&self, objs: &[Self::Handle], addrs: &mut [*const ()]) {
+        for (&obj, addr) in objs.iter().zip(addrs) {
+            *addr = self.pin(obj);
+        }
+    }
 
+    /// Removes a pin from each of the given GC allocations. The default implementation unpins each allocation in
+    /// turn.
+    fn unpin_all(&self, objs: &[Self::Handle]) {
+        for &obj in objs {
+            self.unpin(obj);
+        }
+    }
+
+    /// Returns the current address of the given GC allocation, without pinning it. The address is only valid until
+    /// the strategy next moves objects, so it must not be dereferenced across allocations, collections, or
+    /// safepoints. Concurrent copying strategies implement their read barrier here, e.g. by returning the address of
+    /// the to-space copy of the allocation, copying it first if necessary. The returned address must not be null.
+    ///
+    /// The default implementation pins and immediately unpins the allocation.
+    fn resolve(&self, obj: Self::Handle) -> *const () {
+        let addr = self.pin(obj);
+        self.unpin(obj);
+        addr
+    }
+
+    /// Pins the given GC allocation for an indefinite time, such as while its address is held by foreign code. This
+    /// is used by [`PinnedPtr`](crate::PinnedPtr), and follows the same rules as [`GcAllocator::pin`].
+    ///
+    /// Strategies may override this to keep count of long-lived pins, e.g. to report them in
+    /// [`HeapStats::long_lived_pins`] as a cause of fragmentation, or to move the allocation out of a compacted space
+    /// before pinning it. The default implementation calls [`GcAllocator::pin`].
+    fn pin_long_lived(&self, obj: Self::Handle) -> *const () {
+        self.pin(obj)
+    }
+
+    /// Removes a pin added by [`GcAllocator::pin_long_lived`]. The default implementation calls
+    /// [`GcAllocator::unpin`].
+    fn unpin_long_lived(&self, obj: Self::Handle) {
+        self.unpin(obj)
+    }
+
+    /// Returns unused memory to the operating system, such as free pages left behind by a spike in allocations, and
+    /// reports how much was returned. This does not collect garbage, so it is most effective right after a
+    /// collection. The default implementation releases nothing.
+    fn shrink(&self) -> ShrinkStats {
+        ShrinkStats::default()
+    }
+
+    /// Returns whether the given handle refers to a live GC allocation of this heap. This is used to catch handles
+    /// passed to the wrong heap in debug builds, so it should be cheap, but it need not be exact: strategies that
+    /// cannot afford an exact answer may return `true` for handles that merely look plausible.
+    fn contains(&self, obj: Self::Handle) -> bool;
+
+    /// Returns the vtable the given GC allocation was allocated with. The result is unspecified if the allocation
+    /// has been reclaimed.
+    fn vtable(&self, obj: Self::Handle) -> &'static GcVtable;
+}
+
+/// The part of a [`GcStrategy`] that keeps track of roots, for handles of type `H`. A root manager may be shared by
+/// several strategies with the same handle type.
+///
+/// # Safety
+/// Implementations of this trait must uphold the contracts of all defined methods, as well as the documentation of
+/// [`GcStrategy`].
+pub unsafe trait RootManager<H: StrategyHandle> {
     /// Adds a root that references the given GC handle.
-    fn root(&self, obj: Handle);
+    fn root(&self, obj: H);
 
     /// Removes a root referencing the given GC handle.
-    fn unroot(&self, obj: Handle);
+    fn unroot(&self, obj: H);
+
+    /// Adds a root for each of the given GC handles, as if by calling [`RootManager::root`] on each. A handle may
+    /// appear more than once, in which case it is rooted as many times. Strategies may override this to root a batch
+    /// of objects with a single synchronized operation. The default implementation roots each handle in turn.
+    fn root_all(&self, objs: &[H]) {
+        for &obj in objs {
+            self.root(obj);
+        }
+    }
+
+    /// Removes a root for each of the given GC handles, as if by calling [`RootManager::unroot`] on each. The default
+    /// implementation unroots each handle in turn.
+    fn unroot_all(&self, objs: &[H]) {
+        for &obj in objs {
+            self.unroot(obj);
+        }
+    }
+
+    /// Makes the given GC allocation immortal, for objects that are known to live as long as the heap, such as
+    /// interned symbols or the global object. The allocation is never reclaimed or finalized, and strategies may
+    /// exclude it from future collections, e.g. by moving it to a permanent space that is neither traced nor
+    /// compacted. Objects it refers to must still be kept alive, such as by treating its edges as roots, or by
+    /// recording them through the write barrier.
+    ///
+    /// The default implementation roots the allocation without ever unrooting it.
+    fn freeze(&self, obj: H) {
+        self.root(obj);
+    }
+
+    /// Registers a provider of additional roots. Until the provider is unregistered, the strategy must treat every
+    /// object it visits through [`RootProvider::trace_roots`] as rooted, asking it again on every collection.
+    ///
+    /// The default implementation panics, as the strategy would otherwise reclaim the objects rooted by the provider.
+    ///
+    /// # Safety
+    /// `provider` must stay valid until it is passed to [`RootManager::unregister_root_provider`].
+    unsafe fn register_root_provider(&self, provider: &dyn RootProvider) {
+        let _ = provider;
+        panic!("this strategy does not support root providers");
+    }
+
+    /// Unregisters a provider registered with [`RootManager::register_root_provider`]. Providers are identified by
+    /// address. The default implementation panics.
+    ///
+    /// # Safety
+    /// `provider` must be currently registered.
+    unsafe fn unregister_root_provider(&self, provider: &dyn RootProvider) {
+        let _ = provider;
+        panic!("this strategy does not support root providers");
+    }
+}
+
+/// The part of a [`GcStrategy`] that determines which GC allocations are reachable, for handles of type `H`. This
+/// includes collections, barriers, and weak references.
+///
+/// # Tracing and Mutation
+/// A strategy must not trace an object while a thread may be writing to it through interior mutability, such as a
+/// [`Cell`](core::cell::Cell), a [`GcCell`](crate::cell::GcCell), or the guard of a [`GcMutex`](crate::sync::GcMutex).
+/// Threads only write to objects outside the heap,
+/// so it suffices to trace while every thread that may write to the object is inside it: allocating, collecting,
+/// paused in [`Collector::poll_safepoint`], or in a safepoint entered with [`Collector::enter_safepoint`], in which
+/// threads do not write to objects. Mark workers may trace on other threads on behalf of the thread that collects.
+/// Strategies that are not `Sync` have a single mutator thread, and meet this requirement by only tracing when called
+/// by it.
+///
+/// This is a change to the contract of [`Trace`], which used to allow tracing concurrently with any access to the
+/// object, including writes through types that are not `Sync`. The `Trace` impls of the cells of the
+/// [`cell`](crate::cell) module, of the locks of the [`sync`](crate::sync) module, and of
+/// [`Cell`](core::cell::Cell), [`OnceCell`](core::cell::OnceCell), and [`LazyCell`](core::cell::LazyCell) read their
+/// contents without synchronization and rely on it. Strategies that trace on a background thread while mutators run
+/// must stop every mutator at a safepoint before tracing objects.
+///
+/// # Safety
+/// Implementations of this trait must uphold the contracts of all defined methods, as well as the documentation of
+/// [`GcStrategy`].
+pub unsafe trait Collector<H: StrategyHandle> {
+    /// Notifies the strategy that a reference to `child` has been stored into the GC object `parent`. Strategies that
+    /// trace the heap in pieces, such as generational and incremental strategies, use this to record edges created
+    /// after `parent` was traced. The default implementation does nothing.
+    fn write_barrier(&self, parent: H, child: H) {
+        let _ = (parent, child);
+    }
+
+    /// Allocates a weak cell referring to `target`, returning its handle. The weak cell is a GC object that is
+    /// traced like any other, but it does not keep `target` alive. Once `target` is no longer reachable through strong
+    /// references, the strategy clears the cell, before `target` is finalized. The returned cell is rooted and
+    /// Initialized.
+    ///
+    /// Returns None if there is not enough heap space for the cell. The default implementation panics, as the
+    /// strategy would otherwise be unable to clear the cell.
+    fn allocate_weak(&self, target: H) -> Option<H> {
+        let _ = target;
+        panic!("this strategy does not support weak references");
+    }
+
+    /// Returns the target of the given weak cell after rooting it, or None if the cell has been cleared.
+    ///
+    /// # Safety
+    /// `cell` must be a live weak cell returned by [`Collector::allocate_weak`].
+    unsafe fn upgrade_weak(&self, cell: H) -> Option<H> {
+        let _ = cell;
+        panic!("this strategy does not support weak references");
+    }
+
+    /// Allocates an ephemeron cell with the given key and value, returning its handle. Like a weak cell, the
+    /// ephemeron does not keep `key` alive. It keeps `value` alive only while `key` is reachable through strong
+    /// references from outside `value`, even if `value` refers to `key`. Once `key` is no longer reachable, the
+    /// strategy clears both the key and the value of the ephemeron, before `key` is finalized. The returned cell is
+    /// rooted and Initialized.
+    ///
+    /// Returns None if there is not enough heap space for the cell. The default implementation panics.
+    fn allocate_ephemeron(&self, key: H, value: H) -> Option<H> {
+        let _ = (key, value);
+        panic!("this strategy does not support ephemerons");
+    }
+
+    /// Returns the key and value of the given ephemeron cell after rooting both, or None if the cell has been
+    /// cleared.
+    ///
+    /// # Safety
+    /// `cell` must be a live ephemeron cell returned by [`Collector::allocate_ephemeron`].
+    unsafe fn upgrade_ephemeron(&self, cell: H) -> Option<(H, H)> {
+        let _ = cell;
+        panic!("this strategy does not support ephemerons");
+    }
+
+    /// Called by the heap before every allocation, and by [`GcHeap::safepoint`](crate::GcHeap::safepoint), to give
+    /// the strategy a chance to pause the calling thread. Strategies with stop-the-world phases check here whether a
+    /// pause has been requested and, if so, block until it is over. This is called often, so it should be cheap
+    /// when no pause is pending. The default implementation does nothing.
+    ///
+    /// While a thread is paused, the objects it has rooted must stay alive, and the objects it has pinned must not
+    /// be moved.
+    fn poll_safepoint(&self) {}
+
+    /// Notifies the strategy that the calling thread has entered a safepoint for an extended time, such as while
+    /// blocked on I/O, until it calls [`Collector::leave_safepoint`]. Stop-the-world phases may then run without
+    /// waiting for the thread to poll. The default implementation does nothing.
+    ///
+    /// Code in the safepoint may still read the objects it has pinned, and may even allocate, in which case the
+    /// strategy should block it as in [`Collector::leave_safepoint`]. It does not write to objects, so the strategy
+    /// may trace them meanwhile; see [Tracing and Mutation](Collector#tracing-and-mutation).
+    fn enter_safepoint(&self) {}
+
+    /// Notifies the strategy that the calling thread has left the safepoint entered by
+    /// [`Collector::enter_safepoint`]. If a stop-the-world phase is in progress, this blocks until it is over. The
+    /// default implementation does nothing.
+    fn leave_safepoint(&self) {}
+
+    /// Performs a garbage collection as requested by the application, e.g. at a point where it is known to be idle,
+    /// and returns statistics about the collection. Unreachable objects are finalized or registered for
+    /// finalization as usual, and unpinned objects may be moved.
+    ///
+    /// Strategies are free to treat this as a hint, e.g. by running a major collection instead of a minor one when
+    /// the old generation is nearly full. The default implementation does nothing and returns empty
+    /// statistics, which suits strategies that never collect or only collect on their own schedule.
+    fn collect(&self, options: CollectOptions) -> CollectionStats {
+        let _ = options;
+        CollectionStats::default()
+    }
+
+    /// Performs a bounded increment of collection work, starting a new collection if none is in progress. This lets
+    /// latency-sensitive applications spread a collection over many short pauses, e.g. one step per frame. The
+    /// strategy should stop as soon as it can once the budget is used up, though it may overrun it by a small
+    /// amount of indivisible work.
+    ///
+    /// The default implementation does no work and reports the collection complete, which suits strategies that
+    /// cannot collect incrementally.
+    fn collect_step(&self, budget: StepBudget) -> StepResult {
+        let _ = budget;
+        StepResult::Complete(CollectionStats::default())
+    }
+
+    /// Notifies the strategy that the system is low on memory. Strategies are expected to respond in proportion to
+    /// `level`: at [`MemoryPressure::Moderate`], by shrinking their allocation buffers and releasing free memory
+    /// that is cheap to give back; at [`MemoryPressure::Critical`], by running a full collection, compacting if
+    /// possible, and releasing every free page to the operating system. The default implementation does nothing.
+    fn memory_pressure(&self, level: MemoryPressure) {
+        let _ = level;
+    }
+
+    /// Registers a listener that is notified whenever the strategy moves a GC allocation. Until the listener is
+    /// unregistered, the strategy must call [`RelocationListener::relocated`] for every allocation it moves.
+    ///
+    /// The default implementation does nothing, which is correct for strategies that never move allocations.
+    ///
+    /// # Safety
+    /// `listener` must stay valid until it is passed to [`Collector::unregister_relocation_listener`].
+    unsafe fn register_relocation_listener(&self, listener: &dyn RelocationListener) {
+        let _ = listener;
+    }
+
+    /// Unregisters a listener registered with [`Collector::register_relocation_listener`]. Listeners are identified
+    /// by address. The default implementation does nothing.
+    ///
+    /// # Safety
+    /// `listener` must be currently registered.
+    unsafe fn unregister_relocation_listener(&self, listener: &dyn RelocationListener) {
+        let _ = listener;
+    }
+
+    /// Returns the age of the given GC allocation, as the number of collections it has survived, or None if the
+    /// strategy does not keep track of ages. Generational strategies may stop counting once the allocation has been
+    /// tenured, reporting the tenuring age instead. The default implementation returns None.
+    fn age(&self, obj: H) -> Option<u32> {
+        let _ = obj;
+        None
+    }
+
+    /// Moves the given GC allocation to the oldest generation, as if it had survived long enough to be tenured.
+    /// Embedders call this for objects they know to be long-lived, so that they are not copied by further minor
+    /// collections. Pinned allocations may be promoted once they are unpinned. The default implementation does
+    /// nothing.
+    fn promote(&self, obj: H) {
+        let _ = obj;
+    }
+
+    /// Returns statistics about the heap as a whole, for monitoring. The default implementation returns empty
+    /// statistics.
+    fn stats(&self) -> HeapStats {
+        HeapStats::default()
+    }
+}
+
+/// A listener for objects moved by a compacting or copying strategy. Listeners let embedders keep the raw addresses
+/// of unpinned objects in side tables, such as code generated by a JIT compiler or a table of hash-consed values,
+/// and fix them up when the objects move. Register a listener with
+/// [`GcHeap::with_relocation_listener`](crate::GcHeap::with_relocation_listener).
+///
+/// # Safety
+/// As with [`Trace::trace`], the strategy may call [`RelocationListener::relocated`] from a different thread, even
+/// if the listener is not `Sync`, so it must not access any thread-unsafe state.
+pub unsafe trait RelocationListener {
+    /// Called after the object at `old` has been moved to `new`, while the world is stopped. The object must not be
+    /// accessed through `old` afterwards. The listener must not access the heap, except to read the moved object.
+    fn relocated(&self, old: *const (), new: *const ());
+}
+
+/// Identifies a mutator thread registered with [`GcAllocator::register_thread`]. The meaning of the ID is up to the
+/// strategy, e.g. an index into its table of threads.
+///
+/// A token belongs to the thread it was registered on, so it is neither `Send` nor `Sync`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ThreadToken {
+    id: usize,
+    _ph: PhantomData<*const ()>,
+}
+
+impl ThreadToken {
+    pub const fn new(id: usize) -> Self {
+        Self { id, _ph: PhantomData }
+    }
+
+    pub const fn id(self) -> usize {
+        self.id
+    }
 }
 
-pub struct FreshAllocation {
+#[derive(Debug)]
+pub struct FreshAllocation<H = Handle> {
     /// A handle to the GC allocation.
-    pub handle: Handle,
+    pub handle: H,
     /// The address where the value will be stored.
     pub ptr: *mut (),
-}
\ No newline at end of file
+}
+
+/// Options for a collection requested through [`Collector::collect`]. New options may be added in the future, so
+/// start from [`CollectOptions::default`] and set the fields of interest.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CollectOptions {
+    /// How much of the heap to collect. Defaults to [`CollectionKind::Full`].
+    pub kind: CollectionKind,
+    /// Whether the strategy should also compact the heap, if it is able to. Compaction moves every unpinned object,
+    /// so it is usually only worth requesting after a large amount of garbage has been created.
+    pub compact: bool,
+}
+
+/// How much of the heap a collection covers. Strategies that do not distinguish between kinds may run whichever
+/// collection they have, and report the kind that best describes it in [`CollectionStats::kind`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CollectionKind {
+    /// Collects only recently allocated objects, such as the nursery of a generational strategy. This is quick, but
+    /// garbage that has survived earlier collections is not reclaimed.
+    Minor,
+    /// Collects the whole heap in the strategy's usual manner, which may be incremental or concurrent.
+    Major,
+    /// Collects the whole heap as thoroughly as possible, running to completion before returning and reclaiming
+    /// all unreachable objects.
+    #[default]
+    Full,
+}
+
+/// The amount of work to do in one call to [`Collector::collect_step`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StepBudget {
+    /// Work for at most the given time.
+    Time(Duration),
+    /// Trace or reclaim at most the given number of objects.
+    Objects(usize),
+}
+
+/// The result of [`Collector::collect_step`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StepResult {
+    /// The budget was used up before the collection finished. Further steps will continue it.
+    Incomplete,
+    /// The collection finished within the budget. The next step will start a new collection.
+    Complete(CollectionStats),
+}
+
+/// Statistics about a collection, returned by [`Collector::collect`]. Strategies that do not keep track of some
+/// statistic leave it at zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CollectionStats {
+    /// The kind of collection that ran, which may differ from the kind requested, or None if the strategy did not
+    /// collect.
+    pub kind: Option<CollectionKind>,
+    /// The number of objects found to be unreachable.
+    pub objects_reclaimed: usize,
+    /// The number of bytes of heap space freed by the collection.
+    pub bytes_reclaimed: usize,
+    /// The number of bytes of heap space still in use after the collection.
+    pub bytes_live: usize,
+}
+
+/// How urgently the system needs memory, passed to [`Collector::memory_pressure`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MemoryPressure {
+    /// Memory is running low. The strategy should release memory it can give back cheaply.
+    Moderate,
+    /// Memory is nearly exhausted. The strategy should release as much memory as it can, even at the cost of a
+    /// long pause.
+    Critical,
+}
+
+/// Statistics about a GC heap, returned by [`Collector::stats`]. Strategies that do not keep track of some
+/// statistic leave it at zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct HeapStats {
+    /// The number of bytes of memory reserved for the heap, whether or not it is in use.
+    pub heap_size: usize,
+    /// The number of bytes of heap space in use by objects that have not been reclaimed. This includes unreachable
+    /// objects that have not yet been found by a collection.
+    pub bytes_live: usize,
+    /// The number of objects that have not been reclaimed.
+    pub object_count: usize,
+    /// The number of long-lived pins currently held, such as by [`PinnedPtr`](crate::PinnedPtr)s. Each one keeps an
+    /// object in place for an indefinite time, so a high count may explain fragmentation in compacting strategies.
+    /// See [`GcAllocator::pin_long_lived`].
+    pub long_lived_pins: usize,
+    /// The number of collections that have run since the heap was created.
+    pub collection_count: u64,
+    /// The total time mutator threads have been paused by collections since the heap was created.
+    pub total_pause: Duration,
+}
+
+/// The result of [`GcAllocator::shrink`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ShrinkStats {
+    /// The number of bytes of memory returned to the operating system.
+    pub bytes_released: usize,
+    /// The number of bytes of memory still reserved for the heap.
+    pub heap_size: usize,
+}
+
+/// A hint about how a GC allocation will be used, passed to [`GcAllocator::allocate_with_hint`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AllocHint {
+    /// No particular use is known.
+    #[default]
+    Normal,
+    /// The allocation is expected to live for a long time, e.g. because allocation-site profiling shows that its
+    /// siblings always do. Generational strategies should allocate it directly in the oldest generation, rather
+    /// than copying it there after it has survived several collections.
+    Old,
+}
+
+/// The error returned when a GC allocation fails. Strategies may report failures with a more detailed error type,
+/// see [`GcAllocator::Error`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AllocError {
+    /// The strategy does not have enough heap space for the object.
+    OutOfMemory,
+    /// The size of the object overflows the maximum size of an allocation, or its alignment is invalid.
+    InvalidLayout,
+}
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::OutOfMemory => "out of memory",
+            Self::InvalidLayout => "invalid allocation layout",
+        })
+    }
+}
+
+impl core::error::Error for AllocError {}
@@ -1,4 +1,6 @@
 #![no_std]
+#![cfg_attr(feature = "nightly-simd", feature(portable_simd))]
+#![cfg_attr(feature = "nightly-unsize", feature(unsize))]
 
 #[cfg(feature = "alloc")]
 extern crate alloc;
@@ -6,13 +8,61 @@ extern crate alloc;
 #[cfg(feature = "std")]
 extern crate std;
 
-use core::{marker::PhantomData, ops::Deref};
+use core::{
+    any::{type_name, Any, TypeId},
+    cmp::Ordering as CmpOrdering,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    mem::{ManuallyDrop, MaybeUninit},
+    ops::Deref,
+    ptr::NonNull,
+    sync::atomic::{AtomicPtr, Ordering},
+};
 
-use heap::{GcStrategy, GcVtable, Handle};
-use trace::Trace;
+use heap::{
+    AllocError, AllocHint, CollectOptions, CollectionStats, DynamicObject, FreshAllocation,
+    GcStrategy, GcVtable, Handle, HeapStats, MemoryPressure, RelocationListener, ShrinkStats,
+    StepBudget, StepResult, StrategyHandle, ThreadToken,
+};
+use ptr::{DynMetadata, GcPointee, HeaderSlice};
+use trace::{RootProvider, Trace};
 
+/// Implements strategy methods by passing them through to the strategy in `self.$field`, for strategies that wrap
+/// another strategy. Methods are listed by signature, e.g. `delegate!(strategy => fn unpin(&self, obj: H);)`.
+macro_rules! delegate {
+    ($field:ident =>) => {};
+    ($field:ident => fn $name:ident(&self $(, $arg:ident: $ty:ty)*) $(-> $ret:ty)?; $($rest:tt)*) => {
+        fn $name(&self $(, $arg: $ty)*) $(-> $ret)? {
+            self.$field.$name($($arg),*)
+        }
+
+        delegate!($field => $($rest)*);
+    };
+    ($field:ident => unsafe fn $name:ident(&self $(, $arg:ident: $ty:ty)*) $(-> $ret:ty)?; $($rest:tt)*) => {
+        unsafe fn $name(&self $(, $arg: $ty)*) $(-> $ret)? {
+            // SAFETY: caller
+            unsafe { self.$field.$name($($arg),*) }
+        }
+
+        delegate!($field => $($rest)*);
+    };
+}
+
+pub mod cell;
+pub mod closure;
+pub mod collections;
+pub mod decorators;
 pub mod heap;
+pub mod limit;
+pub mod pacer;
+pub mod ptr;
+#[cfg(feature = "alloc")]
+pub mod strategies;
+pub mod sync;
+pub mod testing;
 pub mod trace;
+pub mod weak;
 
 pub struct GcHeap<'lifetime, S: ?Sized> {
     /// The limiting lifetime of this heap. The compiler infers as small a lifetime as necessary,
@@ -23,27 +73,587 @@ pub struct GcHeap<'lifetime, S: ?Sized> {
     strategy: S,
 }
 
+impl<S: GcStrategy> GcHeap<'_, S> {
+    pub fn new(strategy: S) -> Self {
+        Self {
+            _lifetime: PhantomData,
+            strategy,
+        }
+    }
+}
+
 impl<'lifetime, S: ?Sized + GcStrategy> GcHeap<'lifetime, S> {
-    // todo: figure out how allocation should work
-    // note: Send bound here because we eventually want to have dropping handled
+    // note: Send bound here because objects may be finalized on any thread, see `alloc_local`
     pub fn alloc<T: Trace + Send + 'lifetime>(&self, value: T) -> Root<'_, S, T> {
-        let vtable = const { GcVtable::for_type::<T>() };
-        match self.strategy.allocate(vtable) {
-            // SAFETY: the GC heap ensures the allocation is uninitialized and the
-            // pointer is suitable for a value of type `T`.
-            Some(alloc) => unsafe {
-                alloc.ptr.cast::<T>().write(value);
-                self.strategy.set_initialized(alloc.handle);
-                Root {
-                    handle: Gc {
-                        handle: alloc.handle,
-                        _ph: PhantomData,
-                    },
-                    gc: &self.strategy,
-                }
+        self.alloc_cyclic(|_| value)
+    }
+
+    /// Allocates a new GC object, returning the error reported by the strategy instead of panicking if the allocation
+    /// fails.
+    pub fn try_alloc<T: Trace + Send + 'lifetime>(
+        &self,
+        value: T,
+    ) -> Result<Root<'_, S, T>, S::Error> {
+        self.try_alloc_in_place(
+            const { GcVtable::for_type::<T>() },
+            AllocHint::Normal,
+            None,
+            |_, place| place.write(value),
+        )
+    }
+
+    /// Allocates a new GC object that is expected to be long-lived, such as a module or compiled code, directly in
+    /// the oldest generation of the heap. See [`AllocHint::Old`].
+    pub fn alloc_old<T: Trace + Send + 'lifetime>(&self, value: T) -> Root<'_, S, T> {
+        match self.try_alloc_in_place(
+            const { GcVtable::for_type::<T>() },
+            AllocHint::Old,
+            None,
+            |_, place| place.write(value),
+        ) {
+            Ok(root) => root,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Allocates a new GC object whose type is not `Send`, such as a host object holding an `Rc`. The strategy
+    /// finalizes the object on the calling thread, see [`GcVtable::is_local`].
+    pub fn alloc_local<T: Trace + 'lifetime>(&self, value: T) -> Root<'_, S, T> {
+        match self.try_alloc_in_place(
+            const { GcVtable::for_local_type::<T>() },
+            AllocHint::Normal,
+            None,
+            |_, place| place.write(value),
+        ) {
+            Ok(root) => root,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Allocates a new GC object whose value may refer to the object itself. `init` receives a `Gc` to the object
+    /// before it is initialized, which it may store in the returned value.
+    ///
+    /// The object is not initialized until `init` returns, so `init` must not access it, or root it through
+    /// [`GcHeap::root`]. If `init` panics, the allocation is leaked.
+    pub fn alloc_cyclic<T: Trace + Send + 'lifetime>(
+        &self,
+        init: impl FnOnce(Gc<T>) -> T,
+    ) -> Root<'_, S, T> {
+        match self.try_alloc_in_place(
+            const { GcVtable::for_type::<T>() },
+            AllocHint::Normal,
+            None,
+            |gc, place| place.write(init(gc)),
+        ) {
+            Ok(root) => root,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Allocates a new GC object, initializing it in place. This avoids constructing large values on the stack
+    /// before moving them into the heap.
+    ///
+    /// `init` must initialize the value it is given and return a reference to it, typically by calling
+    /// [`MaybeUninit::write`] or [`MaybeUninit::assume_init_mut`]. If `init` panics, the allocation is leaked.
+    ///
+    /// # Panics
+    /// Panics if `init` returns a reference to anything other than the value it was given.
+    pub fn alloc_with<T: Trace + Send + 'lifetime>(
+        &self,
+        init: impl FnOnce(&mut MaybeUninit<T>) -> &mut T,
+    ) -> Root<'_, S, T> {
+        match self.try_alloc_in_place(
+            const { GcVtable::for_type::<T>() },
+            AllocHint::Normal,
+            None,
+            |_, place| init(place),
+        ) {
+            Ok(root) => root,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Allocates a new GC object with the given vtable, which must be the vtable for `T`.
+    fn try_alloc_in_place<T: Trace + 'lifetime>(
+        &self,
+        vtable: &'static GcVtable,
+        hint: AllocHint,
+        thread: Option<ThreadToken>,
+        init: impl FnOnce(Gc<T>, &mut MaybeUninit<T>) -> &mut T,
+    ) -> Result<Root<'_, S, T>, S::Error> {
+        let alloc = allocate(&self.strategy, vtable, 1, hint, thread)?;
+        let handle = Gc {
+            handle: alloc.handle.into_raw(),
+            metadata: (),
+            _ph: PhantomData,
+        };
+        let ptr = alloc.ptr.cast::<MaybeUninit<T>>();
+        // SAFETY: the GC heap ensures the allocation is uninitialized and the
+        // pointer is suitable for a value of type `T`.
+        let value: *mut T = init(handle, unsafe { &mut *ptr });
+        assert!(
+            core::ptr::eq(value, ptr.cast()),
+            "initializer returned a reference to a different value"
+        );
+        // SAFETY: the allocation holds the value `init` returned a reference to
+        unsafe { self.strategy.set_initialized(alloc.handle) };
+        Ok(Root {
+            handle,
+            gc: &self.strategy,
+            pinned: AtomicPtr::new(core::ptr::null_mut()),
+        })
+    }
+
+    /// Allocates a slice containing clones of the elements of `values`. If cloning panics, the allocation is leaked.
+    pub fn alloc_slice<T: Trace + Send + Clone + 'lifetime>(
+        &self,
+        values: &[T],
+    ) -> Root<'_, S, [T]> {
+        self.alloc_from_iter(values.iter().cloned())
+    }
+
+    /// Allocates a slice containing the elements produced by the given iterator. If the iterator panics, the
+    /// allocation is leaked.
+    ///
+    /// # Panics
+    /// Panics if the allocation fails, or if the iterator produces fewer elements than its reported length. Excess
+    /// elements are ignored.
+    pub fn alloc_from_iter<T, I>(&self, iter: I) -> Root<'_, S, [T]>
+    where
+        T: Trace + Send + 'lifetime,
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        match self.try_alloc_from_iter(iter) {
+            Ok(root) => root,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Allocates a slice like [`GcHeap::alloc_from_iter`], returning the error reported by the strategy instead of
+    /// panicking if the allocation fails. The error is [`AllocError::InvalidLayout`] if the slice would be too large
+    /// for any allocation.
+    ///
+    /// # Panics
+    /// Panics if the iterator produces fewer elements than its reported length. Excess elements are ignored.
+    pub fn try_alloc_from_iter<T, I>(&self, iter: I) -> Result<Root<'_, S, [T]>, S::Error>
+    where
+        T: Trace + Send + 'lifetime,
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let mut iter = iter.into_iter();
+        let len = iter.len();
+        let alloc = allocate(
+            &self.strategy,
+            const { GcVtable::for_slice::<T>() },
+            len,
+            AllocHint::Normal,
+            None,
+        )?;
+        let ptr = alloc.ptr.cast::<T>();
+        for idx in 0..len {
+            let value = iter
+                .next()
+                .expect("iterator produced fewer elements than its length");
+            // SAFETY: the allocation is suitable for `len` values of type `T`.
+            unsafe { ptr.add(idx).write(value) };
+        }
+        // SAFETY: every element has been initialized
+        unsafe { self.strategy.set_initialized(alloc.handle) };
+        Ok(Root {
+            handle: Gc {
+                handle: alloc.handle.into_raw(),
+                metadata: len,
+                _ph: PhantomData,
+            },
+            gc: &self.strategy,
+            pinned: AtomicPtr::new(core::ptr::null_mut()),
+        })
+    }
+
+    /// Allocates a [`HeaderSlice`] with the given header, followed by the elements produced by the given iterator.
+    /// This is useful for objects whose size is only known at run time, such as instances of classes defined by an
+    /// interpreted program. If the iterator panics, the allocation is leaked.
+    ///
+    /// # Panics
+    /// Panics if the iterator produces fewer elements than its reported length. Excess elements are ignored.
+    pub fn alloc_header_slice<H, T, I>(&self, header: H, iter: I) -> Root<'_, S, HeaderSlice<H, T>>
+    where
+        H: Trace + Send + 'lifetime,
+        T: Trace + Send + 'lifetime,
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let mut iter = iter.into_iter();
+        let len = iter.len();
+        let vtable = const { GcVtable::for_header_slice::<H, T>() };
+        let alloc = match allocate(&self.strategy, vtable, len, AllocHint::Normal, None) {
+            Ok(alloc) => alloc,
+            Err(err) => panic!("{err}"),
+        };
+        let ptr = core::ptr::slice_from_raw_parts_mut(alloc.ptr.cast::<T>(), len)
+            as *mut HeaderSlice<H, T>;
+        // SAFETY: the allocation is suitable for a header followed by `len` values of type `T`.
+        unsafe {
+            core::ptr::addr_of_mut!((*ptr).header).write(header);
+            let tail = core::ptr::addr_of_mut!((*ptr).tail).cast::<T>();
+            for idx in 0..len {
+                let value = iter
+                    .next()
+                    .expect("iterator produced fewer elements than its length");
+                tail.add(idx).write(value);
+            }
+        }
+        // SAFETY: the header and every element have been initialized
+        unsafe { self.strategy.set_initialized(alloc.handle) };
+        Root {
+            handle: Gc {
+                handle: alloc.handle.into_raw(),
+                metadata: len,
+                _ph: PhantomData,
+            },
+            gc: &self.strategy,
+            pinned: AtomicPtr::new(core::ptr::null_mut()),
+        }
+    }
+
+    /// Allocates a copy of the given string.
+    pub fn alloc_str(&self, value: &str) -> Root<'_, S, str> {
+        self.alloc_concat(&[value])
+    }
+
+    /// Allocates the concatenation of the given strings, without building the result outside the heap first.
+    pub fn alloc_concat(&self, values: &[&str]) -> Root<'_, S, str> {
+        alloc_concat(&self.strategy, values)
+    }
+
+    /// Allocates the string produced by formatting `args`, e.g. `heap.alloc_fmt(format_args!("{x}, {y}"))`.
+    ///
+    /// The arguments are formatted twice: once to compute the length of the string, and once to write it into the
+    /// heap. If formatting panics, the allocation is leaked.
+    ///
+    /// # Panics
+    /// Panics if the arguments format to strings of different lengths each time.
+    pub fn alloc_fmt(&self, args: fmt::Arguments<'_>) -> Root<'_, S, str> {
+        if let Some(value) = args.as_str() {
+            return self.alloc_str(value);
+        }
+        let mut len = StrLen(0);
+        fmt::write(&mut len, args).expect("a formatting trait implementation returned an error");
+        // SAFETY: the writer checks that exactly `len` bytes of UTF-8 are written.
+        unsafe {
+            alloc_str_with(&self.strategy, len.0, |ptr| {
+                let mut writer = StrWriter {
+                    ptr,
+                    remaining: len.0,
+                };
+                fmt::write(&mut writer, args)
+                    .expect("a formatting trait implementation returned an error");
+                assert!(
+                    writer.remaining == 0,
+                    "arguments formatted to a shorter string the second time"
+                );
+            })
+        }
+    }
+
+    /// Allocates a new GC object without initializing it. The object must be initialized through
+    /// [`Root::as_mut_ptr`] before calling [`Root::assume_init`]. If the root is dropped before then, the allocation
+    /// is abandoned.
+    pub fn alloc_uninit<T: Trace + Send + 'lifetime>(&self) -> Root<'_, S, MaybeUninit<T>> {
+        match allocate(
+            &self.strategy,
+            const { GcVtable::for_type::<T>() },
+            1,
+            AllocHint::Normal,
+            None,
+        ) {
+            Ok(alloc) => Root {
+                handle: Gc {
+                    handle: alloc.handle.into_raw(),
+                    metadata: (),
+                    _ph: PhantomData,
+                },
+                gc: &self.strategy,
+                // fresh allocations are pinned, and the root takes over that pin
+                pinned: AtomicPtr::new(alloc.ptr),
+            },
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Allocates an object of a type defined at run time, whose vtable was created with [`GcVtable::builder`]. `len`
+    /// is the number of elements of slice types, and is ignored otherwise. `init` receives the address of the
+    /// object, and must initialize it. If `init` panics, the allocation is leaked.
+    ///
+    /// # Safety
+    /// Once `init` returns, the object must be valid for the functions of `vtable`. Unless the vtable is
+    /// [local](GcVtable::is_local), the object must be safe to trace and finalize from any thread.
+    pub unsafe fn alloc_dynamic(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+        init: impl FnOnce(NonNull<u8>),
+    ) -> Root<'_, S, DynamicObject> {
+        // SAFETY: caller
+        match unsafe { self.try_alloc_dynamic(vtable, len, init) } {
+            Ok(root) => root,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Allocates an object of a type defined at run time as [`GcHeap::alloc_dynamic`], returning the error reported
+    /// by the strategy instead of panicking if the allocation fails.
+    ///
+    /// # Safety
+    /// See [`GcHeap::alloc_dynamic`].
+    pub unsafe fn try_alloc_dynamic(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+        init: impl FnOnce(NonNull<u8>),
+    ) -> Result<Root<'_, S, DynamicObject>, S::Error> {
+        let len = if vtable.is_slice() { len } else { 1 };
+        let alloc = allocate(&self.strategy, vtable, len, AllocHint::Normal, None)?;
+        // SAFETY: the strategy returns a non-null address for fresh allocations
+        init(unsafe { NonNull::new_unchecked(alloc.ptr.cast::<u8>()) });
+        // SAFETY: caller
+        unsafe { self.strategy.set_initialized(alloc.handle) };
+        Ok(Root {
+            handle: Gc {
+                handle: alloc.handle.into_raw(),
+                metadata: (),
+                _ph: PhantomData,
             },
-            None => panic!("out of memory"),
+            gc: &self.strategy,
+            pinned: AtomicPtr::new(core::ptr::null_mut()),
+        })
+    }
+
+    /// Roots the given GC object. See also [`letroot!`], which roots several objects at once.
+    ///
+    /// # Safety
+    /// `gc` must refer to a live object allocated by this heap. For example, it may be read from an object that is
+    /// currently rooted, as long as nothing has written to that object since. The exception is a `Gc` stored into a
+    /// [`Cell`](core::cell::Cell) field with `Cell::set`, which bypasses the write barrier: generational and
+    /// incremental strategies may have reclaimed it by any collection since. Store such values with
+    /// [`Root::set_field`] instead.
+    pub unsafe fn root<T: ?Sized + GcPointee>(&self, gc: Gc<T>) -> Root<'_, S, T> {
+        debug_assert!(self.contains(gc), "{gc:?} does not belong to this heap");
+        self.strategy.root(gc.strategy_handle());
+        Root {
+            handle: gc,
+            gc: &self.strategy,
+            pinned: AtomicPtr::new(core::ptr::null_mut()),
+        }
+    }
+
+    /// Requests a full garbage collection, e.g. at a point where the application is known to be idle. Objects that are
+    /// not reachable from a root may be finalized and reclaimed. See
+    /// [`Collector::collect`](crate::heap::Collector::collect).
+    pub fn collect(&self) -> CollectionStats {
+        self.collect_with(CollectOptions::default())
+    }
+
+    /// Requests a garbage collection with the given options, such as a [`Minor`](heap::CollectionKind::Minor)
+    /// collection.
+    pub fn collect_with(&self, options: CollectOptions) -> CollectionStats {
+        self.strategy.collect(options)
+    }
+
+    /// Performs a bounded increment of collection work, e.g. once per frame of a game loop. See
+    /// [`Collector::collect_step`](crate::heap::Collector::collect_step).
+    pub fn collect_incremental(&self, budget: StepBudget) -> StepResult {
+        self.strategy.collect_step(budget)
+    }
+
+    /// Gives the strategy a chance to pause the calling thread, e.g. for a stop-the-world collection. Allocating
+    /// does this automatically, so this only needs to be called in long-running loops that do not allocate. See
+    /// [`Collector::poll_safepoint`](crate::heap::Collector::poll_safepoint).
+    pub fn safepoint(&self) {
+        self.strategy.poll_safepoint();
+    }
+
+    /// Enters a safepoint until the returned guard is dropped, so that the strategy may pause the world without
+    /// waiting for the calling thread. Use this around blocking operations that do not touch the heap.
+    ///
+    /// # Safety
+    /// Until the guard is dropped, the calling thread must not write to the objects of this heap, e.g. through a
+    /// [`GcCell`](cell::GcCell), as the strategy may trace them meanwhile. See
+    /// [Tracing and Mutation](crate::heap::Collector#tracing-and-mutation).
+    pub unsafe fn enter_safepoint(&self) -> SafepointGuard<'_, S> {
+        self.strategy.enter_safepoint();
+        SafepointGuard {
+            gc: &self.strategy,
+            _ph: PhantomData,
+        }
+    }
+
+    /// Registers the calling thread as a mutator of this heap, see
+    /// [`GcAllocator::register_thread`](crate::heap::GcAllocator::register_thread). Allocating through the returned
+    /// handle lets the strategy use the thread's own state, such as an allocation buffer. The thread is unregistered
+    /// when the handle is dropped.
+    pub fn register_thread(&self) -> MutatorThread<'_, 'lifetime, S> {
+        MutatorThread {
+            token: self.strategy.register_thread(),
+            heap: self,
+        }
+    }
+
+    /// Registers `provider` as a source of roots while `f` runs, for roots that are not held in [`Root`]s. The
+    /// provider is unregistered when `f` returns or panics. See [`RootProvider`].
+    pub fn with_root_provider<R>(&self, provider: &dyn RootProvider, f: impl FnOnce() -> R) -> R {
+        struct Unregister<'a, S: ?Sized + GcStrategy> {
+            gc: &'a S,
+            provider: &'a dyn RootProvider,
+        }
+
+        impl<S: ?Sized + GcStrategy> Drop for Unregister<'_, S> {
+            fn drop(&mut self) {
+                // SAFETY: the provider was registered below
+                unsafe { self.gc.unregister_root_provider(self.provider) };
+            }
         }
+
+        // SAFETY: the provider outlives this call, and is unregistered before it returns
+        unsafe { self.strategy.register_root_provider(provider) };
+        let _guard = Unregister {
+            gc: &self.strategy,
+            provider,
+        };
+        f()
+    }
+
+    /// Roots the given objects in batches. Used by [`letroot!`].
+    ///
+    /// # Safety
+    /// Each handle must satisfy the safety contract of [`GcHeap::root`].
+    #[doc(hidden)]
+    pub unsafe fn __root_all(&self, handles: &[Handle]) {
+        for &handle in handles {
+            debug_assert!(
+                self.strategy.contains(S::Handle::from_raw(handle)),
+                "{handle:?} does not belong to this heap"
+            );
+        }
+        for_each_batch::<S>(handles.iter().copied(), |batch| {
+            self.strategy.root_all(batch)
+        });
+    }
+
+    /// Creates a root for an object that has already been rooted with [`GcHeap::__root_all`].
+    ///
+    /// # Safety
+    /// `gc` must have been rooted with the strategy, and the root is taken over by the returned `Root`.
+    #[doc(hidden)]
+    pub unsafe fn __adopt_root<T: ?Sized + GcPointee>(&self, gc: Gc<T>) -> Root<'_, S, T> {
+        Root {
+            handle: gc,
+            gc: &self.strategy,
+            pinned: AtomicPtr::new(core::ptr::null_mut()),
+        }
+    }
+
+    /// Notifies the strategy that the system is low on memory, e.g. in response to a signal from the operating
+    /// system. See [`Collector::memory_pressure`](crate::heap::Collector::memory_pressure).
+    pub fn memory_pressure(&self, level: MemoryPressure) {
+        self.strategy.memory_pressure(level);
+    }
+
+    /// Returns unused heap memory to the operating system. Call this after [`GcHeap::collect`] to release the memory
+    /// freed by the collection. See [`GcAllocator::shrink`](crate::heap::GcAllocator::shrink).
+    pub fn shrink(&self) -> ShrinkStats {
+        self.strategy.shrink()
+    }
+
+    /// Returns statistics about the heap, such as its size and the number of collections so far. See
+    /// [`Collector::stats`](crate::heap::Collector::stats).
+    pub fn stats(&self) -> HeapStats {
+        self.strategy.stats()
+    }
+
+    /// Runs `f` with a scope through which objects can be pinned, e.g. for the duration of a foreign call. Every
+    /// object pinned through the scope is released when `f` returns, so pins cannot be leaked. See [`PinScope`].
+    #[cfg(feature = "alloc")]
+    pub fn pin_scope<R>(&self, f: impl for<'scope> FnOnce(&PinScope<'scope, S>) -> R) -> R {
+        let scope = PinScope {
+            pinned: core::cell::RefCell::new(alloc::vec::Vec::new()),
+            gc: &self.strategy,
+        };
+        f(&scope)
+    }
+
+    /// Registers `listener` to be notified of moved objects while `f` runs. The listener is unregistered when `f`
+    /// returns or panics. See [`RelocationListener`].
+    pub fn with_relocation_listener<R>(
+        &self,
+        listener: &dyn RelocationListener,
+        f: impl FnOnce() -> R,
+    ) -> R {
+        struct Unregister<'a, S: ?Sized + GcStrategy> {
+            gc: &'a S,
+            listener: &'a dyn RelocationListener,
+        }
+
+        impl<S: ?Sized + GcStrategy> Drop for Unregister<'_, S> {
+            fn drop(&mut self) {
+                // SAFETY: the listener was registered below
+                unsafe { self.gc.unregister_relocation_listener(self.listener) };
+            }
+        }
+
+        // SAFETY: the listener outlives this call, and is unregistered before it returns
+        unsafe { self.strategy.register_relocation_listener(listener) };
+        let _guard = Unregister {
+            gc: &self.strategy,
+            listener,
+        };
+        f()
+    }
+
+    /// Makes the rooted object immortal, so that it is never reclaimed and may be skipped by future collections. Use
+    /// this for objects that live as long as the heap, such as interned symbols. See
+    /// [`RootManager::freeze`](crate::heap::RootManager::freeze).
+    ///
+    /// # Panics
+    /// Panics if `root` was not created by this heap.
+    pub fn freeze<T: ?Sized + GcPointee>(&self, root: &Root<'_, S, T>) {
+        assert!(
+            core::ptr::eq(root.gc, &self.strategy),
+            "root does not belong to this heap"
+        );
+        self.strategy.freeze(root.handle.strategy_handle());
+    }
+
+    /// Drops the value of the object and reclaims its memory immediately, for objects the application knows to be
+    /// dead, such as a dropped compilation unit. See [`GcAllocator::reclaim`](crate::heap::GcAllocator::reclaim).
+    ///
+    /// The object is not finalized by the strategy afterwards, even if it was registered with a finalization queue.
+    /// Weak references to the object are cleared, as are the entries of weak maps whose key or value it is, so
+    /// upgrading them returns None.
+    ///
+    /// # Safety
+    /// `gc` must refer to a live object allocated by this heap. The object must not be rooted or pinned, and must not
+    /// be reachable from any other object. Neither `gc` nor any copy of it may be used afterwards.
+    pub unsafe fn dispose<T: ?Sized + GcPointee>(&self, gc: Gc<T>) {
+        debug_assert!(self.contains(gc), "{gc:?} does not belong to this heap");
+        let handle = gc.strategy_handle();
+        self.strategy.root(handle);
+        let addr = self.strategy.pin(handle).cast_mut();
+        // SAFETY: the strategy returns a non-null address for pinned objects
+        let data = unsafe { NonNull::new_unchecked(addr) };
+        // SAFETY: caller; nothing else refers to the object, so its value may be dropped in place
+        unsafe { T::from_raw_parts(data, gc.metadata).drop_in_place() };
+        self.strategy.unroot(handle);
+        // SAFETY: the object is unreachable, and is pinned once above
+        unsafe { self.strategy.reclaim(handle) };
+    }
+
+    /// Returns whether the given `Gc` refers to a live object of this heap. See
+    /// [`GcAllocator::contains`](crate::heap::GcAllocator::contains).
+    pub fn contains<T: ?Sized + GcPointee>(&self, gc: Gc<T>) -> bool {
+        self.strategy.contains(gc.strategy_handle())
     }
 
     pub fn strategy(&self) -> &S {
@@ -51,44 +661,1369 @@ impl<'lifetime, S: ?Sized + GcStrategy> GcHeap<'lifetime, S> {
     }
 }
 
-pub struct Gc<T: ?Sized> {
+/// A guard for a safepoint entered with [`GcHeap::enter_safepoint`]. The safepoint is left when the guard is
+/// dropped, which may block if the strategy has paused the world in the meantime.
+///
+/// The guard must be dropped on the thread that created it.
+pub struct SafepointGuard<'heap, S: ?Sized + GcStrategy> {
+    gc: &'heap S,
+    _ph: PhantomData<*const ()>,
+}
+
+impl<S: ?Sized + GcStrategy> fmt::Debug for SafepointGuard<'_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SafepointGuard")
+    }
+}
+
+impl<S: ?Sized + GcStrategy> Drop for SafepointGuard<'_, S> {
+    fn drop(&mut self) {
+        self.gc.leave_safepoint();
+    }
+}
+
+/// A thread registered with [`GcHeap::register_thread`]. The thread is unregistered when this is dropped, which
+/// must happen on the same thread, so it is neither `Send` nor `Sync`.
+pub struct MutatorThread<'heap, 'lifetime, S: ?Sized + GcStrategy> {
+    heap: &'heap GcHeap<'lifetime, S>,
+    token: ThreadToken,
+}
+
+impl<'heap, 'lifetime, S: ?Sized + GcStrategy> MutatorThread<'heap, 'lifetime, S> {
+    /// Allocates a new GC object on behalf of this thread. See [`GcHeap::alloc`].
+    pub fn alloc<T: Trace + Send + 'lifetime>(&self, value: T) -> Root<'heap, S, T> {
+        match self.try_alloc(value) {
+            Ok(root) => root,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Allocates a new GC object on behalf of this thread, returning the error reported by the strategy instead of
+    /// panicking if the allocation fails.
+    pub fn try_alloc<T: Trace + Send + 'lifetime>(
+        &self,
+        value: T,
+    ) -> Result<Root<'heap, S, T>, S::Error> {
+        self.heap.try_alloc_in_place(
+            const { GcVtable::for_type::<T>() },
+            AllocHint::Normal,
+            Some(self.token),
+            |_, place| place.write(value),
+        )
+    }
+
+    /// The token the strategy identifies this thread by.
+    pub fn token(&self) -> ThreadToken {
+        self.token
+    }
+
+    /// The heap this thread is registered with.
+    pub fn heap(&self) -> &'heap GcHeap<'lifetime, S> {
+        self.heap
+    }
+}
+
+impl<S: ?Sized + GcStrategy> fmt::Debug for MutatorThread<'_, '_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MutatorThread")
+            .field("token", &self.token)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: ?Sized + GcStrategy> Drop for MutatorThread<'_, '_, S> {
+    fn drop(&mut self) {
+        // SAFETY: the token was registered on this thread, as the handle is not `Send`
+        unsafe { self.heap.strategy.unregister_thread(self.token) };
+    }
+}
+
+/// Passes the given handles to `f` in batches, converted to the handle type of the strategy. This is used for bulk
+/// rooting and unrooting without allocating.
+fn for_each_batch<S: ?Sized + GcStrategy>(
+    handles: impl IntoIterator<Item = Handle>,
+    mut f: impl FnMut(&[S::Handle]),
+) {
+    const BATCH_SIZE: usize = 64;
+
+    let mut batch = [S::Handle::from_raw(Handle::MIN); BATCH_SIZE];
+    let mut len = 0;
+    for handle in handles {
+        batch[len] = S::Handle::from_raw(handle);
+        len += 1;
+        if len == BATCH_SIZE {
+            f(&batch);
+            len = 0;
+        }
+    }
+    if len > 0 {
+        f(&batch[..len]);
+    }
+}
+
+/// Allocates an object of the given length, which is ignored if the vtable is not for a slice type. `thread` is the
+/// token of the [`MutatorThread`] allocating, if any, which must belong to the calling thread.
+fn allocate<S: ?Sized + GcStrategy>(
+    gc: &S,
+    vtable: &'static GcVtable,
+    len: usize,
+    hint: AllocHint,
+    thread: Option<ThreadToken>,
+) -> Result<FreshAllocation<S::Handle>, S::Error> {
+    if vtable.layout(len).is_none() {
+        return Err(AllocError::InvalidLayout.into());
+    }
+    gc.poll_safepoint();
+    match (thread, hint) {
+        // SAFETY: mutator threads are not `Send`, and unregister their token when dropped
+        (Some(token), _) => unsafe { gc.allocate_for_thread(token, vtable, len, hint) },
+        (None, AllocHint::Normal) => gc.allocate(vtable, len),
+        (None, _) => gc.allocate_with_hint(vtable, len, hint),
+    }
+}
+
+/// Allocates a string of `len` bytes, which are written by `init`.
+///
+/// # Safety
+/// `init` must write exactly `len` bytes of UTF-8 to the given pointer.
+unsafe fn alloc_str_with<S: ?Sized + GcStrategy>(
+    gc: &S,
+    len: usize,
+    init: impl FnOnce(*mut u8),
+) -> Root<'_, S, str> {
+    let alloc = match allocate(gc, GcVtable::for_str(), len, AllocHint::Normal, None) {
+        Ok(alloc) => alloc,
+        Err(err) => panic!("{err}"),
+    };
+    init(alloc.ptr.cast::<u8>());
+    // SAFETY: caller
+    unsafe { gc.set_initialized(alloc.handle) };
+    Root {
+        handle: Gc {
+            handle: alloc.handle.into_raw(),
+            metadata: len,
+            _ph: PhantomData,
+        },
+        gc,
+        pinned: AtomicPtr::new(core::ptr::null_mut()),
+    }
+}
+
+/// Allocates the concatenation of the given strings.
+fn alloc_concat<'root, S: ?Sized + GcStrategy>(
+    gc: &'root S,
+    values: &[&str],
+) -> Root<'root, S, str> {
+    let len = values
+        .iter()
+        .try_fold(0usize, |len, value| len.checked_add(value.len()))
+        .expect("string length overflow");
+    // SAFETY: the strings are copied end to end, which is `len` bytes of UTF-8.
+    unsafe {
+        alloc_str_with(gc, len, |mut ptr| {
+            for value in values {
+                core::ptr::copy_nonoverlapping(value.as_ptr(), ptr, value.len());
+                ptr = ptr.add(value.len());
+            }
+        })
+    }
+}
+
+/// Counts the length of a formatted string.
+struct StrLen(usize);
+
+impl fmt::Write for StrLen {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 = self.0.checked_add(s.len()).expect("string length overflow");
+        Ok(())
+    }
+}
+
+/// Writes a formatted string into a buffer of a known length.
+struct StrWriter {
+    ptr: *mut u8,
+    remaining: usize,
+}
+
+impl fmt::Write for StrWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        assert!(
+            s.len() <= self.remaining,
+            "arguments formatted to a longer string the second time"
+        );
+        // SAFETY: the buffer has room for the string
+        unsafe {
+            core::ptr::copy_nonoverlapping(s.as_ptr(), self.ptr, s.len());
+            self.ptr = self.ptr.add(s.len());
+        }
+        self.remaining -= s.len();
+        Ok(())
+    }
+}
+
+/// Roots several GC objects for the rest of the enclosing scope, e.g.
+/// `letroot!(unsafe heap; left = node.left, right = node.right)`. Each name is bound to a [`Root`]. The objects are
+/// rooted together with [`RootManager::root_all`](crate::heap::RootManager::root_all), and unrooted when the scope
+/// ends. The heap expression is evaluated once, followed by the object expressions in order.
+///
+/// # Safety
+/// Each expression must evaluate to a [`Gc`] that satisfies the safety contract of [`GcHeap::root`]. The `unsafe`
+/// keyword acknowledges this at the call site.
+#[macro_export]
+macro_rules! letroot {
+    (unsafe $heap:expr; $($name:ident = $gc:expr),+ $(,)?) => {
+        let heap = &$heap;
+        let ($($name,)+) = ($($gc,)+);
+        // SAFETY: caller
+        unsafe { heap.__root_all(&[$($crate::Gc::__handle($name)),+]) };
+        $(
+            // SAFETY: the object was rooted above
+            let $name = unsafe { heap.__adopt_root($name) };
+        )+
+    };
+}
+
+#[repr(C)]
+pub struct Gc<T: ?Sized + GcPointee> {
     /// Handle that represents the underlying GC allocation.
     handle: Handle,
+    /// Metadata for forming a pointer to the allocation, e.g. the length of a slice.
+    metadata: T::Metadata,
     _ph: PhantomData<fn() -> T>,
 }
 
+impl<T> Gc<T> {
+    /// Converts this `Gc` into a `Gc` of a trait object type. Use [`gc_unsize!`] instead of calling this directly.
+    ///
+    /// # Safety
+    /// `coerce` must return its argument, unsized to `U`.
+    pub unsafe fn unsize_unchecked<U>(self, coerce: fn(*const T) -> *const U) -> Gc<U>
+    where
+        U: ?Sized + GcPointee<Metadata = DynMetadata<U>>,
+    {
+        Gc {
+            handle: self.handle,
+            // SAFETY: caller
+            metadata: unsafe { DynMetadata::new_unchecked(coerce) },
+            _ph: PhantomData,
+        }
+    }
+
+    /// Converts this `Gc` into a `Gc` of a trait object type that `T` implements.
+    ///
+    /// `Gc` cannot implement `CoerceUnsized`, as it stores the metadata of its object separately from the handle.
+    /// This method performs the same conversion explicitly.
+    #[cfg(feature = "nightly-unsize")]
+    pub fn unsize<U>(self) -> Gc<U>
+    where
+        T: core::marker::Unsize<U>,
+        U: ?Sized + GcPointee<Metadata = DynMetadata<U>>,
+    {
+        // SAFETY: casting a thin pointer to a pointer to an unsized supertype is an unsizing coercion.
+        unsafe { self.unsize_unchecked(|ptr| ptr as *const U) }
+    }
+
+    /// Converts this `Gc` into its raw handle, e.g. to store it in a `void*` slot of a foreign host.
+    ///
+    /// This does not keep the object alive. Objects referenced only from outside the GC heap must be rooted for as
+    /// long as the raw handle is in use, which is most easily done with [`Root::into_raw`].
+    pub fn into_raw(self) -> usize {
+        self.handle.get()
+    }
+
+    /// Converts a raw handle back into a `Gc`.
+    ///
+    /// # Safety
+    /// `raw` must have been returned by [`Gc::into_raw`] or [`Root::into_raw`] on a `Gc<T>` or `Root<T>`.
+    pub unsafe fn from_raw(raw: usize) -> Self {
+        Gc {
+            // SAFETY: handles are non-zero
+            handle: unsafe { Handle::new_unchecked(raw) },
+            metadata: (),
+            _ph: PhantomData,
+        }
+    }
+}
+
+impl<T, const N: usize> Gc<[T; N]> {
+    /// Converts a `Gc` of an array into a `Gc` of a slice.
+    pub fn unsize_slice(self) -> Gc<[T]> {
+        Gc {
+            handle: self.handle,
+            metadata: N,
+            _ph: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized + GcPointee> Gc<T> {
+    /// The raw handle of the object. Used by [`letroot!`].
+    #[doc(hidden)]
+    pub fn __handle(self) -> Handle {
+        self.handle
+    }
+
+    /// The handle of the object, as a handle of the strategy that allocated it.
+    pub(crate) fn strategy_handle<H: StrategyHandle>(self) -> H {
+        H::from_raw(self.handle)
+    }
+
+    /// Returns whether two `Gc`s refer to the same GC object. This compares handles rather than addresses, so the
+    /// result is unaffected by objects being moved by the GC. Metadata, such as slice lengths, is not compared.
+    pub fn ptr_eq(this: Self, other: Self) -> bool {
+        this.handle == other.handle
+    }
+}
+
 // if we copy a Gc<T> out of a root, what happens when the root goes away?
 // should Gc<T> be Copy at all? Maybe it should just be a storage type, and
 // all access comes from Root<T>
 
-impl<T: ?Sized> Clone for Gc<T> {
+impl<T: ?Sized + GcPointee> Clone for Gc<T> {
     fn clone(&self) -> Self {
         *self
     }
 }
 
-impl<T: ?Sized> Copy for Gc<T> {}
+impl<T: ?Sized + GcPointee> Copy for Gc<T> {}
+
+/// Formats the handle and the type of the object, e.g. `Gc<alloc::string::String>(42)`. For trait objects, the type
+/// is the trait object type rather than the concrete type of the object.
+impl<T: ?Sized + GcPointee> fmt::Debug for Gc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Gc<{}>({:?})", type_name::<T>(), self.handle)
+    }
+}
+
+/// Compares by identity, see [`Gc::ptr_eq`].
+impl<T: ?Sized + GcPointee> PartialEq for Gc<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Gc::ptr_eq(*self, *other)
+    }
+}
+
+impl<T: ?Sized + GcPointee> Eq for Gc<T> {}
+
+/// Hashes the handle, consistent with [`PartialEq`].
+impl<T: ?Sized + GcPointee> Hash for Gc<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.handle.hash(state);
+    }
+}
+
+/// Orders by handle. The order is arbitrary, but does not change while the objects are alive, even if they are moved.
+impl<T: ?Sized + GcPointee> PartialOrd for Gc<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: ?Sized + GcPointee> Ord for Gc<T> {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        self.handle.cmp(&other.handle)
+    }
+}
 
 // Safety: Gc<T> exposes the API of a shared reference (i.e. allows shared access but not dropping)
-unsafe impl<T: ?Sized + Sync> Send for Gc<T> {}
-unsafe impl<T: ?Sized + Sync> Sync for Gc<T> {}
+unsafe impl<T: ?Sized + GcPointee + Sync> Send for Gc<T> {}
+unsafe impl<T: ?Sized + GcPointee + Sync> Sync for Gc<T> {}
 
-pub struct Root<'root, S: ?Sized + GcStrategy, T: ?Sized> {
+/// A rooted GC object. The object is kept alive for as long as the root exists, and can be accessed through `Deref`.
+///
+/// The object is pinned the first time it is accessed, and stays pinned until the root is dropped. Roots that are
+/// never dereferenced do not pin their object, so moving strategies are free to relocate it.
+///
+/// # Thread Safety
+/// A root only gives shared access to its object and its strategy, so it is `Send` and `Sync` when both the object
+/// type and the strategy are `Sync`. The same holds for [`Pinned`], [`PinnedPtr`], and [`MappedRoot`]. Roots borrow
+/// their strategy, so they can be sent to scoped threads, but not to tasks that must be `'static`; convert them into
+/// an [`OwnedRoot`] for that.
+pub struct Root<'root, S: ?Sized + GcStrategy, T: ?Sized + GcPointee> {
     handle: Gc<T>,
     gc: &'root S,
+    /// The address of the object, if it has been pinned by this root.
+    pinned: AtomicPtr<()>,
 }
 
-impl<S: ?Sized + GcStrategy, T: ?Sized> Deref for Root<'_, S, T> {
-    type Target = Gc<T>;
+impl<'root, S: ?Sized + GcStrategy, T: ?Sized + GcPointee> Root<'root, S, T> {
+    /// The GC handle of the rooted object.
+    pub fn gc(&self) -> Gc<T> {
+        self.handle
+    }
+
+    /// Returns a reference to the rooted object, pinning it if it is not already pinned by this root.
+    pub fn get(&self) -> &T {
+        let mut ptr = self.pinned.load(Ordering::Acquire);
+        if ptr.is_null() {
+            debug_assert!(self.gc.contains(self.handle.strategy_handle()));
+            let addr = self.gc.pin(self.handle.strategy_handle()).cast_mut();
+            match self.pinned.compare_exchange(
+                core::ptr::null_mut(),
+                addr,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => ptr = addr,
+                Err(existing) => {
+                    // another thread pinned the object first
+                    self.gc.unpin(self.handle.strategy_handle());
+                    ptr = existing;
+                }
+            }
+        }
+        // SAFETY: the strategy returns a non-null address for pinned objects
+        let data = unsafe { NonNull::new_unchecked(ptr) };
+        let ptr = T::from_raw_parts(data, self.handle.metadata);
+        // SAFETY: the object is rooted, so it is initialized and alive, and pinned, so it is not moved for the
+        // lifetime of this borrow. The GC never accesses the object mutably while it is rooted.
+        unsafe { ptr.as_ref() }
+    }
+
+    /// Returns the current address of the rooted object. If the object is not pinned, the address is only valid
+    /// until the strategy next moves objects, such as during an allocation, so it must not be held across any
+    /// operation on the heap. Use [`Root::pin`] or [`Root::pin_ptr`] for an address that stays valid.
+    pub fn as_ptr(&self) -> *const T {
+        let mut ptr = self.pinned.load(Ordering::Acquire).cast_const();
+        if ptr.is_null() {
+            debug_assert!(self.gc.contains(self.handle.strategy_handle()));
+            ptr = self.gc.resolve(self.handle.strategy_handle());
+        }
+        // SAFETY: the strategy returns non-null addresses
+        let data = unsafe { NonNull::new_unchecked(ptr.cast_mut()) };
+        T::from_raw_parts(data, self.handle.metadata).as_ptr()
+    }
+
+    /// Projects the root to part of the object, such as one of its fields, e.g. `root.map(|node| &node.name)`. The
+    /// object is pinned for as long as the projection exists, so the projected reference remains valid even under
+    /// compacting strategies.
+    pub fn map<U: ?Sized>(self, f: impl FnOnce(&T) -> &U) -> MappedRoot<'root, S, T, U> {
+        MappedRoot {
+            ptr: NonNull::from(f(self.get())),
+            root: self,
+        }
+    }
+
+    /// Pins the rooted object, returning a guard that keeps the object at a stable address until it is dropped.
+    pub fn pin(&self) -> Pinned<'_, S, T> {
+        debug_assert!(self.gc.contains(self.handle.strategy_handle()));
+        let addr = self.gc.pin(self.handle.strategy_handle()).cast_mut();
+        // SAFETY: the strategy returns a non-null address for pinned objects
+        let data = unsafe { NonNull::new_unchecked(addr) };
+        Pinned {
+            handle: self.handle.handle,
+            ptr: T::from_raw_parts(data, self.handle.metadata),
+            gc: self.gc,
+        }
+    }
+
+    /// Roots and pins the object for the lifetime of the returned pointer, independently of this root. See
+    /// [`PinnedPtr`].
+    pub fn pin_ptr(&self) -> PinnedPtr<'root, S, T> {
+        debug_assert!(self.gc.contains(self.handle.strategy_handle()));
+        self.gc.root(self.handle.strategy_handle());
+        let addr = self
+            .gc
+            .pin_long_lived(self.handle.strategy_handle())
+            .cast_mut();
+        // SAFETY: the strategy returns a non-null address for pinned objects
+        let data = unsafe { NonNull::new_unchecked(addr) };
+        PinnedPtr {
+            handle: self.handle,
+            ptr: T::from_raw_parts(data, self.handle.metadata),
+            gc: self.gc,
+        }
+    }
+
+    /// Returns the number of collections the object has survived, if the strategy keeps track. See
+    /// [`Collector::age`](crate::heap::Collector::age).
+    pub fn age(&self) -> Option<u32> {
+        self.gc.age(self.handle.strategy_handle())
+    }
+
+    /// Moves the object to the oldest generation of the heap, for objects known to be long-lived. See
+    /// [`Collector::promote`](crate::heap::Collector::promote).
+    pub fn promote(&self) {
+        self.gc.promote(self.handle.strategy_handle());
+    }
+
+    /// Invokes the write barrier for every `Gc` in `value`, as having been stored into this object. Types with their
+    /// own interior mutability call this after writing to the object; the cells in the [`cell`] module do so
+    /// automatically.
+    pub fn write_barrier<V: ?Sized + Trace>(&self, value: &V) {
+        cell::write_barrier(self, value);
+    }
+
+    /// Stores a value into a [`Cell`](core::cell::Cell) field of the object, invoking the write barrier, e.g.
+    /// `root.set_field(|node| &node.next, next)`.
+    ///
+    /// # Panics
+    /// Panics if `field` returns a cell that is not part of the object.
+    pub fn set_field<V: Copy + Trace>(
+        &self,
+        field: impl FnOnce(&T) -> &core::cell::Cell<V>,
+        value: V,
+    ) {
+        let field = field(self.get());
+        cell::assert_within(field, self);
+        field.set(value);
+        self.write_barrier(&value);
+    }
+}
+
+impl<'root, S: ?Sized + GcStrategy, T> Root<'root, S, T> {
+    /// Converts this root into a root of a trait object type. Use [`gc_unsize!`] instead of calling this directly.
+    ///
+    /// # Safety
+    /// `coerce` must return its argument, unsized to `U`.
+    pub unsafe fn unsize_unchecked<U>(self, coerce: fn(*const T) -> *const U) -> Root<'root, S, U>
+    where
+        U: ?Sized + GcPointee<Metadata = DynMetadata<U>>,
+    {
+        // the root and pin are transferred to the new value
+        let this = ManuallyDrop::new(self);
+        Root {
+            // SAFETY: caller
+            handle: unsafe { this.handle.unsize_unchecked(coerce) },
+            gc: this.gc,
+            pinned: AtomicPtr::new(this.pinned.load(Ordering::Acquire)),
+        }
+    }
+
+    /// Converts this root into a root of a trait object type that `T` implements. See [`Gc::unsize`].
+    #[cfg(feature = "nightly-unsize")]
+    pub fn unsize<U>(self) -> Root<'root, S, U>
+    where
+        T: core::marker::Unsize<U>,
+        U: ?Sized + GcPointee<Metadata = DynMetadata<U>>,
+    {
+        // SAFETY: casting a thin pointer to a pointer to an unsized supertype is an unsizing coercion.
+        unsafe { self.unsize_unchecked(|ptr| ptr as *const U) }
+    }
+
+    /// Converts this root into its raw handle without unrooting the object. The object stays rooted until the root is
+    /// recovered with [`Root::from_raw`] and dropped.
+    pub fn into_raw(self) -> usize {
+        let this = ManuallyDrop::new(self);
+        if !this.pinned.load(Ordering::Acquire).is_null() {
+            this.gc.unpin(this.handle.strategy_handle());
+        }
+        this.handle.into_raw()
+    }
+
+    /// Recovers a root from a raw handle returned by [`Root::into_raw`].
+    ///
+    /// # Safety
+    /// `raw` must have been returned by [`Root::into_raw`] on a `Root<T>` of the given heap, and each raw handle may
+    /// only be recovered once.
+    pub unsafe fn from_raw<'lifetime>(heap: &'root GcHeap<'lifetime, S>, raw: usize) -> Self {
+        // SAFETY: caller
+        let handle = unsafe { Gc::from_raw(raw) };
+        debug_assert!(
+            heap.contains(handle),
+            "{handle:?} does not belong to this heap"
+        );
+        Root {
+            handle,
+            gc: &heap.strategy,
+            pinned: AtomicPtr::new(core::ptr::null_mut()),
+        }
+    }
+}
+
+impl<'root, S: ?Sized + GcStrategy, T> Root<'root, S, MaybeUninit<T>> {
+    /// The address of the uninitialized object, which may be written to until [`Root::assume_init`] is called.
+    pub fn as_mut_ptr(&self) -> *mut T {
+        self.get();
+        self.pinned.load(Ordering::Acquire).cast()
+    }
+
+    /// Marks the object initialized, returning a root to the initialized value.
+    ///
+    /// # Safety
+    /// The object must have been allocated by [`GcHeap::alloc_uninit`] and must now hold an initialized `T`. No other
+    /// root to the object may be converted with this method.
+    pub unsafe fn assume_init(self) -> Root<'root, S, T> {
+        let this = ManuallyDrop::new(self);
+        // SAFETY: caller. Initializing the object releases the pin held by the root.
+        unsafe { this.gc.set_initialized(this.handle.strategy_handle()) };
+        Root {
+            handle: Gc {
+                handle: this.handle.handle,
+                metadata: (),
+                _ph: PhantomData,
+            },
+            gc: this.gc,
+            pinned: AtomicPtr::new(core::ptr::null_mut()),
+        }
+    }
+}
+
+impl<'root, S: ?Sized + GcStrategy, T, const N: usize> Root<'root, S, [T; N]> {
+    /// Converts a root of an array into a root of a slice.
+    pub fn unsize_slice(self) -> Root<'root, S, [T]> {
+        // the root and pin are transferred to the new value
+        let this = ManuallyDrop::new(self);
+        Root {
+            handle: this.handle.unsize_slice(),
+            gc: this.gc,
+            pinned: AtomicPtr::new(this.pinned.load(Ordering::Acquire)),
+        }
+    }
+}
+
+macro_rules! impl_downcast {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl Gc<$ty> {
+                /// Returns a `Gc` of the concrete type of the object, if it is `T`. The type of the object is looked
+                /// up in the given heap, which must be the heap the object was allocated in.
+                pub fn downcast<T: Any, S: ?Sized + GcStrategy>(self, heap: &GcHeap<'_, S>) -> Option<Gc<T>> {
+                    // only `'static` types can be unsized to `dyn Any`, so a matching type ID means the object is a `T`
+                    (heap.strategy.vtable(self.strategy_handle()).type_id() == TypeId::of::<T>()).then_some(Gc {
+                        handle: self.handle,
+                        metadata: (),
+                        _ph: PhantomData,
+                    })
+                }
+            }
+
+            impl<'root, S: ?Sized + GcStrategy> Root<'root, S, $ty> {
+                /// Converts this root into a root of the concrete type of the object, if it is `T`. Otherwise, the
+                /// root is returned unchanged.
+                pub fn downcast<T: Any>(self) -> Result<Root<'root, S, T>, Self> {
+                    // as above
+                    if self.gc.vtable(self.handle.strategy_handle()).type_id() != TypeId::of::<T>() {
+                        return Err(self);
+                    }
+                    // the root and pin are transferred to the new value
+                    let this = ManuallyDrop::new(self);
+                    Ok(Root {
+                        handle: Gc {
+                            handle: this.handle.handle,
+                            metadata: (),
+                            _ph: PhantomData,
+                        },
+                        gc: this.gc,
+                        pinned: AtomicPtr::new(this.pinned.load(Ordering::Acquire)),
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_downcast! {
+    dyn Any,
+    dyn Any + Send,
+    dyn Any + Send + Sync,
+}
+
+impl<S: ?Sized + GcStrategy, T: ?Sized + GcPointee> Deref for Root<'_, S, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.get()
+    }
+}
+
+/// Adds another root to the object. The new root does not share the pin of this root.
+impl<S: ?Sized + GcStrategy, T: ?Sized + GcPointee> Clone for Root<'_, S, T> {
+    fn clone(&self) -> Self {
+        self.gc.root(self.handle.strategy_handle());
+        Self {
+            handle: self.handle,
+            gc: self.gc,
+            pinned: AtomicPtr::new(core::ptr::null_mut()),
+        }
+    }
+}
+
+impl<S: ?Sized + GcStrategy, T: ?Sized + GcPointee> fmt::Debug for Root<'_, S, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Root")
+            .field("gc", &self.handle)
+            .field("pinned", &!self.pinned.load(Ordering::Relaxed).is_null())
+            .finish()
+    }
+}
+
+/// Unpins and unroots the underlying GC when going out of scope.
+impl<S: ?Sized + GcStrategy, T: ?Sized + GcPointee> Drop for Root<'_, S, T> {
+    fn drop(&mut self) {
+        if !self.pinned.get_mut().is_null() {
+            self.gc.unpin(self.handle.strategy_handle());
+        }
+        self.gc.unroot(self.handle.strategy_handle())
+    }
+}
+
+/// A pinned GC object, created by [`Root::pin`]. The object is not moved until the guard is dropped.
+pub struct Pinned<'root, S: ?Sized + GcStrategy, T: ?Sized> {
+    handle: Handle,
+    ptr: NonNull<T>,
+    gc: &'root S,
+}
+
+// Safety: the guard only gives shared access to the object and the strategy, see `Root`.
+unsafe impl<S: ?Sized + GcStrategy + Sync, T: ?Sized + Sync> Send for Pinned<'_, S, T> {}
+unsafe impl<S: ?Sized + GcStrategy + Sync, T: ?Sized + Sync> Sync for Pinned<'_, S, T> {}
+
+impl<S: ?Sized + GcStrategy, T: ?Sized> Pinned<'_, S, T> {
+    /// The address of the pinned object. The address is stable for the lifetime of the guard.
+    pub fn as_ptr(&self) -> *const T {
+        self.ptr.as_ptr()
+    }
+}
+
+impl<S: ?Sized + GcStrategy, T: ?Sized> Deref for Pinned<'_, S, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: the guard borrows the root, so the object is alive, and the object is pinned.
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+/// Unpins the underlying GC when going out of scope.
+impl<S: ?Sized + GcStrategy, T: ?Sized> Drop for Pinned<'_, S, T> {
+    fn drop(&mut self) {
+        self.gc.unpin(S::Handle::from_raw(self.handle))
+    }
+}
+
+/// A pointer to a rooted and pinned GC object, created by [`Root::pin_ptr`]. Unlike [`Pinned`], this keeps its own
+/// root, so it does not borrow the root it was created from. The address of the object stays valid until the
+/// `PinnedPtr` is dropped, so it can be handed to foreign code that holds on to it across callbacks.
+///
+/// The object is pinned with [`GcAllocator::pin_long_lived`](crate::heap::GcAllocator::pin_long_lived), so that
+/// strategies can account for such pins separately.
+pub struct PinnedPtr<'root, S: ?Sized + GcStrategy, T: ?Sized + GcPointee> {
+    handle: Gc<T>,
+    ptr: NonNull<T>,
+    gc: &'root S,
+}
+
+// Safety: see `Pinned`
+unsafe impl<S: ?Sized + GcStrategy + Sync, T: ?Sized + GcPointee + Sync> Send
+    for PinnedPtr<'_, S, T>
+{
+}
+unsafe impl<S: ?Sized + GcStrategy + Sync, T: ?Sized + GcPointee + Sync> Sync
+    for PinnedPtr<'_, S, T>
+{
+}
+
+impl<S: ?Sized + GcStrategy, T: ?Sized + GcPointee> PinnedPtr<'_, S, T> {
+    /// The address of the pinned object. The address is stable for the lifetime of the `PinnedPtr`.
+    pub fn as_ptr(&self) -> *const T {
+        self.ptr.as_ptr()
+    }
+
+    /// The GC handle of the pinned object.
+    pub fn gc(&self) -> Gc<T> {
+        self.handle
+    }
+}
+
+impl<S: ?Sized + GcStrategy, T: ?Sized + GcPointee> Deref for PinnedPtr<'_, S, T> {
+    type Target = T;
 
     fn deref(&self) -> &Self::Target {
-        &self.handle
+        // SAFETY: the object is rooted and pinned for as long as `self` exists.
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+/// Adds another root and pin to the object.
+impl<S: ?Sized + GcStrategy, T: ?Sized + GcPointee> Clone for PinnedPtr<'_, S, T> {
+    fn clone(&self) -> Self {
+        self.gc.root(self.handle.strategy_handle());
+        self.gc.pin_long_lived(self.handle.strategy_handle());
+        Self {
+            handle: self.handle,
+            ptr: self.ptr,
+            gc: self.gc,
+        }
+    }
+}
+
+impl<S: ?Sized + GcStrategy, T: ?Sized + GcPointee> fmt::Debug for PinnedPtr<'_, S, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PinnedPtr")
+            .field("gc", &self.handle)
+            .field("ptr", &self.ptr)
+            .finish()
     }
 }
 
-/// Unroots the underlying GC when going out of scope.
-impl<S: ?Sized + GcStrategy, T: ?Sized> Drop for Root<'_, S, T> {
+/// Unpins and unroots the underlying GC when going out of scope.
+impl<S: ?Sized + GcStrategy, T: ?Sized + GcPointee> Drop for PinnedPtr<'_, S, T> {
     fn drop(&mut self) {
-        self.gc.unroot(self.handle.handle)
+        self.gc.unpin_long_lived(self.handle.strategy_handle());
+        self.gc.unroot(self.handle.strategy_handle())
+    }
+}
+
+/// A scope in which objects are pinned together, created by [`GcHeap::pin_scope`]. Every object pinned through the
+/// scope is also rooted, and stays rooted and pinned until the scope ends, when all of them are released together.
+#[cfg(feature = "alloc")]
+pub struct PinScope<'scope, S: ?Sized + GcStrategy> {
+    pinned: core::cell::RefCell<alloc::vec::Vec<S::Handle>>,
+    gc: &'scope S,
+}
+
+#[cfg(feature = "alloc")]
+impl<S: ?Sized + GcStrategy> PinScope<'_, S> {
+    /// Pins the object for the rest of the scope, returning a reference to it.
+    ///
+    /// # Panics
+    /// Panics if `root` was not created by the heap of this scope.
+    pub fn pin<'a, T: ?Sized + GcPointee>(&'a self, root: &Root<'_, S, T>) -> &'a T {
+        let [value] = self.pin_all([root]);
+        value
+    }
+
+    /// Pins several objects for the rest of the scope with a single call to
+    /// [`GcAllocator::pin_all`](crate::heap::GcAllocator::pin_all), returning references to them, e.g. to pass their
+    /// addresses to a foreign function.
+    ///
+    /// # Panics
+    /// Panics if any root was not created by the heap of this scope.
+    pub fn pin_all<'a, T: ?Sized + GcPointee, const N: usize>(
+        &'a self,
+        roots: [&Root<'_, S, T>; N],
+    ) -> [&'a T; N] {
+        for root in roots {
+            assert!(
+                core::ptr::eq(root.gc, self.gc),
+                "root does not belong to this heap"
+            );
+        }
+        let handles = roots.map(|root| root.handle.strategy_handle());
+        let mut addrs = [core::ptr::null(); N];
+        self.gc.root_all(&handles);
+        self.gc.pin_all(&handles, &mut addrs);
+        self.pinned.borrow_mut().extend_from_slice(&handles);
+        core::array::from_fn(|idx| {
+            // SAFETY: the strategy returns non-null addresses for pinned objects
+            let data = unsafe { NonNull::new_unchecked(addrs[idx].cast_mut()) };
+            let ptr = T::from_raw_parts(data, roots[idx].handle.metadata);
+            // SAFETY: the object stays rooted and pinned until the scope is dropped
+            unsafe { ptr.as_ref() }
+        })
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S: ?Sized + GcStrategy> fmt::Debug for PinScope<'_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PinScope")
+            .field("pinned", &self.pinned.borrow().len())
+            .finish()
+    }
+}
+
+/// Unpins and unroots every object pinned through the scope when it ends.
+#[cfg(feature = "alloc")]
+impl<S: ?Sized + GcStrategy> Drop for PinScope<'_, S> {
+    fn drop(&mut self) {
+        let pinned = self.pinned.get_mut();
+        self.gc.unpin_all(pinned);
+        self.gc.unroot_all(pinned);
+    }
+}
+
+/// A reference to part of a rooted GC object, such as one of its fields, created by [`Root::map`]. The object stays
+/// rooted and pinned for as long as the projection exists.
+pub struct MappedRoot<'root, S: ?Sized + GcStrategy, T: ?Sized + GcPointee, U: ?Sized> {
+    root: Root<'root, S, T>,
+    ptr: NonNull<U>,
+}
+
+// Safety: the projection only gives shared access to part of the object, see `Root`.
+unsafe impl<S, T, U> Send for MappedRoot<'_, S, T, U>
+where
+    S: ?Sized + GcStrategy + Sync,
+    T: ?Sized + GcPointee + Sync,
+    U: ?Sized + Sync,
+{
+}
+unsafe impl<S, T, U> Sync for MappedRoot<'_, S, T, U>
+where
+    S: ?Sized + GcStrategy + Sync,
+    T: ?Sized + GcPointee + Sync,
+    U: ?Sized + Sync,
+{
+}
+
+impl<'root, S: ?Sized + GcStrategy, T: ?Sized + GcPointee, U: ?Sized> MappedRoot<'root, S, T, U> {
+    /// Projects further into the object.
+    pub fn map<V: ?Sized>(self, f: impl FnOnce(&U) -> &V) -> MappedRoot<'root, S, T, V> {
+        MappedRoot {
+            ptr: NonNull::from(f(&*self)),
+            root: self.root,
+        }
+    }
+
+    /// The root of the whole object.
+    pub fn root(&self) -> &Root<'root, S, T> {
+        &self.root
+    }
+}
+
+impl<S: ?Sized + GcStrategy, T: ?Sized + GcPointee, U: ?Sized> Deref for MappedRoot<'_, S, T, U> {
+    type Target = U;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: the reference was derived from the object, which is kept alive and pinned by the root.
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<S: ?Sized + GcStrategy, T: ?Sized + GcPointee, U: ?Sized + fmt::Debug> fmt::Debug
+    for MappedRoot<'_, S, T, U>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// A root that shares ownership of its heap, so that it is not bound to the lifetime of a heap borrow. Owned roots
+/// can be stored in long-lived registries or moved into spawned tasks.
+///
+/// An owned root is `Send` and `Sync` when the object type is `Sync` and the strategy is `Send` and `Sync`, as
+/// required to share the heap through an `Arc`.
+#[cfg(feature = "alloc")]
+pub struct OwnedRoot<S: GcStrategy + 'static, T: ?Sized + GcPointee> {
+    // the root borrows the strategy inside `heap`, so it must be dropped first
+    root: Root<'static, S, T>,
+    heap: alloc::sync::Arc<GcHeap<'static, S>>,
+}
+
+#[cfg(feature = "alloc")]
+impl<S: GcStrategy + 'static> GcHeap<'static, S> {
+    /// Allocates a new GC object, returning an owned root to it.
+    pub fn alloc_owned<T: Trace + Send + 'static>(
+        self: &alloc::sync::Arc<Self>,
+        value: T,
+    ) -> OwnedRoot<S, T> {
+        OwnedRoot::new(self, self.alloc(value))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S: GcStrategy + 'static, T: ?Sized + GcPointee> OwnedRoot<S, T> {
+    /// Converts a root into an owned root. The root and any pin it holds are transferred to the owned root.
+    ///
+    /// # Panics
+    /// Panics if `root` was not created by `heap`.
+    pub fn new(heap: &alloc::sync::Arc<GcHeap<'static, S>>, root: Root<'_, S, T>) -> Self {
+        assert!(
+            core::ptr::eq(root.gc, &heap.strategy),
+            "root does not belong to this heap"
+        );
+        let root = ManuallyDrop::new(root);
+        // SAFETY: the strategy is kept alive by the `Arc` for as long as the root exists, at the same address.
+        let gc = unsafe { &*(&heap.strategy as *const S) };
+        Self {
+            root: Root {
+                handle: root.handle,
+                gc,
+                pinned: AtomicPtr::new(root.pinned.load(Ordering::Acquire)),
+            },
+            heap: heap.clone(),
+        }
+    }
+
+    pub fn heap(&self) -> &alloc::sync::Arc<GcHeap<'static, S>> {
+        &self.heap
+    }
+
+    /// The GC handle of the rooted object.
+    pub fn gc(&self) -> Gc<T> {
+        self.root.handle
+    }
+
+    /// Borrows this owned root as a plain root.
+    pub fn as_root(&self) -> &Root<'_, S, T> {
+        &self.root
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S: GcStrategy + 'static, T: ?Sized + GcPointee> Clone for OwnedRoot<S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            heap: self.heap.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S: GcStrategy + 'static, T: ?Sized + GcPointee> Deref for OwnedRoot<S, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.root.get()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S: GcStrategy + 'static, T: ?Sized + GcPointee> fmt::Debug for OwnedRoot<S, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("OwnedRoot").field(&self.root).finish()
+    }
+}
+
+/// A collection of rooted GC objects, such as the entries of a global table. Objects are rooted when inserted, and
+/// unrooted when removed or when the set is dropped.
+///
+/// An object may be inserted more than once, in which case it stays rooted until every copy has been removed.
+#[cfg(feature = "alloc")]
+pub struct RootSet<'heap, S: ?Sized + GcStrategy, T: ?Sized + GcPointee> {
+    gcs: alloc::vec::Vec<Gc<T>>,
+    gc: &'heap S,
+}
+
+#[cfg(feature = "alloc")]
+impl<'heap, S: ?Sized + GcStrategy, T: ?Sized + GcPointee> RootSet<'heap, S, T> {
+    pub fn new<'lifetime>(heap: &'heap GcHeap<'lifetime, S>) -> Self {
+        Self {
+            gcs: alloc::vec::Vec::new(),
+            gc: &heap.strategy,
+        }
+    }
+
+    /// Adds the object referenced by the given root to the set.
+    ///
+    /// # Panics
+    /// Panics if `root` was not created by the heap of this set.
+    pub fn insert(&mut self, root: &Root<'_, S, T>) {
+        assert!(
+            core::ptr::eq(root.gc, self.gc),
+            "root does not belong to this heap"
+        );
+        // SAFETY: the object is rooted, so it is alive.
+        unsafe { self.insert_all(core::slice::from_ref(&root.handle)) }
+    }
+
+    /// Adds the given objects to the set.
+    ///
+    /// # Safety
+    /// Each `Gc` must satisfy the safety contract of [`GcHeap::root`] for the heap of this set.
+    pub unsafe fn insert_all(&mut self, gcs: &[Gc<T>]) {
+        for gc in gcs {
+            debug_assert!(
+                self.gc.contains(gc.strategy_handle()),
+                "{gc:?} does not belong to this heap"
+            );
+        }
+        for_each_batch::<S>(gcs.iter().map(|gc| gc.handle), |batch| {
+            self.gc.root_all(batch)
+        });
+        self.gcs.extend_from_slice(gcs);
+    }
+
+    /// Removes one copy of the given object from the set, returning whether it was present.
+    pub fn remove(&mut self, gc: Gc<T>) -> bool {
+        match self.gcs.iter().position(|&g| g == gc) {
+            Some(idx) => {
+                self.gcs.swap_remove(idx);
+                self.gc.unroot(gc.strategy_handle());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes all objects from the set.
+    pub fn clear(&mut self) {
+        for_each_batch::<S>(self.gcs.drain(..).map(|gc| gc.handle), |batch| {
+            self.gc.unroot_all(batch)
+        });
+    }
+
+    pub fn contains(&self, gc: Gc<T>) -> bool {
+        self.gcs.contains(&gc)
+    }
+
+    pub fn len(&self) -> usize {
+        self.gcs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.gcs.is_empty()
+    }
+
+    /// Iterates over the objects in the set, in no particular order.
+    pub fn iter(&self) -> core::iter::Copied<core::slice::Iter<'_, Gc<T>>> {
+        self.gcs.iter().copied()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, S: ?Sized + GcStrategy, T: ?Sized + GcPointee> IntoIterator for &'a RootSet<'_, S, T> {
+    type Item = Gc<T>;
+    type IntoIter = core::iter::Copied<core::slice::Iter<'a, Gc<T>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S: ?Sized + GcStrategy, T: ?Sized + GcPointee> fmt::Debug for RootSet<'_, S, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(&self.gcs).finish()
+    }
+}
+
+/// Unroots every object in the set when going out of scope.
+#[cfg(feature = "alloc")]
+impl<S: ?Sized + GcStrategy, T: ?Sized + GcPointee> Drop for RootSet<'_, S, T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    #[cfg(feature = "std")]
+    use alloc::sync::Arc;
+    use alloc::vec::Vec;
+    use core::{
+        any::Any,
+        cell::Cell,
+        ptr,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    #[cfg(feature = "std")]
+    use crate::heap::{
+        CollectOptions, CollectionStats, Collector, FreshAllocation, GcAllocator, GcStrategy,
+        GcVtable, HeapStats, RootManager,
+    };
+    use crate::{
+        cell::GcCell,
+        heap::AllocError,
+        strategies::{MarkSweep, Semispace},
+        testing::Node,
+        GcHeap, Root, RootSet,
+    };
+
+    #[test]
+    fn oversized_slices_have_an_invalid_layout() {
+        let heap = GcHeap::new(MarkSweep::new());
+        let error = heap
+            .try_alloc_from_iter(core::iter::repeat_n(0u64, usize::MAX / 4))
+            .unwrap_err();
+        assert_eq!(error, AllocError::InvalidLayout);
+        assert_eq!(
+            heap.try_alloc_from_iter([1u64, 2]).as_deref(),
+            Ok(&[1, 2][..])
+        );
+    }
+
+    #[test]
+    fn letroot_evaluates_the_heap_once() {
+        let heap = GcHeap::new(MarkSweep::new());
+        let (first, second) = (heap.alloc(1u32), heap.alloc(2u32));
+        let (a, b) = (first.gc(), second.gc());
+        let evaluations = Cell::new(0);
+        let heap_ref = || {
+            evaluations.set(evaluations.get() + 1);
+            &heap
+        };
+        // SAFETY: both objects are rooted
+        letroot!(unsafe heap_ref(); a = a, b = b);
+        assert_eq!(evaluations.get(), 1);
+        drop((first, second));
+        heap.collect();
+        assert_eq!((*a, *b), (1, 2));
+    }
+
+    #[test]
+    fn downcasting_checks_the_type() {
+        let heap = GcHeap::new(MarkSweep::new());
+        macro_rules! check {
+            ($($ty:tt)+) => {{
+                let root = crate::gc_unsize!(heap.alloc(7u32) => $($ty)+);
+                let gc = root.gc();
+                assert!(gc.downcast::<u64, _>(&heap).is_none());
+                // SAFETY: the object is rooted
+                assert_eq!(*unsafe { heap.root(gc.downcast::<u32, _>(&heap).unwrap()) }, 7);
+                let Err(root) = root.downcast::<u64>() else {
+                    panic!("downcast to the wrong type succeeded");
+                };
+                // the root is returned unchanged, and still keeps the object alive
+                heap.collect();
+                assert_eq!(heap.stats().object_count, 1);
+                let Ok(root) = root.downcast::<u32>() else {
+                    panic!("downcast to the right type failed");
+                };
+                assert_eq!(*root, 7);
+                drop(root);
+                heap.collect();
+                assert_eq!(heap.stats().object_count, 0);
+            }};
+        }
+        check!(dyn Any);
+        check!(dyn Any + Send);
+        check!(dyn Any + Send + Sync);
+    }
+
+    trait Shape: Sync {
+        fn area(&self) -> u32;
+    }
+
+    crate::gc_dyn!(dyn Shape);
+
+    struct Square(u32);
+
+    struct Rect(u32, u32);
+
+    crate::unsafe_empty_trace! { Square Rect }
+
+    impl Shape for Square {
+        fn area(&self) -> u32 {
+            self.0 * self.0
+        }
+    }
+
+    impl Shape for Rect {
+        fn area(&self) -> u32 {
+            self.0 * self.1
+        }
+    }
+
+    #[test]
+    fn unsized_handles_survive_moving_collections() {
+        let heap = GcHeap::new(Semispace::new());
+        let square = crate::gc_unsize!(heap.alloc(Square(3)) => dyn Shape);
+        let rect = crate::gc_unsize!(heap.alloc(Rect(2, 5)).gc() => dyn Shape);
+        let holder = heap.alloc(GcCell::new(Some(rect)));
+        let slice = heap.alloc([1u32, 2, 3]).unsize_slice();
+        let (square_addr, slice_addr) = (square.as_ptr(), slice.as_ptr());
+        heap.collect();
+        assert!(!ptr::addr_eq(square.as_ptr(), square_addr));
+        assert!(!ptr::addr_eq(slice.as_ptr(), slice_addr));
+        assert_eq!(square.area(), 9);
+        assert_eq!(slice.iter().sum::<u32>(), 6);
+        // the handle stored in the holder was traced, and updated along with its metadata
+        let rect = holder.get().get().unwrap();
+        // SAFETY: the handle is read from an object that is rooted
+        assert_eq!(unsafe { heap.root(rect) }.area(), 10);
+    }
+
+    #[test]
+    fn root_sets_keep_objects_alive() {
+        let drops = AtomicUsize::new(0);
+        let heap = GcHeap::new(MarkSweep::new());
+        let mut set = RootSet::new(&heap);
+        let roots = (0..10)
+            .map(|value| heap.alloc(Node::new(&drops, value, None)))
+            .collect::<Vec<_>>();
+        let gcs = roots.iter().map(Root::gc).collect::<Vec<_>>();
+        // SAFETY: the objects are rooted
+        unsafe { set.insert_all(&gcs) };
+        set.insert(&roots[0]);
+        drop(roots);
+        heap.collect();
+        assert_eq!(drops.load(Ordering::Relaxed), 0);
+        assert_eq!(set.len(), 11);
+        // the first object is still in the set once more
+        assert!(set.remove(gcs[0]));
+        heap.collect();
+        assert_eq!(drops.load(Ordering::Relaxed), 0);
+        set.clear();
+        heap.collect();
+        assert_eq!(drops.load(Ordering::Relaxed), 10);
+
+        let root = heap.alloc(Node::new(&drops, 10, None));
+        let mut set = RootSet::new(&heap);
+        // SAFETY: as above
+        unsafe { set.insert_all(&[root.gc(), root.gc()]) };
+        drop(root);
+        heap.collect();
+        assert_eq!(drops.load(Ordering::Relaxed), 10);
+        drop(set);
+        heap.collect();
+        assert_eq!(drops.load(Ordering::Relaxed), 11);
+    }
+
+    /// A strategy that may be shared between threads, for tests that hand a heap from one thread to another and
+    /// never use it from two at once.
+    #[cfg(feature = "std")]
+    struct Exclusive<S> {
+        strategy: S,
+    }
+
+    // SAFETY: the strategy is only used by one thread at a time, and handed over with `spawn` and `join`, which
+    // synchronize
+    #[cfg(feature = "std")]
+    unsafe impl<S> Send for Exclusive<S> {}
+    #[cfg(feature = "std")]
+    unsafe impl<S> Sync for Exclusive<S> {}
+
+    // SAFETY: every method is passed through to the wrapped strategy
+    #[cfg(feature = "std")]
+    unsafe impl<S: GcStrategy> GcAllocator for Exclusive<S> {
+        type Handle = S::Handle;
+        type Error = S::Error;
+
+        delegate! { strategy =>
+            fn allocate(&self, vtable: &'static GcVtable, len: usize) -> Result<FreshAllocation<S::Handle>, S::Error>;
+            unsafe fn set_initialized(&self, obj: S::Handle);
+            unsafe fn set_finalized(&self, obj: S::Handle);
+            unsafe fn reclaim(&self, obj: S::Handle);
+            fn pin(&self, obj: S::Handle) -> *const ();
+            fn unpin(&self, obj: S::Handle);
+            fn resolve(&self, obj: S::Handle) -> *const ();
+            fn contains(&self, obj: S::Handle) -> bool;
+            fn vtable(&self, obj: S::Handle) -> &'static GcVtable;
+        }
+    }
+
+    // SAFETY: as above
+    #[cfg(feature = "std")]
+    unsafe impl<S: GcStrategy> RootManager<S::Handle> for Exclusive<S> {
+        delegate! { strategy =>
+            fn root(&self, obj: S::Handle);
+            fn unroot(&self, obj: S::Handle);
+        }
+    }
+
+    // SAFETY: as above
+    #[cfg(feature = "std")]
+    unsafe impl<S: GcStrategy> Collector<S::Handle> for Exclusive<S> {
+        delegate! { strategy =>
+            fn collect(&self, options: CollectOptions) -> CollectionStats;
+            fn stats(&self) -> HeapStats;
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn owned_roots_keep_the_heap_alive_on_other_threads() {
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+        let heap = Arc::new(GcHeap::new(Exclusive {
+            strategy: MarkSweep::new(),
+        }));
+        let root = heap.alloc_owned(Node::new(&DROPS, 7, None));
+        heap.alloc(Node::new(&DROPS, 8, None));
+        drop(heap);
+        std::thread::spawn(move || {
+            // the owned root holds the last reference to the heap
+            assert_eq!(Arc::strong_count(root.heap()), 1);
+            root.heap().collect();
+            assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+            assert_eq!(root.value, 7);
+            drop(root);
+            // dropping the heap finalizes the remaining object
+            assert_eq!(DROPS.load(Ordering::Relaxed), 2);
+        })
+        .join()
+        .unwrap();
     }
 }
@@ -0,0 +1,266 @@
+//! Limits on the size of a GC heap.
+//!
+//! [`HeapLimit`] wraps another strategy and caps the number of bytes of heap space its objects may occupy, e.g. to
+//! give each tenant of a service its own budget. When an allocation would exceed the cap, the limit first runs a
+//! full collection, and only fails the allocation if that does not free enough space. A callback registered with
+//! [`HeapLimit::on_threshold`] warns the application before that point is reached.
+//!
+//! The limit is enforced against [`HeapStats::bytes_live`], so the wrapped strategy must report it.
+
+use core::{
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{
+    heap::{
+        AllocError, AllocHint, CollectOptions, CollectionStats, Collector, FreshAllocation,
+        GcAllocator, GcStrategy, GcVtable, HeapStats, MemoryPressure, RelocationListener,
+        RootManager, ShrinkStats, StepBudget, StepResult, ThreadToken,
+    },
+    trace::RootProvider,
+};
+
+/// A strategy that limits the heap space used by another strategy. See the [module documentation](self).
+///
+/// Every other operation is passed through to the wrapped strategy unchanged.
+pub struct HeapLimit<S, F = fn(&HeapStats)> {
+    strategy: S,
+    max_bytes: usize,
+    /// The usage at which the callback is invoked, and the callback itself.
+    threshold: Option<(usize, F)>,
+    /// Whether the usage has crossed the threshold since the callback was last invoked. The callback is invoked
+    /// again once the usage has fallen below the threshold and crossed it anew.
+    crossed: AtomicBool,
+}
+
+impl<S: GcStrategy> HeapLimit<S> {
+    /// Limits the heap space used by objects of `strategy` to `max_bytes`.
+    pub fn new(strategy: S, max_bytes: usize) -> Self {
+        Self {
+            strategy,
+            max_bytes,
+            threshold: None,
+            crossed: AtomicBool::new(false),
+        }
+    }
+}
+
+impl<S: GcStrategy, F: Fn(&HeapStats)> HeapLimit<S, F> {
+    /// Registers a callback that is invoked when an allocation brings the heap usage to `fraction` of the limit or
+    /// above, before any allocation fails. The callback receives the statistics of the heap at that point, and may
+    /// e.g. log a warning or shed load. It is invoked on the allocating thread, and must not allocate in the heap.
+    ///
+    /// # Panics
+    /// Panics if `fraction` is not between 0 and 1.
+    pub fn on_threshold<G: Fn(&HeapStats)>(self, fraction: f64, callback: G) -> HeapLimit<S, G> {
+        assert!(
+            (0.0..=1.0).contains(&fraction),
+            "threshold {fraction} is not between 0 and 1"
+        );
+        HeapLimit {
+            strategy: self.strategy,
+            max_bytes: self.max_bytes,
+            threshold: Some(((self.max_bytes as f64 * fraction) as usize, callback)),
+            crossed: AtomicBool::new(false),
+        }
+    }
+
+    /// The maximum number of bytes of heap space objects may occupy.
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// The wrapped strategy.
+    pub fn strategy(&self) -> &S {
+        &self.strategy
+    }
+
+    /// Invokes the threshold callback if the heap usage after allocating `size` bytes crosses the threshold.
+    fn check_threshold(&self, stats: &HeapStats, size: usize) {
+        let Some((threshold, callback)) = &self.threshold else {
+            return;
+        };
+        if stats.bytes_live.saturating_add(size) < *threshold {
+            self.crossed.store(false, Ordering::Relaxed);
+        } else if !self.crossed.swap(true, Ordering::Relaxed) {
+            callback(stats);
+        }
+    }
+
+    /// Checks that an allocation fits within the limit, collecting if it does not.
+    fn reserve(&self, vtable: &GcVtable, len: usize) -> Result<(), S::Error> {
+        let size = match vtable.layout(len) {
+            Some(layout) => layout.size(),
+            None => return Err(AllocError::InvalidLayout.into()),
+        };
+        let mut stats = self.strategy.stats();
+        if !self.fits(&stats, size) {
+            self.strategy.collect(CollectOptions::default());
+            stats = self.strategy.stats();
+            if !self.fits(&stats, size) {
+                return Err(AllocError::OutOfMemory.into());
+            }
+        }
+        self.check_threshold(&stats, size);
+        Ok(())
+    }
+
+    /// Returns whether an allocation of `size` bytes fits within the limit.
+    fn fits(&self, stats: &HeapStats, size: usize) -> bool {
+        stats
+            .bytes_live
+            .checked_add(size)
+            .is_some_and(|bytes| bytes <= self.max_bytes)
+    }
+}
+
+impl<S: fmt::Debug, F> fmt::Debug for HeapLimit<S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HeapLimit")
+            .field("strategy", &self.strategy)
+            .field("max_bytes", &self.max_bytes)
+            .field(
+                "threshold",
+                &self.threshold.as_ref().map(|(bytes, _)| bytes),
+            )
+            .finish()
+    }
+}
+
+// SAFETY: every method is passed through to the wrapped strategy, except that allocations may fail early.
+unsafe impl<S: GcStrategy, F: Fn(&HeapStats)> GcAllocator for HeapLimit<S, F> {
+    type Handle = S::Handle;
+    type Error = S::Error;
+
+    fn allocate(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+    ) -> Result<FreshAllocation<Self::Handle>, Self::Error> {
+        self.reserve(vtable, len)?;
+        self.strategy.allocate(vtable, len)
+    }
+
+    fn allocate_with_hint(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+        hint: AllocHint,
+    ) -> Result<FreshAllocation<Self::Handle>, Self::Error> {
+        self.reserve(vtable, len)?;
+        self.strategy.allocate_with_hint(vtable, len, hint)
+    }
+
+    unsafe fn allocate_for_thread(
+        &self,
+        token: ThreadToken,
+        vtable: &'static GcVtable,
+        len: usize,
+        hint: AllocHint,
+    ) -> Result<FreshAllocation<Self::Handle>, Self::Error> {
+        self.reserve(vtable, len)?;
+        // SAFETY: caller
+        unsafe { self.strategy.allocate_for_thread(token, vtable, len, hint) }
+    }
+
+    delegate! { strategy =>
+        fn register_thread(&self) -> ThreadToken;
+        unsafe fn unregister_thread(&self, token: ThreadToken);
+        unsafe fn set_initialized(&self, obj: S::Handle);
+        unsafe fn set_finalized(&self, obj: S::Handle);
+        unsafe fn reclaim(&self, obj: S::Handle);
+        fn pin(&self, obj: S::Handle) -> *const ();
+        fn unpin(&self, obj: S::Handle);
+        fn pin_all(&self, objs: &[S::Handle], addrs: &mut [*const ()]);
+        fn unpin_all(&self, objs: &[S::Handle]);
+        fn resolve(&self, obj: S::Handle) -> *const ();
+        fn pin_long_lived(&self, obj: S::Handle) -> *const ();
+        fn unpin_long_lived(&self, obj: S::Handle);
+        fn shrink(&self) -> ShrinkStats;
+        fn contains(&self, obj: S::Handle) -> bool;
+        fn vtable(&self, obj: S::Handle) -> &'static GcVtable;
+    }
+}
+
+// SAFETY: every method is passed through to the wrapped strategy.
+unsafe impl<S: GcStrategy, F> RootManager<S::Handle> for HeapLimit<S, F> {
+    delegate! { strategy =>
+        fn root(&self, obj: S::Handle);
+        fn unroot(&self, obj: S::Handle);
+        fn root_all(&self, objs: &[S::Handle]);
+        fn unroot_all(&self, objs: &[S::Handle]);
+        fn freeze(&self, obj: S::Handle);
+        unsafe fn register_root_provider(&self, provider: &dyn RootProvider);
+        unsafe fn unregister_root_provider(&self, provider: &dyn RootProvider);
+    }
+}
+
+// SAFETY: every method is passed through to the wrapped strategy.
+unsafe impl<S: GcStrategy, F> Collector<S::Handle> for HeapLimit<S, F> {
+    delegate! { strategy =>
+        fn write_barrier(&self, parent: S::Handle, child: S::Handle);
+        fn allocate_weak(&self, target: S::Handle) -> Option<S::Handle>;
+        unsafe fn upgrade_weak(&self, cell: S::Handle) -> Option<S::Handle>;
+        fn allocate_ephemeron(&self, key: S::Handle, value: S::Handle) -> Option<S::Handle>;
+        unsafe fn upgrade_ephemeron(&self, cell: S::Handle) -> Option<(S::Handle, S::Handle)>;
+        fn poll_safepoint(&self);
+        fn enter_safepoint(&self);
+        fn leave_safepoint(&self);
+        fn collect(&self, options: CollectOptions) -> CollectionStats;
+        fn collect_step(&self, budget: StepBudget) -> StepResult;
+        fn memory_pressure(&self, level: MemoryPressure);
+        unsafe fn register_relocation_listener(&self, listener: &dyn RelocationListener);
+        unsafe fn unregister_relocation_listener(&self, listener: &dyn RelocationListener);
+        fn age(&self, obj: S::Handle) -> Option<u32>;
+        fn promote(&self, obj: S::Handle);
+        fn stats(&self) -> HeapStats;
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::vec::Vec;
+    use core::cell::Cell;
+
+    use super::*;
+    use crate::{strategies::MarkSweep, GcHeap};
+
+    #[test]
+    fn fails_allocations_beyond_the_limit() {
+        let heap = GcHeap::new(HeapLimit::new(MarkSweep::new(), 64 << 10));
+        let mut roots = Vec::new();
+        let error = loop {
+            match heap.try_alloc([0u64; 64]) {
+                Ok(root) => roots.push(root),
+                Err(error) => break error,
+            }
+        };
+        assert_eq!(error, AllocError::OutOfMemory);
+        assert_eq!(roots.len(), (64 << 10) / 512);
+        roots.clear();
+        // the limit collects before failing
+        assert!(heap.try_alloc([0u64; 64]).is_ok());
+    }
+
+    #[test]
+    fn invokes_threshold_callback_once_per_crossing() {
+        let calls = Cell::new(0);
+        let limit = HeapLimit::new(MarkSweep::new(), 64 << 10)
+            .on_threshold(0.5, |_| calls.set(calls.get() + 1));
+        let heap = GcHeap::new(limit);
+        let mut roots = Vec::new();
+        for _ in 0..100 {
+            roots.push(heap.alloc([0u64; 64]));
+        }
+        assert_eq!(calls.get(), 1);
+        roots.clear();
+        heap.collect();
+        heap.alloc([0u64; 64]);
+        assert_eq!(calls.get(), 1);
+        for _ in 0..100 {
+            roots.push(heap.alloc([0u64; 64]));
+        }
+        assert_eq!(calls.get(), 2);
+    }
+}
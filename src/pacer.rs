@@ -0,0 +1,221 @@
+//! Policies for when to collect.
+//!
+//! Strategies decide how to collect, but the decision of when to collect is a matter of policy that depends on the
+//! application: a batch job wants as few collections as possible, while a game wants many short ones. Strategies
+//! that accept a [`GcPacer`] at construction consult it on allocation, and report each finished collection back to
+//! it. This module provides a [`ThroughputPacer`] and a [`LatencyPacer`].
+
+use core::time::Duration;
+
+use crate::heap::{CollectionKind, CollectionStats};
+
+/// The state of a heap as seen by a pacer. Strategies that do not keep track of some statistic leave it at zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct PacingInput {
+    /// The number of bytes allocated since the last collection.
+    pub bytes_allocated: usize,
+    /// The number of bytes of heap space in use, including garbage that has not been collected yet.
+    pub bytes_live: usize,
+    /// The number of bytes of heap space in use after the last major or full collection.
+    pub bytes_live_after_major: usize,
+    /// The fraction of the bytes allocated before the last minor collection that survived it, between 0 and 1.
+    pub survival_rate: f64,
+    /// The duration of the last pause.
+    pub last_pause: Duration,
+    /// The average duration of recent pauses, weighted as the strategy sees fit.
+    pub average_pause: Duration,
+}
+
+/// A policy that decides when a strategy should collect.
+pub trait GcPacer {
+    /// Returns the kind of collection to start, or None if the strategy should keep allocating. Strategies call
+    /// this often, e.g. whenever they refill an allocation buffer, so it should be cheap.
+    fn should_collect(&mut self, input: &PacingInput) -> Option<CollectionKind>;
+
+    /// Notifies the pacer that a collection has finished, so that it can adapt to the heap. `input` reflects the
+    /// state of the heap after the collection. The default implementation does nothing.
+    fn collection_finished(&mut self, stats: &CollectionStats, input: &PacingInput) {
+        let _ = (stats, input);
+    }
+}
+
+/// A pacer that minimizes the total time spent collecting, at the cost of longer pauses and a larger heap. It lets
+/// the heap grow by a fixed factor between major collections, and runs minor collections after a fixed amount of
+/// allocation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ThroughputPacer {
+    /// The number of bytes to allocate between minor collections.
+    pub nursery_bytes: usize,
+    /// The factor the heap may grow by between major collections.
+    pub growth_factor: f64,
+    /// The number of bytes of heap space below which no major collection is started.
+    pub min_heap_bytes: usize,
+}
+
+impl Default for ThroughputPacer {
+    fn default() -> Self {
+        Self {
+            nursery_bytes: 8 << 20,
+            growth_factor: 2.0,
+            min_heap_bytes: 64 << 20,
+        }
+    }
+}
+
+impl GcPacer for ThroughputPacer {
+    fn should_collect(&mut self, input: &PacingInput) -> Option<CollectionKind> {
+        let major_at = (input.bytes_live_after_major as f64 * self.growth_factor) as usize;
+        if input.bytes_live >= self.min_heap_bytes.max(major_at) {
+            Some(CollectionKind::Major)
+        } else if input.bytes_allocated >= self.nursery_bytes {
+            Some(CollectionKind::Minor)
+        } else {
+            None
+        }
+    }
+}
+
+/// A pacer that keeps pauses short, at the cost of collecting more often. It adapts the amount of allocation
+/// between minor collections so that pauses stay below a target, and starts major collections early so that each
+/// has less work to do.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LatencyPacer {
+    /// The longest pause the pacer aims for.
+    pub target_pause: Duration,
+    /// The factor the heap may grow by between major collections.
+    pub growth_factor: f64,
+    /// The current number of bytes to allocate between minor collections.
+    nursery_bytes: usize,
+}
+
+impl LatencyPacer {
+    /// The fewest bytes the pacer allocates between minor collections.
+    const MIN_NURSERY_BYTES: usize = 256 << 10;
+    /// The most bytes the pacer allocates between minor collections.
+    const MAX_NURSERY_BYTES: usize = 32 << 20;
+
+    /// Creates a pacer that aims for pauses no longer than `target_pause`.
+    pub fn new(target_pause: Duration) -> Self {
+        Self {
+            target_pause,
+            growth_factor: 1.5,
+            nursery_bytes: 2 << 20,
+        }
+    }
+
+    /// The current number of bytes to allocate between minor collections.
+    pub fn nursery_bytes(&self) -> usize {
+        self.nursery_bytes
+    }
+}
+
+impl Default for LatencyPacer {
+    /// A pacer that aims for pauses of one millisecond.
+    fn default() -> Self {
+        Self::new(Duration::from_millis(1))
+    }
+}
+
+impl GcPacer for LatencyPacer {
+    fn should_collect(&mut self, input: &PacingInput) -> Option<CollectionKind> {
+        let major_at = (input.bytes_live_after_major as f64 * self.growth_factor) as usize;
+        if input.bytes_live_after_major > 0 && input.bytes_live >= major_at {
+            Some(CollectionKind::Major)
+        } else if input.bytes_allocated >= self.nursery_bytes {
+            Some(CollectionKind::Minor)
+        } else {
+            None
+        }
+    }
+
+    fn collection_finished(&mut self, stats: &CollectionStats, input: &PacingInput) {
+        if stats.kind != Some(CollectionKind::Minor) {
+            return;
+        }
+        // pause times grow with the amount of surviving data, so scale the nursery to the target
+        self.nursery_bytes = if input.last_pause > self.target_pause {
+            self.nursery_bytes / 2
+        } else {
+            self.nursery_bytes + self.nursery_bytes / 4
+        }
+        .clamp(Self::MIN_NURSERY_BYTES, Self::MAX_NURSERY_BYTES);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The pacing input of a heap with the given statistics.
+    fn input(
+        bytes_allocated: usize,
+        bytes_live: usize,
+        bytes_live_after_major: usize,
+    ) -> PacingInput {
+        PacingInput {
+            bytes_allocated,
+            bytes_live,
+            bytes_live_after_major,
+            ..PacingInput::default()
+        }
+    }
+
+    #[test]
+    fn throughput_pacer_collects_after_growth() {
+        let mut pacer = ThroughputPacer::default();
+        assert_eq!(pacer.should_collect(&input(1 << 20, 1 << 20, 0)), None);
+        assert_eq!(
+            pacer.should_collect(&input(8 << 20, 8 << 20, 0)),
+            Some(CollectionKind::Minor)
+        );
+        // below the minimum heap size, the heap may grow beyond the growth factor
+        assert_eq!(pacer.should_collect(&input(0, 48 << 20, 16 << 20)), None);
+        assert_eq!(
+            pacer.should_collect(&input(0, 64 << 20, 16 << 20)),
+            Some(CollectionKind::Major)
+        );
+        assert_eq!(pacer.should_collect(&input(0, 100 << 20, 64 << 20)), None);
+        assert_eq!(
+            pacer.should_collect(&input(0, 128 << 20, 64 << 20)),
+            Some(CollectionKind::Major)
+        );
+    }
+
+    #[test]
+    fn latency_pacer_adapts_to_pauses() {
+        let mut pacer = LatencyPacer::new(Duration::from_millis(1));
+        let nursery_bytes = pacer.nursery_bytes();
+        assert_eq!(
+            pacer.should_collect(&input(nursery_bytes, nursery_bytes, 0)),
+            Some(CollectionKind::Minor)
+        );
+        let minor = CollectionStats {
+            kind: Some(CollectionKind::Minor),
+            ..CollectionStats::default()
+        };
+        let slow = PacingInput {
+            last_pause: Duration::from_millis(2),
+            ..PacingInput::default()
+        };
+        pacer.collection_finished(&minor, &slow);
+        assert_eq!(pacer.nursery_bytes(), nursery_bytes / 2);
+        pacer.collection_finished(&minor, &PacingInput::default());
+        assert!(pacer.nursery_bytes() > nursery_bytes / 2);
+        for _ in 0..100 {
+            pacer.collection_finished(&minor, &slow);
+        }
+        assert_eq!(pacer.nursery_bytes(), LatencyPacer::MIN_NURSERY_BYTES);
+        // major collections do not change the nursery
+        let major = CollectionStats {
+            kind: Some(CollectionKind::Major),
+            ..CollectionStats::default()
+        };
+        pacer.collection_finished(&major, &PacingInput::default());
+        assert_eq!(pacer.nursery_bytes(), LatencyPacer::MIN_NURSERY_BYTES);
+        assert_eq!(
+            pacer.should_collect(&input(0, 15 << 20, 10 << 20)),
+            Some(CollectionKind::Major)
+        );
+    }
+}
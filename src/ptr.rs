@@ -0,0 +1,164 @@
+use core::ptr::NonNull;
+
+use crate::trace::{EdgeContext, Trace, TraceContext};
+
+/// A type that can be the target of a [`Gc`](crate::Gc).
+///
+/// A [`Gc`](crate::Gc) stores the metadata needed to form a pointer to its object alongside the object's handle.
+/// For sized types, there is no metadata, and a `Gc<T>` is the same size as a handle. Slices and `str` store their
+/// length. Trait objects store a [`DynMetadata`], and must opt in using the [`gc_dyn!`](crate::gc_dyn) macro.
+///
+/// # Safety
+/// [`GcPointee::from_raw_parts`] must return a pointer with the same address as `data`.
+pub unsafe trait GcPointee {
+    /// The metadata stored alongside the handle in a [`Gc`](crate::Gc).
+    type Metadata: Copy + Send + Sync + 'static;
+
+    /// Forms a pointer to a GC object from its address and metadata.
+    fn from_raw_parts(data: NonNull<()>, metadata: Self::Metadata) -> NonNull<Self>;
+}
+
+/// SAFETY: a thin pointer cast preserves the address.
+unsafe impl<T> GcPointee for T {
+    type Metadata = ();
+
+    fn from_raw_parts(data: NonNull<()>, _: ()) -> NonNull<Self> {
+        data.cast()
+    }
+}
+
+/// SAFETY: a slice pointer has the address of its first element.
+unsafe impl<T> GcPointee for [T] {
+    type Metadata = usize;
+
+    fn from_raw_parts(data: NonNull<()>, len: usize) -> NonNull<Self> {
+        NonNull::slice_from_raw_parts(data.cast(), len)
+    }
+}
+
+/// SAFETY: a `str` pointer has the address of its first byte.
+unsafe impl GcPointee for str {
+    type Metadata = usize;
+
+    fn from_raw_parts(data: NonNull<()>, len: usize) -> NonNull<Self> {
+        let bytes = NonNull::slice_from_raw_parts(data.cast::<u8>(), len);
+        // SAFETY: `[u8]` and `str` have the same layout and metadata.
+        unsafe { NonNull::new_unchecked(bytes.as_ptr() as *mut str) }
+    }
+}
+
+/// A GC object made of a fixed set of fields followed by a slice whose length is chosen at allocation time, such as
+/// an instance of a class defined at run time. Allocate with
+/// [`GcHeap::alloc_header_slice`](crate::GcHeap::alloc_header_slice).
+#[repr(C)]
+pub struct HeaderSlice<H, T> {
+    pub header: H,
+    pub tail: [T],
+}
+
+/// SAFETY: a pointer to a slice-tailed struct has the address of the struct.
+unsafe impl<H, T> GcPointee for HeaderSlice<H, T> {
+    type Metadata = usize;
+
+    fn from_raw_parts(data: NonNull<()>, len: usize) -> NonNull<Self> {
+        let tail = NonNull::slice_from_raw_parts(data.cast::<T>(), len);
+        // SAFETY: the cast keeps the address and the length of the tail
+        unsafe { NonNull::new_unchecked(tail.as_ptr() as *mut Self) }
+    }
+}
+
+/// SAFETY: the header and each element are traced, as for a tuple followed by a slice.
+unsafe impl<H: Trace, T: Trace> Trace for HeaderSlice<H, T> {
+    const NEEDS_TRACE: bool = H::NEEDS_TRACE || T::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        self.header.trace(ctx);
+        self.tail.trace(ctx);
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        self.header.trace_edges(ctx);
+        self.tail.trace_edges(ctx);
+    }
+}
+
+/// The metadata for trait objects stored in a [`Gc`](crate::Gc).
+///
+/// Stable Rust cannot name the vtable of a trait object, so this instead stores the unsizing coercion that produced
+/// the trait object. The coercion is specific to the concrete type of the object, and is replayed on the object's
+/// address whenever a pointer is needed.
+pub struct DynMetadata<T: ?Sized> {
+    coerce: fn(*const ()) -> *const T,
+}
+
+impl<T: ?Sized> DynMetadata<T> {
+    /// Creates trait object metadata from an unsizing coercion from `U` to `T`.
+    ///
+    /// # Safety
+    /// `coerce` must return its argument, unsized to `T`. Typically this is a closure of the form
+    /// `|ptr| ptr as *const dyn Trait`.
+    pub unsafe fn new_unchecked<U>(coerce: fn(*const U) -> *const T) -> Self {
+        Self {
+            // SAFETY: thin pointers are ABI compatible, so the function may be called through either signature.
+            coerce: unsafe {
+                core::mem::transmute::<fn(*const U) -> *const T, fn(*const ()) -> *const T>(coerce)
+            },
+        }
+    }
+
+    /// Forms a pointer to a trait object from the address of its concrete value.
+    pub fn project(self, data: NonNull<()>) -> NonNull<T> {
+        let ptr = (self.coerce)(data.as_ptr());
+        // SAFETY: the coercion preserves the address, which is non-null.
+        unsafe { NonNull::new_unchecked(ptr as *mut T) }
+    }
+}
+
+impl<T: ?Sized> Clone for DynMetadata<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for DynMetadata<T> {}
+
+/// Allows a trait object type to be the target of a [`Gc`](crate::Gc), by implementing [`GcPointee`] for it.
+/// Auto trait combinations are distinct types, and need to be declared separately, e.g.
+/// `gc_dyn!(dyn Node); gc_dyn!(dyn Node + Send);`.
+#[macro_export]
+macro_rules! gc_dyn {
+    (dyn $($tr:tt)+) => {
+        // SAFETY: `DynMetadata` preserves the address of the object.
+        unsafe impl $crate::ptr::GcPointee for dyn $($tr)+ {
+            type Metadata = $crate::ptr::DynMetadata<Self>;
+
+            fn from_raw_parts(
+                data: ::core::ptr::NonNull<()>,
+                metadata: Self::Metadata,
+            ) -> ::core::ptr::NonNull<Self> {
+                metadata.project(data)
+            }
+        }
+    };
+}
+
+/// Converts a [`Gc`](crate::Gc) or [`Root`](crate::Root) of a concrete type into one of a trait object type, e.g.
+/// `gc_unsize!(root => dyn Node)`. The trait object type must have been declared with [`gc_dyn!`](crate::gc_dyn).
+///
+/// With the `nightly-unsize` feature, `Gc::unsize` and `Root::unsize` perform the same conversion without a macro.
+/// Arrays are converted to slices using [`Gc::unsize_slice`](crate::Gc::unsize_slice).
+#[macro_export]
+macro_rules! gc_unsize {
+    ($gc:expr => dyn $($tr:tt)+) => {
+        match $gc {
+            // SAFETY: casting a thin pointer to a trait object pointer is an unsizing coercion. The parentheses are
+            // needed when the trait object type has several bounds.
+            #[allow(unused_parens)]
+            gc => unsafe { gc.unsize_unchecked(|ptr| ptr as *const (dyn $($tr)+)) },
+        }
+    };
+}
+
+gc_dyn!(dyn core::any::Any);
+gc_dyn!(dyn core::any::Any + Send);
+gc_dyn!(dyn core::any::Any + Send + Sync);
@@ -0,0 +1,56 @@
+//! Ready-made strategies.
+//!
+//! The rest of this crate defines the contract between a [`GcHeap`](crate::GcHeap) and its strategy, but leaves
+//! the strategy itself to the embedder. The strategies in this module implement that contract in full, so they can
+//! be used directly by applications that have no special needs, and serve as a reference for strategy authors.
+//!
+//! - [`MarkSweep`] is a non-moving, stop-the-world mark-sweep collector with a segregated free-list allocator.
+//! - [`Semispace`] is a copying collector with bump allocation, which moves objects but only ever visits live ones.
+//! - [`Generational`] combines a copying nursery with a mark-sweep old space, so that most collections only visit
+//!   recently allocated objects.
+//! - [`Incremental`] is a non-moving mark-sweep collector that works in bounded steps, for applications that cannot
+//!   afford to pause for a whole collection.
+//! - [`RefCounting`] reclaims most objects shortly after they become unreferenced, without tracing, and finds garbage
+//!   cycles by trial deletion.
+//! - [`Leak`] never collects, and frees everything at once when the heap is dropped, for tests, benchmarks, and
+//!   short-lived heaps.
+//! - [`Arena`] never traces either, but reclaims everything at once when reset, and reuses the memory for the next
+//!   batch of objects.
+//! - [`SystemAlloc`] is a mark-sweep collector that allocates each object from the global allocator, so the heap
+//!   never holds free memory of its own.
+//!
+//! The strategies run finalizers on the collecting thread, once the objects being finalized are known to be
+//! unreachable but before their memory is reused. A collection requested while finalizers run, e.g. by a finalizer
+//! that allocates, does nothing, so such an allocation fails if the heap is full.
+//!
+//! With the `mmap` feature on Unix and Windows, `HeapRegion` reserves a contiguous range of address space and commits
+//! memory in it as needed, for strategies that need a contiguous heap.
+
+mod arena;
+mod bump;
+mod free_list;
+mod generational;
+mod incremental;
+mod leak;
+mod mark_sweep;
+#[cfg(feature = "std")]
+mod parallel;
+mod ref_counting;
+#[cfg(all(feature = "mmap", any(unix, windows)))]
+mod region;
+mod semispace;
+mod system_alloc;
+mod table;
+#[cfg(test)]
+mod tests;
+
+pub use arena::{Arena, ArenaOptions};
+pub use generational::{Generational, GenerationalOptions};
+pub use incremental::{Incremental, IncrementalOptions};
+pub use leak::{Leak, LeakOptions};
+pub use mark_sweep::{MarkSweep, MarkSweepOptions};
+pub use ref_counting::{RefCounting, RefCountingOptions};
+#[cfg(all(feature = "mmap", any(unix, windows)))]
+pub use region::{HeapRegion, HeapRegionOptions};
+pub use semispace::{Semispace, SemispaceOptions};
+pub use system_alloc::{SystemAlloc, SystemAllocOptions};
@@ -0,0 +1,476 @@
+use alloc::vec::Vec;
+use core::{cell::RefCell, fmt};
+
+use super::{
+    bump::Bump,
+    table::{self, Object, ObjectTable, State},
+};
+use crate::{
+    heap::{
+        AllocError, CollectOptions, CollectionKind, CollectionStats, Collector, FreshAllocation,
+        GcAllocator, GcVtable, GenerationalHandle, HeapStats, RootManager, ShrinkStats, StepBudget,
+        StepResult,
+    },
+    trace::RootProvider,
+};
+
+/// Options for an [`Arena`] heap. New options may be added in the future, so start from [`ArenaOptions::default`]
+/// and set the fields of interest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ArenaOptions {
+    /// The most bytes of memory the heap may reserve. Once the heap has reached this size, allocations fail with
+    /// [`AllocError::OutOfMemory`] until the arena is reset. Defaults to no limit.
+    pub max_heap_size: usize,
+    /// The number of bytes the heap grows by when the last chunk is full, which must not be zero. Objects larger
+    /// than a quarter of a chunk are allocated on their own. Defaults to 64 KiB.
+    pub chunk_size: usize,
+    /// Whether to finalize the objects reclaimed by a reset, or when the heap is dropped. Turning this off saves the
+    /// finalizer calls, but leaks the values in the arena instead of dropping them, so it only suits arenas of values
+    /// that own nothing outside the heap. Defaults to true.
+    pub finalize: bool,
+}
+
+impl Default for ArenaOptions {
+    fn default() -> Self {
+        Self {
+            max_heap_size: usize::MAX,
+            chunk_size: 64 << 10,
+            finalize: true,
+        }
+    }
+}
+
+/// A strategy that never traces, and reclaims every object at once when reset. See the
+/// [module documentation](super).
+///
+/// Objects are bump-allocated in chunks, like in [`Leak`](super::Leak). Instead of finding out which objects are
+/// unreachable, the arena keeps count of the roots and pins on all of its objects, and [`Arena::reset`] reclaims
+/// everything once there are none left. The chunks are then reused for the next batch of objects, so a heap that
+/// serves one request at a time only grows as large as its largest request. Every collection is a reset, and does
+/// nothing while any object is rooted, pinned, or frozen, or a root provider is registered.
+///
+/// Weak cells are supported, and do not hold up a reset: a reset clears the weak cells still in use, and reclaims
+/// the rest. Ephemerons are not supported. Like the other strategies in this module, the strategy is neither `Send`
+/// nor `Sync`.
+pub struct Arena {
+    heap: RefCell<Heap>,
+    options: ArenaOptions,
+}
+
+impl Arena {
+    /// Creates an empty heap with the default options.
+    pub fn new() -> Self {
+        Self::with_options(ArenaOptions::default())
+    }
+
+    /// Creates an empty heap with the given options.
+    ///
+    /// # Panics
+    /// Panics if `options.chunk_size` is zero.
+    pub fn with_options(options: ArenaOptions) -> Self {
+        assert!(options.chunk_size > 0, "chunk size is zero");
+        Self {
+            heap: RefCell::new(Heap {
+                objects: ObjectTable::new(),
+                memory: Bump::new(options.chunk_size),
+                holds: 0,
+                providers: 0,
+                bytes_live: 0,
+                collection_count: 0,
+            }),
+            options,
+        }
+    }
+
+    /// The options this heap was created with.
+    pub fn options(&self) -> &ArenaOptions {
+        &self.options
+    }
+
+    /// Reclaims every object in the arena, finalizing them first if [`ArenaOptions::finalize`] is set, and keeps the
+    /// memory they took up for new objects. Weak cells that are still rooted are cleared instead. Returns None,
+    /// leaving the arena as it was, if any other object is still rooted, pinned, or frozen, or a root provider is
+    /// registered, as nothing can be reclaimed then without tracing, and while finalizers run.
+    ///
+    /// # Panics
+    /// Panics if a finalizer leaves an object in the arena rooted.
+    pub fn reset(&self) -> Option<CollectionStats> {
+        {
+            let heap = self.heap.borrow();
+            if heap.holds > 0 || heap.providers > 0 || heap.objects.is_finalizing() {
+                return None;
+            }
+        }
+        // finalizers may allocate objects that need finalizing in turn
+        loop {
+            let finalize = {
+                let mut heap = self.heap.borrow_mut();
+                let mut finalize = Vec::new();
+                if self.options.finalize {
+                    for index in heap.objects.indices().collect::<Vec<_>>() {
+                        let object = heap.objects.at_mut(index);
+                        if object.state == State::Initialized && object.vtable.needs_finalize() {
+                            object.state = State::Finalized;
+                            finalize.push(index);
+                        }
+                    }
+                }
+                finalize
+            };
+            if finalize.is_empty() {
+                break;
+            }
+            table::run_finalizers(&self.heap, |heap| &heap.objects, &finalize);
+            let heap = self.heap.borrow();
+            assert!(
+                heap.holds == 0 && heap.providers == 0,
+                "an object was rooted while the arena was being reset"
+            );
+        }
+        let mut heap = self.heap.borrow_mut();
+        let mut stats = CollectionStats {
+            kind: Some(CollectionKind::Full),
+            ..CollectionStats::default()
+        };
+        for index in heap.objects.indices().collect::<Vec<_>>() {
+            let object = heap.objects.at_mut(index);
+            if object.weak.is_some() && object.roots > 0 {
+                object.weak = Some(None);
+                continue;
+            }
+            let object = heap.objects.remove(index);
+            stats.objects_reclaimed += 1;
+            stats.bytes_reclaimed += object.memory;
+        }
+        // SAFETY: every object but the weak cells, which take up no memory, has been removed, and none of them was
+        // held
+        unsafe { heap.memory.reset() };
+        heap.bytes_live = 0;
+        heap.collection_count += 1;
+        Some(stats)
+    }
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Arena {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let heap = self.heap.borrow();
+        f.debug_struct("Arena")
+            .field("heap_size", &heap.memory.heap_size())
+            .field("bytes_live", &heap.bytes_live)
+            .field("object_count", &heap.objects.object_count())
+            .field("options", &self.options)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        // the chunks are released when the heap is
+        if self.options.finalize {
+            self.heap.get_mut().objects.finalize_all();
+        }
+    }
+}
+
+// SAFETY: objects are only reclaimed by a reset while none of them is held, so none is reachable, or when the heap
+// is dropped. Objects never move. Initialized objects are finalized at most once, when they are reclaimed.
+unsafe impl GcAllocator for Arena {
+    type Handle = GenerationalHandle;
+    type Error = AllocError;
+
+    fn allocate(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+    ) -> Result<FreshAllocation<GenerationalHandle>, AllocError> {
+        let layout = vtable.layout(len).ok_or(AllocError::InvalidLayout)?;
+        let mut heap = self.heap.borrow_mut();
+        let (addr, size) = heap
+            .memory
+            .bump(layout, self.options.max_heap_size)
+            .ok_or(AllocError::OutOfMemory)?;
+        heap.bytes_live += size;
+        // fresh objects are rooted and pinned
+        heap.holds += 2;
+        let handle = heap.objects.insert(Object::fresh(addr, vtable, len, size));
+        Ok(FreshAllocation {
+            handle,
+            ptr: addr.as_ptr().cast(),
+        })
+    }
+
+    unsafe fn set_initialized(&self, obj: GenerationalHandle) {
+        let mut heap = self.heap.borrow_mut();
+        let object = heap.objects.get_mut(obj);
+        object.pins -= 1;
+        object.state = State::Initialized;
+        heap.holds -= 1;
+    }
+
+    unsafe fn set_finalized(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).state = State::Finalized;
+    }
+
+    unsafe fn reclaim(&self, obj: GenerationalHandle) {
+        let mut heap = self.heap.borrow_mut();
+        // the memory is reused once the arena is reset
+        let object = heap.objects.reclaim(obj);
+        if object.weak.is_none() {
+            heap.holds -= object.roots + object.pins;
+        }
+        heap.bytes_live -= object.memory;
+    }
+
+    fn pin(&self, obj: GenerationalHandle) -> *const () {
+        let mut heap = self.heap.borrow_mut();
+        let object = heap.objects.get_mut(obj);
+        object.pins += 1;
+        let addr = object.addr.as_ptr().cast();
+        heap.holds += 1;
+        addr
+    }
+
+    fn unpin(&self, obj: GenerationalHandle) {
+        let mut heap = self.heap.borrow_mut();
+        heap.objects.get_mut(obj).pins -= 1;
+        heap.holds -= 1;
+    }
+
+    fn pin_long_lived(&self, obj: GenerationalHandle) -> *const () {
+        self.heap.borrow_mut().objects.add_long_lived_pin();
+        self.pin(obj)
+    }
+
+    fn unpin_long_lived(&self, obj: GenerationalHandle) {
+        self.unpin(obj);
+        self.heap.borrow_mut().objects.remove_long_lived_pin();
+    }
+
+    fn resolve(&self, obj: GenerationalHandle) -> *const () {
+        self.heap.borrow().objects.get(obj).addr.as_ptr().cast()
+    }
+
+    fn shrink(&self) -> ShrinkStats {
+        let memory = &mut self.heap.borrow_mut().memory;
+        ShrinkStats {
+            bytes_released: memory.shrink(),
+            heap_size: memory.heap_size(),
+        }
+    }
+
+    fn contains(&self, obj: GenerationalHandle) -> bool {
+        self.heap.borrow().objects.lookup(obj).is_some()
+    }
+
+    fn vtable(&self, obj: GenerationalHandle) -> &'static GcVtable {
+        self.heap.borrow().objects.get(obj).vtable
+    }
+}
+
+// SAFETY: the arena is only reset while no object other than a weak cell is rooted and no root provider is
+// registered, and rooted weak cells are kept.
+unsafe impl RootManager<GenerationalHandle> for Arena {
+    fn root(&self, obj: GenerationalHandle) {
+        let mut heap = self.heap.borrow_mut();
+        let object = heap.objects.get_mut(obj);
+        object.roots += 1;
+        // weak cells do not hold up a reset
+        if object.weak.is_none() {
+            heap.holds += 1;
+        }
+    }
+
+    fn unroot(&self, obj: GenerationalHandle) {
+        let mut heap = self.heap.borrow_mut();
+        let object = heap.objects.get_mut(obj);
+        object.roots -= 1;
+        if object.weak.is_none() {
+            heap.holds -= 1;
+        }
+    }
+
+    fn freeze(&self, obj: GenerationalHandle) {
+        let mut heap = self.heap.borrow_mut();
+        let object = heap.objects.get_mut(obj);
+        if !object.frozen {
+            object.frozen = true;
+            heap.holds += 1;
+        }
+    }
+
+    unsafe fn register_root_provider(&self, provider: &dyn RootProvider) {
+        let _ = provider;
+        self.heap.borrow_mut().providers += 1;
+    }
+
+    unsafe fn unregister_root_provider(&self, provider: &dyn RootProvider) {
+        let _ = provider;
+        self.heap.borrow_mut().providers -= 1;
+    }
+}
+
+// SAFETY: a reset clears the weak cells it keeps, as it reclaims every other object.
+unsafe impl Collector<GenerationalHandle> for Arena {
+    fn allocate_weak(&self, target: GenerationalHandle) -> Option<GenerationalHandle> {
+        let mut heap = self.heap.borrow_mut();
+        heap.objects.get(target);
+        Some(heap.objects.insert(Object::weak(target, 0)))
+    }
+
+    unsafe fn upgrade_weak(&self, cell: GenerationalHandle) -> Option<GenerationalHandle> {
+        let mut heap = self.heap.borrow_mut();
+        let target = heap
+            .objects
+            .get(cell)
+            .weak
+            .expect("object is not a weak cell")?;
+        heap.objects.get_mut(target).roots += 1;
+        heap.holds += 1;
+        Some(target)
+    }
+
+    fn collect(&self, options: CollectOptions) -> CollectionStats {
+        let _ = options;
+        self.reset().unwrap_or_default()
+    }
+
+    fn collect_step(&self, budget: StepBudget) -> StepResult {
+        let _ = budget;
+        StepResult::Complete(self.reset().unwrap_or_default())
+    }
+
+    fn stats(&self) -> HeapStats {
+        let heap = self.heap.borrow();
+        HeapStats {
+            heap_size: heap.memory.heap_size(),
+            bytes_live: heap.bytes_live,
+            object_count: heap.objects.object_count(),
+            long_lived_pins: heap.objects.long_lived_pins(),
+            collection_count: heap.collection_count,
+            ..HeapStats::default()
+        }
+    }
+}
+
+/// The state of an [`Arena`] heap. The memory of each object is the number of bytes it takes up.
+struct Heap {
+    objects: ObjectTable<usize>,
+    memory: Bump,
+    /// The number of roots and pins on all objects other than weak cells, plus the number of frozen objects.
+    holds: usize,
+    /// The number of registered root providers.
+    providers: usize,
+    bytes_live: usize,
+    collection_count: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{
+        testing::{self, Node},
+        trace::TraceContext,
+        GcHeap,
+    };
+
+    #[test]
+    fn conformance() {
+        testing::run_all(Arena::new);
+    }
+
+    #[test]
+    fn reentrant_finalizers() {
+        testing::reentrant_finalizers(Arena::new());
+    }
+
+    /// A root provider with no roots.
+    struct NoRoots;
+
+    // SAFETY: there are no roots
+    unsafe impl RootProvider for NoRoots {
+        fn trace_roots(&self, ctx: &mut TraceContext<'_>) {
+            let _ = ctx;
+        }
+    }
+
+    #[test]
+    fn reset_waits_until_nothing_is_held() {
+        let drops = AtomicUsize::new(0);
+        let heap = GcHeap::new(Arena::new());
+        let root = heap.alloc(Node::new(&drops, 0, None));
+        heap.alloc(Node::new(&drops, 1, None));
+        assert_eq!(heap.strategy().reset(), None);
+
+        let pinned = root.pin_ptr();
+        drop(root);
+        assert_eq!(heap.strategy().reset(), None);
+        drop(pinned);
+
+        let frozen = heap.alloc(Node::new(&drops, 2, None));
+        heap.freeze(&frozen);
+        drop(frozen);
+        assert_eq!(heap.strategy().reset(), None);
+        assert_eq!(drops.load(Ordering::Relaxed), 0);
+
+        let heap = GcHeap::new(Arena::new());
+        heap.alloc(Node::new(&drops, 3, None));
+        heap.with_root_provider(&NoRoots, || assert_eq!(heap.strategy().reset(), None));
+        let stats = heap.strategy().reset().unwrap();
+        assert_eq!(stats.objects_reclaimed, 1);
+        assert_eq!(drops.load(Ordering::Relaxed), 1);
+        assert_eq!(heap.stats().object_count, 0);
+    }
+
+    #[test]
+    fn reset_clears_weak_cells() {
+        let heap = GcHeap::new(Arena::new());
+        let root = heap.alloc(1u32);
+        let weak = root.downgrade();
+        assert_eq!(heap.strategy().reset(), None);
+        drop(root);
+        let stats = heap.strategy().reset().unwrap();
+        assert_eq!(stats.objects_reclaimed, 1);
+        assert!(weak.upgrade().is_none());
+        drop(weak);
+        assert_eq!(heap.strategy().reset().unwrap().objects_reclaimed, 1);
+        assert_eq!(heap.stats().object_count, 0);
+    }
+
+    #[test]
+    fn reuses_memory_after_reset() {
+        let heap = GcHeap::new(Arena::new());
+        for _ in 0..1000 {
+            heap.alloc([0u64; 16]);
+        }
+        let heap_size = heap.stats().heap_size;
+        heap.collect();
+        for _ in 0..1000 {
+            heap.alloc([0u64; 16]);
+        }
+        assert_eq!(heap.stats().heap_size, heap_size);
+    }
+
+    #[test]
+    fn leaks_values_without_finalize() {
+        let drops = AtomicUsize::new(0);
+        let options = ArenaOptions {
+            finalize: false,
+            ..ArenaOptions::default()
+        };
+        let heap = GcHeap::new(Arena::with_options(options));
+        heap.alloc(Node::new(&drops, 0, None));
+        assert!(heap.strategy().reset().is_some());
+        heap.alloc(Node::new(&drops, 1, None));
+        drop(heap);
+        assert_eq!(drops.load(Ordering::Relaxed), 0);
+    }
+}
@@ -0,0 +1,152 @@
+use alloc::{
+    alloc::{alloc, dealloc},
+    vec::Vec,
+};
+use core::{alloc::Layout, ptr::NonNull};
+
+/// The alignment of every chunk.
+const CHUNK_ALIGN: usize = 16;
+
+/// A bump allocator over a list of chunks, which only frees memory all at once. Objects larger than a quarter of a
+/// chunk are allocated on their own, so that they do not waste the rest of a chunk.
+pub(super) struct Bump {
+    /// The chunks objects are allocated in, in the order they are filled.
+    chunks: Vec<Chunk>,
+    /// The index of the chunk being allocated in. The chunks after it are empty.
+    current: usize,
+    /// The offset in the current chunk of the next allocation.
+    top: usize,
+    /// The objects too large to allocate in a chunk.
+    large: Vec<Chunk>,
+    chunk_size: usize,
+    heap_size: usize,
+}
+
+/// A block of memory from the global allocator.
+struct Chunk {
+    base: NonNull<u8>,
+    layout: Layout,
+}
+
+impl Chunk {
+    fn new(layout: Layout) -> Option<Self> {
+        // SAFETY: chunks have a non-zero size
+        let base = NonNull::new(unsafe { alloc(layout) })?;
+        Some(Self { base, layout })
+    }
+
+    fn release(&self) {
+        // SAFETY: the chunk was allocated with this layout
+        unsafe { dealloc(self.base.as_ptr(), self.layout) };
+    }
+}
+
+impl Bump {
+    /// Creates an allocator with no chunks, which allocates memory `chunk_size` bytes at a time.
+    pub(super) fn new(chunk_size: usize) -> Self {
+        Self {
+            chunks: Vec::new(),
+            current: 0,
+            top: 0,
+            large: Vec::new(),
+            chunk_size,
+            heap_size: 0,
+        }
+    }
+
+    /// The number of bytes of memory reserved, whether or not it is in use.
+    pub(super) fn heap_size(&self) -> usize {
+        self.heap_size
+    }
+
+    /// Reserves memory for an object with the given layout, returning its address and the number of bytes it takes
+    /// up. Returns None if the heap would grow beyond `max_heap_size`, or the global allocator fails.
+    pub(super) fn bump(
+        &mut self,
+        layout: Layout,
+        max_heap_size: usize,
+    ) -> Option<(NonNull<u8>, usize)> {
+        // zero-sized objects still need an address of their own
+        let size = layout.size().max(1);
+        if size > self.chunk_size / 4 {
+            let layout = Layout::from_size_align(size, layout.align()).ok()?;
+            let chunk = self.reserve(layout, max_heap_size)?;
+            let addr = chunk.base;
+            self.large.push(chunk);
+            return Some((addr, size));
+        }
+        loop {
+            if let Some(chunk) = self.chunks.get(self.current) {
+                let base = chunk.base.as_ptr() as usize;
+                let start = (base + self.top).checked_next_multiple_of(layout.align())? - base;
+                if start + size <= chunk.layout.size() {
+                    self.top = start + size;
+                    // SAFETY: the object is within the chunk
+                    return Some((unsafe { chunk.base.add(start) }, size));
+                }
+                if self.current + 1 < self.chunks.len() {
+                    self.current += 1;
+                    self.top = 0;
+                    continue;
+                }
+            }
+            // objects are at most a quarter of a chunk, so any alignment up to that fits in a fresh one
+            let layout =
+                Layout::from_size_align(self.chunk_size, CHUNK_ALIGN.max(layout.align())).ok()?;
+            let chunk = self.reserve(layout, max_heap_size)?;
+            self.chunks.push(chunk);
+            self.current = self.chunks.len() - 1;
+            self.top = 0;
+        }
+    }
+
+    /// Allocates a chunk with the given layout, unless the heap would grow beyond `max_heap_size`.
+    fn reserve(&mut self, layout: Layout, max_heap_size: usize) -> Option<Chunk> {
+        let heap_size = self.heap_size.checked_add(layout.size())?;
+        if heap_size > max_heap_size {
+            return None;
+        }
+        let chunk = Chunk::new(layout)?;
+        self.heap_size = heap_size;
+        Some(chunk)
+    }
+
+    /// Frees every object at once, keeping the chunks to allocate in again, and releasing the memory of large
+    /// objects.
+    ///
+    /// # Safety
+    /// The memory of the objects allocated so far must no longer be used.
+    pub(super) unsafe fn reset(&mut self) {
+        for chunk in self.large.drain(..) {
+            self.heap_size -= chunk.layout.size();
+            chunk.release();
+        }
+        self.current = 0;
+        self.top = 0;
+    }
+
+    /// Releases the chunks that have not been allocated in since the last reset, returning the number of bytes
+    /// released.
+    pub(super) fn shrink(&mut self) -> usize {
+        let keep = if self.top == 0 {
+            self.current
+        } else {
+            self.current + 1
+        };
+        let mut released = 0;
+        for chunk in self.chunks.drain(keep..) {
+            released += chunk.layout.size();
+            chunk.release();
+        }
+        self.heap_size -= released;
+        released
+    }
+}
+
+impl Drop for Bump {
+    fn drop(&mut self) {
+        for chunk in self.chunks.iter().chain(&self.large) {
+            chunk.release();
+        }
+    }
+}
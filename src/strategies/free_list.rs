@@ -0,0 +1,240 @@
+use alloc::{
+    alloc::{alloc, dealloc},
+    vec::Vec,
+};
+use core::{alloc::Layout, ptr::NonNull};
+
+#[cfg(all(feature = "mmap", any(unix, windows)))]
+use super::region::RegionChunks;
+
+/// The sizes of the cells that small objects are allocated in. Objects larger than the largest class, or aligned to
+/// more than [`CELL_ALIGN`], are allocated on their own.
+const SIZE_CLASSES: [usize; 14] = [
+    16, 32, 48, 64, 96, 128, 192, 256, 384, 512, 768, 1024, 1536, 2048,
+];
+
+/// The alignment of every cell. Chunks are aligned to this, and every size class is a multiple of it.
+const CELL_ALIGN: usize = 16;
+
+/// The size of the largest cell, which is also the smallest possible chunk size.
+pub(super) const MAX_CELL_SIZE: usize = SIZE_CLASSES[SIZE_CLASSES.len() - 1];
+
+/// A segregated free-list allocator, shared by the non-moving spaces of the strategies in this module.
+///
+/// Small objects are allocated in cells of a fixed set of sizes, which are carved out of chunks of memory and kept
+/// on a free list for each size. Chunks come from the global allocator, or from a region if there is one. Large and
+/// overaligned objects are allocated directly from the global allocator.
+pub(super) struct FreeList {
+    /// The free cells of each size class.
+    free_cells: [Vec<NonNull<u8>>; SIZE_CLASSES.len()],
+    /// The chunks the cells are carved out of.
+    chunks: Vec<Chunk>,
+    chunk_size: usize,
+    heap_size: usize,
+    #[cfg(all(feature = "mmap", any(unix, windows)))]
+    region: Option<RegionChunks>,
+}
+
+/// Where the memory of an object came from.
+#[derive(Clone, Copy)]
+pub(super) enum Cell {
+    /// A cell of the given size class.
+    Small(usize),
+    /// A separate allocation with the given layout.
+    Large(Layout),
+}
+
+impl Cell {
+    pub(super) fn size(self) -> usize {
+        match self {
+            Self::Small(class) => SIZE_CLASSES[class],
+            Self::Large(layout) => layout.size(),
+        }
+    }
+}
+
+struct Chunk {
+    addr: NonNull<u8>,
+    class: usize,
+}
+
+impl FreeList {
+    /// Creates an empty allocator that grows by `chunk_size` bytes at a time, which must be at least
+    /// [`MAX_CELL_SIZE`].
+    pub(super) fn new(chunk_size: usize) -> Self {
+        debug_assert!(chunk_size >= MAX_CELL_SIZE);
+        Self {
+            free_cells: [const { Vec::new() }; SIZE_CLASSES.len()],
+            chunks: Vec::new(),
+            chunk_size,
+            heap_size: 0,
+            #[cfg(all(feature = "mmap", any(unix, windows)))]
+            region: None,
+        }
+    }
+
+    /// Creates an empty allocator like [`FreeList::new`], which carves its chunks out of a region of `region_size`
+    /// bytes, or allocates them from the global allocator if `region_size` is zero.
+    ///
+    /// # Panics
+    /// Panics if the region cannot be reserved.
+    #[cfg(all(feature = "mmap", any(unix, windows)))]
+    pub(super) fn with_region(
+        chunk_size: usize,
+        region_size: usize,
+        region_options: super::HeapRegionOptions,
+    ) -> Self {
+        let mut free_list = Self::new(chunk_size);
+        if region_size > 0 {
+            let region = super::HeapRegion::reserve(region_size, region_options)
+                .expect("cannot reserve the heap region");
+            free_list.region = Some(RegionChunks::new(region, chunk_size));
+        }
+        free_list
+    }
+
+    /// The number of bytes of memory reserved, whether or not it is in use.
+    pub(super) fn heap_size(&self) -> usize {
+        self.heap_size
+    }
+
+    fn chunk_layout(&self) -> Layout {
+        Layout::from_size_align(self.chunk_size, CELL_ALIGN).unwrap()
+    }
+
+    /// Allocates the memory of a chunk.
+    fn alloc_chunk(&mut self) -> Option<NonNull<u8>> {
+        #[cfg(all(feature = "mmap", any(unix, windows)))]
+        if let Some(region) = &mut self.region {
+            return region.alloc();
+        }
+        // SAFETY: chunks are at least `MAX_CELL_SIZE` bytes
+        NonNull::new(unsafe { alloc(self.chunk_layout()) })
+    }
+
+    /// Frees the memory of a chunk.
+    ///
+    /// # Safety
+    /// The chunk must have been allocated by [`FreeList::alloc_chunk`], and none of its cells may be used afterwards.
+    unsafe fn free_chunk(&mut self, addr: NonNull<u8>) {
+        #[cfg(all(feature = "mmap", any(unix, windows)))]
+        if let Some(region) = &mut self.region {
+            // SAFETY: caller
+            return unsafe { region.free(addr) };
+        }
+        // SAFETY: caller
+        unsafe { dealloc(addr.as_ptr(), self.chunk_layout()) };
+    }
+
+    /// Reserves memory with the given layout, or returns None if the allocator would grow beyond `max_heap_size`
+    /// bytes, or the global allocator fails.
+    pub(super) fn reserve(
+        &mut self,
+        layout: Layout,
+        max_heap_size: usize,
+    ) -> Option<(NonNull<u8>, Cell)> {
+        let class = SIZE_CLASSES
+            .iter()
+            .position(|&size| size >= layout.size())
+            .filter(|_| layout.align() <= CELL_ALIGN);
+        let Some(class) = class else {
+            // zero-sized overaligned objects still need an address of their own
+            let layout = Layout::from_size_align(layout.size().max(1), layout.align()).ok()?;
+            if self.heap_size.checked_add(layout.size())? > max_heap_size {
+                return None;
+            }
+            // SAFETY: the layout has a non-zero size
+            let addr = NonNull::new(unsafe { alloc(layout) })?;
+            self.heap_size += layout.size();
+            return Some((addr, Cell::Large(layout)));
+        };
+        if self.free_cells[class].is_empty() {
+            self.grow(class, max_heap_size)?;
+        }
+        let addr = self.free_cells[class].pop()?;
+        Some((addr, Cell::Small(class)))
+    }
+
+    /// Allocates a chunk and splits it into free cells of the given size class.
+    fn grow(&mut self, class: usize, max_heap_size: usize) -> Option<()> {
+        if self.heap_size.checked_add(self.chunk_size)? > max_heap_size {
+            return None;
+        }
+        let addr = self.alloc_chunk()?;
+        self.heap_size += self.chunk_size;
+        self.chunks.push(Chunk { addr, class });
+        let size = SIZE_CLASSES[class];
+        // in reverse, so that cells are handed out in address order
+        self.free_cells[class].extend(
+            (0..self.chunk_size / size)
+                .rev()
+                // SAFETY: the cell is within the chunk
+                .map(|i| unsafe { addr.add(i * size) }),
+        );
+        Some(())
+    }
+
+    /// Frees memory reserved by [`FreeList::reserve`].
+    ///
+    /// # Safety
+    /// `addr` and `cell` must have been returned together by `reserve`, and the memory must not be used afterwards.
+    pub(super) unsafe fn free(&mut self, addr: NonNull<u8>, cell: Cell) {
+        match cell {
+            Cell::Small(class) => self.free_cells[class].push(addr),
+            Cell::Large(layout) => {
+                // SAFETY: caller
+                unsafe { dealloc(addr.as_ptr(), layout) };
+                self.heap_size -= layout.size();
+            }
+        }
+    }
+
+    /// Frees the chunks whose cells are all free, returning the number of bytes released.
+    pub(super) fn shrink(&mut self) -> usize {
+        let Self {
+            free_cells,
+            chunks,
+            chunk_size,
+            ..
+        } = self;
+        let chunk_size = *chunk_size;
+        let mut released = Vec::new();
+        chunks.retain(|chunk| {
+            let start = chunk.addr.as_ptr() as usize;
+            let in_chunk = |cell: &NonNull<u8>| {
+                (start..start + chunk_size).contains(&(cell.as_ptr() as usize))
+            };
+            let free = &mut free_cells[chunk.class];
+            if free.iter().filter(|cell| in_chunk(cell)).count()
+                < chunk_size / SIZE_CLASSES[chunk.class]
+            {
+                return true;
+            }
+            free.retain(|cell| !in_chunk(cell));
+            released.push(chunk.addr);
+            false
+        });
+        for &addr in &released {
+            // SAFETY: the chunk was allocated in `FreeList::grow`, and none of its cells are in use
+            unsafe { self.free_chunk(addr) };
+        }
+        let bytes_released = released.len() * chunk_size;
+        self.heap_size -= bytes_released;
+        bytes_released
+    }
+}
+
+impl Drop for FreeList {
+    fn drop(&mut self) {
+        // large objects are freed along with the objects themselves, and a region releases all its chunks at once
+        #[cfg(all(feature = "mmap", any(unix, windows)))]
+        if self.region.is_some() {
+            return;
+        }
+        let chunk_layout = self.chunk_layout();
+        for chunk in &self.chunks {
+            // SAFETY: the chunk was allocated in `FreeList::grow` with this layout
+            unsafe { dealloc(chunk.addr.as_ptr(), chunk_layout) };
+        }
+    }
+}
@@ -0,0 +1,1211 @@
+use alloc::{
+    alloc::{alloc, dealloc},
+    vec::Vec,
+};
+use core::{
+    alloc::Layout,
+    cell::{RefCell, RefMut},
+    fmt, mem, ptr,
+    ptr::NonNull,
+    time::Duration,
+};
+
+use super::{
+    free_list::{Cell, FreeList, MAX_CELL_SIZE},
+    table::{self, Object, ObjectTable, State, Sweep},
+};
+use crate::{
+    heap::{
+        AllocError, AllocHint, CollectOptions, CollectionKind, CollectionStats, Collector,
+        FreshAllocation, GcAllocator, GcVtable, GenerationalHandle, Handle, HeapStats,
+        MemoryPressure, RelocationListener, RootManager, ShrinkStats, StepBudget, StepResult,
+    },
+    pacer::{GcPacer, PacingInput, ThroughputPacer},
+    trace::{RootProvider, TraceContext},
+};
+
+/// The size of each nursery block.
+const BLOCK_SIZE: usize = 32 << 10;
+
+/// The alignment of each nursery block.
+const BLOCK_ALIGN: usize = 16;
+
+/// The layout of each nursery block.
+const BLOCK_LAYOUT: Layout = match Layout::from_size_align(BLOCK_SIZE, BLOCK_ALIGN) {
+    Ok(layout) => layout,
+    Err(_) => panic!("invalid nursery block layout"),
+};
+
+/// The size from which objects are allocated directly in the old space, so that a nursery block never has more
+/// than a quarter of its space wasted at its end.
+const LARGE_OBJECT_SIZE: usize = BLOCK_SIZE / 4;
+
+/// Options for a [`Generational`] heap. New options may be added in the future, so start from
+/// [`GenerationalOptions::default`] and set the fields of interest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct GenerationalOptions {
+    /// The number of bytes that may be allocated in the nursery between minor collections, which must be at least
+    /// 32 KiB. A larger nursery gives objects more time to die before they are collected, at the cost of longer
+    /// minor pauses. Defaults to 4 MiB.
+    pub nursery_size: usize,
+    /// The number of minor collections an object must survive before it is promoted to the old space, which must
+    /// be at least 1. Until then, it is copied within the nursery by each minor collection. Defaults to 2.
+    pub promotion_threshold: u32,
+    /// The most bytes of memory the old space may reserve, including free space. Once the old space has reached
+    /// this size, an allocation that does not fit in its free space runs a major collection, and fails with
+    /// [`AllocError::OutOfMemory`] if that does not free enough. Survivors that do not fit stay in the nursery.
+    /// Defaults to no limit.
+    pub max_old_size: usize,
+    /// The number of bytes the old space grows by when it runs out of cells of some size, which must be at least
+    /// 2 KiB. Defaults to 64 KiB.
+    pub chunk_size: usize,
+    /// The number of threads that mark the heap during major collections, including the collecting thread, which
+    /// must be at least 1. Minor collections copy objects as they mark them, so they always run on the collecting
+    /// thread alone, as does all marking without the `std` feature. Defaults to 1.
+    pub mark_workers: usize,
+    /// The number of bytes of address space to reserve up front in a [`HeapRegion`](super::HeapRegion) for the
+    /// chunks of the old space, or zero to allocate chunks from the global allocator, like
+    /// [`MarkSweepOptions::region_size`](super::MarkSweepOptions::region_size). Nursery blocks are always allocated
+    /// from the global allocator. Only available on Unix and Windows with the `mmap` feature. Defaults to zero.
+    #[cfg(all(feature = "mmap", any(unix, windows)))]
+    pub region_size: usize,
+    /// The options of the region reserved for the chunks of the old space, if [`GenerationalOptions::region_size`] is
+    /// not zero. Only available on Unix and Windows with the `mmap` feature.
+    #[cfg(all(feature = "mmap", any(unix, windows)))]
+    pub region_options: super::HeapRegionOptions,
+}
+
+impl Default for GenerationalOptions {
+    fn default() -> Self {
+        Self {
+            nursery_size: 4 << 20,
+            promotion_threshold: 2,
+            max_old_size: usize::MAX,
+            chunk_size: 64 << 10,
+            mark_workers: 1,
+            #[cfg(all(feature = "mmap", any(unix, windows)))]
+            region_size: 0,
+            #[cfg(all(feature = "mmap", any(unix, windows)))]
+            region_options: super::HeapRegionOptions::default(),
+        }
+    }
+}
+
+/// A generational strategy with a copying nursery and a mark-sweep old space. See the
+/// [module documentation](super).
+///
+/// Objects are bump-allocated in the nursery, which is made of fixed-size blocks. Once the nursery is full, or the
+/// pacer asks for a minor collection, the young objects reachable from roots, root providers, and the remembered
+/// set are copied into fresh nursery blocks, and those that have survived enough minor collections are promoted
+/// into the old space instead. Emptied blocks are reused. The old space is managed like a
+/// [`MarkSweep`](super::MarkSweep) heap, and is only collected by major collections, which mark and sweep both
+/// generations without moving anything. Large and overaligned objects, and those allocated with [`AllocHint::Old`],
+/// go straight into the old space.
+///
+/// This crate has no interface for remembered sets of its own, so the strategy keeps one: each old object that may
+/// refer to young objects is recorded when [`Collector::write_barrier`] reports such a store, or when it is promoted
+/// with young children, and is traced as a root by minor collections until it no longer refers to any. Objects
+/// allocated directly in the old space are remembered until their first minor collection, as they may be
+/// initialized with young references. Every reference stored into an old object must therefore be reported.
+///
+/// Pinned young objects are not moved, and keep their block alive until they are unpinned and die or are moved.
+/// Registered [`RelocationListener`]s are told about every move. The pacer is consulted whenever a nursery block is
+/// filled and on every allocation in the old space, and decides between minor and major collections. Weak cells are
+/// supported, but ephemerons are not. Like the other strategies in this module, collections run on the allocating
+/// thread, so the strategy is neither `Send` nor `Sync`.
+pub struct Generational<P = ThroughputPacer> {
+    heap: RefCell<Heap>,
+    pacer: RefCell<P>,
+    options: GenerationalOptions,
+}
+
+impl Generational {
+    /// Creates an empty heap with the default options, collected whenever a [`ThroughputPacer`] or a full nursery
+    /// says so.
+    pub fn new() -> Self {
+        Self::with_options(GenerationalOptions::default(), ThroughputPacer::default())
+    }
+}
+
+impl Default for Generational {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: GcPacer> Generational<P> {
+    /// Creates an empty heap with the given options, collected whenever `pacer` or a full nursery says so.
+    ///
+    /// # Panics
+    /// Panics if `options.nursery_size` is less than 32 KiB, `options.promotion_threshold` is zero,
+    /// `options.chunk_size` is less than 2 KiB, or `options.mark_workers` is zero. With the `mmap` feature, also
+    /// panics if `options.region_size` is not zero and the region cannot be reserved.
+    pub fn with_options(options: GenerationalOptions, pacer: P) -> Self {
+        assert!(
+            options.nursery_size >= BLOCK_SIZE,
+            "nursery size {} is less than 32 KiB",
+            options.nursery_size
+        );
+        assert!(
+            options.promotion_threshold > 0,
+            "promotion threshold is zero"
+        );
+        assert!(
+            options.chunk_size >= MAX_CELL_SIZE,
+            "chunk size {} is less than 2 KiB",
+            options.chunk_size
+        );
+        assert!(options.mark_workers > 0, "mark worker count is zero");
+        #[cfg(all(feature = "mmap", any(unix, windows)))]
+        let old = FreeList::with_region(
+            options.chunk_size,
+            options.region_size,
+            options.region_options,
+        );
+        #[cfg(not(all(feature = "mmap", any(unix, windows))))]
+        let old = FreeList::new(options.chunk_size);
+        Self {
+            heap: RefCell::new(Heap {
+                objects: ObjectTable::new(),
+                memory: Memory {
+                    nursery: Nursery {
+                        blocks: Vec::new(),
+                        free_blocks: Vec::new(),
+                        eden: None,
+                        survivor: None,
+                        bytes_allocated: 0,
+                    },
+                    old,
+                    young_bytes: 0,
+                    old_bytes: 0,
+                },
+                young: Vec::new(),
+                remembered: Vec::new(),
+                weak_cells: Vec::new(),
+                listeners: Vec::new(),
+                old_bytes_after_major: 0,
+                survival_rate: 0.0,
+                collection_count: 0,
+                last_pause: Duration::ZERO,
+                total_pause: Duration::ZERO,
+            }),
+            pacer: RefCell::new(pacer),
+            options,
+        }
+    }
+
+    /// The options this heap was created with.
+    pub fn options(&self) -> &GenerationalOptions {
+        &self.options
+    }
+
+    /// Copies or promotes the live young objects, then finalizes and reclaims the rest. Does nothing while
+    /// finalizers run.
+    fn collect_minor(&self) -> CollectionStats {
+        if self.heap.borrow().objects.is_finalizing() {
+            return CollectionStats::default();
+        }
+        #[cfg(feature = "std")]
+        let start = std::time::Instant::now();
+        let (sweep, young_before) = {
+            let mut heap = self.heap.borrow_mut();
+            let young_before = heap.memory.young_bytes;
+            heap.mark_young(&self.options);
+            (heap.sweep_young(), young_before)
+        };
+        // finalized objects are still in their nursery blocks, which are only released below
+        table::run_finalizers(&self.heap, |heap| &heap.objects, &sweep.finalize);
+        let mut heap = self.heap.borrow_mut();
+        let stats = heap.finish(sweep);
+        heap.memory.nursery.bytes_allocated = 0;
+        heap.survival_rate = match young_before {
+            0 => 0.0,
+            young_before => 1.0 - stats.bytes_reclaimed as f64 / young_before as f64,
+        };
+        #[cfg(feature = "std")]
+        heap.record_pause(start.elapsed());
+        self.finish(heap, stats)
+    }
+
+    /// Marks, finalizes, and sweeps both generations, without moving anything. Does nothing while finalizers run.
+    fn collect_major(&self) -> CollectionStats {
+        if self.heap.borrow().objects.is_finalizing() {
+            return CollectionStats::default();
+        }
+        #[cfg(feature = "std")]
+        let start = std::time::Instant::now();
+        let sweep = {
+            let mut heap = self.heap.borrow_mut();
+            let Heap {
+                objects, memory, ..
+            } = &mut *heap;
+            objects.mark_parallel(self.options.mark_workers);
+            objects.sweep(|object| memory.free(object))
+        };
+        table::run_finalizers(&self.heap, |heap| &heap.objects, &sweep.finalize);
+        let mut heap = self.heap.borrow_mut();
+        let mut stats = heap.finish(sweep);
+        stats.kind = Some(CollectionKind::Major);
+        heap.old_bytes_after_major = heap.memory.old_bytes;
+        #[cfg(feature = "std")]
+        heap.record_pause(start.elapsed());
+        self.finish(heap, stats)
+    }
+
+    /// Counts a finished collection, and tells the pacer about it.
+    fn finish(&self, mut heap: RefMut<'_, Heap>, stats: CollectionStats) -> CollectionStats {
+        heap.collection_count += 1;
+        let input = heap.pacing_input();
+        drop(heap);
+        self.pacer.borrow_mut().collection_finished(&stats, &input);
+        stats
+    }
+
+    /// Runs the collection the pacer asks for, if any.
+    fn pace(&self) {
+        let input = self.heap.borrow().pacing_input();
+        // the pacer is borrowed again once the collection finishes
+        let kind = self.pacer.borrow_mut().should_collect(&input);
+        match kind {
+            None => return,
+            Some(CollectionKind::Minor) => self.collect_minor(),
+            Some(CollectionKind::Major | CollectionKind::Full) => self.collect_major(),
+        };
+    }
+
+    /// Reserves memory for a young object with the given layout, collecting the nursery first if it is full.
+    fn reserve_young(&self, layout: Layout) -> Result<(NonNull<u8>, Place), AllocError> {
+        let size = layout.size().max(1);
+        let (full, refill) = {
+            let nursery = &self.heap.borrow().memory.nursery;
+            (
+                nursery.bytes_allocated + size > self.options.nursery_size,
+                !nursery.eden_fits(layout),
+            )
+        };
+        if full {
+            self.collect_minor();
+        } else if refill {
+            self.pace();
+        }
+        let memory = &mut self.heap.borrow_mut().memory;
+        let addr = (memory.nursery)
+            .bump(layout, false)
+            .ok_or(AllocError::OutOfMemory)?;
+        memory.nursery.bytes_allocated += size;
+        memory.young_bytes += size;
+        Ok((addr, Place::Young { size, age: 0 }))
+    }
+
+    /// Reserves memory for an old object with the given layout, collecting first if the pacer says so, and running
+    /// a major collection if the old space is full.
+    fn reserve_old(&self, layout: Layout) -> Result<(NonNull<u8>, Place), AllocError> {
+        self.pace();
+        let reserve = || {
+            let memory = &mut self.heap.borrow_mut().memory;
+            let (addr, cell) = memory.old.reserve(layout, self.options.max_old_size)?;
+            memory.old_bytes += cell.size();
+            Some((
+                addr,
+                Place::Old {
+                    cell,
+                    remembered: true,
+                },
+            ))
+        };
+        if let Some(reserved) = reserve() {
+            return Ok(reserved);
+        }
+        self.collect_major();
+        reserve().ok_or(AllocError::OutOfMemory)
+    }
+}
+
+impl<P> fmt::Debug for Generational<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let heap = self.heap.borrow();
+        f.debug_struct("Generational")
+            .field("nursery_blocks", &heap.memory.nursery.blocks.len())
+            .field("young_bytes", &heap.memory.young_bytes)
+            .field("old_bytes", &heap.memory.old_bytes)
+            .field("object_count", &heap.objects.object_count())
+            .field("options", &self.options)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P> Drop for Generational<P> {
+    fn drop(&mut self) {
+        let Heap {
+            objects, memory, ..
+        } = self.heap.get_mut();
+        objects.finalize_all();
+        for index in objects.indices().collect::<Vec<_>>() {
+            memory.free(objects.remove(index));
+        }
+        let nursery = &mut memory.nursery;
+        for block in mem::take(&mut nursery.blocks) {
+            nursery.free_blocks.push(block.base);
+        }
+        nursery.release_free_blocks();
+    }
+}
+
+// SAFETY: objects are kept alive while rooted, pinned, frozen, or reachable from one that is, and minor collections
+// treat every old object that may refer to a young one as a root. Only unpinned young objects are moved, and their
+// handles are updated in the object table. Unreachable objects are finalized once before their memory is freed,
+// and Uninitialized ones are never traced or finalized.
+unsafe impl<P: GcPacer> GcAllocator for Generational<P> {
+    type Handle = GenerationalHandle;
+    type Error = AllocError;
+
+    fn allocate(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+    ) -> Result<FreshAllocation<GenerationalHandle>, AllocError> {
+        self.allocate_with_hint(vtable, len, AllocHint::Normal)
+    }
+
+    fn allocate_with_hint(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+        hint: AllocHint,
+    ) -> Result<FreshAllocation<GenerationalHandle>, AllocError> {
+        let layout = vtable.layout(len).ok_or(AllocError::InvalidLayout)?;
+        let (addr, place) = if hint == AllocHint::Old
+            || layout.size() >= LARGE_OBJECT_SIZE
+            || layout.align() > BLOCK_ALIGN
+        {
+            self.reserve_old(layout)?
+        } else {
+            self.reserve_young(layout)?
+        };
+        let mut heap = self.heap.borrow_mut();
+        let handle = heap.objects.insert(Object::fresh(addr, vtable, len, place));
+        match place {
+            Place::Young { .. } => heap.young.push(handle),
+            _ => heap.remembered.push(handle),
+        }
+        Ok(FreshAllocation {
+            handle,
+            ptr: addr.as_ptr().cast(),
+        })
+    }
+
+    unsafe fn set_initialized(&self, obj: GenerationalHandle) {
+        let mut heap = self.heap.borrow_mut();
+        let object = heap.objects.get_mut(obj);
+        object.pins -= 1;
+        object.state = State::Initialized;
+    }
+
+    unsafe fn set_finalized(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).state = State::Finalized;
+    }
+
+    unsafe fn reclaim(&self, obj: GenerationalHandle) {
+        let Heap {
+            objects, memory, ..
+        } = &mut *self.heap.borrow_mut();
+        memory.free(objects.reclaim(obj));
+    }
+
+    fn pin(&self, obj: GenerationalHandle) -> *const () {
+        let mut heap = self.heap.borrow_mut();
+        let object = heap.objects.get_mut(obj);
+        object.pins += 1;
+        object.addr.as_ptr().cast()
+    }
+
+    fn unpin(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).pins -= 1;
+    }
+
+    fn pin_long_lived(&self, obj: GenerationalHandle) -> *const () {
+        self.heap.borrow_mut().objects.add_long_lived_pin();
+        self.pin(obj)
+    }
+
+    fn unpin_long_lived(&self, obj: GenerationalHandle) {
+        self.unpin(obj);
+        self.heap.borrow_mut().objects.remove_long_lived_pin();
+    }
+
+    fn resolve(&self, obj: GenerationalHandle) -> *const () {
+        self.heap.borrow().objects.get(obj).addr.as_ptr().cast()
+    }
+
+    fn shrink(&self) -> ShrinkStats {
+        let memory = &mut self.heap.borrow_mut().memory;
+        ShrinkStats {
+            bytes_released: memory.old.shrink() + memory.nursery.release_free_blocks(),
+            heap_size: memory.heap_size(),
+        }
+    }
+
+    fn contains(&self, obj: GenerationalHandle) -> bool {
+        self.heap.borrow().objects.lookup(obj).is_some()
+    }
+
+    fn vtable(&self, obj: GenerationalHandle) -> &'static GcVtable {
+        self.heap.borrow().objects.get(obj).vtable
+    }
+}
+
+// SAFETY: roots and root providers are traced by every collection.
+unsafe impl<P> RootManager<GenerationalHandle> for Generational<P> {
+    fn root(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).roots += 1;
+    }
+
+    fn unroot(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).roots -= 1;
+    }
+
+    fn freeze(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).frozen = true;
+    }
+
+    unsafe fn register_root_provider(&self, provider: &dyn RootProvider) {
+        // SAFETY: caller
+        unsafe {
+            self.heap
+                .borrow_mut()
+                .objects
+                .register_root_provider(provider)
+        };
+    }
+
+    unsafe fn unregister_root_provider(&self, provider: &dyn RootProvider) {
+        self.heap
+            .borrow_mut()
+            .objects
+            .unregister_root_provider(provider);
+    }
+}
+
+// SAFETY: old objects that are reported to refer to young ones are remembered until a minor collection finds that
+// they no longer do. Weak cells are cleared during the collection that finds their targets unreachable, before the
+// targets are finalized. Every move is reported to the registered listeners.
+unsafe impl<P: GcPacer> Collector<GenerationalHandle> for Generational<P> {
+    fn write_barrier(&self, parent: GenerationalHandle, child: GenerationalHandle) {
+        let Heap {
+            objects,
+            remembered,
+            ..
+        } = &mut *self.heap.borrow_mut();
+        if !matches!(objects.get(child).memory, Place::Young { .. }) {
+            return;
+        }
+        if let Place::Old {
+            remembered: flag @ false,
+            ..
+        } = &mut objects.get_mut(parent).memory
+        {
+            *flag = true;
+            remembered.push(parent);
+        }
+    }
+
+    fn allocate_weak(&self, target: GenerationalHandle) -> Option<GenerationalHandle> {
+        let mut heap = self.heap.borrow_mut();
+        heap.objects.get(target);
+        let cell = heap.objects.insert(Object::weak(target, Place::None));
+        heap.weak_cells.push(cell);
+        Some(cell)
+    }
+
+    unsafe fn upgrade_weak(&self, cell: GenerationalHandle) -> Option<GenerationalHandle> {
+        let mut heap = self.heap.borrow_mut();
+        let target = heap
+            .objects
+            .get(cell)
+            .weak
+            .expect("object is not a weak cell")?;
+        heap.objects.get_mut(target).roots += 1;
+        Some(target)
+    }
+
+    fn collect(&self, options: CollectOptions) -> CollectionStats {
+        match options.kind {
+            CollectionKind::Minor => self.collect_minor(),
+            CollectionKind::Major | CollectionKind::Full => self.collect_major(),
+        }
+    }
+
+    fn collect_step(&self, budget: StepBudget) -> StepResult {
+        let _ = budget;
+        StepResult::Complete(self.collect_major())
+    }
+
+    fn memory_pressure(&self, level: MemoryPressure) {
+        if level == MemoryPressure::Critical {
+            self.collect_major();
+        }
+        self.shrink();
+    }
+
+    unsafe fn register_relocation_listener(&self, listener: &dyn RelocationListener) {
+        // SAFETY: the caller keeps the listener valid until it is unregistered
+        let listener = unsafe {
+            mem::transmute::<*const dyn RelocationListener, *const (dyn RelocationListener + 'static)>(
+                listener,
+            )
+        };
+        self.heap.borrow_mut().listeners.push(listener);
+    }
+
+    unsafe fn unregister_relocation_listener(&self, listener: &dyn RelocationListener) {
+        let listeners = &mut self.heap.borrow_mut().listeners;
+        let position =
+            (listeners.iter()).position(|&registered| ptr::addr_eq(registered, listener));
+        listeners.swap_remove(position.expect("relocation listener is not registered"));
+    }
+
+    fn age(&self, obj: GenerationalHandle) -> Option<u32> {
+        match self.heap.borrow().objects.get(obj).memory {
+            Place::Young { age, .. } => Some(age),
+            Place::Old { .. } => Some(self.options.promotion_threshold),
+            Place::None => None,
+        }
+    }
+
+    fn promote(&self, obj: GenerationalHandle) {
+        // moving the object now could invalidate addresses the mutator still holds, so leave it to the next minor
+        // collection
+        if let Place::Young { age, .. } = &mut self.heap.borrow_mut().objects.get_mut(obj).memory {
+            *age = (*age).max(self.options.promotion_threshold - 1);
+        }
+    }
+
+    fn stats(&self) -> HeapStats {
+        let heap = self.heap.borrow();
+        HeapStats {
+            heap_size: heap.memory.heap_size(),
+            bytes_live: heap.memory.bytes_live(),
+            object_count: heap.objects.object_count(),
+            long_lived_pins: heap.objects.long_lived_pins(),
+            collection_count: heap.collection_count,
+            total_pause: heap.total_pause,
+        }
+    }
+}
+
+/// The state of a [`Generational`] heap.
+struct Heap {
+    objects: ObjectTable<Place>,
+    memory: Memory,
+    /// The objects in the nursery. Handles of reclaimed objects are dropped by the next collection.
+    young: Vec<GenerationalHandle>,
+    /// The old objects that may refer to young objects.
+    remembered: Vec<GenerationalHandle>,
+    weak_cells: Vec<GenerationalHandle>,
+    listeners: Vec<*const (dyn RelocationListener + 'static)>,
+    old_bytes_after_major: usize,
+    /// The fraction of the young bytes that survived the last minor collection.
+    survival_rate: f64,
+    collection_count: u64,
+    last_pause: Duration,
+    total_pause: Duration,
+}
+
+/// Where an object is.
+#[derive(Clone, Copy)]
+enum Place {
+    /// In the nursery, taking up the given number of bytes, having survived `age` minor collections.
+    Young { size: usize, age: u32 },
+    /// In the old space, remembered if it may refer to young objects.
+    Old { cell: Cell, remembered: bool },
+    /// Nowhere, as the object has no contents.
+    None,
+}
+
+impl Place {
+    fn size(self) -> usize {
+        match self {
+            Self::Young { size, .. } => size,
+            Self::Old { cell, .. } => cell.size(),
+            Self::None => 0,
+        }
+    }
+}
+
+impl Heap {
+    fn pacing_input(&self) -> PacingInput {
+        PacingInput {
+            bytes_allocated: self.memory.nursery.bytes_allocated,
+            bytes_live: self.memory.bytes_live(),
+            bytes_live_after_major: self.old_bytes_after_major,
+            survival_rate: self.survival_rate,
+            last_pause: self.last_pause,
+            average_pause: match self.collection_count {
+                0 => Duration::ZERO,
+                count => self.total_pause / count as u32,
+            },
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn record_pause(&mut self, pause: Duration) {
+        self.last_pause = pause;
+        self.total_pause += pause;
+    }
+
+    /// Marks the young objects reachable from held young objects, root providers, and remembered old objects,
+    /// copying or promoting each as it is marked, and rebuilds the remembered set.
+    fn mark_young(&mut self, options: &GenerationalOptions) {
+        let Heap {
+            objects,
+            memory,
+            young,
+            remembered,
+            listeners,
+            ..
+        } = self;
+        young.retain(|&handle| objects.lookup(handle).is_some());
+        for &handle in young.iter() {
+            objects.get_mut(handle).marked = false;
+        }
+        memory.nursery.survivor = None;
+        let mut marker = MinorMarker {
+            memory,
+            listeners,
+            options,
+            queue: Vec::new(),
+            refers_young: false,
+        };
+        for &handle in young.iter() {
+            if objects.get(handle).is_held() {
+                marker.mark(objects, handle.index());
+            }
+        }
+        objects.trace_root_providers(|objects, handle| marker.visit(objects, handle));
+        for handle in mem::take(remembered) {
+            let Some(object) = objects.lookup(handle) else {
+                continue;
+            };
+            // an Uninitialized object may still be initialized with young references
+            let keep = object.state == State::Uninitialized
+                || (object.state == State::Initialized && marker.trace(objects, handle.index()));
+            if let Place::Old {
+                remembered: flag, ..
+            } = &mut objects.get_mut(handle).memory
+            {
+                *flag = keep;
+            }
+            if keep {
+                remembered.push(handle);
+            }
+        }
+        let mut scan = 0;
+        while let Some(&index) = marker.queue.get(scan) {
+            scan += 1;
+            let refers_young = marker.trace(objects, index);
+            if let Place::Old {
+                remembered: flag, ..
+            } = &mut objects.at_mut(index).memory
+            {
+                if refers_young {
+                    *flag = true;
+                    remembered.push(objects.handle(index));
+                }
+            }
+        }
+    }
+
+    /// Clears the weak cells of young targets that were not marked, and removes unmarked young objects. Objects
+    /// that have a finalizer are only marked Finalized and left for the caller to finalize and remove.
+    fn sweep_young(&mut self) -> Sweep {
+        let Heap {
+            objects,
+            memory,
+            young,
+            weak_cells,
+            ..
+        } = self;
+        weak_cells.retain(|&cell| objects.lookup(cell).is_some());
+        for &cell in weak_cells.iter() {
+            let Some(Some(target)) = objects.get(cell).weak else {
+                continue;
+            };
+            if objects.lookup(target).is_some_and(|target| {
+                matches!(target.memory, Place::Young { .. }) && !target.marked
+            }) {
+                objects.get_mut(cell).weak = Some(None);
+            }
+        }
+        let mut sweep = Sweep {
+            finalize: Vec::new(),
+            stats: CollectionStats {
+                kind: Some(CollectionKind::Minor),
+                ..CollectionStats::default()
+            },
+        };
+        young.retain(|&handle| {
+            let object = objects.get_mut(handle);
+            if object.marked {
+                // promoted objects leave the nursery
+                return matches!(object.memory, Place::Young { .. });
+            }
+            sweep.stats.objects_reclaimed += 1;
+            if object.state == State::Initialized && object.vtable.needs_finalize() {
+                object.state = State::Finalized;
+                sweep.finalize.push(handle.index());
+            } else {
+                sweep.stats.bytes_reclaimed += memory.free(objects.remove(handle.index()));
+            }
+            false
+        });
+        sweep
+    }
+
+    /// Removes the objects finalized after a sweep, drops the handles of reclaimed objects, and releases the
+    /// nursery blocks that no longer hold any objects.
+    fn finish(&mut self, sweep: Sweep) -> CollectionStats {
+        let mut stats = sweep.stats;
+        let Heap {
+            objects,
+            memory,
+            young,
+            remembered,
+            weak_cells,
+            ..
+        } = self;
+        for index in sweep.finalize {
+            stats.bytes_reclaimed += memory.free(objects.remove(index));
+        }
+        for handles in [&mut *young, remembered, weak_cells] {
+            handles.retain(|&handle| objects.lookup(handle).is_some());
+        }
+        memory.nursery.release_empty_blocks(objects, young);
+        stats.bytes_live = memory.bytes_live();
+        stats
+    }
+}
+
+/// The memory of a [`Generational`] heap, and statistics about its use.
+struct Memory {
+    nursery: Nursery,
+    old: FreeList,
+    /// The number of bytes taken up by young objects, including those allocated since the last collection.
+    young_bytes: usize,
+    old_bytes: usize,
+}
+
+impl Memory {
+    fn heap_size(&self) -> usize {
+        self.nursery.heap_size() + self.old.heap_size()
+    }
+
+    fn bytes_live(&self) -> usize {
+        self.young_bytes + self.old_bytes
+    }
+
+    /// Frees the memory of a removed object, returning the number of bytes freed. Memory in the nursery is only
+    /// freed once its whole block is released.
+    fn free(&mut self, object: Object<Place>) -> usize {
+        match object.memory {
+            Place::Young { size, .. } => self.young_bytes -= size,
+            Place::Old { cell, .. } => {
+                self.old_bytes -= cell.size();
+                // SAFETY: the object has been removed, so its memory is no longer used
+                unsafe { self.old.free(object.addr, cell) };
+            }
+            Place::None => {}
+        }
+        object.memory.size()
+    }
+}
+
+/// Marks and evacuates young objects during a minor collection.
+struct MinorMarker<'a> {
+    memory: &'a mut Memory,
+    listeners: &'a [*const (dyn RelocationListener + 'static)],
+    options: &'a GenerationalOptions,
+    queue: Vec<usize>,
+    /// Whether the object being traced refers to an object that is still young.
+    refers_young: bool,
+}
+
+impl MinorMarker<'_> {
+    /// Marks a young object and copies it out of the nursery block it is in, unless it is pinned. Old objects are
+    /// left alone.
+    fn mark(&mut self, objects: &mut ObjectTable<Place>, index: usize) {
+        let object = objects.at_mut(index);
+        let Place::Young { size, age } = object.memory else {
+            return;
+        };
+        if !object.marked {
+            object.marked = true;
+            let age = age.saturating_add(1);
+            object.memory = Place::Young { size, age };
+            if object.pins == 0 {
+                self.evacuate(object, size, age);
+            }
+            self.queue.push(index);
+        }
+        if matches!(object.memory, Place::Young { .. }) {
+            self.refers_young = true;
+        }
+    }
+
+    fn visit(&mut self, objects: &mut ObjectTable<Place>, handle: Handle) {
+        if let Some(index) = objects.resolve(handle) {
+            self.mark(objects, index);
+        }
+    }
+
+    /// Traces an object, returning whether it refers to objects that are still young.
+    fn trace(&mut self, objects: &mut ObjectTable<Place>, index: usize) -> bool {
+        let object = objects.at(index);
+        if !object.needs_trace() {
+            return false;
+        }
+        let (vtable, addr, len) = (object.vtable, object.addr, object.len);
+        self.refers_young = false;
+        // SAFETY: the object is Initialized
+        unsafe {
+            vtable.trace(
+                addr.cast(),
+                len,
+                &mut TraceContext::new(&mut |handle| self.visit(objects, handle)),
+            )
+        };
+        self.refers_young
+    }
+
+    /// Promotes an object to the old space if it is old enough, and copies it to a survivor block otherwise or if
+    /// the old space is full. Objects that fit in neither are left where they are.
+    fn evacuate(&mut self, object: &mut Object<Place>, size: usize, age: u32) {
+        let layout =
+            Layout::from_size_align(size, object.vtable.layout(object.len).unwrap().align())
+                .unwrap();
+        let memory = &mut *self.memory;
+        let promoted = (age >= self.options.promotion_threshold)
+            .then(|| memory.old.reserve(layout, self.options.max_old_size))
+            .flatten()
+            .map(|(addr, cell)| {
+                memory.young_bytes -= size;
+                memory.old_bytes += cell.size();
+                (
+                    addr,
+                    Place::Old {
+                        cell,
+                        remembered: false,
+                    },
+                )
+            });
+        let Some((addr, place)) = promoted.or_else(|| {
+            let addr = memory.nursery.bump(layout, true)?;
+            Some((addr, Place::Young { size, age }))
+        }) else {
+            return;
+        };
+        // SAFETY: the copy is in memory that was free
+        unsafe { ptr::copy_nonoverlapping(object.addr.as_ptr(), addr.as_ptr(), size) };
+        for &listener in self.listeners {
+            // SAFETY: listeners stay valid until they are unregistered
+            unsafe { &*listener }.relocated(object.addr.as_ptr().cast(), addr.as_ptr().cast());
+        }
+        object.addr = addr;
+        object.memory = place;
+    }
+}
+
+/// The nursery, made of fixed-size blocks that young objects are bump-allocated in.
+struct Nursery {
+    /// The blocks that hold young objects.
+    blocks: Vec<Block>,
+    /// Empty blocks kept for reuse.
+    free_blocks: Vec<NonNull<u8>>,
+    /// The index of the block new objects are allocated in.
+    eden: Option<usize>,
+    /// The index of the block survivors are copied to by the current minor collection.
+    survivor: Option<usize>,
+    /// The number of bytes allocated since the last collection.
+    bytes_allocated: usize,
+}
+
+struct Block {
+    base: NonNull<u8>,
+    /// The offset of the next allocation.
+    top: usize,
+}
+
+impl Block {
+    fn bump(&mut self, layout: Layout) -> Option<NonNull<u8>> {
+        // zero-sized objects still need an address of their own
+        let size = layout.size().max(1);
+        let base = self.base.as_ptr() as usize;
+        let start = (base + self.top).checked_next_multiple_of(layout.align())? - base;
+        let end = start.checked_add(size)?;
+        if end > BLOCK_SIZE {
+            return None;
+        }
+        self.top = end;
+        // SAFETY: the object is within the block
+        Some(unsafe { self.base.add(start) })
+    }
+}
+
+impl Nursery {
+    fn heap_size(&self) -> usize {
+        (self.blocks.len() + self.free_blocks.len()) * BLOCK_SIZE
+    }
+
+    /// Returns whether an object with the given layout fits in the current eden block.
+    fn eden_fits(&self, layout: Layout) -> bool {
+        self.eden.is_some_and(|index| {
+            let block = &self.blocks[index];
+            let base = block.base.as_ptr() as usize;
+            (base + block.top)
+                .checked_next_multiple_of(layout.align())
+                .is_some_and(|start| start - base + layout.size().max(1) <= BLOCK_SIZE)
+        })
+    }
+
+    /// Bump-allocates memory with the given layout in the eden or survivor block, moving on to a fresh block if it
+    /// does not fit. Returns None if no block can be reserved.
+    fn bump(&mut self, layout: Layout, survivor: bool) -> Option<NonNull<u8>> {
+        let Self {
+            blocks,
+            free_blocks,
+            eden,
+            survivor: survivor_block,
+            ..
+        } = self;
+        let current = if survivor { survivor_block } else { eden };
+        if let Some(addr) = (*current).and_then(|index| blocks[index].bump(layout)) {
+            return Some(addr);
+        }
+        // SAFETY: blocks have a non-zero size
+        let base = free_blocks
+            .pop()
+            .or_else(|| NonNull::new(unsafe { alloc(BLOCK_LAYOUT) }))?;
+        blocks.push(Block { base, top: 0 });
+        *current = Some(blocks.len() - 1);
+        blocks.last_mut().unwrap().bump(layout)
+    }
+
+    /// Moves the blocks that no longer hold any of the `young` objects to the free blocks, and starts allocating in
+    /// fresh blocks.
+    fn release_empty_blocks(&mut self, objects: &ObjectTable<Place>, young: &[GenerationalHandle]) {
+        self.blocks.sort_unstable_by_key(|block| block.base);
+        let mut counts = alloc::vec![0usize; self.blocks.len()];
+        for &handle in young {
+            let addr = objects.get(handle).addr;
+            let index = self.blocks.partition_point(|block| block.base <= addr);
+            counts[index - 1] += 1;
+        }
+        let mut counts = counts.into_iter();
+        let free_blocks = &mut self.free_blocks;
+        self.blocks.retain(|block| {
+            let live = counts.next().unwrap() > 0;
+            if !live {
+                free_blocks.push(block.base);
+            }
+            live
+        });
+        self.eden = None;
+        self.survivor = None;
+    }
+
+    /// Frees the empty blocks, returning the number of bytes released.
+    fn release_free_blocks(&mut self) -> usize {
+        let bytes_released = self.free_blocks.len() * BLOCK_SIZE;
+        for base in self.free_blocks.drain(..) {
+            // SAFETY: the block was allocated in `Nursery::bump` with this layout
+            unsafe { dealloc(base.as_ptr(), BLOCK_LAYOUT) };
+        }
+        bytes_released
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{
+        heap::{CollectOptions, CollectionStats},
+        strategies::tests,
+        testing::{self, Node},
+        GcHeap,
+    };
+
+    /// A pacer that asks for a collection of the given kind on every allocation.
+    struct Always(CollectionKind, usize);
+
+    impl GcPacer for Always {
+        fn should_collect(&mut self, input: &PacingInput) -> Option<CollectionKind> {
+            let _ = input;
+            Some(self.0)
+        }
+
+        fn collection_finished(&mut self, stats: &CollectionStats, input: &PacingInput) {
+            let _ = (stats, input);
+            self.1 += 1;
+        }
+    }
+
+    #[test]
+    fn pacer_triggers_collections() {
+        for kind in [CollectionKind::Minor, CollectionKind::Major] {
+            let heap = GcHeap::new(Generational::with_options(
+                GenerationalOptions::default(),
+                Always(kind, 0),
+            ));
+            let kept = heap.alloc_old(7u64);
+            // each allocation of a nursery block's worth of data refills eden, which consults the pacer
+            for i in 0..4 * BLOCK_SIZE / 64 {
+                let _ = heap.alloc([i as u64; 8]);
+            }
+            assert_eq!(*kept, 7);
+            assert!(heap.strategy().pacer.borrow().1 > 0);
+        }
+    }
+
+    #[test]
+    fn conformance() {
+        testing::run_all(Generational::new);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn conformance_with_mark_workers() {
+        testing::run_all(|| {
+            let options = GenerationalOptions {
+                mark_workers: 4,
+                ..GenerationalOptions::default()
+            };
+            Generational::with_options(options, ThroughputPacer::default())
+        });
+    }
+
+    #[test]
+    fn reentrant_finalizers() {
+        testing::reentrant_finalizers(Generational::new());
+    }
+
+    #[test]
+    fn reclamation() {
+        tests::reclamation(Generational::new());
+    }
+
+    #[test]
+    fn pinning() {
+        tests::pinning(Generational::new());
+    }
+
+    #[test]
+    fn weak_cells() {
+        tests::weak_cells(Generational::new());
+    }
+
+    #[test]
+    fn relocation() {
+        // young objects are copied out of their nursery block by each minor collection
+        tests::relocation(
+            Generational::new(),
+            CollectOptions {
+                kind: CollectionKind::Minor,
+                ..CollectOptions::default()
+            },
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn reclamation_with_mark_workers() {
+        let options = GenerationalOptions {
+            mark_workers: 4,
+            ..GenerationalOptions::default()
+        };
+        tests::reclamation(Generational::with_options(
+            options,
+            ThroughputPacer::default(),
+        ));
+        tests::weak_cells(Generational::with_options(
+            options,
+            ThroughputPacer::default(),
+        ));
+    }
+
+    /// Runs a collection of the given kind.
+    fn collect(heap: &GcHeap<'_, Generational>, kind: CollectionKind) -> CollectionStats {
+        heap.collect_with(CollectOptions {
+            kind,
+            ..CollectOptions::default()
+        })
+    }
+
+    #[test]
+    fn minor_collections_only_reclaim_young_objects() {
+        let drops = AtomicUsize::new(0);
+        let heap = GcHeap::new(Generational::new());
+        heap.alloc_old(Node::new(&drops, 0, None));
+        let kept = heap.alloc(Node::new(&drops, 1, None));
+        for value in 0..10 {
+            heap.alloc(Node::new(&drops, value, None));
+        }
+        let stats = collect(&heap, CollectionKind::Minor);
+        assert_eq!(stats.kind, Some(CollectionKind::Minor));
+        assert_eq!(drops.load(Ordering::Relaxed), 10);
+        assert_eq!(kept.value, 1);
+        let stats = collect(&heap, CollectionKind::Major);
+        assert_eq!(stats.kind, Some(CollectionKind::Major));
+        assert_eq!(drops.load(Ordering::Relaxed), 11);
+    }
+
+    #[test]
+    fn survivors_are_promoted() {
+        let heap = GcHeap::new(Generational::new());
+        let root = heap.alloc(7u64);
+        assert_eq!(root.age(), Some(0));
+        collect(&heap, CollectionKind::Minor);
+        assert_eq!(root.age(), Some(1));
+        collect(&heap, CollectionKind::Minor);
+        assert_eq!(root.age(), Some(2));
+        let addr = root.as_ptr();
+        collect(&heap, CollectionKind::Minor);
+        assert_eq!(root.as_ptr(), addr);
+        assert_eq!(*root, 7);
+
+        let promoted = heap.alloc(8u64);
+        promoted.promote();
+        collect(&heap, CollectionKind::Minor);
+        assert_eq!(promoted.age(), Some(2));
+    }
+
+    #[cfg(all(feature = "mmap", any(unix, windows)))]
+    #[test]
+    fn allocates_old_chunks_in_region() {
+        let region_options = GenerationalOptions {
+            region_size: 1 << 20,
+            ..GenerationalOptions::default()
+        };
+        let region = || Generational::with_options(region_options, ThroughputPacer::default());
+        testing::run_all(region);
+        tests::reclamation(region());
+    }
+
+    #[cfg(all(feature = "mmap", any(unix, windows)))]
+    #[test]
+    #[should_panic = "out of memory"]
+    fn old_space_cannot_grow_beyond_region() {
+        let heap = GcHeap::new(Generational::with_options(
+            GenerationalOptions {
+                region_size: 1 << 20,
+                ..GenerationalOptions::default()
+            },
+            ThroughputPacer::default(),
+        ));
+        let mut roots = Vec::new();
+        loop {
+            roots.push(heap.alloc_old([0u64; 16]));
+        }
+    }
+}
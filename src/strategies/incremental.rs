@@ -0,0 +1,781 @@
+use alloc::vec::Vec;
+use core::{alloc::Layout, cell::RefCell, fmt, ptr::NonNull};
+
+use super::{
+    free_list::{Cell, FreeList, MAX_CELL_SIZE},
+    mark_sweep::Memory,
+    table::{self, Object, ObjectTable, State},
+};
+use crate::{
+    heap::{
+        AllocError, CollectOptions, CollectionKind, CollectionStats, Collector, FreshAllocation,
+        GcAllocator, GcVtable, GenerationalHandle, HeapStats, MemoryPressure, RootManager,
+        ShrinkStats, StepBudget, StepResult,
+    },
+    pacer::{GcPacer, ThroughputPacer},
+    trace::{RootProvider, TraceContext},
+};
+
+/// Options for an [`Incremental`] heap. New options may be added in the future, so start from
+/// [`IncrementalOptions::default`] and set the fields of interest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct IncrementalOptions {
+    /// The most bytes of memory the heap may reserve, including free space. Once the heap has reached this size, an
+    /// allocation that does not fit in the free space finishes the collection in progress, then runs a full one,
+    /// and fails with [`AllocError::OutOfMemory`] if that does not free enough. Defaults to no limit.
+    pub max_heap_size: usize,
+    /// The number of bytes the heap grows by when it runs out of cells of some size, which must be at least 2 KiB.
+    /// Defaults to 64 KiB.
+    pub chunk_size: usize,
+    /// The number of objects traced or swept by each allocation while a collection is in progress, which must be
+    /// at least 2, so that collections finish even if every object survives. Larger steps finish collections
+    /// sooner, so the heap grows less in the meantime, at the cost of slower allocation. Defaults to 32.
+    pub allocation_step: usize,
+    /// The number of bytes of address space to reserve up front in a [`HeapRegion`](super::HeapRegion) for the
+    /// chunks of the heap, or zero to allocate chunks from the global allocator, like
+    /// [`MarkSweepOptions::region_size`](super::MarkSweepOptions::region_size). Only available on Unix and Windows
+    /// with the `mmap` feature. Defaults to zero.
+    #[cfg(all(feature = "mmap", any(unix, windows)))]
+    pub region_size: usize,
+    /// The options of the region reserved for the chunks of the heap, if [`IncrementalOptions::region_size`] is not
+    /// zero. Only available on Unix and Windows with the `mmap` feature.
+    #[cfg(all(feature = "mmap", any(unix, windows)))]
+    pub region_options: super::HeapRegionOptions,
+}
+
+impl Default for IncrementalOptions {
+    fn default() -> Self {
+        Self {
+            max_heap_size: usize::MAX,
+            chunk_size: 64 << 10,
+            allocation_step: 32,
+            #[cfg(all(feature = "mmap", any(unix, windows)))]
+            region_size: 0,
+            #[cfg(all(feature = "mmap", any(unix, windows)))]
+            region_options: super::HeapRegionOptions::default(),
+        }
+    }
+}
+
+/// An incremental mark-sweep strategy, which spreads each collection over many short steps. See the
+/// [module documentation](super).
+///
+/// Objects are allocated like in a [`MarkSweep`](super::MarkSweep) heap, and never move. Once the pacer asks for a
+/// collection, each allocation does a little of it, and [`Collector::collect_step`] does as much as its budget
+/// allows, so no pause takes time in proportion to the heap. A collection scans the table for held objects, marks
+/// the objects reachable from them, then sweeps the table, finalizing and reclaiming the unmarked objects a few at a
+/// time. The registered root providers are scanned once marking has run out of work, as their roots are not
+/// reported as they change.
+///
+/// Marking maintains the tri-color invariant that no marked object that has been traced (black) refers to an
+/// unmarked one (white). The mutator can only break it by storing a reference into an object, which must be
+/// reported to [`Collector::write_barrier`], or by initializing a fresh object, which is traced again once
+/// Initialized. The barrier marks the stored object and queues it to be traced (gray), whatever the color of the
+/// object it was stored into. Objects that are rooted, pinned, or frozen while marking are grayed in the same way,
+/// and objects allocated while marking are black. Garbage created during a collection is reclaimed by the next one.
+///
+/// Without the `std` feature, time budgets cannot be measured, so a step with a time budget traces or sweeps about
+/// one object per microsecond instead. Weak cells are supported, but ephemerons are not. Like the other strategies
+/// in this module, steps run on the allocating thread, so the strategy is neither `Send` nor `Sync`.
+pub struct Incremental<P = ThroughputPacer> {
+    heap: RefCell<Heap>,
+    pacer: RefCell<P>,
+    options: IncrementalOptions,
+}
+
+impl Incremental {
+    /// Creates an empty heap with the default options, which starts collections whenever a [`ThroughputPacer`]
+    /// says so.
+    pub fn new() -> Self {
+        Self::with_options(IncrementalOptions::default(), ThroughputPacer::default())
+    }
+}
+
+impl Default for Incremental {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: GcPacer> Incremental<P> {
+    /// Creates an empty heap with the given options, which starts collections whenever `pacer` says so.
+    ///
+    /// # Panics
+    /// Panics if `options.chunk_size` is less than 2 KiB, or `options.allocation_step` is less than 2. With the `mmap`
+    /// feature, also panics if `options.region_size` is not zero and the region cannot be reserved.
+    pub fn with_options(options: IncrementalOptions, pacer: P) -> Self {
+        assert!(
+            options.chunk_size >= MAX_CELL_SIZE,
+            "chunk size {} is less than 2 KiB",
+            options.chunk_size
+        );
+        assert!(
+            options.allocation_step >= 2,
+            "allocation step {} is less than 2",
+            options.allocation_step
+        );
+        #[cfg(all(feature = "mmap", any(unix, windows)))]
+        let cells = FreeList::with_region(
+            options.chunk_size,
+            options.region_size,
+            options.region_options,
+        );
+        #[cfg(not(all(feature = "mmap", any(unix, windows))))]
+        let cells = FreeList::new(options.chunk_size);
+        Self {
+            heap: RefCell::new(Heap {
+                objects: ObjectTable::new(),
+                memory: Memory::new(cells),
+                phase: Phase::Idle,
+                gray: Vec::new(),
+                weak_cells: Vec::new(),
+                cycle: CollectionStats::default(),
+            }),
+            pacer: RefCell::new(pacer),
+            options,
+        }
+    }
+
+    /// The options this heap was created with.
+    pub fn options(&self) -> &IncrementalOptions {
+        &self.options
+    }
+
+    /// Does up to `work` of the collection in progress, starting one if there is none. Returns the statistics of
+    /// the collection if it finished. Does nothing while finalizers run.
+    fn step(&self, mut work: Work) -> Option<CollectionStats> {
+        if self.heap.borrow().objects.is_finalizing() {
+            return None;
+        }
+        #[cfg(feature = "std")]
+        let start = std::time::Instant::now();
+        let mut heap = self.heap.borrow_mut();
+        if heap.phase == Phase::Idle {
+            heap.phase = Phase::Marking {
+                cursor: 0,
+                end: heap.objects.slot_count(),
+            };
+            heap.cycle = CollectionStats {
+                kind: Some(CollectionKind::Full),
+                ..CollectionStats::default()
+            };
+        }
+        if let Phase::Marking { .. } = heap.phase {
+            if !heap.mark(&mut work) {
+                #[cfg(feature = "std")]
+                heap.memory.record_pause(start.elapsed());
+                return None;
+            }
+            heap.finish_marking();
+        }
+        let finalize = heap.sweep(&mut work);
+        drop(heap);
+        table::run_finalizers(&self.heap, |heap| &heap.objects, &finalize);
+        let mut heap = self.heap.borrow_mut();
+        let Heap {
+            objects,
+            memory,
+            phase,
+            cycle,
+            ..
+        } = &mut *heap;
+        for index in finalize {
+            cycle.bytes_reclaimed += memory.free(objects.remove(index));
+        }
+        #[cfg(feature = "std")]
+        memory.record_pause(start.elapsed());
+        if *phase != Phase::Idle {
+            return None;
+        }
+        let mut stats = *cycle;
+        stats.bytes_live = memory.bytes_live;
+        memory.bytes_allocated = 0;
+        memory.bytes_live_after_collection = memory.bytes_live;
+        memory.collection_count += 1;
+        let input = memory.pacing_input();
+        drop(heap);
+        self.pacer.borrow_mut().collection_finished(&stats, &input);
+        Some(stats)
+    }
+
+    /// Finishes the collection in progress, if any, then runs a whole collection, so that every object that is
+    /// unreachable now is reclaimed. Does nothing while finalizers run.
+    fn collect_full(&self) -> CollectionStats {
+        if self.heap.borrow().objects.is_finalizing() {
+            return CollectionStats::default();
+        }
+        if self.heap.borrow().phase != Phase::Idle {
+            self.step(Work::UNLIMITED);
+        }
+        self.step(Work::UNLIMITED).unwrap()
+    }
+
+    /// Reserves memory for an object with the given layout, doing a step of collection first if one is in progress
+    /// or the pacer asks for one, and collecting fully if the heap is full.
+    fn reserve(&self, layout: Layout) -> Result<(NonNull<u8>, Cell), AllocError> {
+        let idle = self.heap.borrow().phase == Phase::Idle;
+        let input = self.heap.borrow().memory.pacing_input();
+        if !idle || self.pacer.borrow_mut().should_collect(&input).is_some() {
+            self.step(Work::objects(self.options.allocation_step));
+        }
+        let max_heap_size = self.options.max_heap_size;
+        if let Some(memory) = self.heap.borrow_mut().memory.reserve(layout, max_heap_size) {
+            return Ok(memory);
+        }
+        self.collect_full();
+        (self.heap.borrow_mut().memory)
+            .reserve(layout, max_heap_size)
+            .ok_or(AllocError::OutOfMemory)
+    }
+}
+
+impl<P> fmt::Debug for Incremental<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let heap = self.heap.borrow();
+        f.debug_struct("Incremental")
+            .field("heap_size", &heap.memory.cells.heap_size())
+            .field("bytes_live", &heap.memory.bytes_live)
+            .field("object_count", &heap.objects.object_count())
+            .field("phase", &heap.phase)
+            .field("options", &self.options)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P> Drop for Incremental<P> {
+    fn drop(&mut self) {
+        let Heap {
+            objects, memory, ..
+        } = self.heap.get_mut();
+        objects.finalize_all();
+        for index in objects.indices().collect::<Vec<_>>() {
+            memory.free(objects.remove(index));
+        }
+    }
+}
+
+// SAFETY: objects are kept alive while rooted, pinned, frozen, or reachable from one that is, as the barriers keep
+// every such object marked or gray while marking, and objects allocated while sweeping are not swept. Objects never
+// move. Unreachable objects are finalized once they are swept, and Uninitialized ones are never traced or
+// finalized.
+unsafe impl<P: GcPacer> GcAllocator for Incremental<P> {
+    type Handle = GenerationalHandle;
+    type Error = AllocError;
+
+    fn allocate(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+    ) -> Result<FreshAllocation<GenerationalHandle>, AllocError> {
+        let layout = vtable.layout(len).ok_or(AllocError::InvalidLayout)?;
+        let (addr, cell) = self.reserve(layout)?;
+        let mut heap = self.heap.borrow_mut();
+        let handle = heap
+            .objects
+            .insert(Object::fresh(addr, vtable, len, Some(cell)));
+        heap.allocate_black(handle);
+        Ok(FreshAllocation {
+            handle,
+            ptr: addr.as_ptr().cast(),
+        })
+    }
+
+    unsafe fn set_initialized(&self, obj: GenerationalHandle) {
+        let Heap {
+            objects,
+            phase,
+            gray,
+            ..
+        } = &mut *self.heap.borrow_mut();
+        let object = objects.get_mut(obj);
+        object.pins -= 1;
+        object.state = State::Initialized;
+        if let Phase::Marking { .. } = phase {
+            if object.needs_trace() {
+                // the object may have been initialized with references to white objects
+                object.marked = true;
+                gray.push(obj.index());
+            }
+        }
+    }
+
+    unsafe fn set_finalized(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).state = State::Finalized;
+    }
+
+    unsafe fn reclaim(&self, obj: GenerationalHandle) {
+        let Heap {
+            objects,
+            memory,
+            gray,
+            ..
+        } = &mut *self.heap.borrow_mut();
+        gray.retain(|&index| index != obj.index());
+        memory.free(objects.reclaim(obj));
+    }
+
+    fn pin(&self, obj: GenerationalHandle) -> *const () {
+        let mut heap = self.heap.borrow_mut();
+        heap.shade(obj);
+        let object = heap.objects.get_mut(obj);
+        object.pins += 1;
+        object.addr.as_ptr().cast()
+    }
+
+    fn unpin(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).pins -= 1;
+    }
+
+    fn pin_long_lived(&self, obj: GenerationalHandle) -> *const () {
+        self.heap.borrow_mut().objects.add_long_lived_pin();
+        self.pin(obj)
+    }
+
+    fn unpin_long_lived(&self, obj: GenerationalHandle) {
+        self.unpin(obj);
+        self.heap.borrow_mut().objects.remove_long_lived_pin();
+    }
+
+    fn resolve(&self, obj: GenerationalHandle) -> *const () {
+        self.heap.borrow().objects.get(obj).addr.as_ptr().cast()
+    }
+
+    fn shrink(&self) -> ShrinkStats {
+        let cells = &mut self.heap.borrow_mut().memory.cells;
+        ShrinkStats {
+            bytes_released: cells.shrink(),
+            heap_size: cells.heap_size(),
+        }
+    }
+
+    fn contains(&self, obj: GenerationalHandle) -> bool {
+        self.heap.borrow().objects.lookup(obj).is_some()
+    }
+
+    fn vtable(&self, obj: GenerationalHandle) -> &'static GcVtable {
+        self.heap.borrow().objects.get(obj).vtable
+    }
+}
+
+// SAFETY: held objects are found by scanning the table, or grayed as they are rooted while marking. Root providers
+// are scanned before marking finishes.
+unsafe impl<P> RootManager<GenerationalHandle> for Incremental<P> {
+    fn root(&self, obj: GenerationalHandle) {
+        let mut heap = self.heap.borrow_mut();
+        heap.shade(obj);
+        heap.objects.get_mut(obj).roots += 1;
+    }
+
+    fn unroot(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).roots -= 1;
+    }
+
+    fn freeze(&self, obj: GenerationalHandle) {
+        let mut heap = self.heap.borrow_mut();
+        heap.shade(obj);
+        heap.objects.get_mut(obj).frozen = true;
+    }
+
+    unsafe fn register_root_provider(&self, provider: &dyn RootProvider) {
+        // SAFETY: caller
+        unsafe {
+            self.heap
+                .borrow_mut()
+                .objects
+                .register_root_provider(provider)
+        };
+    }
+
+    unsafe fn unregister_root_provider(&self, provider: &dyn RootProvider) {
+        self.heap
+            .borrow_mut()
+            .objects
+            .unregister_root_provider(provider);
+    }
+}
+
+// SAFETY: the write barrier grays every object stored while marking, so marking finds every reachable object. Weak
+// cells are cleared as soon as marking finishes, before their targets are swept and finalized.
+unsafe impl<P: GcPacer> Collector<GenerationalHandle> for Incremental<P> {
+    fn write_barrier(&self, parent: GenerationalHandle, child: GenerationalHandle) {
+        let _ = parent;
+        self.heap.borrow_mut().shade(child);
+    }
+
+    fn allocate_weak(&self, target: GenerationalHandle) -> Option<GenerationalHandle> {
+        let mut heap = self.heap.borrow_mut();
+        heap.objects.get(target);
+        let cell = heap.objects.insert(Object::weak(target, None));
+        heap.allocate_black(cell);
+        heap.weak_cells.push(cell);
+        Some(cell)
+    }
+
+    unsafe fn upgrade_weak(&self, cell: GenerationalHandle) -> Option<GenerationalHandle> {
+        let mut heap = self.heap.borrow_mut();
+        let target = heap
+            .objects
+            .get(cell)
+            .weak
+            .expect("object is not a weak cell")?;
+        heap.shade(target);
+        heap.objects.get_mut(target).roots += 1;
+        Some(target)
+    }
+
+    fn collect(&self, options: CollectOptions) -> CollectionStats {
+        let _ = options;
+        self.collect_full()
+    }
+
+    fn collect_step(&self, budget: StepBudget) -> StepResult {
+        match self.step(Work::new(budget)) {
+            Some(stats) => StepResult::Complete(stats),
+            None => StepResult::Incomplete,
+        }
+    }
+
+    fn memory_pressure(&self, level: MemoryPressure) {
+        if level == MemoryPressure::Critical {
+            self.collect_full();
+        }
+        self.shrink();
+    }
+
+    fn stats(&self) -> HeapStats {
+        let heap = self.heap.borrow();
+        HeapStats {
+            heap_size: heap.memory.cells.heap_size(),
+            bytes_live: heap.memory.bytes_live,
+            object_count: heap.objects.object_count(),
+            long_lived_pins: heap.objects.long_lived_pins(),
+            collection_count: heap.memory.collection_count,
+            total_pause: heap.memory.total_pause,
+        }
+    }
+}
+
+/// The state of an [`Incremental`] heap.
+struct Heap {
+    /// The objects, and the cells they are allocated in. Weak cells take up no memory.
+    objects: ObjectTable<Option<Cell>>,
+    memory: Memory,
+    phase: Phase,
+    /// The objects that have been marked but not traced yet.
+    gray: Vec<usize>,
+    /// The weak cells, to be cleared once marking finishes. Handles of reclaimed cells are dropped then.
+    weak_cells: Vec<GenerationalHandle>,
+    /// The statistics of the collection in progress.
+    cycle: CollectionStats,
+}
+
+/// The progress of the collection in progress. While no collection is in progress, every object is unmarked.
+///
+/// Each phase only visits the slots that existed when it started, up to `end`, so that it finishes however fast
+/// objects are allocated. Objects allocated in later slots are already black while marking, and are left unmarked
+/// while sweeping.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Phase {
+    Idle,
+    /// Scanning the table for held objects, having reached `cursor`, and tracing gray objects.
+    Marking {
+        cursor: usize,
+        end: usize,
+    },
+    /// Sweeping the table, having reached `cursor`.
+    Sweeping {
+        cursor: usize,
+        end: usize,
+    },
+}
+
+impl Heap {
+    /// Marks a fresh object if it will not be swept by the collection in progress.
+    fn allocate_black(&mut self, obj: GenerationalHandle) {
+        let marked = match self.phase {
+            Phase::Idle => false,
+            Phase::Marking { .. } => true,
+            Phase::Sweeping { cursor, end } => (cursor..end).contains(&obj.index()),
+        };
+        self.objects.get_mut(obj).marked = marked;
+    }
+
+    /// Grays an object that has become reachable while marking, if it is white.
+    fn shade(&mut self, obj: GenerationalHandle) {
+        if let Phase::Marking { .. } = self.phase {
+            let object = self.objects.get_mut(obj);
+            if !object.marked {
+                object.marked = true;
+                self.gray.push(obj.index());
+            }
+        }
+    }
+
+    /// Marks until `work` is used up, returning whether marking has finished.
+    fn mark(&mut self, work: &mut Work) -> bool {
+        let Heap {
+            objects,
+            phase,
+            gray,
+            ..
+        } = self;
+        let Phase::Marking { cursor, end } = phase else {
+            unreachable!()
+        };
+        loop {
+            if *cursor < *end {
+                if let Some(object) = objects.slot_mut(*cursor) {
+                    if object.is_held() && !object.marked {
+                        object.marked = true;
+                        gray.push(*cursor);
+                    }
+                }
+                *cursor += 1;
+            } else if let Some(index) = gray.pop() {
+                let object = objects.at(index);
+                if object.needs_trace() {
+                    let (vtable, addr, len) = (object.vtable, object.addr, object.len);
+                    let mut visit = |handle| {
+                        if let Some(index) = objects.resolve(handle) {
+                            let object = objects.at_mut(index);
+                            if !object.marked {
+                                object.marked = true;
+                                gray.push(index);
+                            }
+                        }
+                    };
+                    // SAFETY: the object is Initialized
+                    unsafe { vtable.trace(addr.cast(), len, &mut TraceContext::new(&mut visit)) };
+                }
+            } else {
+                objects.trace_root_providers(|objects, handle| {
+                    if let Some(index) = objects.resolve(handle) {
+                        let object = objects.at_mut(index);
+                        if !object.marked {
+                            object.marked = true;
+                            gray.push(index);
+                        }
+                    }
+                });
+                if gray.is_empty() {
+                    return true;
+                }
+            }
+            if work.spend() {
+                return false;
+            }
+        }
+    }
+
+    /// Clears the weak cells of unmarked targets, and starts sweeping.
+    fn finish_marking(&mut self) {
+        let Heap {
+            objects,
+            weak_cells,
+            ..
+        } = self;
+        weak_cells.retain(|&cell| objects.lookup(cell).is_some());
+        for &cell in weak_cells.iter() {
+            let Some(Some(target)) = objects.get(cell).weak else {
+                continue;
+            };
+            if !objects.lookup(target).is_some_and(|target| target.marked) {
+                objects.get_mut(cell).weak = Some(None);
+            }
+        }
+        self.phase = Phase::Sweeping {
+            cursor: 0,
+            end: objects.slot_count(),
+        };
+    }
+
+    /// Sweeps until `work` is used up, unmarking marked objects and reclaiming the rest. Returns the objects to
+    /// finalize before they are removed, which are marked Finalized.
+    fn sweep(&mut self, work: &mut Work) -> Vec<usize> {
+        let Heap {
+            objects,
+            memory,
+            phase,
+            cycle,
+            ..
+        } = self;
+        let Phase::Sweeping { cursor, end } = phase else {
+            unreachable!()
+        };
+        let mut finalize = Vec::new();
+        while *cursor < *end {
+            let index = *cursor;
+            *cursor += 1;
+            if let Some(object) = objects.slot_mut(index) {
+                if object.marked {
+                    object.marked = false;
+                } else {
+                    cycle.objects_reclaimed += 1;
+                    if object.state == State::Initialized && object.vtable.needs_finalize() {
+                        object.state = State::Finalized;
+                        finalize.push(index);
+                    } else {
+                        cycle.bytes_reclaimed += memory.free(objects.remove(index));
+                    }
+                }
+            }
+            if work.spend() {
+                return finalize;
+            }
+        }
+        *phase = Phase::Idle;
+        finalize
+    }
+}
+
+/// The work left in a step.
+struct Work {
+    /// The number of objects left to trace or sweep.
+    objects: usize,
+    #[cfg(feature = "std")]
+    deadline: Option<std::time::Instant>,
+}
+
+impl Work {
+    const UNLIMITED: Self = Self {
+        objects: usize::MAX,
+        #[cfg(feature = "std")]
+        deadline: None,
+    };
+
+    /// How often to check the clock, in objects.
+    #[cfg(feature = "std")]
+    const CLOCK_INTERVAL: usize = 64;
+
+    fn objects(objects: usize) -> Self {
+        Self {
+            objects,
+            #[cfg(feature = "std")]
+            deadline: None,
+        }
+    }
+
+    fn new(budget: StepBudget) -> Self {
+        match budget {
+            StepBudget::Objects(objects) => Self::objects(objects),
+            #[cfg(feature = "std")]
+            StepBudget::Time(time) => Self {
+                deadline: std::time::Instant::now().checked_add(time),
+                ..Self::UNLIMITED
+            },
+            #[cfg(not(feature = "std"))]
+            StepBudget::Time(time) => {
+                Self::objects(usize::try_from(time.as_micros()).unwrap_or(usize::MAX))
+            }
+        }
+    }
+
+    /// Counts one object, returning whether the work is used up.
+    fn spend(&mut self) -> bool {
+        self.objects = self.objects.saturating_sub(1);
+        #[cfg(feature = "std")]
+        if let Some(deadline) = self.deadline {
+            if self.objects.is_multiple_of(Self::CLOCK_INTERVAL) {
+                return std::time::Instant::now() >= deadline;
+            }
+        }
+        self.objects == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{
+        strategies::tests,
+        testing::{self, Node},
+        GcHeap,
+    };
+
+    #[test]
+    fn conformance() {
+        testing::run_all(Incremental::new);
+    }
+
+    #[test]
+    fn reentrant_finalizers() {
+        testing::reentrant_finalizers(Incremental::new());
+    }
+
+    #[test]
+    fn reclamation() {
+        tests::reclamation(Incremental::new());
+    }
+
+    #[test]
+    fn pinning() {
+        tests::pinning(Incremental::new());
+    }
+
+    #[test]
+    fn weak_cells() {
+        tests::weak_cells(Incremental::new());
+    }
+
+    #[test]
+    fn collects_in_bounded_steps() {
+        let [drops, kept_drops]: [AtomicUsize; 2] = Default::default();
+        let heap = GcHeap::new(Incremental::new());
+        let parent = heap.alloc(Node::new(&kept_drops, 0, None));
+        for value in 0..100 {
+            heap.alloc(Node::new(&drops, value, None));
+        }
+        let mut steps = 0;
+        let stats = loop {
+            steps += 1;
+            if steps == 2 {
+                // an edge stored mid-cycle, to an object allocated mid-cycle
+                let child = heap.alloc(Node::new(&kept_drops, 1, None));
+                parent
+                    .next
+                    .store(&parent, Some(child.gc()), Ordering::Relaxed);
+            }
+            match heap.collect_incremental(StepBudget::Objects(10)) {
+                StepResult::Incomplete => {}
+                StepResult::Complete(stats) => break stats,
+            }
+        };
+        assert!(steps > 2);
+        assert_eq!(stats.objects_reclaimed, 100);
+        assert_eq!(drops.load(Ordering::Relaxed), 100);
+        heap.collect();
+        assert_eq!(kept_drops.load(Ordering::Relaxed), 0);
+        let child = parent.next.load(Ordering::Relaxed).unwrap();
+        // SAFETY: the object is reachable from `parent`
+        assert_eq!(unsafe { heap.root(child) }.value, 1);
+    }
+
+    #[cfg(all(feature = "mmap", any(unix, windows)))]
+    #[test]
+    fn allocates_chunks_in_region() {
+        let region_options = IncrementalOptions {
+            region_size: 1 << 20,
+            ..IncrementalOptions::default()
+        };
+        let region = || Incremental::with_options(region_options, ThroughputPacer::default());
+        testing::run_all(region);
+        tests::reclamation(region());
+        let heap = GcHeap::new(region());
+        let mut roots = Vec::new();
+        let error = loop {
+            match heap.try_alloc([0u64; 16]) {
+                Ok(root) => roots.push(root),
+                Err(error) => break error,
+            }
+        };
+        assert_eq!(error, AllocError::OutOfMemory);
+        assert_eq!(heap.stats().heap_size, 1 << 20);
+        roots.clear();
+        heap.collect();
+        assert_eq!(heap.shrink().bytes_released, 1 << 20);
+        assert!(heap.try_alloc([0u64; 16]).is_ok());
+    }
+}
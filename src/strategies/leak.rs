@@ -0,0 +1,289 @@
+use core::{cell::RefCell, fmt};
+
+use super::{
+    bump::Bump,
+    table::{Object, ObjectTable, State},
+};
+use crate::{
+    heap::{
+        AllocError, Collector, FreshAllocation, GcAllocator, GcVtable, GenerationalHandle,
+        HeapStats, RootManager, ShrinkStats,
+    },
+    trace::RootProvider,
+};
+
+/// Options for a [`Leak`] heap. New options may be added in the future, so start from [`LeakOptions::default`] and
+/// set the fields of interest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct LeakOptions {
+    /// The most bytes of memory the heap may reserve. Once the heap has reached this size, allocations fail with
+    /// [`AllocError::OutOfMemory`]. Defaults to no limit.
+    pub max_heap_size: usize,
+    /// The number of bytes the heap grows by when the last chunk is full, which must not be zero. Objects larger
+    /// than a quarter of a chunk are allocated on their own. Defaults to 64 KiB.
+    pub chunk_size: usize,
+}
+
+impl Default for LeakOptions {
+    fn default() -> Self {
+        Self {
+            max_heap_size: usize::MAX,
+            chunk_size: 64 << 10,
+        }
+    }
+}
+
+/// A strategy that never collects. See the [module documentation](super).
+///
+/// Objects are bump-allocated in chunks, and live until the heap is dropped, which finalizes every Initialized
+/// object and releases the chunks all at once. Roots and pins are not counted, and root providers are ignored, as
+/// they would make no difference. Weak cells never lose their targets.
+///
+/// As nothing is ever traced, the strategy suits tests and benchmarks that should not be disturbed by collections,
+/// and heaps that live for a bounded amount of work, such as a single request. Memory use grows with every
+/// allocation, so [`LeakOptions::max_heap_size`] is a useful safeguard.
+///
+/// Like the other strategies in this module, the strategy is neither `Send` nor `Sync`.
+pub struct Leak {
+    heap: RefCell<Heap>,
+    options: LeakOptions,
+}
+
+impl Leak {
+    /// Creates an empty heap with the default options.
+    pub fn new() -> Self {
+        Self::with_options(LeakOptions::default())
+    }
+
+    /// Creates an empty heap with the given options.
+    ///
+    /// # Panics
+    /// Panics if `options.chunk_size` is zero.
+    pub fn with_options(options: LeakOptions) -> Self {
+        assert!(options.chunk_size > 0, "chunk size is zero");
+        Self {
+            heap: RefCell::new(Heap {
+                objects: ObjectTable::new(),
+                memory: Bump::new(options.chunk_size),
+                bytes_live: 0,
+            }),
+            options,
+        }
+    }
+
+    /// The options this heap was created with.
+    pub fn options(&self) -> &LeakOptions {
+        &self.options
+    }
+}
+
+impl Default for Leak {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Leak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let heap = self.heap.borrow();
+        f.debug_struct("Leak")
+            .field("heap_size", &heap.memory.heap_size())
+            .field("bytes_live", &heap.bytes_live)
+            .field("object_count", &heap.objects.object_count())
+            .field("options", &self.options)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for Leak {
+    fn drop(&mut self) {
+        // the chunks are released when the heap is
+        self.heap.get_mut().objects.finalize_all();
+    }
+}
+
+// SAFETY: objects are only reclaimed, and Initialized objects only finalized, when the heap is dropped. Objects
+// never move.
+unsafe impl GcAllocator for Leak {
+    type Handle = GenerationalHandle;
+    type Error = AllocError;
+
+    fn allocate(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+    ) -> Result<FreshAllocation<GenerationalHandle>, AllocError> {
+        let layout = vtable.layout(len).ok_or(AllocError::InvalidLayout)?;
+        let mut heap = self.heap.borrow_mut();
+        let (addr, size) = heap
+            .memory
+            .bump(layout, self.options.max_heap_size)
+            .ok_or(AllocError::OutOfMemory)?;
+        heap.bytes_live += size;
+        let handle = heap.objects.insert(Object::fresh(addr, vtable, len, size));
+        Ok(FreshAllocation {
+            handle,
+            ptr: addr.as_ptr().cast(),
+        })
+    }
+
+    unsafe fn set_initialized(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).state = State::Initialized;
+    }
+
+    unsafe fn set_finalized(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).state = State::Finalized;
+    }
+
+    unsafe fn reclaim(&self, obj: GenerationalHandle) {
+        let mut heap = self.heap.borrow_mut();
+        // the memory stays allocated until the heap is dropped
+        let size = heap.objects.reclaim(obj).memory;
+        heap.bytes_live -= size;
+    }
+
+    fn pin(&self, obj: GenerationalHandle) -> *const () {
+        self.resolve(obj)
+    }
+
+    fn unpin(&self, obj: GenerationalHandle) {
+        let _ = obj;
+    }
+
+    fn pin_long_lived(&self, obj: GenerationalHandle) -> *const () {
+        self.heap.borrow_mut().objects.add_long_lived_pin();
+        self.pin(obj)
+    }
+
+    fn unpin_long_lived(&self, obj: GenerationalHandle) {
+        self.unpin(obj);
+        self.heap.borrow_mut().objects.remove_long_lived_pin();
+    }
+
+    fn resolve(&self, obj: GenerationalHandle) -> *const () {
+        self.heap.borrow().objects.get(obj).addr.as_ptr().cast()
+    }
+
+    fn shrink(&self) -> ShrinkStats {
+        let memory = &mut self.heap.borrow_mut().memory;
+        ShrinkStats {
+            bytes_released: memory.shrink(),
+            heap_size: memory.heap_size(),
+        }
+    }
+
+    fn contains(&self, obj: GenerationalHandle) -> bool {
+        self.heap.borrow().objects.lookup(obj).is_some()
+    }
+
+    fn vtable(&self, obj: GenerationalHandle) -> &'static GcVtable {
+        self.heap.borrow().objects.get(obj).vtable
+    }
+}
+
+// SAFETY: no object is reclaimed before the heap is dropped, so every object stays alive as long as it is rooted.
+unsafe impl RootManager<GenerationalHandle> for Leak {
+    fn root(&self, obj: GenerationalHandle) {
+        let _ = obj;
+    }
+
+    fn unroot(&self, obj: GenerationalHandle) {
+        let _ = obj;
+    }
+
+    unsafe fn register_root_provider(&self, provider: &dyn RootProvider) {
+        let _ = provider;
+    }
+
+    unsafe fn unregister_root_provider(&self, provider: &dyn RootProvider) {
+        let _ = provider;
+    }
+}
+
+// SAFETY: weak cells are never cleared, as their targets are never reclaimed.
+unsafe impl Collector<GenerationalHandle> for Leak {
+    fn allocate_weak(&self, target: GenerationalHandle) -> Option<GenerationalHandle> {
+        let mut heap = self.heap.borrow_mut();
+        heap.objects.get(target);
+        Some(heap.objects.insert(Object::weak(target, 0)))
+    }
+
+    unsafe fn upgrade_weak(&self, cell: GenerationalHandle) -> Option<GenerationalHandle> {
+        self.heap
+            .borrow()
+            .objects
+            .get(cell)
+            .weak
+            .expect("object is not a weak cell")
+    }
+
+    fn stats(&self) -> HeapStats {
+        let heap = self.heap.borrow();
+        HeapStats {
+            heap_size: heap.memory.heap_size(),
+            bytes_live: heap.bytes_live,
+            object_count: heap.objects.object_count(),
+            long_lived_pins: heap.objects.long_lived_pins(),
+            ..HeapStats::default()
+        }
+    }
+}
+
+/// The state of a [`Leak`] heap. The memory of each object is the number of bytes it takes up.
+struct Heap {
+    objects: ObjectTable<usize>,
+    memory: Bump,
+    bytes_live: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{
+        testing::{self, Node},
+        GcHeap,
+    };
+
+    #[test]
+    fn conformance() {
+        testing::run_all(Leak::new);
+    }
+
+    #[test]
+    fn reentrant_finalizers() {
+        testing::reentrant_finalizers(Leak::new());
+    }
+
+    #[test]
+    fn reclaims_nothing_until_dropped() {
+        let drops = AtomicUsize::new(0);
+        let heap = GcHeap::new(Leak::new());
+        for value in 0..10 {
+            heap.alloc(Node::new(&drops, value, None));
+        }
+        let weak = heap.alloc(7u32).downgrade();
+        heap.collect();
+        assert_eq!(drops.load(Ordering::Relaxed), 0);
+        assert_eq!(weak.upgrade().as_deref(), Some(&7));
+        assert_eq!(heap.stats().object_count, 12);
+        drop(weak);
+        drop(heap);
+        assert_eq!(drops.load(Ordering::Relaxed), 10);
+    }
+
+    #[test]
+    fn fails_beyond_max_heap_size() {
+        let options = LeakOptions {
+            max_heap_size: 64 << 10,
+            ..LeakOptions::default()
+        };
+        let heap = GcHeap::new(Leak::with_options(options));
+        let allocated = (0..)
+            .take_while(|_| heap.try_alloc([0u64; 64]).is_ok())
+            .count();
+        assert_eq!(allocated, (64 << 10) / 512);
+    }
+}
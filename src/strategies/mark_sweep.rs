@@ -0,0 +1,580 @@
+use alloc::vec::Vec;
+use core::{alloc::Layout, cell::RefCell, fmt, ptr::NonNull, time::Duration};
+
+use super::{
+    free_list::{Cell, FreeList, MAX_CELL_SIZE},
+    table::{self, Object, ObjectTable, State},
+};
+use crate::{
+    heap::{
+        AllocError, CollectOptions, CollectionStats, Collector, FreshAllocation, GcAllocator,
+        GcVtable, GenerationalHandle, HeapStats, MemoryPressure, RootManager, ShrinkStats,
+        StepBudget, StepResult,
+    },
+    pacer::{GcPacer, PacingInput, ThroughputPacer},
+    trace::RootProvider,
+};
+
+/// Options for a [`MarkSweep`] heap. New options may be added in the future, so start from
+/// [`MarkSweepOptions::default`] and set the fields of interest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MarkSweepOptions {
+    /// The most bytes of memory the heap may reserve, including free space. Once the heap has reached this size, an
+    /// allocation that does not fit in the free space runs a full collection, and fails with
+    /// [`AllocError::OutOfMemory`] if that does not free enough. Defaults to no limit.
+    pub max_heap_size: usize,
+    /// The number of bytes the heap grows by when it runs out of cells of some size, which must be at least 2 KiB.
+    /// Larger chunks make the heap grow less often, at the cost of more free space. Defaults to 64 KiB.
+    pub chunk_size: usize,
+    /// The number of threads that mark the heap during collections, including the collecting thread, which must be at
+    /// least 1. Extra threads are spawned for each collection and steal work from each other, so they only pay off
+    /// for heaps with many objects. Without the `std` feature, marking always runs on the collecting thread alone.
+    /// Defaults to 1.
+    pub mark_workers: usize,
+    /// The number of bytes of address space to reserve up front in a [`HeapRegion`](super::HeapRegion) for the
+    /// chunks of the heap, or zero to allocate chunks from the global allocator. Chunks are committed in the region as
+    /// the heap grows, and the heap cannot grow beyond it. A chunk freed by shrinking the heap is only returned to the
+    /// operating system once every chunk after it in the region is free too, so the chunk size should be a whole
+    /// number of pages. Large and overaligned objects are still allocated from the global allocator. Only available
+    /// on Unix and Windows with the `mmap` feature. Defaults to zero.
+    #[cfg(all(feature = "mmap", any(unix, windows)))]
+    pub region_size: usize,
+    /// The options of the region reserved for the chunks of the heap, if [`MarkSweepOptions::region_size`] is not
+    /// zero. Only available on Unix and Windows with the `mmap` feature.
+    #[cfg(all(feature = "mmap", any(unix, windows)))]
+    pub region_options: super::HeapRegionOptions,
+}
+
+impl Default for MarkSweepOptions {
+    fn default() -> Self {
+        Self {
+            max_heap_size: usize::MAX,
+            chunk_size: 64 << 10,
+            mark_workers: 1,
+            #[cfg(all(feature = "mmap", any(unix, windows)))]
+            region_size: 0,
+            #[cfg(all(feature = "mmap", any(unix, windows)))]
+            region_options: super::HeapRegionOptions::default(),
+        }
+    }
+}
+
+/// A non-moving mark-sweep strategy. See the [module documentation](super).
+///
+/// Small objects are allocated in cells of a fixed set of sizes, which are carved out of chunks of memory and kept
+/// on a free list for each size. Large and overaligned objects are allocated directly from the global allocator.
+/// Handles are [`GenerationalHandle`]s into a table of objects, so the use of a stale handle is caught in debug
+/// builds.
+///
+/// The pacer decides when to collect, and is consulted on every allocation. Every collection is a full collection,
+/// whatever kind the pacer asks for. A collection marks the objects reachable from roots, pins, frozen objects,
+/// and registered root providers by tracing them with their vtables, then reclaims the rest. Objects with a
+/// finalizer are finalized once marking is done, and reclaimed afterwards. Weak cells and ephemerons are supported.
+///
+/// Collections run on the allocating thread without stopping the others, so the strategy is neither `Send` nor
+/// `Sync`, and a heap belongs to the thread that created it. Marking may be shared with helper threads (see
+/// [`MarkSweepOptions::mark_workers`]), but finalizers always run on the allocating thread. In exchange, objects
+/// never move, and thread-local objects are always finalized on the thread that allocated them.
+pub struct MarkSweep<P = ThroughputPacer> {
+    heap: RefCell<Heap>,
+    pacer: RefCell<P>,
+    options: MarkSweepOptions,
+}
+
+impl MarkSweep {
+    /// Creates an empty heap with the default options, collected whenever a [`ThroughputPacer`] says so.
+    pub fn new() -> Self {
+        Self::with_options(MarkSweepOptions::default(), ThroughputPacer::default())
+    }
+}
+
+impl Default for MarkSweep {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: GcPacer> MarkSweep<P> {
+    /// Creates an empty heap with the given options, collected whenever `pacer` says so.
+    ///
+    /// # Panics
+    /// Panics if `options.chunk_size` is less than 2 KiB, or `options.mark_workers` is zero. With the `mmap` feature,
+    /// also panics if `options.region_size` is not zero and the region cannot be reserved.
+    pub fn with_options(options: MarkSweepOptions, pacer: P) -> Self {
+        assert!(
+            options.chunk_size >= MAX_CELL_SIZE,
+            "chunk size {} is less than 2 KiB",
+            options.chunk_size
+        );
+        assert!(options.mark_workers > 0, "mark worker count is zero");
+        #[cfg(all(feature = "mmap", any(unix, windows)))]
+        let cells = FreeList::with_region(
+            options.chunk_size,
+            options.region_size,
+            options.region_options,
+        );
+        #[cfg(not(all(feature = "mmap", any(unix, windows))))]
+        let cells = FreeList::new(options.chunk_size);
+        Self {
+            heap: RefCell::new(Heap::new(cells)),
+            pacer: RefCell::new(pacer),
+            options,
+        }
+    }
+
+    /// The options this heap was created with.
+    pub fn options(&self) -> &MarkSweepOptions {
+        &self.options
+    }
+
+    /// Marks, finalizes, and sweeps the whole heap. Does nothing while finalizers run.
+    fn collect_full(&self) -> CollectionStats {
+        if self.heap.borrow().objects.is_finalizing() {
+            return CollectionStats::default();
+        }
+        #[cfg(feature = "std")]
+        let start = std::time::Instant::now();
+        let sweep = {
+            let mut heap = self.heap.borrow_mut();
+            let Heap { objects, memory } = &mut *heap;
+            objects.mark_parallel(self.options.mark_workers);
+            objects.sweep(|object| memory.free(object))
+        };
+        table::run_finalizers(&self.heap, |heap| &heap.objects, &sweep.finalize);
+        let mut stats = sweep.stats;
+        let mut heap = self.heap.borrow_mut();
+        let Heap { objects, memory } = &mut *heap;
+        for index in sweep.finalize {
+            stats.bytes_reclaimed += memory.free(objects.remove(index));
+        }
+        stats.bytes_live = memory.bytes_live;
+        memory.bytes_allocated = 0;
+        memory.bytes_live_after_collection = memory.bytes_live;
+        memory.collection_count += 1;
+        #[cfg(feature = "std")]
+        memory.record_pause(start.elapsed());
+        let input = memory.pacing_input();
+        drop(heap);
+        self.pacer.borrow_mut().collection_finished(&stats, &input);
+        stats
+    }
+
+    /// Reserves memory for an object with the given layout, collecting first if the pacer says so, and again if the
+    /// heap is full.
+    fn reserve(&self, layout: Layout) -> Result<(NonNull<u8>, Cell), AllocError> {
+        let input = self.heap.borrow().memory.pacing_input();
+        if self.pacer.borrow_mut().should_collect(&input).is_some() {
+            self.collect_full();
+        }
+        if let Some(memory) = self
+            .heap
+            .borrow_mut()
+            .memory
+            .reserve(layout, self.options.max_heap_size)
+        {
+            return Ok(memory);
+        }
+        self.collect_full();
+        (self.heap.borrow_mut().memory)
+            .reserve(layout, self.options.max_heap_size)
+            .ok_or(AllocError::OutOfMemory)
+    }
+}
+
+impl<P> fmt::Debug for MarkSweep<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let heap = self.heap.borrow();
+        f.debug_struct("MarkSweep")
+            .field("heap_size", &heap.memory.cells.heap_size())
+            .field("bytes_live", &heap.memory.bytes_live)
+            .field("object_count", &heap.objects.object_count())
+            .field("options", &self.options)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P> Drop for MarkSweep<P> {
+    fn drop(&mut self) {
+        let Heap { objects, memory } = self.heap.get_mut();
+        objects.finalize_all();
+        for index in objects.indices().collect::<Vec<_>>() {
+            memory.free(objects.remove(index));
+        }
+    }
+}
+
+// SAFETY: objects are kept alive while rooted, pinned, frozen, or reachable from one that is, and never move.
+// Unreachable objects are finalized once after marking, and Uninitialized ones are never traced or finalized.
+unsafe impl<P: GcPacer> GcAllocator for MarkSweep<P> {
+    type Handle = GenerationalHandle;
+    type Error = AllocError;
+
+    fn allocate(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+    ) -> Result<FreshAllocation<GenerationalHandle>, AllocError> {
+        let layout = vtable.layout(len).ok_or(AllocError::InvalidLayout)?;
+        let (addr, cell) = self.reserve(layout)?;
+        let mut heap = self.heap.borrow_mut();
+        let handle = heap
+            .objects
+            .insert(Object::fresh(addr, vtable, len, Some(cell)));
+        Ok(FreshAllocation {
+            handle,
+            ptr: addr.as_ptr().cast(),
+        })
+    }
+
+    unsafe fn set_initialized(&self, obj: GenerationalHandle) {
+        let mut heap = self.heap.borrow_mut();
+        let object = heap.objects.get_mut(obj);
+        object.pins -= 1;
+        object.state = State::Initialized;
+    }
+
+    unsafe fn set_finalized(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).state = State::Finalized;
+    }
+
+    unsafe fn reclaim(&self, obj: GenerationalHandle) {
+        let Heap { objects, memory } = &mut *self.heap.borrow_mut();
+        memory.free(objects.reclaim(obj));
+    }
+
+    fn pin(&self, obj: GenerationalHandle) -> *const () {
+        let mut heap = self.heap.borrow_mut();
+        let object = heap.objects.get_mut(obj);
+        object.pins += 1;
+        object.addr.as_ptr().cast()
+    }
+
+    fn unpin(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).pins -= 1;
+    }
+
+    fn pin_long_lived(&self, obj: GenerationalHandle) -> *const () {
+        self.heap.borrow_mut().objects.add_long_lived_pin();
+        self.pin(obj)
+    }
+
+    fn unpin_long_lived(&self, obj: GenerationalHandle) {
+        self.unpin(obj);
+        self.heap.borrow_mut().objects.remove_long_lived_pin();
+    }
+
+    fn resolve(&self, obj: GenerationalHandle) -> *const () {
+        self.heap.borrow().objects.get(obj).addr.as_ptr().cast()
+    }
+
+    fn shrink(&self) -> ShrinkStats {
+        let cells = &mut self.heap.borrow_mut().memory.cells;
+        ShrinkStats {
+            bytes_released: cells.shrink(),
+            heap_size: cells.heap_size(),
+        }
+    }
+
+    fn contains(&self, obj: GenerationalHandle) -> bool {
+        self.heap.borrow().objects.lookup(obj).is_some()
+    }
+
+    fn vtable(&self, obj: GenerationalHandle) -> &'static GcVtable {
+        self.heap.borrow().objects.get(obj).vtable
+    }
+}
+
+// SAFETY: roots and root providers are traced by every collection.
+unsafe impl<P> RootManager<GenerationalHandle> for MarkSweep<P> {
+    fn root(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).roots += 1;
+    }
+
+    fn unroot(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).roots -= 1;
+    }
+
+    fn freeze(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).frozen = true;
+    }
+
+    unsafe fn register_root_provider(&self, provider: &dyn RootProvider) {
+        // SAFETY: caller
+        unsafe {
+            self.heap
+                .borrow_mut()
+                .objects
+                .register_root_provider(provider)
+        };
+    }
+
+    unsafe fn unregister_root_provider(&self, provider: &dyn RootProvider) {
+        self.heap
+            .borrow_mut()
+            .objects
+            .unregister_root_provider(provider);
+    }
+}
+
+// SAFETY: weak cells and ephemerons are cleared during the collection that finds their targets unreachable, before
+// the targets are finalized. Marking only marks the value of an ephemeron once it has marked the key.
+unsafe impl<P: GcPacer> Collector<GenerationalHandle> for MarkSweep<P> {
+    fn allocate_weak(&self, target: GenerationalHandle) -> Option<GenerationalHandle> {
+        let mut heap = self.heap.borrow_mut();
+        heap.objects.get(target);
+        Some(heap.objects.insert(Object::weak(target, None)))
+    }
+
+    unsafe fn upgrade_weak(&self, cell: GenerationalHandle) -> Option<GenerationalHandle> {
+        let mut heap = self.heap.borrow_mut();
+        let target = heap
+            .objects
+            .get(cell)
+            .weak
+            .expect("object is not a weak cell")?;
+        heap.objects.get_mut(target).roots += 1;
+        Some(target)
+    }
+
+    fn allocate_ephemeron(
+        &self,
+        key: GenerationalHandle,
+        value: GenerationalHandle,
+    ) -> Option<GenerationalHandle> {
+        let mut heap = self.heap.borrow_mut();
+        heap.objects.get(key);
+        heap.objects.get(value);
+        Some(heap.objects.insert(Object::ephemeron(key, value, None)))
+    }
+
+    unsafe fn upgrade_ephemeron(
+        &self,
+        cell: GenerationalHandle,
+    ) -> Option<(GenerationalHandle, GenerationalHandle)> {
+        let mut heap = self.heap.borrow_mut();
+        let object = heap.objects.get(cell);
+        let key = object.weak.expect("object is not an ephemeron")?;
+        let value = object.value.expect("object is not an ephemeron");
+        heap.objects.get_mut(key).roots += 1;
+        heap.objects.get_mut(value).roots += 1;
+        Some((key, value))
+    }
+
+    fn collect(&self, options: CollectOptions) -> CollectionStats {
+        let _ = options;
+        self.collect_full()
+    }
+
+    fn collect_step(&self, budget: StepBudget) -> StepResult {
+        let _ = budget;
+        StepResult::Complete(self.collect_full())
+    }
+
+    fn memory_pressure(&self, level: MemoryPressure) {
+        if level == MemoryPressure::Critical {
+            self.collect_full();
+        }
+        self.shrink();
+    }
+
+    fn stats(&self) -> HeapStats {
+        let heap = self.heap.borrow();
+        HeapStats {
+            heap_size: heap.memory.cells.heap_size(),
+            bytes_live: heap.memory.bytes_live,
+            object_count: heap.objects.object_count(),
+            long_lived_pins: heap.objects.long_lived_pins(),
+            collection_count: heap.memory.collection_count,
+            total_pause: heap.memory.total_pause,
+        }
+    }
+}
+
+/// The state of a [`MarkSweep`] heap.
+struct Heap {
+    /// The objects, and the cells they are allocated in. Weak cells take up no memory.
+    objects: ObjectTable<Option<Cell>>,
+    memory: Memory,
+}
+
+impl Heap {
+    fn new(cells: FreeList) -> Self {
+        Self {
+            objects: ObjectTable::new(),
+            memory: Memory::new(cells),
+        }
+    }
+}
+
+/// The memory of a [`MarkSweep`] heap, and statistics about its use. Also used by
+/// [`Incremental`](super::Incremental), which allocates the same way.
+pub(super) struct Memory {
+    pub(super) cells: FreeList,
+    pub(super) bytes_live: usize,
+    /// The number of bytes allocated since the last collection.
+    pub(super) bytes_allocated: usize,
+    pub(super) bytes_live_after_collection: usize,
+    pub(super) collection_count: u64,
+    pub(super) last_pause: Duration,
+    pub(super) total_pause: Duration,
+}
+
+impl Memory {
+    pub(super) fn new(cells: FreeList) -> Self {
+        Self {
+            cells,
+            bytes_live: 0,
+            bytes_allocated: 0,
+            bytes_live_after_collection: 0,
+            collection_count: 0,
+            last_pause: Duration::ZERO,
+            total_pause: Duration::ZERO,
+        }
+    }
+
+    /// Reserves memory for an object with the given layout, or returns None if the heap would grow beyond
+    /// `max_heap_size` bytes.
+    pub(super) fn reserve(
+        &mut self,
+        layout: Layout,
+        max_heap_size: usize,
+    ) -> Option<(NonNull<u8>, Cell)> {
+        let reserved = self.cells.reserve(layout, max_heap_size)?;
+        self.bytes_allocated += reserved.1.size();
+        self.bytes_live += reserved.1.size();
+        Some(reserved)
+    }
+
+    /// Frees the memory of a removed object, returning the number of bytes freed.
+    pub(super) fn free(&mut self, object: Object<Option<Cell>>) -> usize {
+        let Some(cell) = object.memory else {
+            return 0;
+        };
+        self.bytes_live -= cell.size();
+        // SAFETY: the object has been removed, so its memory is no longer used
+        unsafe { self.cells.free(object.addr, cell) };
+        cell.size()
+    }
+
+    #[cfg(feature = "std")]
+    pub(super) fn record_pause(&mut self, pause: Duration) {
+        self.last_pause = pause;
+        self.total_pause += pause;
+    }
+
+    pub(super) fn pacing_input(&self) -> PacingInput {
+        PacingInput {
+            bytes_allocated: self.bytes_allocated,
+            bytes_live: self.bytes_live,
+            bytes_live_after_major: self.bytes_live_after_collection,
+            last_pause: self.last_pause,
+            average_pause: match self.collection_count {
+                0 => Duration::ZERO,
+                count => self.total_pause / count as u32,
+            },
+            ..PacingInput::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{strategies::tests, testing, GcHeap};
+
+    #[test]
+    fn conformance() {
+        testing::run_all(MarkSweep::new);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn conformance_with_mark_workers() {
+        testing::run_all(|| {
+            let options = MarkSweepOptions {
+                mark_workers: 4,
+                ..MarkSweepOptions::default()
+            };
+            MarkSweep::with_options(options, ThroughputPacer::default())
+        });
+    }
+
+    #[test]
+    fn reentrant_finalizers() {
+        testing::reentrant_finalizers(MarkSweep::new());
+    }
+
+    #[test]
+    fn reclamation() {
+        tests::reclamation(MarkSweep::new());
+    }
+
+    #[test]
+    fn pinning() {
+        tests::pinning(MarkSweep::new());
+    }
+
+    #[test]
+    fn weak_cells() {
+        tests::weak_cells(MarkSweep::new());
+    }
+
+    #[test]
+    fn ephemerons() {
+        tests::ephemerons(MarkSweep::new());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn reclamation_with_mark_workers() {
+        let options = MarkSweepOptions {
+            mark_workers: 4,
+            ..MarkSweepOptions::default()
+        };
+        tests::reclamation(MarkSweep::with_options(options, ThroughputPacer::default()));
+        tests::weak_cells(MarkSweep::with_options(options, ThroughputPacer::default()));
+        tests::ephemerons(MarkSweep::with_options(options, ThroughputPacer::default()));
+    }
+
+    #[cfg(all(feature = "mmap", any(unix, windows)))]
+    #[test]
+    fn allocates_chunks_in_region() {
+        let region_options = MarkSweepOptions {
+            region_size: 1 << 20,
+            ..MarkSweepOptions::default()
+        };
+        let region = || MarkSweep::with_options(region_options, ThroughputPacer::default());
+        testing::run_all(region);
+        tests::reclamation(region());
+        let heap = GcHeap::new(region());
+        let mut roots = Vec::new();
+        let error = loop {
+            match heap.try_alloc([0u64; 16]) {
+                Ok(root) => roots.push(root),
+                Err(error) => break error,
+            }
+        };
+        assert_eq!(error, AllocError::OutOfMemory);
+        assert_eq!(heap.stats().heap_size, 1 << 20);
+        roots.clear();
+        heap.collect();
+        assert_eq!(heap.shrink().bytes_released, 1 << 20);
+        assert!(heap.try_alloc([0u64; 16]).is_ok());
+    }
+
+    #[test]
+    fn reuses_freed_memory() {
+        let heap = GcHeap::new(MarkSweep::new());
+        for _ in 0..1000 {
+            heap.alloc([0u64; 16]);
+        }
+        heap.collect();
+        let heap_size = heap.stats().heap_size;
+        assert!(heap_size > 0);
+        for _ in 0..1000 {
+            heap.alloc([0u64; 16]);
+        }
+        assert_eq!(heap.stats().heap_size, heap_size);
+    }
+}
@@ -0,0 +1,103 @@
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::{sync::Mutex, thread};
+
+/// The number of objects a worker keeps to itself. Beyond this, it offers half of its mark stack to the others,
+/// checking whether they need more work after tracing this many objects.
+const SHARE_SIZE: usize = 64;
+
+/// Passes the index of each object referred to by the object with the given index to the visitor.
+pub(super) type TraceFn<'a> = dyn Fn(usize, &mut dyn FnMut(usize)) + Sync + 'a;
+
+/// Marks every object reachable from `roots` with `workers` threads, including the calling one. Objects are
+/// identified by their index in `marks`, and `roots` must already be marked.
+///
+/// Each worker traces the objects on its own mark stack, and every so often moves half of it to a shared queue if
+/// that queue is empty and the stack has grown large. Workers that run out of objects steal half of the queue of
+/// another worker, and marking finishes once every worker is out of objects and every queue is empty.
+pub(super) fn mark(marks: &[AtomicBool], roots: Vec<usize>, workers: usize, trace: &TraceFn<'_>) {
+    let queues: Vec<Mutex<Vec<usize>>> = (0..workers).map(|_| Mutex::new(Vec::new())).collect();
+    let chunk = roots.len().div_ceil(workers).max(1);
+    for (queue, roots) in queues.iter().zip(roots.chunks(chunk)) {
+        queue.lock().unwrap().extend_from_slice(roots);
+    }
+    let marker = Marker {
+        marks,
+        queues: &queues,
+        idle: AtomicUsize::new(0),
+        trace,
+    };
+    thread::scope(|scope| {
+        for id in 1..workers {
+            let marker = &marker;
+            scope.spawn(move || marker.run(id));
+        }
+        marker.run(0);
+    });
+}
+
+struct Marker<'a> {
+    marks: &'a [AtomicBool],
+    /// The objects each worker has made available to the others.
+    queues: &'a [Mutex<Vec<usize>>],
+    /// The number of workers that are out of objects.
+    idle: AtomicUsize,
+    trace: &'a TraceFn<'a>,
+}
+
+impl Marker<'_> {
+    fn run(&self, id: usize) {
+        let mut stack = Vec::new();
+        let mut traced = 0usize;
+        loop {
+            while let Some(index) = stack.pop() {
+                (self.trace)(index, &mut |child| {
+                    if !self.marks[child].swap(true, Ordering::Relaxed) {
+                        stack.push(child);
+                    }
+                });
+                traced += 1;
+                if traced.is_multiple_of(SHARE_SIZE) && stack.len() > SHARE_SIZE {
+                    let mut queue = self.queues[id].lock().unwrap();
+                    if queue.is_empty() {
+                        queue.extend(stack.drain(..stack.len() / 2));
+                    }
+                }
+            }
+            if self.steal(id, &mut stack) {
+                continue;
+            }
+            // idle workers cannot create work, so once all of them are idle, any work left is in a queue
+            self.idle.fetch_add(1, Ordering::SeqCst);
+            loop {
+                if self
+                    .queues
+                    .iter()
+                    .any(|queue| !queue.lock().unwrap().is_empty())
+                {
+                    self.idle.fetch_sub(1, Ordering::SeqCst);
+                    break;
+                }
+                if self.idle.load(Ordering::SeqCst) == self.queues.len() {
+                    return;
+                }
+                thread::yield_now();
+            }
+        }
+    }
+
+    /// Moves half of the first non-empty queue, starting with the worker's own, onto its mark stack. Returns
+    /// whether there was anything to steal.
+    fn steal(&self, id: usize, stack: &mut Vec<usize>) -> bool {
+        let workers = self.queues.len();
+        for victim in (id..workers).chain(0..id) {
+            let mut queue = self.queues[victim].lock().unwrap();
+            if !queue.is_empty() {
+                let keep = if victim == id { 0 } else { queue.len() / 2 };
+                stack.extend(queue.drain(keep..));
+                return true;
+            }
+        }
+        false
+    }
+}
@@ -0,0 +1,807 @@
+use alloc::vec::Vec;
+use core::{alloc::Layout, cell::RefCell, fmt, mem, ptr::NonNull};
+
+use super::{
+    free_list::{Cell, FreeList, MAX_CELL_SIZE},
+    table::{self, Object, ObjectTable, State},
+};
+use crate::{
+    heap::{
+        AllocError, CollectOptions, CollectionKind, CollectionStats, Collector, FreshAllocation,
+        GcAllocator, GcVtable, GenerationalHandle, HeapStats, MemoryPressure, RootManager,
+        ShrinkStats, StepBudget, StepResult,
+    },
+    trace::{RootProvider, TraceContext},
+};
+
+/// Options for a [`RefCounting`] heap. New options may be added in the future, so start from
+/// [`RefCountingOptions::default`] and set the fields of interest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RefCountingOptions {
+    /// The most bytes of memory the heap may reserve, including free space. Once the heap has reached this size, an
+    /// allocation that does not fit in the free space collects cycles, and fails with [`AllocError::OutOfMemory`]
+    /// if that does not free enough. Defaults to no limit.
+    pub max_heap_size: usize,
+    /// The number of bytes the heap grows by when it runs out of cells of some size, which must be at least 2 KiB.
+    /// Defaults to 64 KiB.
+    pub chunk_size: usize,
+    /// The number of possible roots of garbage cycles to gather before collecting cycles. Larger buffers make cycle
+    /// collection rarer, at the cost of keeping garbage cycles alive for longer. Defaults to 4096.
+    pub cycle_buffer_size: usize,
+    /// The number of bytes of address space to reserve up front in a [`HeapRegion`](super::HeapRegion) for the
+    /// chunks of the heap, or zero to allocate chunks from the global allocator, like
+    /// [`MarkSweepOptions::region_size`](super::MarkSweepOptions::region_size). Only available on Unix and Windows
+    /// with the `mmap` feature. Defaults to zero.
+    #[cfg(all(feature = "mmap", any(unix, windows)))]
+    pub region_size: usize,
+    /// The options of the region reserved for the chunks of the heap, if [`RefCountingOptions::region_size`] is not
+    /// zero. Only available on Unix and Windows with the `mmap` feature.
+    #[cfg(all(feature = "mmap", any(unix, windows)))]
+    pub region_options: super::HeapRegionOptions,
+}
+
+impl Default for RefCountingOptions {
+    fn default() -> Self {
+        Self {
+            max_heap_size: usize::MAX,
+            chunk_size: 64 << 10,
+            cycle_buffer_size: 4096,
+            #[cfg(all(feature = "mmap", any(unix, windows)))]
+            region_size: 0,
+            #[cfg(all(feature = "mmap", any(unix, windows)))]
+            region_options: super::HeapRegionOptions::default(),
+        }
+    }
+}
+
+/// A deferred reference counting strategy, which collects garbage cycles by trial deletion. See the
+/// [module documentation](super).
+///
+/// Each object counts the references to it from other objects. Those are found by tracing the object when it is
+/// Initialized, and again after [`Collector::write_barrier`] reports a store into it, so every reference stored into
+/// an Initialized object must be reported. A store that only removes a reference is not reported, so the object it
+/// referred to stays counted until its referrer is traced again or reclaimed. Roots and pins are counted separately,
+/// and root providers are checked before anything is reclaimed.
+///
+/// Stores are only counted, and objects whose counts drop to zero only reclaimed, on the next allocation or
+/// collection, so that dropping a root never runs a finalizer and a burst of stores into one object traces it once.
+/// Most garbage is reclaimed then, without tracing anything but the objects written to.
+///
+/// Objects that lose a reference but stay referenced may be the roots of garbage cycles, which counting alone never
+/// reclaims. They are buffered, and once the buffer is full, or a major or full collection is requested, cycles are
+/// found with the synchronous algorithm of Bacon and Rajan: the references within the subgraphs reachable from the
+/// buffered objects are subtracted from their counts, and the objects whose counts drop to zero, and that are not
+/// reachable from an object that kept a count, are garbage. Only the buffered subgraphs are visited, and the
+/// references of each object are recorded when it is traced, so cycle collection never calls a vtable.
+///
+/// Objects never move. Weak cells are supported, but ephemerons are not. Like the other strategies in this module,
+/// objects are reclaimed on the allocating thread, so the strategy is neither `Send` nor `Sync`.
+pub struct RefCounting {
+    heap: RefCell<Heap>,
+    options: RefCountingOptions,
+}
+
+impl RefCounting {
+    /// Creates an empty heap with the default options.
+    pub fn new() -> Self {
+        Self::with_options(RefCountingOptions::default())
+    }
+
+    /// Creates an empty heap with the given options.
+    ///
+    /// # Panics
+    /// Panics if `options.chunk_size` is less than 2 KiB. With the `mmap` feature, also panics if `options.region_size`
+    /// is not zero and the region cannot be reserved.
+    pub fn with_options(options: RefCountingOptions) -> Self {
+        assert!(
+            options.chunk_size >= MAX_CELL_SIZE,
+            "chunk size {} is less than 2 KiB",
+            options.chunk_size
+        );
+        #[cfg(all(feature = "mmap", any(unix, windows)))]
+        let cells = FreeList::with_region(
+            options.chunk_size,
+            options.region_size,
+            options.region_options,
+        );
+        #[cfg(not(all(feature = "mmap", any(unix, windows))))]
+        let cells = FreeList::new(options.chunk_size);
+        Self {
+            heap: RefCell::new(Heap {
+                objects: ObjectTable::new(),
+                cells,
+                pending: Vec::new(),
+                dirty: Vec::new(),
+                candidates: Vec::new(),
+                weak_cells: Vec::new(),
+                bytes_live: 0,
+                collection_count: 0,
+            }),
+            options,
+        }
+    }
+
+    /// The options this heap was created with.
+    pub fn options(&self) -> &RefCountingOptions {
+        &self.options
+    }
+
+    /// Reclaims the objects whose counts have dropped to zero, and those that become unreferenced in turn. Does
+    /// nothing while finalizers run, which leaves the objects to the next call.
+    fn release(&self) -> CollectionStats {
+        if self.heap.borrow().objects.is_finalizing() {
+            return CollectionStats::default();
+        }
+        let mut stats = CollectionStats {
+            kind: Some(CollectionKind::Minor),
+            ..CollectionStats::default()
+        };
+        loop {
+            let dead = self.heap.borrow_mut().take_garbage();
+            if dead.is_empty() {
+                break;
+            }
+            self.reclaim_dead(dead, &mut stats);
+        }
+        stats.bytes_live = self.heap.borrow().bytes_live;
+        stats
+    }
+
+    /// Reclaims the objects whose counts have dropped to zero, then collects the garbage cycles among the buffered
+    /// objects. Does nothing while finalizers run.
+    fn collect_cycles(&self) -> CollectionStats {
+        if self.heap.borrow().objects.is_finalizing() {
+            return CollectionStats::default();
+        }
+        let mut stats = self.release();
+        let dead = self.heap.borrow_mut().find_cycles();
+        self.reclaim_dead(dead, &mut stats);
+        stats.kind = Some(CollectionKind::Full);
+        let mut heap = self.heap.borrow_mut();
+        heap.collection_count += 1;
+        stats.bytes_live = heap.bytes_live;
+        stats
+    }
+
+    /// Clears the weak cells of the given dying objects, finalizes them, and frees them.
+    fn reclaim_dead(&self, dead: Vec<usize>, stats: &mut CollectionStats) {
+        let finalize = {
+            let mut heap = self.heap.borrow_mut();
+            let Heap {
+                objects,
+                weak_cells,
+                ..
+            } = &mut *heap;
+            weak_cells.retain(|&cell| objects.lookup(cell).is_some());
+            for &cell in weak_cells.iter() {
+                let Some(Some(target)) = objects.get(cell).weak else {
+                    continue;
+                };
+                if objects.lookup(target).is_some_and(|target| target.marked) {
+                    objects.get_mut(cell).weak = Some(None);
+                }
+            }
+            let mut finalize = Vec::new();
+            for &index in &dead {
+                let object = objects.at_mut(index);
+                if object.state == State::Initialized && object.vtable.needs_finalize() {
+                    object.state = State::Finalized;
+                    finalize.push(index);
+                }
+            }
+            finalize
+        };
+        table::run_finalizers(&self.heap, |heap| &heap.objects, &finalize);
+        let mut heap = self.heap.borrow_mut();
+        stats.objects_reclaimed += dead.len();
+        for index in dead {
+            let object = heap.objects.remove(index);
+            stats.bytes_reclaimed += heap.free(object);
+        }
+    }
+
+    /// Reserves memory for an object with the given layout, reclaiming garbage first, and collecting cycles if the
+    /// heap is full.
+    fn reserve(&self, layout: Layout) -> Result<(NonNull<u8>, Cell), AllocError> {
+        self.release();
+        if self.heap.borrow().candidates.len() >= self.options.cycle_buffer_size {
+            self.collect_cycles();
+        }
+        let reserve = || {
+            let mut heap = self.heap.borrow_mut();
+            let reserved = heap.cells.reserve(layout, self.options.max_heap_size)?;
+            heap.bytes_live += reserved.1.size();
+            Some(reserved)
+        };
+        if let Some(reserved) = reserve() {
+            return Ok(reserved);
+        }
+        self.collect_cycles();
+        reserve().ok_or(AllocError::OutOfMemory)
+    }
+}
+
+impl Default for RefCounting {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for RefCounting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let heap = self.heap.borrow();
+        f.debug_struct("RefCounting")
+            .field("heap_size", &heap.cells.heap_size())
+            .field("bytes_live", &heap.bytes_live)
+            .field("object_count", &heap.objects.object_count())
+            .field("candidates", &heap.candidates.len())
+            .field("options", &self.options)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for RefCounting {
+    fn drop(&mut self) {
+        let heap = self.heap.get_mut();
+        heap.objects.finalize_all();
+        for index in heap.objects.indices().collect::<Vec<_>>() {
+            let object = heap.objects.remove(index);
+            heap.free(object);
+        }
+    }
+}
+
+// SAFETY: objects are only reclaimed once they are not held, not referenced by a root provider, and either not
+// referenced by any other object, or only by other garbage. Objects never move. Unreachable objects are finalized
+// once before they are freed, and Uninitialized ones are never traced or finalized.
+unsafe impl GcAllocator for RefCounting {
+    type Handle = GenerationalHandle;
+    type Error = AllocError;
+
+    fn allocate(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+    ) -> Result<FreshAllocation<GenerationalHandle>, AllocError> {
+        let layout = vtable.layout(len).ok_or(AllocError::InvalidLayout)?;
+        let (addr, cell) = self.reserve(layout)?;
+        let mut heap = self.heap.borrow_mut();
+        let handle = heap
+            .objects
+            .insert(Object::fresh(addr, vtable, len, Counts::new(Some(cell))));
+        Ok(FreshAllocation {
+            handle,
+            ptr: addr.as_ptr().cast(),
+        })
+    }
+
+    unsafe fn set_initialized(&self, obj: GenerationalHandle) {
+        let mut heap = self.heap.borrow_mut();
+        let object = heap.objects.get_mut(obj);
+        object.pins -= 1;
+        object.state = State::Initialized;
+        heap.retrace(obj.index());
+        heap.pending.push(obj);
+    }
+
+    unsafe fn set_finalized(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).state = State::Finalized;
+    }
+
+    unsafe fn reclaim(&self, obj: GenerationalHandle) {
+        let mut heap = self.heap.borrow_mut();
+        let object = heap.objects.reclaim(obj);
+        heap.free(object);
+    }
+
+    fn pin(&self, obj: GenerationalHandle) -> *const () {
+        let mut heap = self.heap.borrow_mut();
+        let object = heap.objects.get_mut(obj);
+        object.pins += 1;
+        object.addr.as_ptr().cast()
+    }
+
+    fn unpin(&self, obj: GenerationalHandle) {
+        let mut heap = self.heap.borrow_mut();
+        heap.objects.get_mut(obj).pins -= 1;
+        heap.pending.push(obj);
+    }
+
+    fn pin_long_lived(&self, obj: GenerationalHandle) -> *const () {
+        self.heap.borrow_mut().objects.add_long_lived_pin();
+        self.pin(obj)
+    }
+
+    fn unpin_long_lived(&self, obj: GenerationalHandle) {
+        self.unpin(obj);
+        self.heap.borrow_mut().objects.remove_long_lived_pin();
+    }
+
+    fn resolve(&self, obj: GenerationalHandle) -> *const () {
+        self.heap.borrow().objects.get(obj).addr.as_ptr().cast()
+    }
+
+    fn shrink(&self) -> ShrinkStats {
+        let cells = &mut self.heap.borrow_mut().cells;
+        ShrinkStats {
+            bytes_released: cells.shrink(),
+            heap_size: cells.heap_size(),
+        }
+    }
+
+    fn contains(&self, obj: GenerationalHandle) -> bool {
+        self.heap.borrow().objects.lookup(obj).is_some()
+    }
+
+    fn vtable(&self, obj: GenerationalHandle) -> &'static GcVtable {
+        self.heap.borrow().objects.get(obj).vtable
+    }
+}
+
+// SAFETY: held objects are never reclaimed, and the objects referenced by root providers are found before anything
+// is reclaimed.
+unsafe impl RootManager<GenerationalHandle> for RefCounting {
+    fn root(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).roots += 1;
+    }
+
+    fn unroot(&self, obj: GenerationalHandle) {
+        let mut heap = self.heap.borrow_mut();
+        heap.objects.get_mut(obj).roots -= 1;
+        heap.pending.push(obj);
+    }
+
+    fn freeze(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).frozen = true;
+    }
+
+    unsafe fn register_root_provider(&self, provider: &dyn RootProvider) {
+        // SAFETY: caller
+        unsafe {
+            self.heap
+                .borrow_mut()
+                .objects
+                .register_root_provider(provider)
+        };
+    }
+
+    unsafe fn unregister_root_provider(&self, provider: &dyn RootProvider) {
+        // the objects only the provider referred to were buffered, so cycle collection will find them
+        self.heap
+            .borrow_mut()
+            .objects
+            .unregister_root_provider(provider);
+    }
+}
+
+// SAFETY: the counts are kept up to date by the write barrier. Weak cells are cleared before their targets are
+// finalized.
+unsafe impl Collector<GenerationalHandle> for RefCounting {
+    fn write_barrier(&self, parent: GenerationalHandle, child: GenerationalHandle) {
+        let _ = child;
+        let mut heap = self.heap.borrow_mut();
+        let object = heap.objects.get_mut(parent);
+        if object.state == State::Initialized && !object.memory.dirty {
+            object.memory.dirty = true;
+            heap.dirty.push(parent);
+        }
+    }
+
+    fn allocate_weak(&self, target: GenerationalHandle) -> Option<GenerationalHandle> {
+        let mut heap = self.heap.borrow_mut();
+        heap.objects.get(target);
+        let cell = heap.objects.insert(Object::weak(target, Counts::new(None)));
+        heap.weak_cells.push(cell);
+        Some(cell)
+    }
+
+    unsafe fn upgrade_weak(&self, cell: GenerationalHandle) -> Option<GenerationalHandle> {
+        let mut heap = self.heap.borrow_mut();
+        let target = heap
+            .objects
+            .get(cell)
+            .weak
+            .expect("object is not a weak cell")?;
+        heap.objects.get_mut(target).roots += 1;
+        Some(target)
+    }
+
+    fn collect(&self, options: CollectOptions) -> CollectionStats {
+        match options.kind {
+            CollectionKind::Minor => self.release(),
+            CollectionKind::Major | CollectionKind::Full => self.collect_cycles(),
+        }
+    }
+
+    fn collect_step(&self, budget: StepBudget) -> StepResult {
+        let _ = budget;
+        StepResult::Complete(self.collect_cycles())
+    }
+
+    fn memory_pressure(&self, level: MemoryPressure) {
+        if level == MemoryPressure::Critical {
+            self.collect_cycles();
+        } else {
+            self.release();
+        }
+        self.shrink();
+    }
+
+    fn stats(&self) -> HeapStats {
+        let heap = self.heap.borrow();
+        HeapStats {
+            heap_size: heap.cells.heap_size(),
+            bytes_live: heap.bytes_live,
+            object_count: heap.objects.object_count(),
+            long_lived_pins: heap.objects.long_lived_pins(),
+            collection_count: heap.collection_count,
+            total_pause: Default::default(),
+        }
+    }
+}
+
+/// The state of a [`RefCounting`] heap.
+struct Heap {
+    objects: ObjectTable<Counts>,
+    cells: FreeList,
+    /// The objects that have lost a root, pin, or reference since garbage was last reclaimed.
+    pending: Vec<GenerationalHandle>,
+    /// The objects written to since garbage was last reclaimed, which need to be traced again.
+    dirty: Vec<GenerationalHandle>,
+    /// The possible roots of garbage cycles.
+    candidates: Vec<GenerationalHandle>,
+    weak_cells: Vec<GenerationalHandle>,
+    bytes_live: usize,
+    collection_count: u64,
+}
+
+/// The reference count of an object, and what cycle collection needs to know about it.
+struct Counts {
+    /// The cell the object is allocated in. Weak cells take up no memory.
+    cell: Option<Cell>,
+    /// The number of references to the object from other objects.
+    count: usize,
+    /// The slots of the objects this object referred to when it was last traced, once per reference.
+    edges: Vec<usize>,
+    color: Color,
+    /// Whether the object is in the candidate buffer.
+    buffered: bool,
+    /// Whether the object has been written to since it was last traced.
+    dirty: bool,
+}
+
+/// The colors of the cycle collection algorithm.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Color {
+    /// In use, or not visited by the cycle collection in progress.
+    Black,
+    /// Visited by the cycle collection in progress, which has subtracted its references from the counts of the
+    /// objects it refers to.
+    Gray,
+    /// Garbage, found by the cycle collection in progress.
+    White,
+    /// A possible root of a garbage cycle, in the candidate buffer.
+    Purple,
+}
+
+impl Counts {
+    fn new(cell: Option<Cell>) -> Self {
+        Self {
+            cell,
+            count: 0,
+            edges: Vec::new(),
+            color: Color::Black,
+            buffered: false,
+            dirty: false,
+        }
+    }
+}
+
+impl Heap {
+    /// Frees the memory of a removed object, returning the number of bytes freed.
+    fn free(&mut self, object: Object<Counts>) -> usize {
+        let Some(cell) = object.memory.cell else {
+            return 0;
+        };
+        self.bytes_live -= cell.size();
+        // SAFETY: the object has been removed, so its memory is no longer used
+        unsafe { self.cells.free(object.addr, cell) };
+        cell.size()
+    }
+
+    /// Traces an Initialized object to record its references, adjusting the counts of the objects it refers to.
+    fn retrace(&mut self, index: usize) {
+        let object = self.objects.at(index);
+        let mut edges = Vec::new();
+        if object.needs_trace() {
+            let objects = &self.objects;
+            let mut visit = |handle| edges.extend(objects.resolve(handle));
+            // SAFETY: the object is Initialized
+            unsafe {
+                (object.vtable).trace(
+                    object.addr.cast(),
+                    object.len,
+                    &mut TraceContext::new(&mut visit),
+                )
+            };
+        }
+        // count the new references before dropping the old, so that no count drops to zero in between
+        for &child in &edges {
+            let counts = &mut self.objects.at_mut(child).memory;
+            counts.count += 1;
+            counts.color = Color::Black;
+        }
+        let old = mem::replace(&mut self.objects.at_mut(index).memory.edges, edges);
+        for child in old {
+            self.objects.at_mut(child).memory.count -= 1;
+            self.pending.push(self.objects.handle(child));
+        }
+    }
+
+    /// The slots of the objects referred to by root providers, sorted.
+    fn provider_roots(&mut self) -> Vec<usize> {
+        let mut roots = Vec::new();
+        self.objects
+            .trace_root_providers(|objects, handle| roots.extend(objects.resolve(handle)));
+        roots.sort_unstable();
+        roots
+    }
+
+    /// Traces the objects written to, then finds the pending objects that are garbage, and those that become
+    /// unreferenced in turn, marks them, and subtracts their references from the counts of the objects they refer
+    /// to. Pending objects that are still referenced by other objects are buffered as possible roots of garbage
+    /// cycles.
+    fn take_garbage(&mut self) -> Vec<usize> {
+        for handle in mem::take(&mut self.dirty) {
+            if let Some(object) = self.objects.lookup(handle) {
+                if object.memory.dirty {
+                    self.objects.get_mut(handle).memory.dirty = false;
+                    self.retrace(handle.index());
+                }
+            }
+        }
+        let mut dead = Vec::new();
+        let mut provider_roots = None;
+        while let Some(handle) = self.pending.pop() {
+            let Some(object) = self.objects.lookup(handle) else {
+                continue;
+            };
+            if object.is_held() || object.marked {
+                continue;
+            }
+            if object.memory.count > 0 {
+                self.buffer(handle);
+                continue;
+            }
+            let provider_roots = provider_roots.get_or_insert_with(|| self.provider_roots());
+            if provider_roots.binary_search(&handle.index()).is_ok() {
+                // checked again by the next cycle collection
+                self.buffer(handle);
+                continue;
+            }
+            let object = self.objects.get_mut(handle);
+            object.marked = true;
+            for child in mem::take(&mut object.memory.edges) {
+                self.objects.at_mut(child).memory.count -= 1;
+                self.pending.push(self.objects.handle(child));
+            }
+            dead.push(handle.index());
+        }
+        dead
+    }
+
+    /// Adds an object to the candidate buffer, unless it is already there.
+    fn buffer(&mut self, handle: GenerationalHandle) {
+        let counts = &mut self.objects.get_mut(handle).memory;
+        counts.color = Color::Purple;
+        if !counts.buffered {
+            counts.buffered = true;
+            self.candidates.push(handle);
+        }
+    }
+
+    /// Finds the garbage cycles among the buffered objects, and marks them.
+    fn find_cycles(&mut self) -> Vec<usize> {
+        let provider_roots = self.provider_roots();
+        let mut roots = Vec::new();
+        for handle in mem::take(&mut self.candidates) {
+            let Some(object) = self.objects.lookup(handle) else {
+                continue;
+            };
+            if object.memory.color == Color::Purple {
+                roots.push(handle.index());
+            } else {
+                self.objects.get_mut(handle).memory.buffered = false;
+            }
+        }
+        for &root in &roots {
+            self.mark_gray(root);
+        }
+        for &root in &roots {
+            self.scan(root, &provider_roots);
+        }
+        let mut dead = Vec::new();
+        for &root in &roots {
+            self.objects.at_mut(root).memory.buffered = false;
+            self.collect_white(root, &mut dead);
+        }
+        for root in roots {
+            let object = self.objects.at(root);
+            if !object.marked && object.memory.count == 0 {
+                // kept alive by a root provider, so buffer it again to check it next time
+                self.pending.push(self.objects.handle(root));
+            }
+        }
+        dead
+    }
+
+    /// Subtracts the references of the objects reachable from `root` from the counts of the objects they refer to,
+    /// coloring them gray.
+    fn mark_gray(&mut self, root: usize) {
+        let mut stack = alloc::vec![root];
+        while let Some(index) = stack.pop() {
+            let counts = &mut self.objects.at_mut(index).memory;
+            if counts.color == Color::Gray {
+                continue;
+            }
+            counts.color = Color::Gray;
+            for child in counts.edges.clone() {
+                self.objects.at_mut(child).memory.count -= 1;
+                stack.push(child);
+            }
+        }
+    }
+
+    /// Colors the gray objects reachable from `root` white if nothing outside the subgraph refers to them, and
+    /// black along with everything they refer to otherwise.
+    fn scan(&mut self, root: usize, provider_roots: &[usize]) {
+        let mut stack = alloc::vec![root];
+        while let Some(index) = stack.pop() {
+            let object = self.objects.at_mut(index);
+            if object.memory.color != Color::Gray {
+                continue;
+            }
+            if object.memory.count > 0
+                || object.is_held()
+                || provider_roots.binary_search(&index).is_ok()
+            {
+                self.scan_black(index);
+            } else {
+                object.memory.color = Color::White;
+                stack.extend_from_slice(&object.memory.edges);
+            }
+        }
+    }
+
+    /// Restores the counts of the objects reachable from `root`, coloring them black.
+    fn scan_black(&mut self, root: usize) {
+        let mut stack = alloc::vec![root];
+        self.objects.at_mut(root).memory.color = Color::Black;
+        while let Some(index) = stack.pop() {
+            for child in self.objects.at(index).memory.edges.clone() {
+                let counts = &mut self.objects.at_mut(child).memory;
+                counts.count += 1;
+                if counts.color != Color::Black {
+                    counts.color = Color::Black;
+                    stack.push(child);
+                }
+            }
+        }
+    }
+
+    /// Marks the white objects reachable from `root` as dying and adds them to `dead`. Their references have
+    /// already been subtracted from the counts of the objects they refer to.
+    fn collect_white(&mut self, root: usize, dead: &mut Vec<usize>) {
+        let mut stack = alloc::vec![root];
+        while let Some(index) = stack.pop() {
+            let object = self.objects.at_mut(index);
+            if object.memory.color != Color::White || object.memory.buffered {
+                continue;
+            }
+            object.memory.color = Color::Black;
+            object.marked = true;
+            stack.extend(mem::take(&mut object.memory.edges));
+            dead.push(index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{
+        strategies::tests,
+        testing::{self, Node},
+        GcHeap,
+    };
+
+    #[test]
+    fn conformance() {
+        testing::run_all(RefCounting::new);
+    }
+
+    #[test]
+    fn reentrant_finalizers() {
+        testing::reentrant_finalizers(RefCounting::new());
+    }
+
+    #[test]
+    fn reclamation() {
+        tests::reclamation(RefCounting::new());
+    }
+
+    #[test]
+    fn pinning() {
+        tests::pinning(RefCounting::new());
+    }
+
+    #[test]
+    fn weak_cells() {
+        tests::weak_cells(RefCounting::new());
+    }
+
+    #[test]
+    fn reclaims_chains_without_tracing() {
+        let drops = AtomicUsize::new(0);
+        let heap = GcHeap::new(RefCounting::new());
+        let mut head = heap.alloc(Node::new(&drops, 0, None));
+        for value in 1..10 {
+            head = heap.alloc(Node::new(&drops, value, Some(head.gc())));
+        }
+        drop(head);
+        let stats = heap.collect_with(CollectOptions {
+            kind: CollectionKind::Minor,
+            ..CollectOptions::default()
+        });
+        assert_eq!(stats.objects_reclaimed, 10);
+        assert_eq!(drops.load(Ordering::Relaxed), 10);
+    }
+
+    #[test]
+    fn collects_garbage_cycles() {
+        let drops = AtomicUsize::new(0);
+        let heap = GcHeap::new(RefCounting::new());
+        let first = heap.alloc(Node::new(&drops, 0, None));
+        let second = heap.alloc(Node::new(&drops, 1, Some(first.gc())));
+        first
+            .next
+            .store(&first, Some(second.gc()), Ordering::Relaxed);
+        drop((first, second));
+        heap.collect_with(CollectOptions {
+            kind: CollectionKind::Minor,
+            ..CollectOptions::default()
+        });
+        assert_eq!(drops.load(Ordering::Relaxed), 0);
+        heap.collect();
+        assert_eq!(drops.load(Ordering::Relaxed), 2);
+    }
+
+    #[cfg(all(feature = "mmap", any(unix, windows)))]
+    #[test]
+    fn allocates_chunks_in_region() {
+        let region_options = RefCountingOptions {
+            region_size: 1 << 20,
+            ..RefCountingOptions::default()
+        };
+        let region = || RefCounting::with_options(region_options);
+        testing::run_all(region);
+        tests::reclamation(region());
+        let heap = GcHeap::new(region());
+        let mut roots = Vec::new();
+        let error = loop {
+            match heap.try_alloc([0u64; 16]) {
+                Ok(root) => roots.push(root),
+                Err(error) => break error,
+            }
+        };
+        assert_eq!(error, AllocError::OutOfMemory);
+        assert_eq!(heap.stats().heap_size, 1 << 20);
+        roots.clear();
+        heap.collect();
+        assert_eq!(heap.shrink().bytes_released, 1 << 20);
+        assert!(heap.try_alloc([0u64; 16]).is_ok());
+    }
+}
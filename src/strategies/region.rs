@@ -0,0 +1,445 @@
+use alloc::vec::Vec;
+use core::{fmt, ptr::NonNull};
+
+/// Options for a [`HeapRegion`]. New options may be added in the future, so start from
+/// [`HeapRegionOptions::default`] and set the fields of interest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct HeapRegionOptions {
+    /// Whether to ask the operating system to back the region with transparent huge pages, which makes address
+    /// translation cheaper for large heaps at the cost of committing memory in larger steps. Only has an effect on
+    /// Linux, when transparent huge pages are enabled. Defaults to false.
+    pub huge_pages: bool,
+    /// Whether to keep an inaccessible page on either side of the region, so that running off either end faults
+    /// instead of touching a neighboring mapping. Defaults to true.
+    pub guard_pages: bool,
+}
+
+impl Default for HeapRegionOptions {
+    fn default() -> Self {
+        Self {
+            huge_pages: false,
+            guard_pages: true,
+        }
+    }
+}
+
+/// A contiguous range of address space reserved up front, in which memory is committed lazily. Only available on
+/// Unix and Windows with the `mmap` feature.
+///
+/// Reserving address space costs no memory, so a region can be as large as the largest heap a strategy will ever
+/// need, and a strategy that allocates in it can tell whether an address belongs to the heap with a range check.
+/// Memory is committed from the start of the region as the heap grows, and decommitted from the end when it
+/// shrinks, which returns the pages to the operating system. Uncommitted memory is inaccessible.
+///
+/// The strategies that allocate objects in chunks, [`MarkSweep`](super::MarkSweep),
+/// [`Incremental`](super::Incremental), [`RefCounting`](super::RefCounting) and the old space of
+/// [`Generational`](super::Generational), can carve their chunks out of a region (see
+/// [`MarkSweepOptions::region_size`](super::MarkSweepOptions::region_size)). The other strategies in this module
+/// only rely on the global allocator, which is available on every target.
+pub struct HeapRegion {
+    base: NonNull<u8>,
+    size: usize,
+    committed: usize,
+    guard: usize,
+}
+
+// SAFETY: the region owns its memory, and only changes it through `&mut self`
+unsafe impl Send for HeapRegion {}
+
+// SAFETY: as above
+unsafe impl Sync for HeapRegion {}
+
+impl HeapRegion {
+    /// The size of a page of memory. Regions are reserved, committed, and decommitted in whole pages.
+    pub fn page_size() -> usize {
+        sys::page_size()
+    }
+
+    /// Reserves a region of at least `size` bytes, rounded up to a whole number of pages, with nothing committed.
+    /// Returns None if `size` is zero or the address space cannot be reserved.
+    pub fn reserve(size: usize, options: HeapRegionOptions) -> Option<Self> {
+        let page_size = Self::page_size();
+        if size == 0 {
+            return None;
+        }
+        let size = size.checked_next_multiple_of(page_size)?;
+        let guard = if options.guard_pages { page_size } else { 0 };
+        let total = size.checked_add(2 * guard)?;
+        let addr = sys::reserve(total)?;
+        // SAFETY: the guard pages are within the reservation
+        let base = unsafe { addr.add(guard) };
+        if options.huge_pages {
+            // SAFETY: the range is within the reservation
+            unsafe { sys::advise_huge_pages(base, size) };
+        }
+        Some(Self {
+            base,
+            size,
+            committed: 0,
+            guard,
+        })
+    }
+
+    /// The start of the region.
+    pub fn base(&self) -> NonNull<u8> {
+        self.base
+    }
+
+    /// The number of bytes reserved, not counting guard pages.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The number of bytes committed from the start of the region, which may be read and written.
+    pub fn committed(&self) -> usize {
+        self.committed
+    }
+
+    /// Whether the given address is within the region, whether or not it is committed.
+    pub fn contains(&self, addr: *const u8) -> bool {
+        let offset = (addr as usize).wrapping_sub(self.base.as_ptr() as usize);
+        offset < self.size
+    }
+
+    /// Commits memory so that at least the first `size` bytes of the region are committed, rounded up to a whole
+    /// number of pages. Newly committed memory is zeroed. Returns whether the memory is committed, which it is not
+    /// if `size` is larger than the region or the operating system refuses.
+    pub fn commit(&mut self, size: usize) -> bool {
+        if size > self.size {
+            return false;
+        }
+        let size = size.next_multiple_of(Self::page_size()).min(self.size);
+        if size <= self.committed {
+            return true;
+        }
+        // SAFETY: the range is within the reservation, and was not accessible
+        if !unsafe { sys::commit(self.base.add(self.committed), size - self.committed) } {
+            return false;
+        }
+        self.committed = size;
+        true
+    }
+
+    /// Decommits memory so that only the first `size` bytes of the region stay committed, rounded up to a whole
+    /// number of pages, and returns the number of bytes decommitted. Decommitted memory is returned to the
+    /// operating system and becomes inaccessible.
+    ///
+    /// # Safety
+    /// The memory being decommitted must no longer be used.
+    pub unsafe fn decommit(&mut self, size: usize) -> usize {
+        if size >= self.committed {
+            return 0;
+        }
+        let size = size.next_multiple_of(Self::page_size());
+        let released = self.committed - size;
+        if released == 0 {
+            return 0;
+        }
+        // SAFETY: the range is within the reservation and committed, and the caller no longer uses it
+        if !unsafe { sys::decommit(self.base.add(size), released) } {
+            return 0;
+        }
+        self.committed = size;
+        released
+    }
+}
+
+impl Drop for HeapRegion {
+    fn drop(&mut self) {
+        // SAFETY: this is the whole reservation, which nothing uses any more
+        unsafe { sys::release(self.base.sub(self.guard), self.size + 2 * self.guard) };
+    }
+}
+
+impl fmt::Debug for HeapRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HeapRegion")
+            .field("base", &self.base)
+            .field("size", &self.size)
+            .field("committed", &self.committed)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Chunks of a fixed size carved out of a [`HeapRegion`] in address order, for the allocators of the strategies in
+/// this module. The chunks carved out so far are committed. Free chunks are handed out again before new ones are
+/// carved out, and only decommitted once every chunk after them is free too.
+pub(super) struct RegionChunks {
+    region: HeapRegion,
+    /// The distance between the starts of consecutive chunks, which is a whole number of pages.
+    stride: usize,
+    /// The number of chunks carved out so far.
+    top: usize,
+    /// The indices of the free chunks below `top`.
+    free: Vec<usize>,
+}
+
+impl RegionChunks {
+    /// Carves chunks of `chunk_size` bytes out of `region`. Each chunk takes up a whole number of pages.
+    pub(super) fn new(region: HeapRegion, chunk_size: usize) -> Self {
+        Self {
+            region,
+            stride: chunk_size.next_multiple_of(HeapRegion::page_size()),
+            top: 0,
+            free: Vec::new(),
+        }
+    }
+
+    /// Returns a free chunk, aligned to a page, or None if the region is full or cannot be committed.
+    pub(super) fn alloc(&mut self) -> Option<NonNull<u8>> {
+        // the lowest free chunk, so that the chunks at the end of the region become free first
+        let index = match (0..self.free.len()).min_by_key(|&i| self.free[i]) {
+            Some(i) => self.free.swap_remove(i),
+            None => {
+                if !self.region.commit((self.top + 1).checked_mul(self.stride)?) {
+                    return None;
+                }
+                self.top += 1;
+                self.top - 1
+            }
+        };
+        // SAFETY: the chunk is within the committed part of the region
+        Some(unsafe { self.region.base().add(index * self.stride) })
+    }
+
+    /// Frees a chunk, decommitting the free chunks at the end of the region.
+    ///
+    /// # Safety
+    /// The chunk must have been returned by [`RegionChunks::alloc`], and must not be used afterwards.
+    pub(super) unsafe fn free(&mut self, chunk: NonNull<u8>) {
+        let index = (chunk.as_ptr() as usize - self.region.base().as_ptr() as usize) / self.stride;
+        self.free.push(index);
+        while let Some(i) = self.free.iter().position(|&index| index + 1 == self.top) {
+            self.free.swap_remove(i);
+            self.top -= 1;
+        }
+        // SAFETY: the chunks at or above `top` are free, and the caller no longer uses them
+        unsafe { self.region.decommit(self.top * self.stride) };
+    }
+}
+
+/// Reserving and committing memory with `mmap`.
+#[cfg(unix)]
+mod sys {
+    use core::ptr::{self, NonNull};
+
+    /// The flags for address space that is reserved but not committed. Inaccessible private mappings are not
+    /// charged against the system's commit limit until they are made writable, so only committed memory is.
+    const MAP_RESERVE: libc::c_int = libc::MAP_PRIVATE | libc::MAP_ANON;
+
+    pub(super) fn page_size() -> usize {
+        // SAFETY: sysconf has no preconditions
+        let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        usize::try_from(size).unwrap_or(4096)
+    }
+
+    /// Reserves `size` bytes of inaccessible address space.
+    pub(super) fn reserve(size: usize) -> Option<NonNull<u8>> {
+        // SAFETY: an anonymous mapping at an address of the kernel's choosing does not affect existing memory
+        let addr =
+            unsafe { libc::mmap(ptr::null_mut(), size, libc::PROT_NONE, MAP_RESERVE, -1, 0) };
+        if addr == libc::MAP_FAILED {
+            return None;
+        }
+        NonNull::new(addr.cast())
+    }
+
+    /// Asks for the range to be backed by transparent huge pages. This is a hint, so failure is harmless.
+    ///
+    /// # Safety
+    /// The range must be reserved.
+    pub(super) unsafe fn advise_huge_pages(addr: NonNull<u8>, size: usize) {
+        #[cfg(target_os = "linux")]
+        // SAFETY: caller
+        unsafe {
+            libc::madvise(addr.as_ptr().cast(), size, libc::MADV_HUGEPAGE)
+        };
+        #[cfg(not(target_os = "linux"))]
+        let _ = (addr, size);
+    }
+
+    /// Makes the range readable and writable, returning whether that succeeded.
+    ///
+    /// # Safety
+    /// The range must be reserved.
+    pub(super) unsafe fn commit(addr: NonNull<u8>, size: usize) -> bool {
+        // SAFETY: caller
+        unsafe {
+            libc::mprotect(
+                addr.as_ptr().cast(),
+                size,
+                libc::PROT_READ | libc::PROT_WRITE,
+            ) == 0
+        }
+    }
+
+    /// Returns the memory of the range to the operating system and makes it inaccessible, keeping it reserved.
+    /// Returns whether that succeeded.
+    ///
+    /// # Safety
+    /// The range must be reserved, and must not be used afterwards.
+    pub(super) unsafe fn decommit(addr: NonNull<u8>, size: usize) -> bool {
+        // mapping inaccessible pages over the range discards its contents, and keeps it reserved
+        // SAFETY: caller
+        let addr = unsafe {
+            libc::mmap(
+                addr.as_ptr().cast(),
+                size,
+                libc::PROT_NONE,
+                MAP_RESERVE | libc::MAP_FIXED,
+                -1,
+                0,
+            )
+        };
+        addr != libc::MAP_FAILED
+    }
+
+    /// Releases a reservation.
+    ///
+    /// # Safety
+    /// The range must be a whole reservation returned by [`reserve`], which must not be used afterwards.
+    pub(super) unsafe fn release(addr: NonNull<u8>, size: usize) {
+        // SAFETY: caller
+        unsafe { libc::munmap(addr.as_ptr().cast(), size) };
+    }
+}
+
+/// Reserving and committing memory with `VirtualAlloc`.
+#[cfg(windows)]
+mod sys {
+    use core::{
+        ffi::c_void,
+        mem::MaybeUninit,
+        ptr::{self, NonNull},
+    };
+
+    const MEM_COMMIT: u32 = 0x1000;
+    const MEM_RESERVE: u32 = 0x2000;
+    const MEM_DECOMMIT: u32 = 0x4000;
+    const MEM_RELEASE: u32 = 0x8000;
+    const PAGE_NOACCESS: u32 = 0x01;
+    const PAGE_READWRITE: u32 = 0x04;
+
+    /// `SYSTEM_INFO`.
+    #[repr(C)]
+    struct SystemInfo {
+        oem_id: u32,
+        page_size: u32,
+        minimum_application_address: *mut c_void,
+        maximum_application_address: *mut c_void,
+        active_processor_mask: usize,
+        number_of_processors: u32,
+        processor_type: u32,
+        allocation_granularity: u32,
+        processor_level: u16,
+        processor_revision: u16,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetSystemInfo(info: *mut SystemInfo);
+        fn VirtualAlloc(
+            addr: *mut c_void,
+            size: usize,
+            allocation_type: u32,
+            protect: u32,
+        ) -> *mut c_void;
+        fn VirtualFree(addr: *mut c_void, size: usize, free_type: u32) -> i32;
+    }
+
+    pub(super) fn page_size() -> usize {
+        let mut info = MaybeUninit::<SystemInfo>::uninit();
+        // SAFETY: GetSystemInfo fills in the whole structure
+        let info = unsafe {
+            GetSystemInfo(info.as_mut_ptr());
+            info.assume_init()
+        };
+        info.page_size as usize
+    }
+
+    /// Reserves `size` bytes of inaccessible address space.
+    pub(super) fn reserve(size: usize) -> Option<NonNull<u8>> {
+        // SAFETY: a reservation at an address of the system's choosing does not affect existing memory
+        let addr = unsafe { VirtualAlloc(ptr::null_mut(), size, MEM_RESERVE, PAGE_NOACCESS) };
+        NonNull::new(addr.cast())
+    }
+
+    /// Huge pages cannot be committed lazily on Windows, so this does nothing.
+    ///
+    /// # Safety
+    /// The range must be reserved.
+    pub(super) unsafe fn advise_huge_pages(addr: NonNull<u8>, size: usize) {
+        let _ = (addr, size);
+    }
+
+    /// Makes the range readable and writable, returning whether that succeeded.
+    ///
+    /// # Safety
+    /// The range must be reserved.
+    pub(super) unsafe fn commit(addr: NonNull<u8>, size: usize) -> bool {
+        // SAFETY: caller
+        !unsafe { VirtualAlloc(addr.as_ptr().cast(), size, MEM_COMMIT, PAGE_READWRITE) }.is_null()
+    }
+
+    /// Returns the memory of the range to the operating system and makes it inaccessible, keeping it reserved.
+    /// Returns whether that succeeded.
+    ///
+    /// # Safety
+    /// The range must be reserved, and must not be used afterwards.
+    pub(super) unsafe fn decommit(addr: NonNull<u8>, size: usize) -> bool {
+        // SAFETY: caller
+        unsafe { VirtualFree(addr.as_ptr().cast(), size, MEM_DECOMMIT) != 0 }
+    }
+
+    /// Releases a reservation.
+    ///
+    /// # Safety
+    /// The range must be a whole reservation returned by [`reserve`], which must not be used afterwards.
+    pub(super) unsafe fn release(addr: NonNull<u8>, size: usize) {
+        let _ = size;
+        // SAFETY: caller
+        unsafe { VirtualFree(addr.as_ptr().cast(), 0, MEM_RELEASE) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commits_and_decommits_pages() {
+        let page_size = HeapRegion::page_size();
+        let mut region = HeapRegion::reserve(4 * page_size, HeapRegionOptions::default()).unwrap();
+        assert!(region.contains(region.base().as_ptr()));
+        assert!(!region.contains(region.base().as_ptr().wrapping_add(4 * page_size)));
+        assert!(region.commit(page_size + 1));
+        assert_eq!(region.committed(), 2 * page_size);
+        // SAFETY: the first two pages are committed
+        unsafe { region.base().add(2 * page_size - 1).write(1) };
+        assert!(!region.commit(5 * page_size));
+        // SAFETY: nothing uses the memory
+        assert_eq!(unsafe { region.decommit(0) }, 2 * page_size);
+        assert!(region.commit(page_size));
+        // SAFETY: the first page is committed again, and zeroed
+        assert_eq!(unsafe { region.base().read() }, 0);
+    }
+
+    #[test]
+    fn reuses_free_chunks() {
+        let page_size = HeapRegion::page_size();
+        let region = HeapRegion::reserve(3 * page_size, HeapRegionOptions::default()).unwrap();
+        let mut chunks = RegionChunks::new(region, page_size);
+        let [a, b, c] = [(); 3].map(|_| chunks.alloc().unwrap());
+        assert!(chunks.alloc().is_none());
+        // SAFETY: the chunks are not used
+        unsafe {
+            chunks.free(b);
+            assert_eq!(chunks.region.committed(), 3 * page_size);
+            chunks.free(c);
+            assert_eq!(chunks.region.committed(), page_size);
+        }
+        assert_eq!(chunks.alloc(), Some(b));
+        assert_ne!(a, b);
+    }
+}
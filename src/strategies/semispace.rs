@@ -0,0 +1,661 @@
+use alloc::{
+    alloc::{alloc, dealloc, handle_alloc_error},
+    vec::Vec,
+};
+use core::{alloc::Layout, cell::RefCell, fmt, mem, ptr, ptr::NonNull, time::Duration};
+
+use super::table::{self, Object, ObjectTable, State};
+use crate::heap::{
+    AllocError, CollectOptions, CollectionStats, Collector, FreshAllocation, GcAllocator, GcVtable,
+    GenerationalHandle, HeapStats, MemoryPressure, RelocationListener, RootManager, ShrinkStats,
+    StepBudget, StepResult,
+};
+use crate::trace::RootProvider;
+
+/// The alignment of every semispace.
+const SPACE_ALIGN: usize = 16;
+
+/// Options for a [`Semispace`] heap. New options may be added in the future, so start from
+/// [`SemispaceOptions::default`] and set the fields of interest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SemispaceOptions {
+    /// The size of each semispace when the heap is created. Defaults to 256 KiB.
+    pub initial_space_size: usize,
+    /// The largest size each semispace may grow to. Once the semispaces have reached this size, an allocation that
+    /// does not fit after a collection fails with [`AllocError::OutOfMemory`]. Defaults to no limit.
+    pub max_space_size: usize,
+    /// The size from which objects are allocated on their own instead of in a semispace. Large objects are never
+    /// copied, as copying them would take longer than it saves. Defaults to 16 KiB.
+    pub large_object_size: usize,
+}
+
+impl Default for SemispaceOptions {
+    fn default() -> Self {
+        Self {
+            initial_space_size: 256 << 10,
+            max_space_size: usize::MAX,
+            large_object_size: 16 << 10,
+        }
+    }
+}
+
+/// A Cheney-style copying strategy. See the [module documentation](super).
+///
+/// Objects are allocated by bumping a pointer through to-space, which is about as fast as allocation gets. When
+/// to-space is full, a collection evacuates the objects reachable from roots into a fresh to-space in breadth-first
+/// order, and the old space is reused as the next to-space. Garbage is never visited, so a collection takes time in
+/// proportion to the live objects only, at the cost of reserving twice the memory they need. The semispaces double
+/// in size whenever more than half of to-space survives a collection.
+///
+/// Handles are [`GenerationalHandle`]s into a table of objects, which records the current address of each object,
+/// so handles stay valid as their objects move. Registered [`RelocationListener`]s are told about every move.
+/// Pinned objects are not moved: a semispace that still holds pinned objects after a collection is kept until they
+/// are unpinned and die, and a fresh one is used in its place. Large objects are allocated on their own and never
+/// move either.
+///
+/// Like [`MarkSweep`](super::MarkSweep), collections run on the allocating thread, so the strategy is neither
+/// `Send` nor `Sync`. Weak cells are supported, but ephemerons are not.
+pub struct Semispace {
+    heap: RefCell<Heap>,
+    options: SemispaceOptions,
+}
+
+impl Semispace {
+    /// Creates an empty heap with the default options.
+    pub fn new() -> Self {
+        Self::with_options(SemispaceOptions::default())
+    }
+
+    /// Creates an empty heap with the given options, reserving the first to-space.
+    ///
+    /// # Panics
+    /// Panics if `options.initial_space_size` is zero or greater than `options.max_space_size`.
+    pub fn with_options(options: SemispaceOptions) -> Self {
+        assert!(
+            0 < options.initial_space_size && options.initial_space_size <= options.max_space_size,
+            "initial semispace size {} is not between 1 and {}",
+            options.initial_space_size,
+            options.max_space_size
+        );
+        let layout = Layout::from_size_align(options.initial_space_size, SPACE_ALIGN).unwrap();
+        // SAFETY: the layout has a non-zero size
+        let base =
+            NonNull::new(unsafe { alloc(layout) }).unwrap_or_else(|| handle_alloc_error(layout));
+        Self {
+            heap: RefCell::new(Heap {
+                objects: ObjectTable::new(),
+                spaces: Spaces {
+                    to_space: Region {
+                        id: 0,
+                        base,
+                        size: layout.size(),
+                    },
+                    top: 0,
+                    space_size: layout.size(),
+                    spare: Region::EMPTY,
+                    retained: Vec::new(),
+                    next_id: 1,
+                    listeners: Vec::new(),
+                    heap_size: layout.size(),
+                    bytes_live: 0,
+                    collection_count: 0,
+                    total_pause: Duration::ZERO,
+                },
+            }),
+            options,
+        }
+    }
+
+    /// The options this heap was created with.
+    pub fn options(&self) -> &SemispaceOptions {
+        &self.options
+    }
+
+    /// Evacuates the live objects into a fresh to-space, then finalizes and reclaims the rest. Returns None without
+    /// collecting if the fresh to-space cannot be reserved, or finalizers are running.
+    fn collect_full(&self) -> Option<CollectionStats> {
+        if self.heap.borrow().objects.is_finalizing() {
+            return None;
+        }
+        #[cfg(feature = "std")]
+        let start = std::time::Instant::now();
+        let sweep = {
+            let mut heap = self.heap.borrow_mut();
+            let Heap { objects, spaces } = &mut *heap;
+            spaces.flip()?;
+            objects.mark(|object| spaces.evacuate(object));
+            objects.sweep(|object| spaces.free(object))
+        };
+        // finalized objects are still in from-space, which is only released below
+        table::run_finalizers(&self.heap, |heap| &heap.objects, &sweep.finalize);
+        let mut stats = sweep.stats;
+        let mut heap = self.heap.borrow_mut();
+        let Heap { objects, spaces } = &mut *heap;
+        for index in sweep.finalize {
+            stats.bytes_reclaimed += spaces.free(objects.remove(index));
+        }
+        spaces.release_old_spaces(objects);
+        if spaces.top > spaces.to_space.size / 2 {
+            spaces.space_size = spaces
+                .space_size
+                .saturating_mul(2)
+                .min(self.options.max_space_size);
+        }
+        stats.bytes_live = spaces.bytes_live;
+        spaces.collection_count += 1;
+        #[cfg(feature = "std")]
+        {
+            spaces.total_pause += start.elapsed();
+        }
+        Some(stats)
+    }
+
+    /// Reserves memory for an object with the given layout, collecting if to-space is full, and growing the
+    /// semispaces if a collection does not free enough.
+    fn reserve(&self, layout: Layout) -> Result<(NonNull<u8>, Place), AllocError> {
+        if layout.size() >= self.options.large_object_size.max(1) {
+            return self.heap.borrow_mut().spaces.reserve_large(layout);
+        }
+        if let Some(reserved) = self.heap.borrow_mut().spaces.bump(layout) {
+            return Ok(reserved);
+        }
+        self.collect_full();
+        if let Some(reserved) = self.heap.borrow_mut().spaces.bump(layout) {
+            return Ok(reserved);
+        }
+        {
+            let spaces = &mut self.heap.borrow_mut().spaces;
+            // make room for everything that survived and the object, with as much again to spare
+            let needed = (spaces
+                .top
+                .saturating_add(layout.size())
+                .saturating_add(layout.align()))
+            .saturating_mul(2);
+            let size = (needed.max(spaces.to_space.size.saturating_mul(2)))
+                .min(self.options.max_space_size);
+            if size <= spaces.to_space.size {
+                return Err(AllocError::OutOfMemory);
+            }
+            spaces.space_size = size;
+        }
+        self.collect_full().ok_or(AllocError::OutOfMemory)?;
+        self.heap
+            .borrow_mut()
+            .spaces
+            .bump(layout)
+            .ok_or(AllocError::OutOfMemory)
+    }
+}
+
+impl Default for Semispace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Semispace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let heap = self.heap.borrow();
+        f.debug_struct("Semispace")
+            .field("space_size", &heap.spaces.to_space.size)
+            .field("bytes_used", &heap.spaces.top)
+            .field("object_count", &heap.objects.object_count())
+            .field("options", &self.options)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for Semispace {
+    fn drop(&mut self) {
+        let Heap { objects, spaces } = self.heap.get_mut();
+        objects.finalize_all();
+        for index in objects.indices().collect::<Vec<_>>() {
+            spaces.free(objects.remove(index));
+        }
+        spaces.to_space.release();
+        spaces.spare.release();
+        for (region, _) in &spaces.retained {
+            region.release();
+        }
+    }
+}
+
+// SAFETY: objects are kept alive while rooted, pinned, frozen, or reachable from one that is. Only unpinned objects
+// are moved, and their handles are updated in the object table. Unreachable objects are finalized once after
+// evacuation, before the space they are in is released, and Uninitialized ones are never traced or finalized.
+unsafe impl GcAllocator for Semispace {
+    type Handle = GenerationalHandle;
+    type Error = AllocError;
+
+    fn allocate(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+    ) -> Result<FreshAllocation<GenerationalHandle>, AllocError> {
+        let layout = vtable.layout(len).ok_or(AllocError::InvalidLayout)?;
+        let (addr, place) = self.reserve(layout)?;
+        let mut heap = self.heap.borrow_mut();
+        heap.spaces.bytes_live += place.size();
+        let handle = heap.objects.insert(Object::fresh(addr, vtable, len, place));
+        Ok(FreshAllocation {
+            handle,
+            ptr: addr.as_ptr().cast(),
+        })
+    }
+
+    unsafe fn set_initialized(&self, obj: GenerationalHandle) {
+        let mut heap = self.heap.borrow_mut();
+        let object = heap.objects.get_mut(obj);
+        object.pins -= 1;
+        object.state = State::Initialized;
+    }
+
+    unsafe fn set_finalized(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).state = State::Finalized;
+    }
+
+    unsafe fn reclaim(&self, obj: GenerationalHandle) {
+        let Heap { objects, spaces } = &mut *self.heap.borrow_mut();
+        spaces.free(objects.reclaim(obj));
+    }
+
+    fn pin(&self, obj: GenerationalHandle) -> *const () {
+        let mut heap = self.heap.borrow_mut();
+        let object = heap.objects.get_mut(obj);
+        object.pins += 1;
+        object.addr.as_ptr().cast()
+    }
+
+    fn unpin(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).pins -= 1;
+    }
+
+    fn pin_long_lived(&self, obj: GenerationalHandle) -> *const () {
+        self.heap.borrow_mut().objects.add_long_lived_pin();
+        self.pin(obj)
+    }
+
+    fn unpin_long_lived(&self, obj: GenerationalHandle) {
+        self.unpin(obj);
+        self.heap.borrow_mut().objects.remove_long_lived_pin();
+    }
+
+    fn resolve(&self, obj: GenerationalHandle) -> *const () {
+        self.heap.borrow().objects.get(obj).addr.as_ptr().cast()
+    }
+
+    fn shrink(&self) -> ShrinkStats {
+        let spaces = &mut self.heap.borrow_mut().spaces;
+        let bytes_released = spaces.spare.size;
+        spaces.heap_size -= bytes_released;
+        mem::replace(&mut spaces.spare, Region::EMPTY).release();
+        ShrinkStats {
+            bytes_released,
+            heap_size: spaces.heap_size,
+        }
+    }
+
+    fn contains(&self, obj: GenerationalHandle) -> bool {
+        self.heap.borrow().objects.lookup(obj).is_some()
+    }
+
+    fn vtable(&self, obj: GenerationalHandle) -> &'static GcVtable {
+        self.heap.borrow().objects.get(obj).vtable
+    }
+}
+
+// SAFETY: roots and root providers are traced by every collection.
+unsafe impl RootManager<GenerationalHandle> for Semispace {
+    fn root(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).roots += 1;
+    }
+
+    fn unroot(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).roots -= 1;
+    }
+
+    fn freeze(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).frozen = true;
+    }
+
+    unsafe fn register_root_provider(&self, provider: &dyn RootProvider) {
+        // SAFETY: caller
+        unsafe {
+            self.heap
+                .borrow_mut()
+                .objects
+                .register_root_provider(provider)
+        };
+    }
+
+    unsafe fn unregister_root_provider(&self, provider: &dyn RootProvider) {
+        self.heap
+            .borrow_mut()
+            .objects
+            .unregister_root_provider(provider);
+    }
+}
+
+// SAFETY: weak cells are cleared during the collection that finds their targets unreachable, before the targets
+// are finalized. Every move is reported to the registered listeners.
+unsafe impl Collector<GenerationalHandle> for Semispace {
+    fn allocate_weak(&self, target: GenerationalHandle) -> Option<GenerationalHandle> {
+        let mut heap = self.heap.borrow_mut();
+        heap.objects.get(target);
+        Some(heap.objects.insert(Object::weak(target, Place::None)))
+    }
+
+    unsafe fn upgrade_weak(&self, cell: GenerationalHandle) -> Option<GenerationalHandle> {
+        let mut heap = self.heap.borrow_mut();
+        let target = heap
+            .objects
+            .get(cell)
+            .weak
+            .expect("object is not a weak cell")?;
+        heap.objects.get_mut(target).roots += 1;
+        Some(target)
+    }
+
+    fn collect(&self, options: CollectOptions) -> CollectionStats {
+        let _ = options;
+        self.collect_full().unwrap_or_default()
+    }
+
+    fn collect_step(&self, budget: StepBudget) -> StepResult {
+        let _ = budget;
+        StepResult::Complete(self.collect_full().unwrap_or_default())
+    }
+
+    fn memory_pressure(&self, level: MemoryPressure) {
+        if level == MemoryPressure::Critical {
+            self.collect_full();
+        }
+        self.shrink();
+    }
+
+    unsafe fn register_relocation_listener(&self, listener: &dyn RelocationListener) {
+        // SAFETY: the caller keeps the listener valid until it is unregistered
+        let listener = unsafe {
+            mem::transmute::<*const dyn RelocationListener, *const (dyn RelocationListener + 'static)>(
+                listener,
+            )
+        };
+        self.heap.borrow_mut().spaces.listeners.push(listener);
+    }
+
+    unsafe fn unregister_relocation_listener(&self, listener: &dyn RelocationListener) {
+        let listeners = &mut self.heap.borrow_mut().spaces.listeners;
+        let position =
+            (listeners.iter()).position(|&registered| ptr::addr_eq(registered, listener));
+        listeners.swap_remove(position.expect("relocation listener is not registered"));
+    }
+
+    fn stats(&self) -> HeapStats {
+        let heap = self.heap.borrow();
+        HeapStats {
+            heap_size: heap.spaces.heap_size,
+            bytes_live: heap.spaces.bytes_live,
+            object_count: heap.objects.object_count(),
+            long_lived_pins: heap.objects.long_lived_pins(),
+            collection_count: heap.spaces.collection_count,
+            total_pause: heap.spaces.total_pause,
+        }
+    }
+}
+
+/// The state of a [`Semispace`] heap.
+struct Heap {
+    objects: ObjectTable<Place>,
+    spaces: Spaces,
+}
+
+/// The memory of a [`Semispace`] heap, and statistics about its use.
+struct Spaces {
+    to_space: Region,
+    /// The offset in to-space of the next allocation.
+    top: usize,
+    /// The size of the next to-space.
+    space_size: usize,
+    /// The last from-space, kept to be reused as the next to-space.
+    spare: Region,
+    /// Old semispaces that still hold pinned objects.
+    retained: Vec<(Region, usize)>,
+    next_id: usize,
+    listeners: Vec<*const (dyn RelocationListener + 'static)>,
+    heap_size: usize,
+    bytes_live: usize,
+    collection_count: u64,
+    total_pause: Duration,
+}
+
+/// Where an object is.
+#[derive(Clone, Copy)]
+enum Place {
+    /// In the semispace with the given ID, taking up the given number of bytes.
+    Space(usize, usize),
+    /// In a separate allocation with the given layout.
+    Large(Layout),
+    /// Nowhere, as the object has no contents.
+    None,
+}
+
+impl Place {
+    fn size(self) -> usize {
+        match self {
+            Self::Space(_, size) => size,
+            Self::Large(layout) => layout.size(),
+            Self::None => 0,
+        }
+    }
+}
+
+/// A semispace. The empty region is a placeholder for a missing spare, and owns no memory.
+struct Region {
+    id: usize,
+    base: NonNull<u8>,
+    size: usize,
+}
+
+impl Region {
+    const EMPTY: Self = Self {
+        id: 0,
+        base: NonNull::dangling(),
+        size: 0,
+    };
+
+    fn layout(&self) -> Layout {
+        Layout::from_size_align(self.size, SPACE_ALIGN).unwrap()
+    }
+
+    fn release(&self) {
+        if self.size > 0 {
+            // SAFETY: the region was allocated with this layout
+            unsafe { dealloc(self.base.as_ptr(), self.layout()) };
+        }
+    }
+}
+
+impl Spaces {
+    /// Bump-allocates memory with the given layout in to-space, or returns None if it does not fit.
+    fn bump(&mut self, layout: Layout) -> Option<(NonNull<u8>, Place)> {
+        // zero-sized objects still need an address of their own
+        let size = layout.size().max(1);
+        let base = self.to_space.base.as_ptr() as usize;
+        let start = (base + self.top).checked_next_multiple_of(layout.align())? - base;
+        let end = start.checked_add(size)?;
+        if end > self.to_space.size {
+            return None;
+        }
+        self.top = end;
+        // SAFETY: the object is within to-space
+        let addr = unsafe { self.to_space.base.add(start) };
+        Some((addr, Place::Space(self.to_space.id, size)))
+    }
+
+    fn reserve_large(&mut self, layout: Layout) -> Result<(NonNull<u8>, Place), AllocError> {
+        // SAFETY: large objects have a non-zero size
+        let addr = NonNull::new(unsafe { alloc(layout) }).ok_or(AllocError::OutOfMemory)?;
+        self.heap_size += layout.size();
+        Ok((addr, Place::Large(layout)))
+    }
+
+    /// Frees the memory of a removed object, returning the number of bytes freed. Memory in a semispace is only
+    /// freed once the whole space is released.
+    fn free(&mut self, object: Object<Place>) -> usize {
+        let place = object.memory;
+        self.bytes_live -= place.size();
+        if let Place::Large(layout) = place {
+            // SAFETY: the memory was allocated in `Spaces::reserve_large` with this layout
+            unsafe { dealloc(object.addr.as_ptr(), layout) };
+            self.heap_size -= layout.size();
+        }
+        place.size()
+    }
+
+    /// Replaces to-space with an empty one of the next size, keeping the old one as from-space until
+    /// [`Spaces::release_old_spaces`].
+    fn flip(&mut self) -> Option<()> {
+        let mut to_space = mem::replace(&mut self.spare, Region::EMPTY);
+        if to_space.size != self.space_size {
+            self.heap_size -= to_space.size;
+            to_space.release();
+            let layout = Layout::from_size_align(self.space_size, SPACE_ALIGN).ok()?;
+            // SAFETY: semispaces have a non-zero size
+            let base = NonNull::new(unsafe { alloc(layout) })?;
+            self.heap_size += layout.size();
+            to_space = Region {
+                id: 0,
+                base,
+                size: layout.size(),
+            };
+        }
+        to_space.id = self.next_id;
+        self.next_id += 1;
+        let from_space = mem::replace(&mut self.to_space, to_space);
+        self.top = 0;
+        self.retained.push((from_space, 0));
+        Some(())
+    }
+
+    /// Copies an object that has just been marked into to-space, unless it is pinned or does not live in a
+    /// semispace. Objects that do not fit are left where they are.
+    fn evacuate(&mut self, object: &mut Object<Place>) {
+        let Place::Space(_, size) = object.memory else {
+            return;
+        };
+        if object.pins > 0 {
+            return;
+        }
+        let layout =
+            Layout::from_size_align(size, object.vtable.layout(object.len).unwrap().align())
+                .unwrap();
+        let Some((addr, place)) = self.bump(layout) else {
+            return;
+        };
+        // SAFETY: the object and its copy are in different semispaces
+        unsafe { ptr::copy_nonoverlapping(object.addr.as_ptr(), addr.as_ptr(), size) };
+        for &listener in &self.listeners {
+            // SAFETY: listeners stay valid until they are unregistered
+            unsafe { &*listener }.relocated(object.addr.as_ptr().cast(), addr.as_ptr().cast());
+        }
+        object.addr = addr;
+        object.memory = place;
+    }
+
+    /// Releases the old semispaces that no longer hold any objects, keeping the last from-space as the spare if it
+    /// can be reused.
+    fn release_old_spaces(&mut self, objects: &ObjectTable<Place>) {
+        for (_, count) in &mut self.retained {
+            *count = 0;
+        }
+        for index in objects.indices() {
+            if let Place::Space(id, _) = objects.at(index).memory {
+                if let Some((_, count)) =
+                    self.retained.iter_mut().find(|(region, _)| region.id == id)
+                {
+                    *count += 1;
+                }
+            }
+        }
+        let Self {
+            retained,
+            spare,
+            heap_size,
+            ..
+        } = self;
+        retained.retain(|(region, count)| {
+            if *count > 0 {
+                return true;
+            }
+            if spare.size == 0 {
+                *spare = Region { ..*region };
+            } else {
+                *heap_size -= region.size;
+                region.release();
+            }
+            false
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{strategies::tests, testing, GcHeap};
+
+    #[test]
+    fn conformance() {
+        testing::run_all(Semispace::new);
+    }
+
+    #[test]
+    fn reentrant_finalizers() {
+        testing::reentrant_finalizers(Semispace::new());
+    }
+
+    #[test]
+    fn reclamation() {
+        tests::reclamation(Semispace::new());
+    }
+
+    #[test]
+    fn pinning() {
+        tests::pinning(Semispace::new());
+    }
+
+    #[test]
+    fn weak_cells() {
+        tests::weak_cells(Semispace::new());
+    }
+
+    #[test]
+    fn relocation() {
+        tests::relocation(Semispace::new(), CollectOptions::default());
+    }
+
+    #[test]
+    fn moves_unpinned_objects() {
+        let heap = GcHeap::new(Semispace::new());
+        let root = heap.alloc(7u64);
+        let addr = root.as_ptr();
+        heap.collect();
+        assert_ne!(root.as_ptr(), addr);
+        assert_eq!(*root, 7);
+    }
+
+    #[test]
+    fn grows_when_survivors_fill_the_space() {
+        let options = SemispaceOptions {
+            initial_space_size: 4 << 10,
+            ..SemispaceOptions::default()
+        };
+        let heap = GcHeap::new(Semispace::with_options(options));
+        let roots: Vec<_> = (0..1000u64).map(|i| heap.alloc([i; 4])).collect();
+        heap.collect();
+        for (i, root) in (0..).zip(&roots) {
+            assert_eq!(**root, [i; 4]);
+        }
+        assert!(heap.stats().heap_size > 2 * options.initial_space_size);
+    }
+}
@@ -0,0 +1,446 @@
+use alloc::{
+    alloc::{alloc, dealloc},
+    vec::Vec,
+};
+use core::{alloc::Layout, cell::RefCell, fmt, ptr::NonNull, time::Duration};
+
+use super::table::{self, Object, ObjectTable, State};
+use crate::{
+    heap::{
+        AllocError, CollectOptions, CollectionStats, Collector, FreshAllocation, GcAllocator,
+        GcVtable, GenerationalHandle, HeapStats, MemoryPressure, RootManager, ShrinkStats,
+        StepBudget, StepResult,
+    },
+    pacer::{GcPacer, PacingInput, ThroughputPacer},
+    trace::RootProvider,
+};
+
+/// Options for a [`SystemAlloc`] heap. New options may be added in the future, so start from
+/// [`SystemAllocOptions::default`] and set the fields of interest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SystemAllocOptions {
+    /// The most bytes of objects the heap may hold. Once the heap has reached this size, an allocation runs a full
+    /// collection, and fails with [`AllocError::OutOfMemory`] if that does not free enough. Defaults to no limit.
+    pub max_heap_size: usize,
+    /// The number of threads that mark the heap during collections, including the collecting thread, which must be at
+    /// least 1. See [`MarkSweepOptions::mark_workers`](super::MarkSweepOptions::mark_workers). Defaults to 1.
+    pub mark_workers: usize,
+}
+
+impl Default for SystemAllocOptions {
+    fn default() -> Self {
+        Self {
+            max_heap_size: usize::MAX,
+            mark_workers: 1,
+        }
+    }
+}
+
+/// A mark-sweep strategy that allocates every object from the global allocator. See the
+/// [module documentation](super).
+///
+/// The heap reserves no memory of its own: each object is a separate allocation, freed as soon as a collection
+/// finds it unreachable, and the table of objects on the side is all the bookkeeping there is. This makes the heap
+/// as large as its objects and no larger, at the cost of a call to the global allocator for every allocation and
+/// every reclaimed object, so [`MarkSweep`](super::MarkSweep) is usually faster for heaps of many small objects.
+///
+/// Collections work like those of [`MarkSweep`](super::MarkSweep): the pacer decides when to collect, every
+/// collection is a full one, and objects never move. Weak cells are supported, but ephemerons are not. The strategy
+/// is neither `Send` nor `Sync`.
+pub struct SystemAlloc<P = ThroughputPacer> {
+    heap: RefCell<Heap>,
+    pacer: RefCell<P>,
+    options: SystemAllocOptions,
+}
+
+impl SystemAlloc {
+    /// Creates an empty heap with the default options, collected whenever a [`ThroughputPacer`] says so.
+    pub fn new() -> Self {
+        Self::with_options(SystemAllocOptions::default(), ThroughputPacer::default())
+    }
+}
+
+impl Default for SystemAlloc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: GcPacer> SystemAlloc<P> {
+    /// Creates an empty heap with the given options, collected whenever `pacer` says so.
+    ///
+    /// # Panics
+    /// Panics if `options.mark_workers` is zero.
+    pub fn with_options(options: SystemAllocOptions, pacer: P) -> Self {
+        assert!(options.mark_workers > 0, "mark worker count is zero");
+        Self {
+            heap: RefCell::new(Heap {
+                objects: ObjectTable::new(),
+                memory: Memory {
+                    bytes_live: 0,
+                    bytes_allocated: 0,
+                    bytes_live_after_collection: 0,
+                    collection_count: 0,
+                    last_pause: Duration::ZERO,
+                    total_pause: Duration::ZERO,
+                },
+            }),
+            pacer: RefCell::new(pacer),
+            options,
+        }
+    }
+
+    /// The options this heap was created with.
+    pub fn options(&self) -> &SystemAllocOptions {
+        &self.options
+    }
+
+    /// Marks, finalizes, and sweeps the whole heap. Does nothing while finalizers run.
+    fn collect_full(&self) -> CollectionStats {
+        if self.heap.borrow().objects.is_finalizing() {
+            return CollectionStats::default();
+        }
+        #[cfg(feature = "std")]
+        let start = std::time::Instant::now();
+        let sweep = {
+            let mut heap = self.heap.borrow_mut();
+            let Heap { objects, memory } = &mut *heap;
+            objects.mark_parallel(self.options.mark_workers);
+            objects.sweep(|object| memory.free(object))
+        };
+        table::run_finalizers(&self.heap, |heap| &heap.objects, &sweep.finalize);
+        let mut stats = sweep.stats;
+        let mut heap = self.heap.borrow_mut();
+        let Heap { objects, memory } = &mut *heap;
+        for index in sweep.finalize {
+            stats.bytes_reclaimed += memory.free(objects.remove(index));
+        }
+        stats.bytes_live = memory.bytes_live;
+        memory.bytes_allocated = 0;
+        memory.bytes_live_after_collection = memory.bytes_live;
+        memory.collection_count += 1;
+        #[cfg(feature = "std")]
+        {
+            let pause = start.elapsed();
+            memory.last_pause = pause;
+            memory.total_pause += pause;
+        }
+        let input = memory.pacing_input();
+        drop(heap);
+        self.pacer.borrow_mut().collection_finished(&stats, &input);
+        stats
+    }
+
+    /// Allocates memory for an object with the given layout, collecting first if the pacer says so, and again if
+    /// the heap is full.
+    fn reserve(&self, layout: Layout) -> Result<(NonNull<u8>, Layout), AllocError> {
+        // zero-sized objects still need an address of their own
+        let layout = Layout::from_size_align(layout.size().max(1), layout.align())
+            .map_err(|_| AllocError::InvalidLayout)?;
+        let input = self.heap.borrow().memory.pacing_input();
+        if self.pacer.borrow_mut().should_collect(&input).is_some() {
+            self.collect_full();
+        }
+        if let Some(addr) = self
+            .heap
+            .borrow_mut()
+            .memory
+            .reserve(layout, self.options.max_heap_size)
+        {
+            return Ok((addr, layout));
+        }
+        self.collect_full();
+        (self.heap.borrow_mut().memory)
+            .reserve(layout, self.options.max_heap_size)
+            .map(|addr| (addr, layout))
+            .ok_or(AllocError::OutOfMemory)
+    }
+}
+
+impl<P> fmt::Debug for SystemAlloc<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let heap = self.heap.borrow();
+        f.debug_struct("SystemAlloc")
+            .field("bytes_live", &heap.memory.bytes_live)
+            .field("object_count", &heap.objects.object_count())
+            .field("options", &self.options)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P> Drop for SystemAlloc<P> {
+    fn drop(&mut self) {
+        let Heap { objects, memory } = self.heap.get_mut();
+        objects.finalize_all();
+        for index in objects.indices().collect::<Vec<_>>() {
+            memory.free(objects.remove(index));
+        }
+    }
+}
+
+// SAFETY: objects are kept alive while rooted, pinned, frozen, or reachable from one that is, and never move.
+// Unreachable objects are finalized once after marking, and Uninitialized ones are never traced or finalized.
+unsafe impl<P: GcPacer> GcAllocator for SystemAlloc<P> {
+    type Handle = GenerationalHandle;
+    type Error = AllocError;
+
+    fn allocate(
+        &self,
+        vtable: &'static GcVtable,
+        len: usize,
+    ) -> Result<FreshAllocation<GenerationalHandle>, AllocError> {
+        let layout = vtable.layout(len).ok_or(AllocError::InvalidLayout)?;
+        let (addr, layout) = self.reserve(layout)?;
+        let mut heap = self.heap.borrow_mut();
+        let handle = heap
+            .objects
+            .insert(Object::fresh(addr, vtable, len, Some(layout)));
+        Ok(FreshAllocation {
+            handle,
+            ptr: addr.as_ptr().cast(),
+        })
+    }
+
+    unsafe fn set_initialized(&self, obj: GenerationalHandle) {
+        let mut heap = self.heap.borrow_mut();
+        let object = heap.objects.get_mut(obj);
+        object.pins -= 1;
+        object.state = State::Initialized;
+    }
+
+    unsafe fn set_finalized(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).state = State::Finalized;
+    }
+
+    unsafe fn reclaim(&self, obj: GenerationalHandle) {
+        let Heap { objects, memory } = &mut *self.heap.borrow_mut();
+        memory.free(objects.reclaim(obj));
+    }
+
+    fn pin(&self, obj: GenerationalHandle) -> *const () {
+        let mut heap = self.heap.borrow_mut();
+        let object = heap.objects.get_mut(obj);
+        object.pins += 1;
+        object.addr.as_ptr().cast()
+    }
+
+    fn unpin(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).pins -= 1;
+    }
+
+    fn pin_long_lived(&self, obj: GenerationalHandle) -> *const () {
+        self.heap.borrow_mut().objects.add_long_lived_pin();
+        self.pin(obj)
+    }
+
+    fn unpin_long_lived(&self, obj: GenerationalHandle) {
+        self.unpin(obj);
+        self.heap.borrow_mut().objects.remove_long_lived_pin();
+    }
+
+    fn resolve(&self, obj: GenerationalHandle) -> *const () {
+        self.heap.borrow().objects.get(obj).addr.as_ptr().cast()
+    }
+
+    fn shrink(&self) -> ShrinkStats {
+        // freed objects go straight back to the global allocator
+        ShrinkStats {
+            bytes_released: 0,
+            heap_size: self.heap.borrow().memory.bytes_live,
+        }
+    }
+
+    fn contains(&self, obj: GenerationalHandle) -> bool {
+        self.heap.borrow().objects.lookup(obj).is_some()
+    }
+
+    fn vtable(&self, obj: GenerationalHandle) -> &'static GcVtable {
+        self.heap.borrow().objects.get(obj).vtable
+    }
+}
+
+// SAFETY: roots and root providers are traced by every collection.
+unsafe impl<P> RootManager<GenerationalHandle> for SystemAlloc<P> {
+    fn root(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).roots += 1;
+    }
+
+    fn unroot(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).roots -= 1;
+    }
+
+    fn freeze(&self, obj: GenerationalHandle) {
+        self.heap.borrow_mut().objects.get_mut(obj).frozen = true;
+    }
+
+    unsafe fn register_root_provider(&self, provider: &dyn RootProvider) {
+        // SAFETY: caller
+        unsafe {
+            self.heap
+                .borrow_mut()
+                .objects
+                .register_root_provider(provider)
+        };
+    }
+
+    unsafe fn unregister_root_provider(&self, provider: &dyn RootProvider) {
+        self.heap
+            .borrow_mut()
+            .objects
+            .unregister_root_provider(provider);
+    }
+}
+
+// SAFETY: weak cells are cleared during the collection that finds their targets unreachable, before the targets
+// are finalized.
+unsafe impl<P: GcPacer> Collector<GenerationalHandle> for SystemAlloc<P> {
+    fn allocate_weak(&self, target: GenerationalHandle) -> Option<GenerationalHandle> {
+        let mut heap = self.heap.borrow_mut();
+        heap.objects.get(target);
+        Some(heap.objects.insert(Object::weak(target, None)))
+    }
+
+    unsafe fn upgrade_weak(&self, cell: GenerationalHandle) -> Option<GenerationalHandle> {
+        let mut heap = self.heap.borrow_mut();
+        let target = heap
+            .objects
+            .get(cell)
+            .weak
+            .expect("object is not a weak cell")?;
+        heap.objects.get_mut(target).roots += 1;
+        Some(target)
+    }
+
+    fn collect(&self, options: CollectOptions) -> CollectionStats {
+        let _ = options;
+        self.collect_full()
+    }
+
+    fn collect_step(&self, budget: StepBudget) -> StepResult {
+        let _ = budget;
+        StepResult::Complete(self.collect_full())
+    }
+
+    fn memory_pressure(&self, level: MemoryPressure) {
+        if level == MemoryPressure::Critical {
+            self.collect_full();
+        }
+    }
+
+    fn stats(&self) -> HeapStats {
+        let heap = self.heap.borrow();
+        HeapStats {
+            heap_size: heap.memory.bytes_live,
+            bytes_live: heap.memory.bytes_live,
+            object_count: heap.objects.object_count(),
+            long_lived_pins: heap.objects.long_lived_pins(),
+            collection_count: heap.memory.collection_count,
+            total_pause: heap.memory.total_pause,
+        }
+    }
+}
+
+/// The state of a [`SystemAlloc`] heap.
+struct Heap {
+    /// The objects, and the layouts they were allocated with. Weak cells take up no memory.
+    objects: ObjectTable<Option<Layout>>,
+    memory: Memory,
+}
+
+/// Statistics about the memory use of a [`SystemAlloc`] heap.
+struct Memory {
+    bytes_live: usize,
+    /// The number of bytes allocated since the last collection.
+    bytes_allocated: usize,
+    bytes_live_after_collection: usize,
+    collection_count: u64,
+    last_pause: Duration,
+    total_pause: Duration,
+}
+
+impl Memory {
+    /// Allocates memory with the given layout, or returns None if the heap would grow beyond `max_heap_size` bytes.
+    fn reserve(&mut self, layout: Layout, max_heap_size: usize) -> Option<NonNull<u8>> {
+        let bytes_live = self.bytes_live.checked_add(layout.size())?;
+        if bytes_live > max_heap_size {
+            return None;
+        }
+        // SAFETY: the layout has a non-zero size
+        let addr = NonNull::new(unsafe { alloc(layout) })?;
+        self.bytes_live = bytes_live;
+        self.bytes_allocated += layout.size();
+        Some(addr)
+    }
+
+    /// Frees the memory of a removed object, returning the number of bytes freed.
+    fn free(&mut self, object: Object<Option<Layout>>) -> usize {
+        let Some(layout) = object.memory else {
+            return 0;
+        };
+        self.bytes_live -= layout.size();
+        // SAFETY: the object has been removed, so its memory is no longer used
+        unsafe { dealloc(object.addr.as_ptr(), layout) };
+        layout.size()
+    }
+
+    fn pacing_input(&self) -> PacingInput {
+        PacingInput {
+            bytes_allocated: self.bytes_allocated,
+            bytes_live: self.bytes_live,
+            bytes_live_after_major: self.bytes_live_after_collection,
+            last_pause: self.last_pause,
+            average_pause: match self.collection_count {
+                0 => Duration::ZERO,
+                count => self.total_pause / count as u32,
+            },
+            ..PacingInput::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{strategies::tests, testing, GcHeap};
+
+    #[test]
+    fn conformance() {
+        testing::run_all(SystemAlloc::new);
+    }
+
+    #[test]
+    fn reentrant_finalizers() {
+        testing::reentrant_finalizers(SystemAlloc::new());
+    }
+
+    #[test]
+    fn reclamation() {
+        tests::reclamation(SystemAlloc::new());
+    }
+
+    #[test]
+    fn pinning() {
+        tests::pinning(SystemAlloc::new());
+    }
+
+    #[test]
+    fn weak_cells() {
+        tests::weak_cells(SystemAlloc::new());
+    }
+
+    #[test]
+    fn returns_memory_when_reclaiming() {
+        let heap = GcHeap::new(SystemAlloc::new());
+        let kept = heap.alloc([1u64; 4]);
+        let bytes_live = heap.stats().bytes_live;
+        for _ in 0..100 {
+            heap.alloc([0u64; 64]);
+        }
+        assert!(heap.stats().bytes_live >= bytes_live + 100 * 512);
+        heap.collect();
+        assert_eq!(heap.stats().bytes_live, bytes_live);
+        assert_eq!(heap.stats().heap_size, bytes_live);
+        assert_eq!(*kept, [1; 4]);
+    }
+}
@@ -0,0 +1,581 @@
+use alloc::vec::Vec;
+use core::{
+    cell::{Cell, RefCell},
+    mem,
+    ptr::{self, NonNull},
+};
+
+use crate::{
+    heap::{CollectionKind, CollectionStats, GcVtable, GenerationalHandle, Handle, StrategyHandle},
+    trace::{GcVisitor, RootProvider, TraceContext},
+};
+
+/// The table of objects shared by the strategies in this module. Handles are indices into the table, so objects
+/// can move without their handles changing. `M` is the strategy's record of where the memory of an object came
+/// from.
+pub(super) struct ObjectTable<M> {
+    slots: Vec<Slot<M>>,
+    /// The indices of the empty slots.
+    free_slots: Vec<usize>,
+    object_count: usize,
+    /// The number of weak cells and ephemerons in the table, so that reclaiming an object only searches the table
+    /// for cells referring to it if there are any.
+    weak_count: usize,
+    /// The number of pins added by [`GcAllocator::pin_long_lived`] and not yet removed.
+    ///
+    /// [`GcAllocator::pin_long_lived`]: crate::heap::GcAllocator::pin_long_lived
+    long_lived_pins: usize,
+    providers: Vec<*const (dyn RootProvider + 'static)>,
+    /// Whether [`run_finalizers`] is running finalizers of objects in the table.
+    finalizing: Cell<bool>,
+}
+
+struct Slot<M> {
+    /// The generation of the object in this slot, or of the next one if the slot is empty.
+    generation: u32,
+    object: Option<Object<M>>,
+}
+
+pub(super) struct Object<M> {
+    pub(super) addr: NonNull<u8>,
+    pub(super) vtable: &'static GcVtable,
+    pub(super) len: usize,
+    pub(super) memory: M,
+    pub(super) state: State,
+    pub(super) roots: usize,
+    pub(super) pins: usize,
+    pub(super) frozen: bool,
+    pub(super) marked: bool,
+    /// For weak cells, the target of the cell, or None once it has been cleared.
+    pub(super) weak: Option<Option<GenerationalHandle>>,
+    /// For ephemerons, which are weak cells whose target is their key, the value kept alive while the key is, or
+    /// None once the ephemeron has been cleared.
+    pub(super) value: Option<GenerationalHandle>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum State {
+    Uninitialized,
+    Initialized,
+    Finalized,
+}
+
+impl<M> Object<M> {
+    /// A fresh allocation, which is rooted, pinned, and Uninitialized.
+    pub(super) fn fresh(
+        addr: NonNull<u8>,
+        vtable: &'static GcVtable,
+        len: usize,
+        memory: M,
+    ) -> Self {
+        Self {
+            addr,
+            vtable,
+            len,
+            memory,
+            state: State::Uninitialized,
+            roots: 1,
+            pins: 1,
+            frozen: false,
+            marked: false,
+            weak: None,
+            value: None,
+        }
+    }
+
+    /// A rooted weak cell referring to `target`, which takes up no memory.
+    pub(super) fn weak(target: GenerationalHandle, memory: M) -> Self {
+        Self {
+            addr: NonNull::dangling(),
+            vtable: GcVtable::for_type::<()>(),
+            len: 1,
+            memory,
+            state: State::Initialized,
+            roots: 1,
+            pins: 0,
+            frozen: false,
+            marked: false,
+            weak: Some(Some(target)),
+            value: None,
+        }
+    }
+
+    /// A rooted ephemeron with the given key and value, which takes up no memory.
+    pub(super) fn ephemeron(key: GenerationalHandle, value: GenerationalHandle, memory: M) -> Self {
+        Self {
+            value: Some(value),
+            ..Self::weak(key, memory)
+        }
+    }
+
+    /// Returns whether the object is a root of the collection.
+    pub(super) fn is_held(&self) -> bool {
+        self.roots > 0 || self.pins > 0 || self.frozen
+    }
+
+    /// Returns whether the object has references to trace.
+    pub(super) fn needs_trace(&self) -> bool {
+        self.state == State::Initialized && self.weak.is_none() && self.vtable.needs_trace()
+    }
+}
+
+/// The objects found unreachable by [`ObjectTable::sweep`].
+pub(super) struct Sweep {
+    /// The objects to finalize before they are removed.
+    pub(super) finalize: Vec<usize>,
+    pub(super) stats: CollectionStats,
+}
+
+impl<M> ObjectTable<M> {
+    pub(super) fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free_slots: Vec::new(),
+            object_count: 0,
+            weak_count: 0,
+            long_lived_pins: 0,
+            providers: Vec::new(),
+            finalizing: Cell::new(false),
+        }
+    }
+
+    pub(super) fn lookup(&self, obj: GenerationalHandle) -> Option<&Object<M>> {
+        let slot = self.slots.get(obj.index())?;
+        if slot.generation != obj.generation() {
+            return None;
+        }
+        slot.object.as_ref()
+    }
+
+    #[track_caller]
+    pub(super) fn get(&self, obj: GenerationalHandle) -> &Object<M> {
+        let slot = &self.slots[obj.index()];
+        obj.check(slot.generation);
+        slot.object.as_ref().expect("object has been reclaimed")
+    }
+
+    #[track_caller]
+    pub(super) fn get_mut(&mut self, obj: GenerationalHandle) -> &mut Object<M> {
+        let slot = &mut self.slots[obj.index()];
+        obj.check(slot.generation);
+        slot.object.as_mut().expect("object has been reclaimed")
+    }
+
+    /// The object in the given slot, which must not be empty.
+    pub(super) fn at(&self, index: usize) -> &Object<M> {
+        self.slots[index].object.as_ref().unwrap()
+    }
+
+    /// The object in the given slot, which must not be empty.
+    pub(super) fn at_mut(&mut self, index: usize) -> &mut Object<M> {
+        self.slots[index].object.as_mut().unwrap()
+    }
+
+    /// The handle of the object in the given slot.
+    pub(super) fn handle(&self, index: usize) -> GenerationalHandle {
+        GenerationalHandle::new(index, self.slots[index].generation)
+    }
+
+    /// The slot of the object a traced handle refers to, or None if it has been reclaimed.
+    pub(super) fn resolve(&self, handle: Handle) -> Option<usize> {
+        let handle = GenerationalHandle::from_raw(handle);
+        let slot = &self.slots[handle.index()];
+        handle.check(slot.generation);
+        slot.object.as_ref().map(|_| handle.index())
+    }
+
+    /// The number of slots, whether or not they hold an object. Slots are only ever added.
+    pub(super) fn slot_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// The object in the given slot, if any.
+    pub(super) fn slot_mut(&mut self, index: usize) -> Option<&mut Object<M>> {
+        self.slots[index].object.as_mut()
+    }
+
+    /// The indices of the slots that hold an object.
+    pub(super) fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        (self.slots.iter().enumerate())
+            .filter_map(|(index, slot)| slot.object.as_ref().map(|_| index))
+    }
+
+    /// Whether finalizers are running. The objects being finalized are still in the table, and their memory must
+    /// not be reused until they are removed, so collections must do nothing while finalizers run, e.g. when a
+    /// finalizer allocates.
+    pub(super) fn is_finalizing(&self) -> bool {
+        self.finalizing.get()
+    }
+
+    pub(super) fn object_count(&self) -> usize {
+        self.object_count
+    }
+
+    pub(super) fn long_lived_pins(&self) -> usize {
+        self.long_lived_pins
+    }
+
+    /// Counts a pin added by [`GcAllocator::pin_long_lived`](crate::heap::GcAllocator::pin_long_lived).
+    pub(super) fn add_long_lived_pin(&mut self) {
+        self.long_lived_pins += 1;
+    }
+
+    /// Counts a pin removed by [`GcAllocator::unpin_long_lived`](crate::heap::GcAllocator::unpin_long_lived).
+    pub(super) fn remove_long_lived_pin(&mut self) {
+        self.long_lived_pins -= 1;
+    }
+
+    pub(super) fn insert(&mut self, object: Object<M>) -> GenerationalHandle {
+        self.object_count += 1;
+        self.weak_count += usize::from(object.weak.is_some());
+        let index = self.free_slots.pop().unwrap_or_else(|| {
+            self.slots.push(Slot {
+                generation: 0,
+                object: None,
+            });
+            self.slots.len() - 1
+        });
+        let slot = &mut self.slots[index];
+        slot.object = Some(object);
+        GenerationalHandle::new(index, slot.generation)
+    }
+
+    /// Removes the object in the given slot, which must not be empty, leaving its memory to the caller.
+    pub(super) fn remove(&mut self, index: usize) -> Object<M> {
+        let slot = &mut self.slots[index];
+        let object = slot.object.take().unwrap();
+        slot.generation = slot.generation.wrapping_add(1) & GenerationalHandle::MAX_GENERATION;
+        self.free_slots.push(index);
+        self.object_count -= 1;
+        self.weak_count -= usize::from(object.weak.is_some());
+        object
+    }
+
+    /// Removes the given object ahead of a collection, as [`GcAllocator::reclaim`], leaving its memory to the
+    /// caller. The weak cells referring to the object are cleared, as are the ephemerons whose key or value it is,
+    /// since its slot may be reused by another object.
+    ///
+    /// [`GcAllocator::reclaim`]: crate::heap::GcAllocator::reclaim
+    #[track_caller]
+    pub(super) fn reclaim(&mut self, obj: GenerationalHandle) -> Object<M> {
+        self.get(obj);
+        if self.weak_count > 0 {
+            for object in self
+                .slots
+                .iter_mut()
+                .filter_map(|slot| slot.object.as_mut())
+            {
+                if object.weak == Some(Some(obj)) || object.value == Some(obj) {
+                    object.weak = Some(None);
+                    object.value = None;
+                }
+            }
+        }
+        self.remove(obj.index())
+    }
+
+    /// # Safety
+    /// The provider must stay valid until it is unregistered.
+    pub(super) unsafe fn register_root_provider(&mut self, provider: &dyn RootProvider) {
+        // SAFETY: caller
+        let provider = unsafe {
+            core::mem::transmute::<*const dyn RootProvider, *const (dyn RootProvider + 'static)>(
+                provider,
+            )
+        };
+        self.providers.push(provider);
+    }
+
+    pub(super) fn unregister_root_provider(&mut self, provider: &dyn RootProvider) {
+        let position =
+            (self.providers.iter()).position(|&registered| ptr::addr_eq(registered, provider));
+        self.providers
+            .swap_remove(position.expect("root provider is not registered"));
+    }
+
+    /// Passes each handle reported by the registered root providers to `visit`, along with the table.
+    pub(super) fn trace_root_providers(&mut self, mut visit: impl FnMut(&mut Self, Handle)) {
+        let providers = mem::take(&mut self.providers);
+        for &provider in &providers {
+            // SAFETY: providers stay valid until they are unregistered
+            unsafe { &*provider }
+                .trace_roots(&mut TraceContext::new(&mut |handle| visit(self, handle)));
+        }
+        self.providers = providers;
+    }
+
+    /// Marks every object reachable from a root, breadth first. Each object is passed to `visit` when it is
+    /// marked, before it is traced, so copying strategies can evacuate it there. The value of a marked ephemeron is
+    /// marked once its key is.
+    pub(super) fn mark(&mut self, mut visit: impl FnMut(&mut Object<M>)) {
+        let Self {
+            slots, providers, ..
+        } = self;
+        for slot in slots.iter_mut() {
+            if let Some(object) = &mut slot.object {
+                object.marked = false;
+            }
+        }
+        let mut marker = Marker {
+            slots,
+            queue: Vec::new(),
+            visit: &mut visit,
+        };
+        for index in 0..marker.slots.len() {
+            if marker.slots[index]
+                .object
+                .as_ref()
+                .is_some_and(Object::is_held)
+            {
+                marker.mark(index);
+            }
+        }
+        for &provider in providers.iter() {
+            // SAFETY: providers stay valid until they are unregistered
+            unsafe { &*provider }.trace_roots(&mut TraceContext::new(&mut marker));
+        }
+        let ephemerons = ephemerons(marker.slots);
+        let mut scan = 0;
+        loop {
+            while let Some(&index) = marker.queue.get(scan) {
+                scan += 1;
+                let object = marker.slots[index].object.as_ref().unwrap();
+                if !object.needs_trace() {
+                    continue;
+                }
+                let (vtable, addr, len) = (object.vtable, object.addr, object.len);
+                // SAFETY: the object is Initialized
+                unsafe { vtable.trace(addr.cast(), len, &mut TraceContext::new(&mut marker)) };
+            }
+            for &index in &ephemerons {
+                let slots = &*marker.slots;
+                let marked = |index: usize| slots[index].object.as_ref().unwrap().marked;
+                if let Some(value) = ephemeron_value(slots, index, marked) {
+                    marker.mark(value);
+                }
+            }
+            if scan == marker.queue.len() {
+                break;
+            }
+        }
+    }
+
+    /// Marks every object reachable from a root like [`ObjectTable::mark`], with `workers` threads including the
+    /// calling one. Root providers are traced on the calling thread. Without the `std` feature, or with a single
+    /// worker, this is the same as `mark`.
+    pub(super) fn mark_parallel(&mut self, workers: usize) {
+        #[cfg(feature = "std")]
+        if workers > 1 {
+            use core::sync::atomic::{AtomicBool, Ordering};
+
+            let marks: Vec<AtomicBool> = (self.slots.iter())
+                .map(|slot| AtomicBool::new(slot.object.as_ref().is_some_and(Object::is_held)))
+                .collect();
+            let mut roots: Vec<usize> = (0..marks.len())
+                .filter(|&index| marks[index].load(Ordering::Relaxed))
+                .collect();
+            for &provider in &self.providers {
+                let mut visit = |handle| {
+                    let handle = GenerationalHandle::from_raw(handle);
+                    handle.check(self.slots[handle.index()].generation);
+                    if !marks[handle.index()].swap(true, Ordering::Relaxed) {
+                        roots.push(handle.index());
+                    }
+                };
+                // SAFETY: providers stay valid until they are unregistered
+                unsafe { &*provider }.trace_roots(&mut TraceContext::new(&mut visit));
+            }
+            let slots = SharedSlots(&self.slots);
+            let ephemerons = ephemerons(&self.slots);
+            while !roots.is_empty() {
+                super::parallel::mark(&marks, roots, workers, &|index, visit| {
+                    slots.trace(index, visit)
+                });
+                roots = (ephemerons.iter())
+                    .filter_map(|&index| {
+                        ephemeron_value(&self.slots, index, |index| {
+                            marks[index].load(Ordering::Relaxed)
+                        })
+                    })
+                    .filter(|&value| !marks[value].swap(true, Ordering::Relaxed))
+                    .collect();
+            }
+            for (slot, mark) in self.slots.iter_mut().zip(marks) {
+                if let Some(object) = &mut slot.object {
+                    object.marked = mark.into_inner();
+                }
+            }
+            return;
+        }
+        let _ = workers;
+        self.mark(|_| {});
+    }
+
+    /// Clears the weak cells and ephemerons of unmarked targets and removes unmarked objects, passing each to `free`
+    /// to release its memory, which returns the number of bytes freed. Objects that have a finalizer are only marked
+    /// Finalized and left for the caller to finalize and remove.
+    pub(super) fn sweep(&mut self, mut free: impl FnMut(Object<M>) -> usize) -> Sweep {
+        for index in 0..self.slots.len() {
+            let Some(Some(target)) = self.slots[index]
+                .object
+                .as_ref()
+                .and_then(|object| object.weak)
+            else {
+                continue;
+            };
+            if !self.lookup(target).is_some_and(|target| target.marked) {
+                let object = self.slots[index].object.as_mut().unwrap();
+                object.weak = Some(None);
+                object.value = None;
+            }
+        }
+        let mut sweep = Sweep {
+            finalize: Vec::new(),
+            stats: CollectionStats {
+                kind: Some(CollectionKind::Full),
+                ..CollectionStats::default()
+            },
+        };
+        for index in 0..self.slots.len() {
+            let Some(object) = &mut self.slots[index].object else {
+                continue;
+            };
+            if object.marked {
+                continue;
+            }
+            sweep.stats.objects_reclaimed += 1;
+            if object.state == State::Initialized && object.vtable.needs_finalize() {
+                object.state = State::Finalized;
+                sweep.finalize.push(index);
+            } else {
+                sweep.stats.bytes_reclaimed += free(self.remove(index));
+            }
+        }
+        sweep
+    }
+
+    /// Runs the finalizers of all Initialized objects, when the heap is destroyed.
+    pub(super) fn finalize_all(&mut self) {
+        for slot in &mut self.slots {
+            if let Some(object) = &mut slot.object {
+                if object.state == State::Initialized {
+                    // SAFETY: the heap is being destroyed, so the object will not be used again
+                    unsafe { object.vtable.finalize(object.addr.cast(), object.len) };
+                    object.state = State::Finalized;
+                }
+            }
+        }
+    }
+}
+
+/// Runs the finalizers of the objects left by [`ObjectTable::sweep`]. Finalizers may drop roots of other objects,
+/// so the heap is only borrowed to look up each object, through `table`. The table reports that it is finalizing
+/// until they are done, and for good if one of them panics.
+pub(super) fn run_finalizers<H, M>(
+    heap: &RefCell<H>,
+    table: impl Fn(&H) -> &ObjectTable<M>,
+    finalize: &[usize],
+) {
+    if finalize.is_empty() {
+        return;
+    }
+    table(&heap.borrow()).finalizing.set(true);
+    for &index in finalize {
+        let (vtable, addr, len) = {
+            let heap = heap.borrow();
+            let object = table(&heap).at(index);
+            (object.vtable, object.addr, object.len)
+        };
+        // SAFETY: the object was Initialized when it was found unreachable, and is removed right after
+        unsafe { vtable.finalize(addr.cast(), len) };
+    }
+    table(&heap.borrow()).finalizing.set(false);
+}
+
+/// The slots of the ephemerons that have not been cleared.
+fn ephemerons<M>(slots: &[Slot<M>]) -> Vec<usize> {
+    (slots.iter().enumerate())
+        .filter(|(_, slot)| {
+            slot.object
+                .as_ref()
+                .is_some_and(|object| object.value.is_some())
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// The slot of the value of the ephemeron in the given slot, if the ephemeron and its key are marked according to
+/// `marked`, which is passed the slot of an object.
+fn ephemeron_value<M>(
+    slots: &[Slot<M>],
+    index: usize,
+    marked: impl Fn(usize) -> bool,
+) -> Option<usize> {
+    let object = slots[index].object.as_ref().unwrap();
+    let (key, value) = (object.weak??, object.value?);
+    (marked(index) && marked(key.index())).then_some(value.index())
+}
+
+/// Marks the objects visited during tracing, and queues them so that their own references are traced.
+struct Marker<'a, M> {
+    slots: &'a mut [Slot<M>],
+    queue: Vec<usize>,
+    visit: &'a mut dyn FnMut(&mut Object<M>),
+}
+
+impl<M> Marker<'_, M> {
+    fn mark(&mut self, index: usize) {
+        let object = self.slots[index].object.as_mut().unwrap();
+        if !object.marked {
+            object.marked = true;
+            (self.visit)(object);
+            self.queue.push(index);
+        }
+    }
+}
+
+impl<M> GcVisitor for Marker<'_, M> {
+    fn visit(&mut self, handle: Handle) {
+        let handle = GenerationalHandle::from_raw(handle);
+        let slot = &self.slots[handle.index()];
+        handle.check(slot.generation);
+        if slot.object.is_some() {
+            self.mark(handle.index());
+        }
+    }
+}
+
+/// The slots of a table, shared with the threads of [`ObjectTable::mark_parallel`].
+#[cfg(feature = "std")]
+struct SharedSlots<'a, M>(&'a [Slot<M>]);
+
+// SAFETY: the slots are only read while they are shared, and `Trace` implementations may be called from any thread.
+#[cfg(feature = "std")]
+unsafe impl<M> Sync for SharedSlots<'_, M> {}
+
+#[cfg(feature = "std")]
+impl<M> SharedSlots<'_, M> {
+    /// Passes the slot of each object referred to by the object in the given slot to `visit`.
+    fn trace(&self, index: usize, visit: &mut dyn FnMut(usize)) {
+        let object = self.0[index].object.as_ref().unwrap();
+        if !object.needs_trace() {
+            return;
+        }
+        let mut visit = |handle| {
+            let handle = GenerationalHandle::from_raw(handle);
+            let slot = &self.0[handle.index()];
+            handle.check(slot.generation);
+            if slot.object.is_some() {
+                visit(handle.index());
+            }
+        };
+        // SAFETY: the object is Initialized
+        unsafe {
+            (object.vtable).trace(
+                object.addr.cast(),
+                object.len,
+                &mut TraceContext::new(&mut visit),
+            )
+        };
+    }
+}
@@ -0,0 +1,151 @@
+//! Checks of behavior that the strategies in this module share, beyond what the [conformance tests](crate::testing)
+//! require.
+
+use core::{
+    ptr,
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
+};
+
+use crate::{
+    heap::{CollectOptions, GcStrategy, RelocationListener},
+    testing::Node,
+    weak::{EphemeronRoot, GcWeakMap},
+    GcHeap,
+};
+
+/// Checks that a collection reclaims every unreachable object, and nothing else.
+pub(super) fn reclamation<S: GcStrategy>(strategy: S) {
+    let drops = AtomicUsize::new(0);
+    let heap = GcHeap::new(strategy);
+    let kept = heap.alloc(Node::new(&drops, 1, None));
+    for value in 0..10 {
+        heap.alloc(Node::new(&drops, value, None));
+    }
+    heap.collect();
+    assert_eq!(drops.load(Ordering::Relaxed), 10);
+    assert_eq!(heap.stats().object_count, 1);
+    assert_eq!(kept.value, 1);
+    drop(kept);
+    heap.collect();
+    assert_eq!(drops.load(Ordering::Relaxed), 11);
+    assert_eq!(heap.stats().object_count, 0);
+}
+
+/// Checks that an object pinned for the long term stays alive and in place without any roots, is counted in the heap
+/// statistics while pinned, and is reclaimed once unpinned.
+pub(super) fn pinning<S: GcStrategy>(strategy: S) {
+    let drops = AtomicUsize::new(0);
+    let heap = GcHeap::new(strategy);
+    let pinned = heap.alloc(Node::new(&drops, 1, None)).pin_ptr();
+    let short_lived = heap.alloc(Node::new(&drops, 2, None));
+    let _pin = short_lived.pin();
+    let addr = pinned.as_ptr();
+    heap.collect();
+    assert_eq!(drops.load(Ordering::Relaxed), 0);
+    assert_eq!(pinned.as_ptr(), addr);
+    assert_eq!(pinned.value, 1);
+    assert_eq!(heap.stats().long_lived_pins, 1);
+    drop(pinned);
+    assert_eq!(heap.stats().long_lived_pins, 0);
+    heap.collect();
+    assert_eq!(drops.load(Ordering::Relaxed), 1);
+}
+
+/// Checks that a collection clears the weak cells of the objects it reclaims, and only those, and that disposing of an
+/// object clears its weak cells immediately.
+pub(super) fn weak_cells<S: GcStrategy>(strategy: S) {
+    let heap = GcHeap::new(strategy);
+    let kept = heap.alloc(1u32);
+    let lost = heap.alloc(2u32);
+    let (kept_weak, lost_weak) = (kept.downgrade(), lost.downgrade());
+    heap.collect();
+    assert!(lost_weak.upgrade().is_some());
+    drop(lost);
+    heap.collect();
+    assert!(lost_weak.upgrade().is_none());
+    assert_eq!(kept_weak.upgrade().as_deref(), Some(&1));
+
+    let disposed = heap.alloc(3u64);
+    let disposed_weak = disposed.downgrade();
+    let gc = disposed.gc();
+    drop(disposed);
+    // SAFETY: the object is no longer rooted, and not accessed again
+    unsafe { heap.dispose(gc) };
+    // the memory of the disposed object may be reused for an object of another type
+    let _reused = heap.alloc([u8::MAX; 8]);
+    assert!(disposed_weak.upgrade().is_none());
+}
+
+/// Checks that an ephemeron keeps its value alive only while its key is reachable, even if the value refers to the
+/// key, and is cleared along with the entries of a [`GcWeakMap`] once the key is not, or once the key is disposed of.
+pub(super) fn ephemerons<S: GcStrategy>(strategy: S) {
+    let drops = AtomicUsize::new(0);
+    let heap = GcHeap::new(strategy);
+    let key = heap.alloc(Node::new(&drops, 1, None));
+    let value = heap.alloc(Node::new(&drops, 2, Some(key.gc())));
+    // the value of an ephemeron may itself be a key
+    let next = heap.alloc(Node::new(&drops, 3, None));
+    let ephemeron = EphemeronRoot::new(&key, &value);
+    let chained = EphemeronRoot::new(&value, &next);
+    let mut map = GcWeakMap::new(&heap);
+    map.insert(&key, &next);
+    drop((value, next));
+    heap.collect();
+    assert_eq!(drops.load(Ordering::Relaxed), 0);
+    let (_, value) = ephemeron.upgrade().unwrap();
+    assert_eq!(value.value, 2);
+    assert_eq!(map.get(&key).map(|next| next.value), Some(3));
+    drop((key, value));
+    heap.collect();
+    assert_eq!(drops.load(Ordering::Relaxed), 3);
+    assert!(ephemeron.upgrade().is_none());
+    assert!(chained.upgrade().is_none());
+    map.purge();
+    assert!(map.is_empty());
+
+    let key = heap.alloc(4u64);
+    let value = heap.alloc(5u64);
+    let ephemeron = EphemeronRoot::new(&key, &value);
+    let gc = key.gc();
+    drop(key);
+    // SAFETY: the object is no longer rooted, and not accessed again
+    unsafe { heap.dispose(gc) };
+    let _reused = heap.alloc([u8::MAX; 8]);
+    assert!(ephemeron.upgrade().is_none());
+}
+
+/// Records the moves reported to a [`RelocationListener`], keeping only the last one.
+#[derive(Default)]
+struct MoveRecorder {
+    moves: AtomicUsize,
+    old: AtomicPtr<()>,
+    new: AtomicPtr<()>,
+}
+
+// SAFETY: the recorder only accesses atomics
+unsafe impl RelocationListener for MoveRecorder {
+    fn relocated(&self, old: *const (), new: *const ()) {
+        self.moves.fetch_add(1, Ordering::Relaxed);
+        self.old.store(old.cast_mut(), Ordering::Relaxed);
+        self.new.store(new.cast_mut(), Ordering::Relaxed);
+    }
+}
+
+/// Checks that a collection of the given kind moves an unpinned object and reports the move to a registered
+/// relocation listener, and that the listener is not notified once unregistered.
+pub(super) fn relocation<S: GcStrategy>(strategy: S, options: CollectOptions) {
+    let heap = GcHeap::new(strategy);
+    let root = heap.alloc(7u64);
+    let recorder = MoveRecorder::default();
+    let old = root.as_ptr();
+    heap.with_relocation_listener(&recorder, || heap.collect_with(options));
+    let new = root.as_ptr();
+    assert_ne!(new, old);
+    assert_eq!(recorder.moves.load(Ordering::Relaxed), 1);
+    assert!(ptr::eq(recorder.old.load(Ordering::Relaxed), old.cast()));
+    assert!(ptr::eq(recorder.new.load(Ordering::Relaxed), new.cast()));
+    heap.collect_with(options);
+    assert_ne!(root.as_ptr(), new);
+    assert_eq!(recorder.moves.load(Ordering::Relaxed), 1);
+    assert_eq!(*root, 7);
+}
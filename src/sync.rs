@@ -0,0 +1,731 @@
+//! Synchronization primitives that can be stored in GC objects.
+//!
+//! Standard library locks deny shared access to their contents while locked, so they cannot implement [`Trace`].
+//! The locks in this module provide the same mutual exclusion between mutator threads, but allow the GC to trace the
+//! protected data at any time. The module also provides [`AtomicGc`] and [`AtomicOptionGc`], which hold a `Gc` that
+//! can be swapped from multiple threads without a lock.
+//!
+//! # Tracing Locked Data
+//! The GC traces the protected data without taking the lock, so objects may be allocated and collections may run
+//! while a guard is held, on any thread. This is sound because tracing only reads the data, and never overlaps with a
+//! write to it:
+//! - Strategies do not trace an object while a thread that may write to it is running outside the heap, as described
+//!   in [Tracing and Mutation](crate::heap::Collector#tracing-and-mutation). Every write through a guard has finished
+//!   before tracing starts, and none starts before it ends, whether or not the writer still holds the guard. The
+//!   strategy synchronizes with the threads it stops, so the writes happen before the data is traced.
+//! - Guards do not hold a `&mut T` themselves. Each call to `deref_mut` reborrows a fresh `&mut T` from the raw
+//!   pointer of the lock, so the reads of the GC do not invalidate a mutable reference that the guard uses afterwards.
+//!
+//! Waiting for the lock instead could deadlock, as the guard may be held by the very thread that is collecting, or by
+//! one that the strategy has stopped.
+
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use crate::{
+    cell::assert_within,
+    heap::{GcStrategy, Handle},
+    ptr::GcPointee,
+    trace::{EdgeContext, Trace, TraceContext},
+    Gc, Root,
+};
+
+/// The state of a [`GcRwLock`] that is write locked.
+const WRITE_LOCKED: usize = usize::MAX;
+
+/// Waits a little before retrying to acquire a lock.
+fn relax(spins: &mut u32) {
+    if *spins < 64 {
+        *spins += 1;
+        core::hint::spin_loop();
+    } else {
+        #[cfg(feature = "std")]
+        std::thread::yield_now();
+        #[cfg(not(feature = "std"))]
+        core::hint::spin_loop();
+    }
+}
+
+/// A mutual exclusion lock whose contents can be traced by the GC while locked.
+pub struct GcMutex<T: ?Sized> {
+    locked: AtomicBool,
+    data: UnsafeCell<T>,
+}
+
+// SAFETY: the lock provides exclusive access to the data, the same as `std::sync::Mutex`.
+unsafe impl<T: ?Sized + Send> Send for GcMutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for GcMutex<T> {}
+
+impl<T> GcMutex<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> GcMutex<T> {
+    /// Acquires the lock, spinning until it is available.
+    pub fn lock(&self) -> GcMutexGuard<'_, T> {
+        let mut spins = 0;
+        loop {
+            if let Some(guard) = self.try_lock() {
+                return guard;
+            }
+            while self.locked.load(Ordering::Relaxed) {
+                relax(&mut spins);
+            }
+        }
+    }
+
+    /// Acquires the lock if it is available.
+    pub fn try_lock(&self) -> Option<GcMutexGuard<'_, T>> {
+        self.locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| GcMutexGuard { mutex: self })
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+impl<T: Default> Default for GcMutex<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for GcMutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("GcMutex");
+        match self.try_lock() {
+            Some(guard) => d.field("data", &&*guard),
+            None => d.field("data", &format_args!("<locked>")),
+        };
+        d.finish_non_exhaustive()
+    }
+}
+
+/// SAFETY: tracing does not overlap with writes to the data, see the [module documentation](self).
+unsafe impl<T: ?Sized + Trace> Trace for GcMutex<T> {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        // SAFETY: see above
+        unsafe { (*self.data.get()).trace(ctx) }
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        self.get_mut().trace_edges(ctx);
+    }
+}
+
+/// Exclusive access to the data protected by a [`GcMutex`]. The lock is released when the guard is dropped.
+pub struct GcMutexGuard<'a, T: ?Sized> {
+    mutex: &'a GcMutex<T>,
+}
+
+// SAFETY: sharing the guard only gives shared access to the data.
+unsafe impl<T: ?Sized + Sync> Sync for GcMutexGuard<'_, T> {}
+
+impl<T: ?Sized> Deref for GcMutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: the lock is held
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for GcMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: the lock is held. The reference is reborrowed on each call, rather than stored, so that tracing may
+        // read the data between calls.
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for GcMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.locked.store(false, Ordering::Release);
+    }
+}
+
+/// A reader-writer lock whose contents can be traced by the GC while locked.
+pub struct GcRwLock<T: ?Sized> {
+    /// The number of readers, or `WRITE_LOCKED` if write locked.
+    state: AtomicUsize,
+    data: UnsafeCell<T>,
+}
+
+// SAFETY: the lock provides shared or exclusive access to the data, the same as `std::sync::RwLock`.
+unsafe impl<T: ?Sized + Send> Send for GcRwLock<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for GcRwLock<T> {}
+
+impl<T> GcRwLock<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            state: AtomicUsize::new(0),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> GcRwLock<T> {
+    /// Acquires a shared lock, spinning until no writer holds the lock.
+    pub fn read(&self) -> GcRwLockReadGuard<'_, T> {
+        let mut spins = 0;
+        loop {
+            if let Some(guard) = self.try_read() {
+                return guard;
+            }
+            relax(&mut spins);
+        }
+    }
+
+    /// Acquires a shared lock if no writer holds the lock.
+    pub fn try_read(&self) -> Option<GcRwLockReadGuard<'_, T>> {
+        let mut state = self.state.load(Ordering::Relaxed);
+        loop {
+            if state >= WRITE_LOCKED - 1 {
+                // write locked, or too many readers
+                return None;
+            }
+            match self.state.compare_exchange_weak(
+                state,
+                state + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return Some(GcRwLockReadGuard { lock: self }),
+                Err(s) => state = s,
+            }
+        }
+    }
+
+    /// Acquires an exclusive lock, spinning until the lock is available.
+    pub fn write(&self) -> GcRwLockWriteGuard<'_, T> {
+        let mut spins = 0;
+        loop {
+            if let Some(guard) = self.try_write() {
+                return guard;
+            }
+            relax(&mut spins);
+        }
+    }
+
+    /// Acquires an exclusive lock if the lock is available.
+    pub fn try_write(&self) -> Option<GcRwLockWriteGuard<'_, T>> {
+        self.state
+            .compare_exchange(0, WRITE_LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| GcRwLockWriteGuard { lock: self })
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+impl<T: Default> Default for GcRwLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for GcRwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("GcRwLock");
+        match self.try_read() {
+            Some(guard) => d.field("data", &&*guard),
+            None => d.field("data", &format_args!("<locked>")),
+        };
+        d.finish_non_exhaustive()
+    }
+}
+
+/// SAFETY: tracing does not overlap with writes to the data, see the [module documentation](self).
+unsafe impl<T: ?Sized + Trace> Trace for GcRwLock<T> {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        // SAFETY: see above
+        unsafe { (*self.data.get()).trace(ctx) }
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        self.get_mut().trace_edges(ctx);
+    }
+}
+
+/// Shared access to the data protected by a [`GcRwLock`]. The lock is released when the guard is dropped.
+pub struct GcRwLockReadGuard<'a, T: ?Sized> {
+    lock: &'a GcRwLock<T>,
+}
+
+// SAFETY: sharing the guard only gives shared access to the data.
+unsafe impl<T: ?Sized + Sync> Sync for GcRwLockReadGuard<'_, T> {}
+
+impl<T: ?Sized> Deref for GcRwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: a shared lock is held
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for GcRwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.state.fetch_sub(1, Ordering::Release);
+    }
+}
+
+/// Exclusive access to the data protected by a [`GcRwLock`]. The lock is released when the guard is dropped.
+pub struct GcRwLockWriteGuard<'a, T: ?Sized> {
+    lock: &'a GcRwLock<T>,
+}
+
+// SAFETY: sharing the guard only gives shared access to the data.
+unsafe impl<T: ?Sized + Sync> Sync for GcRwLockWriteGuard<'_, T> {}
+
+impl<T: ?Sized> Deref for GcRwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: the exclusive lock is held
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for GcRwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: the exclusive lock is held. As for `GcMutexGuard`, the reference is reborrowed on each call.
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for GcRwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.state.store(0, Ordering::Release);
+    }
+}
+
+/// Converts a raw handle stored in an atomic into a `Gc`.
+fn gc_from_raw<T>(raw: usize) -> Option<Gc<T>> {
+    Handle::new(raw).map(|handle| Gc {
+        handle,
+        metadata: (),
+        _ph: PhantomData,
+    })
+}
+
+/// Invokes the write barrier for `value` having been stored into `parent`. Callers check that the slot is part of
+/// `parent` with [`assert_within`] before storing, so that the barrier is never skipped for a completed store.
+fn write_barrier<S, P>(parent: &Root<'_, S, P>, value: Option<Gc<impl Sized>>)
+where
+    S: ?Sized + GcStrategy,
+    P: ?Sized + GcPointee,
+{
+    if let Some(value) = value {
+        parent
+            .gc
+            .write_barrier(parent.handle.strategy_handle(), value.strategy_handle());
+    }
+}
+
+/// A `Gc` that can be atomically swapped, and that invokes the write barrier when written.
+///
+/// As with [`GcCell`](crate::cell::GcCell), writes take a root of the object that contains the `AtomicGc`.
+pub struct AtomicGc<T> {
+    handle: AtomicUsize,
+    _ph: PhantomData<Gc<T>>,
+}
+
+impl<T> AtomicGc<T> {
+    pub const fn new(gc: Gc<T>) -> Self {
+        Self {
+            handle: AtomicUsize::new(gc.handle.get()),
+            _ph: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> Gc<T> {
+        Self::from_raw(self.handle.into_inner())
+    }
+
+    fn from_raw(raw: usize) -> Gc<T> {
+        // SAFETY: only handles of `Gc`s are stored
+        unsafe { gc_from_raw(raw).unwrap_unchecked() }
+    }
+
+    pub fn load(&self, order: Ordering) -> Gc<T> {
+        Self::from_raw(self.handle.load(order))
+    }
+
+    /// Stores a `Gc`. `parent` is a root of the object containing this `AtomicGc`.
+    ///
+    /// # Panics
+    /// Panics if this `AtomicGc` is not part of `parent`.
+    pub fn store<S, P>(&self, parent: &Root<'_, S, P>, gc: Gc<T>, order: Ordering)
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+    {
+        assert_within(self, parent);
+        self.handle.store(gc.handle.get(), order);
+        write_barrier(parent, Some(gc));
+    }
+
+    /// Stores a `Gc`, returning the previous one. See [`AtomicGc::store`].
+    pub fn swap<S, P>(&self, parent: &Root<'_, S, P>, gc: Gc<T>, order: Ordering) -> Gc<T>
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+    {
+        assert_within(self, parent);
+        let old = self.handle.swap(gc.handle.get(), order);
+        write_barrier(parent, Some(gc));
+        Self::from_raw(old)
+    }
+
+    /// Stores `new` if the current value refers to the same object as `current`. Returns the previous value, which
+    /// is `current` on success. See [`AtomicGc::store`].
+    pub fn compare_exchange<S, P>(
+        &self,
+        parent: &Root<'_, S, P>,
+        current: Gc<T>,
+        new: Gc<T>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Gc<T>, Gc<T>>
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+    {
+        assert_within(self, parent);
+        match self
+            .handle
+            .compare_exchange(current.handle.get(), new.handle.get(), success, failure)
+        {
+            Ok(old) => {
+                write_barrier(parent, Some(new));
+                Ok(Self::from_raw(old))
+            }
+            Err(old) => Err(Self::from_raw(old)),
+        }
+    }
+}
+
+impl<T> fmt::Debug for AtomicGc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.load(Ordering::Relaxed), f)
+    }
+}
+
+/// SAFETY: the handle is loaded atomically.
+unsafe impl<T> Trace for AtomicGc<T> {
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        ctx.accept(self.load(Ordering::Acquire));
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        let mut gc = Self::from_raw(*self.handle.get_mut());
+        ctx.update(&mut gc);
+        *self.handle.get_mut() = gc.handle.get();
+    }
+}
+
+/// An optional `Gc` that can be atomically swapped, and that invokes the write barrier when written. See
+/// [`AtomicGc`].
+pub struct AtomicOptionGc<T> {
+    /// The handle, or 0 if empty.
+    handle: AtomicUsize,
+    _ph: PhantomData<Gc<T>>,
+}
+
+impl<T> AtomicOptionGc<T> {
+    pub const fn new(gc: Option<Gc<T>>) -> Self {
+        Self {
+            handle: AtomicUsize::new(Self::to_raw(gc)),
+            _ph: PhantomData,
+        }
+    }
+
+    pub const fn none() -> Self {
+        Self::new(None)
+    }
+
+    pub fn into_inner(self) -> Option<Gc<T>> {
+        gc_from_raw(self.handle.into_inner())
+    }
+
+    const fn to_raw(gc: Option<Gc<T>>) -> usize {
+        match gc {
+            Some(gc) => gc.handle.get(),
+            None => 0,
+        }
+    }
+
+    pub fn load(&self, order: Ordering) -> Option<Gc<T>> {
+        gc_from_raw(self.handle.load(order))
+    }
+
+    /// Stores a `Gc`, or clears the slot. See [`AtomicGc::store`].
+    pub fn store<S, P>(&self, parent: &Root<'_, S, P>, gc: Option<Gc<T>>, order: Ordering)
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+    {
+        assert_within(self, parent);
+        self.handle.store(Self::to_raw(gc), order);
+        write_barrier(parent, gc);
+    }
+
+    /// Stores a `Gc` or clears the slot, returning the previous value. See [`AtomicGc::store`].
+    pub fn swap<S, P>(
+        &self,
+        parent: &Root<'_, S, P>,
+        gc: Option<Gc<T>>,
+        order: Ordering,
+    ) -> Option<Gc<T>>
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+    {
+        assert_within(self, parent);
+        let old = self.handle.swap(Self::to_raw(gc), order);
+        write_barrier(parent, gc);
+        gc_from_raw(old)
+    }
+
+    /// Stores `new` if the current value is `current`. See [`AtomicGc::compare_exchange`].
+    pub fn compare_exchange<S, P>(
+        &self,
+        parent: &Root<'_, S, P>,
+        current: Option<Gc<T>>,
+        new: Option<Gc<T>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Option<Gc<T>>, Option<Gc<T>>>
+    where
+        S: ?Sized + GcStrategy,
+        P: ?Sized + GcPointee,
+    {
+        assert_within(self, parent);
+        match self.handle.compare_exchange(
+            Self::to_raw(current),
+            Self::to_raw(new),
+            success,
+            failure,
+        ) {
+            Ok(old) => {
+                write_barrier(parent, new);
+                Ok(gc_from_raw(old))
+            }
+            Err(old) => Err(gc_from_raw(old)),
+        }
+    }
+}
+
+impl<T> Default for AtomicOptionGc<T> {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl<T> fmt::Debug for AtomicOptionGc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.load(Ordering::Relaxed), f)
+    }
+}
+
+/// SAFETY: the handle is loaded atomically.
+unsafe impl<T> Trace for AtomicOptionGc<T> {
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        if let Some(gc) = self.load(Ordering::Acquire) {
+            ctx.accept(gc);
+        }
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        if let Some(mut gc) = gc_from_raw::<T>(*self.handle.get_mut()) {
+            ctx.update(&mut gc);
+            *self.handle.get_mut() = gc.handle.get();
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{
+        decorators::StressStrategy,
+        heap::{CollectOptions, CollectionKind},
+        strategies::{Generational, MarkSweep},
+        testing::Node,
+        GcHeap,
+    };
+
+    #[test]
+    fn tracing_ignores_the_lock() {
+        let drops = AtomicUsize::new(0);
+        let heap = GcHeap::new(StressStrategy::new(MarkSweep::new()));
+        let mutex = heap.alloc(GcMutex::new(None));
+        let lock = heap.alloc(GcRwLock::new(None));
+        {
+            let (mut guard, mut write) = (mutex.lock(), lock.write());
+            // each allocation collects while the locks are held
+            *guard = Some(heap.alloc(Node::new(&drops, 1, None)).gc());
+            *write = Some(heap.alloc(Node::new(&drops, 2, None)).gc());
+            heap.collect();
+            assert_eq!(drops.load(Ordering::Relaxed), 0);
+        }
+        {
+            let _read = lock.read();
+            heap.collect();
+        }
+        assert_eq!(drops.load(Ordering::Relaxed), 0);
+        *mutex.lock() = None;
+        *lock.write() = None;
+        heap.collect();
+        assert_eq!(drops.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn atomics_swap_handles() {
+        let heap = GcHeap::new(MarkSweep::new());
+        let (one, two) = (heap.alloc(1u32).gc(), heap.alloc(2u32).gc());
+        let atomic = heap.alloc(AtomicGc::new(one));
+        atomic.store(&atomic, two, Ordering::Relaxed);
+        assert_eq!(atomic.load(Ordering::Relaxed), two);
+        assert_eq!(atomic.swap(&atomic, one, Ordering::Relaxed), two);
+        assert_eq!(
+            atomic.compare_exchange(&atomic, two, one, Ordering::Relaxed, Ordering::Relaxed),
+            Err(one)
+        );
+        assert_eq!(
+            atomic.compare_exchange(&atomic, one, two, Ordering::Relaxed, Ordering::Relaxed),
+            Ok(one)
+        );
+        assert_eq!(atomic.load(Ordering::Relaxed), two);
+
+        let option = heap.alloc(AtomicOptionGc::<u32>::none());
+        assert_eq!(option.swap(&option, Some(one), Ordering::Relaxed), None);
+        assert_eq!(
+            option.compare_exchange(
+                &option,
+                None,
+                Some(two),
+                Ordering::Relaxed,
+                Ordering::Relaxed
+            ),
+            Err(Some(one))
+        );
+        assert_eq!(
+            option.compare_exchange(
+                &option,
+                Some(one),
+                None,
+                Ordering::Relaxed,
+                Ordering::Relaxed
+            ),
+            Ok(Some(one))
+        );
+        option.store(&option, Some(two), Ordering::Relaxed);
+        assert_eq!(option.load(Ordering::Relaxed), Some(two));
+    }
+
+    #[test]
+    fn atomic_stores_invoke_the_write_barrier() {
+        let drops = AtomicUsize::new(0);
+        let heap = GcHeap::new(Generational::new());
+        let atomic = heap.alloc_old(AtomicGc::new(
+            heap.alloc_old(Node::new(&drops, 0, None)).gc(),
+        ));
+        let option = heap.alloc_old(AtomicOptionGc::none());
+        // the atomics are only reachable from an old object, so minor collections do not trace them
+        let holder = heap.alloc_old((atomic.gc(), option.gc()));
+        let minor = CollectOptions {
+            kind: CollectionKind::Minor,
+            ..CollectOptions::default()
+        };
+        // objects allocated in the old space are remembered until their first minor collection
+        heap.collect_with(minor);
+        atomic.store(
+            &atomic,
+            heap.alloc(Node::new(&drops, 1, None)).gc(),
+            Ordering::Relaxed,
+        );
+        option.store(
+            &option,
+            Some(heap.alloc(Node::new(&drops, 2, None)).gc()),
+            Ordering::Relaxed,
+        );
+        drop((atomic, option));
+        heap.collect_with(minor);
+        // only the node first stored in the `AtomicGc` is unreachable, and minor collections do not reclaim it
+        assert_eq!(drops.load(Ordering::Relaxed), 0);
+        // SAFETY: the atomics are read from an object that is rooted
+        let (atomic, option) = unsafe { (heap.root(holder.0), heap.root(holder.1)) };
+        // SAFETY: as above
+        let (first, second) = unsafe {
+            (
+                heap.root(atomic.load(Ordering::Relaxed)),
+                heap.root(option.load(Ordering::Relaxed).unwrap()),
+            )
+        };
+        assert_eq!((first.value, second.value), (1, 2));
+    }
+
+    #[test]
+    #[should_panic = "cell is not part of the given parent object"]
+    fn atomic_stores_check_the_parent() {
+        let heap = GcHeap::new(MarkSweep::new());
+        let gc = heap.alloc(1u32).gc();
+        let (atomic, other) = (heap.alloc(AtomicOptionGc::none()), heap.alloc(0u32));
+        atomic.store(&other, Some(gc), Ordering::Relaxed);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn atomic_stores_to_the_wrong_parent_do_not_happen() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let heap = GcHeap::new(MarkSweep::new());
+        let (one, two) = (heap.alloc(1u32).gc(), heap.alloc(2u32).gc());
+        let (atomic, other) = (heap.alloc(AtomicGc::new(one)), heap.alloc(0u32));
+        let attempts: [&dyn Fn(); 3] = [
+            &|| atomic.store(&other, two, Ordering::Relaxed),
+            &|| {
+                atomic.swap(&other, two, Ordering::Relaxed);
+            },
+            &|| {
+                let _ =
+                    atomic.compare_exchange(&other, one, two, Ordering::Relaxed, Ordering::Relaxed);
+            },
+        ];
+        for attempt in attempts {
+            assert!(catch_unwind(AssertUnwindSafe(attempt)).is_err());
+            assert_eq!(atomic.load(Ordering::Relaxed), one);
+        }
+    }
+}
@@ -0,0 +1,491 @@
+//! Conformance tests for strategy implementations.
+//!
+//! Implementing [`GcStrategy`] correctly is hard, and most mistakes show up as memory corruption far from their
+//! cause. The functions in this module exercise a strategy through a [`GcHeap`] and panic with a description of the
+//! first violation of the strategy contract they observe. Strategy authors call them from their own test suites,
+//! either one at a time or all together with [`run_all`], e.g. `cgc_strategy::testing::run_all(MyStrategy::new)`.
+//!
+//! The tests cover:
+//! - [`allocation`]: fresh objects are distinct, suitably aligned, and belong to the strategy.
+//! - [`lifecycle`]: objects are not dropped before they are initialized, and are dropped at most once.
+//! - [`root_counting`]: an object stays alive for as long as any of its roots does.
+//! - [`pin_stability`]: a pinned object does not move.
+//! - [`trace_completeness`]: objects reachable from a root stay alive, however they are reached.
+//! - [`reentrant_finalizers`]: objects are not reclaimed while being finalized, even by a collection started by a
+//!   finalizer.
+//!
+//! Each test runs every kind of collection the heap supports, including compacting ones, so strategies that ignore
+//! some of them are still tested meaningfully. The tests can only observe reclamation through destructors, so they
+//! check that live objects are never reclaimed, but not that garbage eventually is; a strategy that never collects
+//! passes them. Wrap the strategy in a [`ValidatingStrategy`](crate::decorators::ValidatingStrategy) to also check
+//! the handles and addresses it returns.
+
+#[cfg(feature = "alloc")]
+use alloc::rc::{Rc, Weak};
+use core::{
+    any::TypeId,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    heap::{CollectOptions, CollectionKind, GcStrategy},
+    sync::AtomicOptionGc,
+    trace::{EdgeContext, Trace, TraceContext},
+    Gc, GcHeap, Root,
+};
+
+/// Runs every conformance test in this module, each with a fresh strategy created by `make`.
+pub fn run_all<S: GcStrategy>(mut make: impl FnMut() -> S) {
+    allocation(make());
+    lifecycle(make());
+    root_counting(make());
+    pin_stability(make());
+    trace_completeness(make());
+    #[cfg(feature = "alloc")]
+    reentrant_finalizers(make());
+}
+
+/// Checks that fresh objects are distinct, suitably aligned, and belong to the strategy, for sized types of various
+/// alignments, zero-sized types, slices, and strings, and for objects allocated by a registered thread.
+pub fn allocation<S: GcStrategy>(strategy: S) {
+    let heap = GcHeap::new(strategy);
+    let byte = heap.alloc(0xa5u8);
+    let word = heap.alloc(0x0123_4567_89ab_cdefu64);
+    let aligned = heap.alloc(Overaligned(42));
+    let unit = heap.alloc(());
+    let empty = heap.alloc_slice::<u32>(&[]);
+    let slice = heap.alloc_from_iter(0..1000u32);
+    let string = heap.alloc_str("conformance");
+    let thread = heap.register_thread();
+    let local = thread.alloc(0xfedc_ba98_7654_3210u64);
+    drop(thread);
+
+    check_allocated(&heap, &byte);
+    check_allocated(&heap, &word);
+    check_allocated(&heap, &aligned);
+    check_allocated(&heap, &unit);
+    check_allocated(&heap, &empty);
+    check_allocated(&heap, &slice);
+    check_allocated(&heap, &string);
+    check_allocated(&heap, &local);
+
+    let gcs = [
+        byte.gc().__handle(),
+        word.gc().__handle(),
+        aligned.gc().__handle(),
+        unit.gc().__handle(),
+        empty.gc().__handle(),
+        slice.gc().__handle(),
+        string.gc().__handle(),
+        local.gc().__handle(),
+    ];
+    for (i, a) in gcs.iter().enumerate() {
+        for b in &gcs[i + 1..] {
+            assert_ne!(a, b, "two live objects have the same handle");
+        }
+    }
+    let vtable = heap.strategy().vtable(word.gc().strategy_handle());
+    assert!(
+        vtable.type_id() == TypeId::of::<u64>(),
+        "the strategy returned the wrong vtable for an object"
+    );
+
+    collect_all(&heap);
+    assert_eq!(*byte, 0xa5, "a u8 object was corrupted");
+    assert_eq!(*word, 0x0123_4567_89ab_cdef, "a u64 object was corrupted");
+    assert_eq!(aligned.0, 42, "an overaligned object was corrupted");
+    assert_eq!(empty.len(), 0, "an empty slice was corrupted");
+    assert!(
+        slice.iter().copied().eq(0..1000),
+        "a slice object was corrupted"
+    );
+    assert_eq!(&*string, "conformance", "a str object was corrupted");
+    assert_eq!(
+        *local, 0xfedc_ba98_7654_3210,
+        "an object allocated by a registered thread was corrupted"
+    );
+}
+
+/// Checks that objects are not dropped while being initialized, are dropped immediately when disposed of, and are
+/// dropped at most once, including when the heap itself is dropped.
+pub fn lifecycle<S: GcStrategy>(strategy: S) {
+    let drops = AtomicUsize::new(0);
+    let heap = GcHeap::new(strategy);
+
+    // an object under construction must not be traced or reclaimed
+    let node = heap.alloc_with(|place| {
+        collect_all(&heap);
+        place.write(Node::new(&drops, 1, None))
+    });
+    collect_all(&heap);
+    assert_eq!(
+        drops.load(Ordering::Relaxed),
+        0,
+        "an object was dropped while being initialized"
+    );
+    assert_eq!(
+        node.value, 1,
+        "an object allocated during a collection was corrupted"
+    );
+
+    let gc = node.gc();
+    drop(node);
+    // SAFETY: the object is no longer rooted, and not accessed again
+    unsafe { heap.dispose(gc) };
+    assert_eq!(
+        drops.load(Ordering::Relaxed),
+        1,
+        "disposing of an object did not drop it"
+    );
+    collect_all(&heap);
+    assert_eq!(
+        drops.load(Ordering::Relaxed),
+        1,
+        "a disposed object was dropped again"
+    );
+
+    for i in 0..100 {
+        heap.alloc(Node::new(&drops, i, None));
+    }
+    collect_all(&heap);
+    drop(heap);
+    assert!(
+        drops.load(Ordering::Relaxed) <= 101,
+        "an object was dropped more than once"
+    );
+}
+
+/// Checks that an object stays alive for as long as any of its roots does, however the roots are created, and
+/// that frozen objects stay alive without any roots.
+pub fn root_counting<S: GcStrategy>(strategy: S) {
+    let drops = AtomicUsize::new(0);
+    let heap = GcHeap::new(strategy);
+
+    // roots created by cloning
+    let first = heap.alloc(Node::new(&drops, 1, None));
+    let second = first.clone();
+    let third = second.clone();
+    drop(first);
+    drop(second);
+    collect_all(&heap);
+    check_alive(&drops, &third, 1);
+
+    // roots created from a handle
+    // SAFETY: the object is rooted by `third`
+    let fourth = unsafe { heap.root(third.gc()) };
+    drop(third);
+    collect_all(&heap);
+    check_alive(&drops, &fourth, 1);
+
+    // roots created in bulk
+    let gc = fourth.gc();
+    let handle = gc.strategy_handle();
+    heap.strategy().root_all(&[handle, handle]);
+    drop(fourth);
+    collect_all(&heap);
+    assert_eq!(
+        drops.load(Ordering::Relaxed),
+        0,
+        "an object was dropped while rooted in bulk"
+    );
+    // SAFETY: the object is rooted in bulk
+    let fifth = unsafe { heap.root(gc) };
+    heap.strategy().unroot_all(&[handle, handle]);
+    collect_all(&heap);
+    check_alive(&drops, &fifth, 1);
+
+    // frozen objects have no roots
+    let frozen = heap.alloc(Node::new(&drops, 2, None));
+    heap.freeze(&frozen);
+    drop(frozen);
+    collect_all(&heap);
+    assert_eq!(
+        drops.load(Ordering::Relaxed),
+        0,
+        "a frozen object was dropped"
+    );
+    drop(fifth);
+}
+
+/// Checks that a pinned object keeps its address across collections, that every way of pinning it agrees on the
+/// address, and that it is intact once unpinned again.
+pub fn pin_stability<S: GcStrategy>(strategy: S) {
+    let drops = AtomicUsize::new(0);
+    #[cfg(feature = "alloc")]
+    let other_drops = AtomicUsize::new(0);
+    let heap = GcHeap::new(strategy);
+    let root = heap.alloc(Node::new(&drops, 1, None));
+
+    let pinned = root.pin();
+    let addr = pinned.as_ptr();
+    collect_all(&heap);
+    assert_eq!(pinned.as_ptr(), addr, "a pinned object moved");
+    assert_eq!(
+        root.pin().as_ptr(),
+        addr,
+        "pinning an object twice gave different addresses"
+    );
+    assert_eq!(
+        root.as_ptr(),
+        addr,
+        "resolving a pinned object gave a different address"
+    );
+    check_alive(&drops, &root, 1);
+    drop(pinned);
+
+    let long_lived = root.pin_ptr();
+    let addr = long_lived.as_ptr();
+    collect_all(&heap);
+    assert_eq!(
+        long_lived.as_ptr(),
+        addr,
+        "an object pinned for the long term moved"
+    );
+    assert_eq!(
+        root.as_ptr(),
+        addr,
+        "resolving a pinned object gave a different address"
+    );
+    assert_eq!(
+        long_lived.value, 1,
+        "an object pinned for the long term was corrupted"
+    );
+    drop(long_lived);
+
+    #[cfg(feature = "alloc")]
+    {
+        let other = heap.alloc(Node::new(&other_drops, 2, None));
+        heap.pin_scope(|scope| {
+            let [a, b] = scope.pin_all([&root, &other]);
+            collect_all(&heap);
+            assert!(
+                core::ptr::eq(a, root.as_ptr()),
+                "an object pinned in bulk moved"
+            );
+            assert!(
+                core::ptr::eq(b, other.as_ptr()),
+                "an object pinned in bulk moved"
+            );
+            assert_eq!(
+                (a.value, b.value),
+                (1, 2),
+                "an object pinned in bulk was corrupted"
+            );
+        });
+    }
+
+    // unpinned objects may move, but must stay intact
+    collect_all(&heap);
+    check_alive(&drops, &root, 1);
+}
+
+/// Checks that objects reachable from a root stay alive and intact, whether they are reached through a long chain of
+/// objects, through a slice, through a cycle, or through an edge stored after both objects were allocated.
+pub fn trace_completeness<S: GcStrategy>(strategy: S) {
+    // each case gets its own counter, as objects from earlier cases may be dropped during later ones
+    let [chain, slice, cycle, edge]: [AtomicUsize; 4] = Default::default();
+    let heap = GcHeap::new(strategy);
+
+    // a long chain, rooted only at its head
+    let mut head = heap.alloc(Node::new(&chain, 0, None));
+    for i in 1..100 {
+        head = heap.alloc(Node::new(&chain, i, Some(head.gc())));
+    }
+    collect_all(&heap);
+    let mut node = head;
+    for i in (0..100).rev() {
+        check_alive(&chain, &node, i);
+        match node.next.load(Ordering::Relaxed) {
+            // SAFETY: the object is reachable from `node`
+            Some(next) => node = unsafe { heap.root(next) },
+            None => assert_eq!(i, 0, "an edge was lost"),
+        }
+    }
+    drop(node);
+
+    // a slice of edges
+    let nodes: [_; 10] = core::array::from_fn(|i| heap.alloc(Node::new(&slice, i, None)));
+    let gcs = heap.alloc_from_iter(nodes.iter().map(Root::gc));
+    drop(nodes);
+    collect_all(&heap);
+    for (i, &gc) in gcs.iter().enumerate() {
+        // SAFETY: the object is reachable from `gcs`
+        check_alive(&slice, &unsafe { heap.root(gc) }, i);
+    }
+    drop(gcs);
+
+    // a cycle
+    let node = heap.alloc_cyclic(|gc| Node::new(&cycle, 7, Some(gc)));
+    collect_all(&heap);
+    check_alive(&cycle, &node, 7);
+    let next = node.next.load(Ordering::Relaxed);
+    assert!(next == Some(node.gc()), "a self-edge was corrupted");
+    drop(node);
+
+    // an edge from an old object to a young one, stored after both were allocated
+    let parent = heap.alloc_old(Node::new(&edge, 1, None));
+    collect_all(&heap);
+    let child = heap.alloc(Node::new(&edge, 2, None));
+    parent
+        .next
+        .store(&parent, Some(child.gc()), Ordering::Relaxed);
+    drop(child);
+    collect_all(&heap);
+    check_alive(&edge, &parent, 1);
+    let child = parent
+        .next
+        .load(Ordering::Relaxed)
+        .expect("an edge was lost");
+    // SAFETY: the object is reachable from `parent`
+    check_alive(&edge, &unsafe { heap.root(child) }, 2);
+}
+
+/// Checks that objects are neither reclaimed nor moved while they are being finalized, and are finalized exactly
+/// once, when their finalizers collect and allocate in the same heap.
+#[cfg(feature = "alloc")]
+pub fn reentrant_finalizers<S: GcStrategy>(strategy: S) {
+    let drops = AtomicUsize::new(0);
+    let heap = Rc::new(GcHeap::new(strategy));
+    for value in 0..100 {
+        heap.alloc_local(Reentrant {
+            heap: Rc::downgrade(&heap),
+            drops: &drops,
+            value,
+        });
+    }
+    collect_all(&heap);
+    assert!(
+        drops.load(Ordering::Relaxed) <= 100,
+        "an object was dropped more than once"
+    );
+    drop(heap);
+    assert_eq!(
+        drops.load(Ordering::Relaxed),
+        100,
+        "an object was dropped more than once, or never"
+    );
+}
+
+/// Runs every kind of collection, with and without compaction.
+fn collect_all<S: GcStrategy>(heap: &GcHeap<'_, S>) {
+    for kind in [
+        CollectionKind::Minor,
+        CollectionKind::Major,
+        CollectionKind::Full,
+    ] {
+        for compact in [false, true] {
+            heap.collect_with(CollectOptions { kind, compact });
+        }
+    }
+}
+
+/// Checks the address and ownership of a fresh object.
+fn check_allocated<S: GcStrategy, T: ?Sized + crate::ptr::GcPointee>(
+    heap: &GcHeap<'_, S>,
+    root: &Root<'_, S, T>,
+) {
+    assert!(
+        heap.contains(root.gc()),
+        "a fresh object does not belong to the heap"
+    );
+    let ptr = root.as_ptr();
+    assert!(!ptr.is_null(), "a fresh object has a null address");
+    // SAFETY: the object is rooted and initialized
+    let align = core::mem::align_of_val(unsafe { &*ptr });
+    assert!(
+        (ptr.cast::<()>() as usize).is_multiple_of(align),
+        "a fresh object is not aligned to {align} bytes"
+    );
+}
+
+/// Checks that a rooted node is intact and that no node has been dropped.
+fn check_alive<S: GcStrategy>(drops: &AtomicUsize, node: &Root<'_, S, Node<'_>>, value: usize) {
+    assert_eq!(
+        drops.load(Ordering::Relaxed),
+        0,
+        "a reachable object was dropped"
+    );
+    assert_eq!(node.value, value, "a reachable object was corrupted");
+}
+
+/// A GC object that collects and allocates in its own heap when it is dropped, unless the heap is being dropped.
+#[cfg(feature = "alloc")]
+struct Reentrant<'a, S: GcStrategy> {
+    heap: Weak<GcHeap<'a, S>>,
+    drops: &'a AtomicUsize,
+    value: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl<S: GcStrategy> Drop for Reentrant<'_, S> {
+    fn drop(&mut self) {
+        let value = self.value;
+        if let Some(heap) = self.heap.upgrade() {
+            collect_all(&heap);
+            // reuse any memory the collection freed
+            for _ in 0..10 {
+                let filler = heap.alloc([usize::MAX; 8]);
+                assert_eq!(
+                    filler[7],
+                    usize::MAX,
+                    "an object allocated by a finalizer was corrupted"
+                );
+            }
+        }
+        assert_eq!(
+            self.value, value,
+            "an object was reclaimed while being finalized"
+        );
+        self.drops.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// SAFETY: the object refers to no GC objects
+#[cfg(feature = "alloc")]
+unsafe impl<S: GcStrategy> Trace for Reentrant<'_, S> {
+    const NEEDS_TRACE: bool = false;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        let _ = ctx;
+    }
+}
+
+/// A value with a stricter alignment than any primitive.
+#[repr(align(64))]
+struct Overaligned(u64);
+
+crate::unsafe_empty_trace! { Overaligned }
+
+/// A GC object that counts its drops and may refer to another.
+pub(crate) struct Node<'a> {
+    drops: &'a AtomicUsize,
+    pub(crate) value: usize,
+    pub(crate) next: AtomicOptionGc<Node<'a>>,
+}
+
+impl<'a> Node<'a> {
+    pub(crate) fn new(drops: &'a AtomicUsize, value: usize, next: Option<Gc<Node<'a>>>) -> Self {
+        Self {
+            drops,
+            value,
+            next: AtomicOptionGc::new(next),
+        }
+    }
+}
+
+impl Drop for Node<'_> {
+    fn drop(&mut self) {
+        self.drops.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// SAFETY: the only GC object reachable from a node is its successor
+unsafe impl Trace for Node<'_> {
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        crate::trace_fields!(self, ctx => next);
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        crate::trace_fields!(mut self, ctx => next);
+    }
+}
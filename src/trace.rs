@@ -1,14 +1,159 @@
 use core::marker::{PhantomData, PhantomPinned};
 
-use crate::{heap::Handle, Gc};
+use crate::{heap::Handle, ptr::GcPointee, Gc};
+
+/// A visitor for the GC handles reachable from GC objects, implemented by strategies.
+///
+/// Strategies pass a visitor to [`GcVtable::trace`](crate::heap::GcVtable::trace) and
+/// [`GcVtable::trace_edges`](crate::heap::GcVtable::trace_edges) through a [`TraceContext`] or [`EdgeContext`].
+/// The visitor is accessed mutably, so it may keep its mark stack and other state inline without interior mutability.
+///
+/// Handles are visited in their raw form, as stored in a [`Gc`]. Strategies with their own handle type convert them
+/// with [`StrategyHandle::from_raw`](crate::heap::StrategyHandle::from_raw).
+pub trait GcVisitor {
+    /// Visits a single GC handle.
+    fn visit(&mut self, handle: Handle);
+
+    /// Visits a batch of GC handles. Strategies may override this to mark in bulk.
+    fn visit_slice(&mut self, handles: &[Handle]) {
+        for &handle in handles {
+            self.visit(handle);
+        }
+    }
+
+    /// Visits a GC handle that may be updated in place. This is only called while updating edges.
+    ///
+    /// The default implementation treats the edge as fixed and forwards it to [`GcVisitor::visit`].
+    fn visit_edge(&mut self, handle: &mut Handle) {
+        self.visit(*handle);
+    }
+
+    /// Receives the size of memory outside the GC heap that is owned by the object being traced, such as large
+    /// `malloc`'d buffers. Strategies can sum these reports over all live objects to account for the true memory
+    /// pressure when pacing collections.
+    ///
+    /// The default implementation ignores the report.
+    fn visit_external_memory(&mut self, bytes: usize) {
+        let _ = bytes;
+    }
+
+    /// Visits a range of words, any of which may be a GC handle. The strategy must determine which words are valid
+    /// handles (or point into valid GC objects) and mark those objects. Objects found this way must also be treated
+    /// as pinned, as the words cannot be updated if the objects move.
+    ///
+    /// The default implementation panics, as a strategy that does not recognize conservative references would
+    /// otherwise reclaim objects that are still in use.
+    fn visit_conservative(&mut self, words: &[usize]) {
+        let _ = words;
+        panic!("this strategy does not support conservative scanning");
+    }
+}
+
+impl<F: FnMut(Handle)> GcVisitor for F {
+    fn visit(&mut self, handle: Handle) {
+        self(handle);
+    }
+}
 
 pub struct TraceContext<'a> {
-    gc_visitor: &'a dyn Fn(Handle),
+    visitor: &'a mut dyn GcVisitor,
 }
 
-impl TraceContext<'_> {
-    pub fn accept<T: ?Sized>(&self, gc: Gc<T>) {
-        (self.gc_visitor)(gc.handle);
+impl<'a> TraceContext<'a> {
+    /// The number of handles [`TraceContext::accept_all`] buffers before passing them to the visitor.
+    const BATCH_SIZE: usize = 64;
+
+    /// Creates a trace context that passes visited GC handles to the given visitor.
+    pub fn new(visitor: &'a mut dyn GcVisitor) -> Self {
+        Self { visitor }
+    }
+
+    pub fn accept<T: ?Sized + GcPointee>(&mut self, gc: Gc<T>) {
+        self.visitor.visit(gc.handle);
+    }
+
+    /// Visits a GC object that is not referenced through a `Gc`, such as a weak cell.
+    pub(crate) fn accept_handle(&mut self, handle: Handle) {
+        self.visitor.visit(handle);
+    }
+
+    /// Reports that the object being traced owns `bytes` of memory outside the GC heap. This should be called on
+    /// every trace of the object, as strategies typically only count reports from the most recent collection.
+    pub fn report_external_memory(&mut self, bytes: usize) {
+        self.visitor.visit_external_memory(bytes);
+    }
+
+    /// Conservatively scans `len` words starting at `ptr`, treating every word as a potential GC handle. The strategy
+    /// filters out words that do not refer to GC objects, and keeps the rest alive and in place.
+    ///
+    /// This is intended for memory whose precise layout is unknown, such as foreign stack frames.
+    ///
+    /// # Safety
+    /// `ptr` must be valid for reads of `len` initialized, properly aligned words.
+    pub unsafe fn scan_conservative(&mut self, ptr: *const usize, len: usize) {
+        // SAFETY: caller
+        let words = unsafe { core::slice::from_raw_parts(ptr, len) };
+        self.visitor.visit_conservative(words);
+    }
+
+    /// Visits every GC object in the given slice. The handles are passed to the strategy in a single batch.
+    pub fn accept_slice<T>(&mut self, gcs: &[Gc<T>]) {
+        // SAFETY: `Gc<T>` is a `repr(C)` struct of a `Handle` and zero-sized fields for sized `T`.
+        let handles =
+            unsafe { core::slice::from_raw_parts(gcs.as_ptr().cast::<Handle>(), gcs.len()) };
+        self.visitor.visit_slice(handles);
+    }
+
+    /// Visits every GC object produced by the given iterator. The handles are passed to the strategy in batches.
+    pub fn accept_all<T: ?Sized + GcPointee>(&mut self, gcs: impl IntoIterator<Item = Gc<T>>) {
+        let mut batch = [Handle::MIN; Self::BATCH_SIZE];
+        let mut len = 0;
+        for gc in gcs {
+            batch[len] = gc.handle;
+            len += 1;
+            if len == Self::BATCH_SIZE {
+                self.visitor.visit_slice(&batch);
+                len = 0;
+            }
+        }
+        if len > 0 {
+            self.visitor.visit_slice(&batch[..len]);
+        }
+    }
+}
+
+/// The context for updating GC handles in place, used by moving strategies after relocating objects.
+///
+/// Edges are visited in one of two ways. Edges that are reachable through exclusive access are passed to
+/// [`GcVisitor::visit_edge`], which may overwrite the handle with the object's new handle. Edges that are only
+/// reachable through shared access (such as the contents of an `Rc`) cannot be updated, and are instead passed to
+/// [`GcVisitor::visit`] through the [fixed context](EdgeContext::fixed). The strategy must not relocate the targets of
+/// fixed edges.
+pub struct EdgeContext<'a> {
+    fixed: TraceContext<'a>,
+}
+
+impl<'a> EdgeContext<'a> {
+    /// Creates an edge context that passes visited edges to the given visitor.
+    pub fn new(visitor: &'a mut dyn GcVisitor) -> Self {
+        Self {
+            fixed: TraceContext::new(visitor),
+        }
+    }
+
+    /// Visits an edge that may be updated in place.
+    pub fn update<T: ?Sized + GcPointee>(&mut self, gc: &mut Gc<T>) {
+        self.fixed.visitor.visit_edge(&mut gc.handle);
+    }
+
+    /// Visits an edge to a GC object that is not referenced through a `Gc`, such as a weak cell.
+    pub(crate) fn update_handle(&mut self, handle: &mut Handle) {
+        self.fixed.visitor.visit_edge(handle);
+    }
+
+    /// The trace context for edges that cannot be updated. The targets of these edges must not be relocated.
+    pub fn fixed(&mut self) -> &mut TraceContext<'a> {
+        &mut self.fixed
     }
 }
 
@@ -18,8 +163,8 @@ impl TraceContext<'_> {
 /// This trait may be implemented on a variety of types, including non-`'static`, non-`Send`, and non-`Sync`
 /// types. However, not all types can implement this trait. At a minimum, all GC object types must allow the
 /// GC shared access to all nested GC objects at all times. This prevents types that give exclusive access
-/// to nested GC objects, such as mutexes, from being GC objects. That being said, types that contain only
-/// `Trace` types will generally be able to implement this trait.
+/// to nested GC objects, such as mutexes, from being GC objects (see [`sync`](crate::sync) for locks that can be GC
+/// objects). That being said, types that contain only `Trace` types will generally be able to implement this trait.
 ///
 /// ## Thread Safety
 /// The implementation of [`Trace::trace`] must be thread-safe, as the GC may invoke this method from a different thread
@@ -28,12 +173,16 @@ impl TraceContext<'_> {
 /// a thread-unsafe `Cell`, are still able to implement `Trace` because the thread-unsafe components are not used to access
 /// the pointed-to value.
 ///
+/// Concurrent access does not include writes through types that are not `Sync`, such as
+/// [`GcCell`](crate::cell::GcCell), as strategies must not trace an object while its owning thread may be writing to
+/// it. See [Tracing and Mutation](crate::heap::Collector#tracing-and-mutation).
+///
 /// ## Finalization
 /// GC object types should not rely on timely destruction. The drop glue of a GC object, if it exists, is called a
-/// _finalizer_, and may (or may not!) be invoked at any point after the object becomes unreachable. The GC does not directly
-/// invoke the finalizer of any GC object. Instead, the GC notifies an object's associated finalization queue that
-/// the object may be finalized. If a GC object is not registered with a finalization queue, then that object's finalizer
-/// will *not* be run before the object's storage is reclaimed.
+/// _finalizer_, and may (or may not!) be invoked at any point after the object becomes unreachable. If the object is
+/// registered with a finalization queue, the GC notifies the queue that the object may be finalized. Otherwise, the GC
+/// invokes the finalizer itself through [`GcVtable::finalize`](crate::heap::GcVtable::finalize), before the object's
+/// storage is reclaimed. Strategies may still leak objects without finalizing them, e.g. when the heap is destroyed.
 ///
 /// A GC object must not access nested GC objects from within its finalizer. If a finalizer attempts to root, pin, or otherwise
 /// access the data of any nested GC object, it will result in a panic.
@@ -42,115 +191,471 @@ impl TraceContext<'_> {
 /// Implementations must uphold the contracts of all methods and the trait. Failure to do so
 /// may result in memory corruption or other undefined behavior.
 pub unsafe trait Trace {
+    /// Whether values of this type may contain GC objects that need to be traced.
+    ///
+    /// Leaf types, such as numbers and strings, set this to `false`. The GC may then skip calling [`Trace::trace`]
+    /// for such values entirely, and strategies may allocate them in segregated storage that is never scanned.
+    /// Containers set this according to the types they contain.
+    ///
+    /// # Safety
+    /// If this is `false`, [`Trace::trace`] must not mark any GC objects.
+    const NEEDS_TRACE: bool = true;
+
     /// Mark all GC objects directly reachable from this object.
     ///
     /// # Warning
     /// This method is invoked by the GC in order to determine the reachability of GC objects.
     /// The GC may invoke this method while other threads are concurrently accessing `self` or its fields,
     /// **even if the type of this object is not `Sync`**. It may also invoke this method after any borrows this type
-    /// contains have expired.
-    fn trace(&self, ctx: &TraceContext<'_>);
+    /// contains have expired. The exception is the lifetime bound of the [`GcHeap`](crate::GcHeap) the object was
+    /// allocated in: every GC object outlives that lifetime, so borrows valid for it are valid whenever the GC traces.
+    fn trace(&self, ctx: &mut TraceContext<'_>);
+
+    /// Visit all GC handles directly reachable from this object, allowing a moving strategy to update them in place.
+    ///
+    /// The default implementation passes every edge to [`EdgeContext::fixed`] using [`Trace::trace`], which prevents
+    /// the strategy from relocating any objects directly reachable from this one. Types that can give exclusive access
+    /// to their nested GC handles should override this method to pass them to [`EdgeContext::update`] instead.
+    ///
+    /// # Warning
+    /// This method is only invoked while the GC has exclusive access to the object, such as while the world is
+    /// stopped. Every edge visited by [`Trace::trace`] must be visited by this method exactly once, either as an
+    /// updatable or a fixed edge.
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        self.trace(ctx.fixed());
+    }
 }
 
-macro_rules! empty_trace {
+/// A source of roots that are not held in [`Root`](crate::Root)s, such as the operand stack of an interpreter, a
+/// register file, or a table of handles given out to foreign code. Instead of rooting each object as it is stored,
+/// the embedder registers the provider with [`GcHeap::with_root_provider`](crate::GcHeap::with_root_provider), and
+/// the strategy enumerates its roots whenever it collects.
+///
+/// # Safety
+/// As with [`Trace::trace`], the strategy may call these methods from a different thread while other threads are
+/// accessing the provider, even if the provider is not `Sync`, so they must not access any thread-unsafe state.
+/// Every handle visited must refer to a live object of the heap the provider is registered with.
+pub unsafe trait RootProvider {
+    /// Mark all GC objects rooted by this provider.
+    fn trace_roots(&self, ctx: &mut TraceContext<'_>);
+
+    /// Visit all GC handles rooted by this provider, allowing a moving strategy to update them in place. This is only
+    /// invoked while the world is stopped, so providers that keep their handles in `Cell`s may update them through
+    /// [`Cell::as_ptr`](core::cell::Cell::as_ptr).
+    ///
+    /// The default implementation passes every root to [`EdgeContext::fixed`] using [`RootProvider::trace_roots`],
+    /// which prevents the strategy from relocating any of them.
+    fn trace_root_edges(&self, ctx: &mut EdgeContext<'_>) {
+        self.trace_roots(ctx.fixed());
+    }
+}
+
+/// Implements [`Trace`] for leaf types that contain no GC objects, such as newtypes over integers or FFI handles.
+/// Types are separated by whitespace, e.g. `unsafe_empty_trace! { MyId ffi::RawHandle }`.
+///
+/// # Safety
+/// This macro expands to `unsafe impl`s of [`Trace`] that do nothing. Invoking it is an assertion that no GC object
+/// is reachable from any value of the listed types. If a listed type (transitively) contains a [`Gc`](crate::Gc),
+/// the GC will not see it, and the pointed-to object may be reclaimed while still in use.
+#[macro_export]
+macro_rules! unsafe_empty_trace {
     ($($ty:ty)*) => {
         $(
-            unsafe impl Trace for $ty {
-                fn trace(&self, _: &TraceContext<'_>) {}
+            unsafe impl $crate::trace::Trace for $ty {
+                const NEEDS_TRACE: bool = false;
+
+                fn trace(&self, _: &mut $crate::trace::TraceContext<'_>) {}
             }
         )*
     };
 }
 
-empty_trace! {
-    u8 u16 u32 u64 u128
-    i8 i16 i32 i64 i128
+unsafe_empty_trace! {
+    u8 u16 u32 u64 u128 usize
+    i8 i16 i32 i64 i128 isize
     f32 f64
-    char str core::ffi::CStr
+    bool char str core::ffi::CStr
+    core::num::NonZeroU8 core::num::NonZeroU16 core::num::NonZeroU32
+    core::num::NonZeroU64 core::num::NonZeroU128 core::num::NonZeroUsize
+    core::num::NonZeroI8 core::num::NonZeroI16 core::num::NonZeroI32
+    core::num::NonZeroI64 core::num::NonZeroI128 core::num::NonZeroIsize
+    core::time::Duration
+    core::cmp::Ordering
+    core::net::IpAddr core::net::Ipv4Addr core::net::Ipv6Addr
+    core::net::SocketAddr core::net::SocketAddrV4 core::net::SocketAddrV6
     core::any::TypeId
     PhantomPinned
 }
-empty_trace! { () }
+unsafe_empty_trace! { () }
+
+#[cfg(target_has_atomic = "8")]
+unsafe_empty_trace! { core::sync::atomic::AtomicBool core::sync::atomic::AtomicU8 core::sync::atomic::AtomicI8 }
+#[cfg(target_has_atomic = "16")]
+unsafe_empty_trace! { core::sync::atomic::AtomicU16 core::sync::atomic::AtomicI16 }
+#[cfg(target_has_atomic = "32")]
+unsafe_empty_trace! { core::sync::atomic::AtomicU32 core::sync::atomic::AtomicI32 }
+#[cfg(target_has_atomic = "64")]
+unsafe_empty_trace! { core::sync::atomic::AtomicU64 core::sync::atomic::AtomicI64 }
+#[cfg(target_has_atomic = "ptr")]
+unsafe_empty_trace! { core::sync::atomic::AtomicUsize core::sync::atomic::AtomicIsize }
+
+#[cfg(feature = "alloc")]
+unsafe_empty_trace! { alloc::string::String alloc::ffi::CString }
 
 #[cfg(feature = "std")]
-empty_trace! {
+unsafe_empty_trace! {
     std::path::Path std::ffi::OsStr
-    String std::ffi::CString std::path::PathBuf std::ffi::OsString
+    std::path::PathBuf std::ffi::OsString
+    std::time::Instant std::time::SystemTime
+}
+
+/// Traces the listed fields of a value, for use in manual implementations of [`Trace`].
+///
+/// `trace_fields!(self, ctx => a, b, 0)` expands to a call to [`Trace::trace`] on each field, and is meant to be the
+/// body of [`Trace::trace`]. `trace_fields!(mut self, ctx => a, b, 0)` expands to a call to [`Trace::trace_edges`] on
+/// each field instead, and is meant to be the body of [`Trace::trace_edges`]. Every field that may contain GC objects
+/// must be listed.
+#[macro_export]
+macro_rules! trace_fields {
+    (mut $this:ident, $ctx:ident => $($field:tt),* $(,)?) => {
+        $($crate::trace::Trace::trace_edges(&mut $this.$field, $ctx);)*
+    };
+    ($this:ident, $ctx:ident => $($field:tt),* $(,)?) => {
+        $($crate::trace::Trace::trace(&$this.$field, $ctx);)*
+    };
 }
 
 /// SAFETY: there is nothing to trace
 unsafe impl<T: ?Sized> Trace for PhantomData<T> {
-    fn trace(&self, _: &TraceContext<'_>) {}
+    const NEEDS_TRACE: bool = false;
+
+    fn trace(&self, _: &mut TraceContext<'_>) {}
 }
 
+/// Borrowed data, such as constant tables allocated ahead of time, may be referenced from GC objects. The referent is
+/// not managed by the GC, but any GC objects reachable through it are traced.
+///
+/// # Lifetime Hazards
+/// Tracing dereferences the borrow, which is only valid while the borrow is live. This is upheld by
+/// [`GcHeap::alloc`](crate::GcHeap::alloc), which requires every GC object to outlive the heap's lifetime bound, and by
+/// strategies, which may not trace objects after their heap has been destroyed. Code that invokes [`Trace::trace`]
+/// directly on values outside of a GC heap must ensure the same.
+///
+/// Borrows obtained from rooted GC objects are never long enough to satisfy the heap's lifetime bound, so a GC object
+/// cannot borrow from another GC object. Use [`Gc`] for that instead.
+///
 /// SAFETY: no threading or drop invariants for this type
 unsafe impl<T: Trace + ?Sized> Trace for &T {
-    fn trace(&self, ctx: &TraceContext<'_>) {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
         (**self).trace(ctx);
     }
 }
 
+/// The same lifetime hazards as for shared references apply.
+///
 /// SAFETY: no threading or drop invariants for this type
 unsafe impl<T: Trace + ?Sized> Trace for &mut T {
-    fn trace(&self, ctx: &TraceContext<'_>) {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
         (**self).trace(ctx);
     }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        (**self).trace_edges(ctx);
+    }
 }
 
 /// `Gc<T>` is itself `Trace`! It just forwards itself to the context.
-unsafe impl<T: ?Sized> Trace for Gc<T> {
-    fn trace(&self, ctx: &TraceContext<'_>) {
+unsafe impl<T: ?Sized + GcPointee> Trace for Gc<T> {
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
         ctx.accept(*self);
     }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        ctx.update(self);
+    }
 }
 
 /// SAFETY: arrays impose no additional requirements for accessing elements.
 unsafe impl<T: Trace, const N: usize> Trace for [T; N] {
-    fn trace(&self, ctx: &TraceContext<'_>) {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
         for elem in self {
             elem.trace(ctx);
         }
     }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        for elem in self {
+            elem.trace_edges(ctx);
+        }
+    }
 }
 
 /// SAFETY: slices impose no additional requirements for accessing elements.
 unsafe impl<T: Trace> Trace for [T] {
-    fn trace(&self, ctx: &TraceContext<'_>) {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
         for elem in self {
             elem.trace(ctx);
         }
     }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        for elem in self {
+            elem.trace_edges(ctx);
+        }
+    }
+}
+
+/// SAFETY: `Wrapping` is a transparent wrapper with no additional invariants.
+unsafe impl<T: Trace> Trace for core::num::Wrapping<T> {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        self.0.trace(ctx);
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        self.0.trace_edges(ctx);
+    }
+}
+
+/// SAFETY: `Saturating` is a transparent wrapper with no additional invariants.
+unsafe impl<T: Trace> Trace for core::num::Saturating<T> {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        self.0.trace(ctx);
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        self.0.trace_edges(ctx);
+    }
 }
 
 unsafe impl<T: Trace> Trace for Option<T> {
-    fn trace(&self, ctx: &TraceContext<'_>) {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
         if let Some(v) = self {
             v.trace(ctx);
         }
     }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        if let Some(v) = self {
+            v.trace_edges(ctx);
+        }
+    }
 }
 
 unsafe impl<T: Trace, E: Trace> Trace for Result<T, E> {
-    fn trace(&self, ctx: &TraceContext<'_>) {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE || E::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
         match self {
             Ok(t) => t.trace(ctx),
             Err(e) => e.trace(ctx),
         }
     }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        match self {
+            Ok(t) => t.trace_edges(ctx),
+            Err(e) => e.trace_edges(ctx),
+        }
+    }
+}
+
+/// SAFETY: ranges impose no additional requirements for accessing the endpoints.
+unsafe impl<T: Trace> Trace for core::ops::Range<T> {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        self.start.trace(ctx);
+        self.end.trace(ctx);
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        self.start.trace_edges(ctx);
+        self.end.trace_edges(ctx);
+    }
+}
+
+unsafe impl<T: Trace> Trace for core::ops::RangeInclusive<T> {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        self.start().trace(ctx);
+        self.end().trace(ctx);
+    }
+}
+
+unsafe impl<T: Trace> Trace for core::ops::RangeFrom<T> {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        self.start.trace(ctx);
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        self.start.trace_edges(ctx);
+    }
+}
+
+unsafe impl<T: Trace> Trace for core::ops::RangeTo<T> {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        self.end.trace(ctx);
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        self.end.trace_edges(ctx);
+    }
+}
+
+unsafe impl<T: Trace> Trace for core::ops::RangeToInclusive<T> {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        self.end.trace(ctx);
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        self.end.trace_edges(ctx);
+    }
+}
+
+unsafe_empty_trace! { core::ops::RangeFull }
+
+unsafe impl<T: Trace> Trace for core::ops::Bound<T> {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        match self {
+            core::ops::Bound::Included(v) | core::ops::Bound::Excluded(v) => v.trace(ctx),
+            core::ops::Bound::Unbounded => {}
+        }
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        match self {
+            core::ops::Bound::Included(v) | core::ops::Bound::Excluded(v) => v.trace_edges(ctx),
+            core::ops::Bound::Unbounded => {}
+        }
+    }
+}
+
+/// SAFETY: the value is copied out with [`Cell::get`](core::cell::Cell::get) and the copy is traced. `Cell` never
+/// hands out references to its contents, so no borrow of the contained value can be invalidated by tracing, and
+/// `T: Copy` guarantees that the copy has no drop glue to run.
+///
+/// # Concurrency
+/// Tracing never runs concurrently with writes to a `Cell`: `Cell` is not `Sync`, so only the thread owning the
+/// object can write to it, and strategies do not trace the object while that thread may be writing to it. See
+/// [Tracing and Mutation](crate::heap::Collector#tracing-and-mutation).
+///
+/// # Write Barrier
+/// [`Cell::set`](core::cell::Cell::set) bypasses the write barrier, so a `Gc` stored with it into an object that has
+/// already been traced, e.g. one promoted to an older generation, is missed by generational and incremental
+/// strategies, which may reclaim it while it is still reachable. Such a `Gc` must not be passed to
+/// [`GcHeap::root`](crate::GcHeap::root) once the heap may have collected. Store `Gc` values into a `Cell` field
+/// with [`Root::set_field`](crate::Root::set_field), which invokes the barrier, or use
+/// [`GcCell`](crate::cell::GcCell).
+///
+/// # Why not `RefCell`?
+/// The GC requires shared access to nested GC objects at all times, including while a `RefCell` has handed out a
+/// mutable borrow of its contents. Reading the contents then is only sound if the borrow does not hold on to a
+/// `&mut T` between its uses, which `RefMut` does not promise, and writes through a `RefMut` bypass any write
+/// barrier. Skipping the contents while they are mutably borrowed is not an option either, as any `Gc` values inside
+/// would not be marked. [`GcRefCell`](crate::cell::GcRefCell) makes these guarantees, so it is traced whatever its
+/// borrow state; see [Tracing Borrowed Data](crate::cell::GcRefCell#tracing-borrowed-data).
+unsafe impl<T: Copy + Trace> Trace for core::cell::Cell<T> {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        self.get().trace(ctx);
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        self.get_mut().trace_edges(ctx);
+    }
+}
+
+/// SAFETY: an initialized `OnceCell` is never written to again through a shared reference, so tracing the
+/// contained value only requires shared access.
+///
+/// # Concurrency
+/// Tracing never runs concurrently with the initialization of a `OnceCell`: the cell is not `Sync`, so only the
+/// owning thread initializes it, and strategies do not trace the object while that thread may be writing to it. See
+/// [Tracing and Mutation](crate::heap::Collector#tracing-and-mutation). Initialization bypasses any write barrier, so
+/// a `Gc` stored into the cell between the steps of an incremental collection may be missed.
+unsafe impl<T: Trace> Trace for core::cell::OnceCell<T> {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        if let Some(v) = self.get() {
+            v.trace(ctx);
+        }
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        if let Some(v) = self.get_mut() {
+            v.trace_edges(ctx);
+        }
+    }
+}
+
+/// SAFETY: the value is only traced once the cell has been forced. The initializer is never run by tracing.
+///
+/// # Concurrency
+/// Tracing never runs concurrently with forcing the cell, as for [`core::cell::OnceCell`].
+unsafe impl<T: Trace, F: FnOnce() -> T> Trace for core::cell::LazyCell<T, F> {
+    const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        if let Some(v) = core::cell::LazyCell::get(self) {
+            v.trace(ctx);
+        }
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        if let Some(v) = core::cell::LazyCell::get_mut(self) {
+            v.trace_edges(ctx);
+        }
+    }
 }
 
 macro_rules! tuple_trace {
     ($($ty:ident)*) => {
         // SAFETY: tuples have no additional invariants
         unsafe impl<$($ty: Trace),*> Trace for ($($ty),* ,) {
+            const NEEDS_TRACE: bool = $($ty::NEEDS_TRACE)||*;
+
             #[expect(non_snake_case)]
-            fn trace(&self, ctx: &TraceContext<'_>) {
+            fn trace(&self, ctx: &mut TraceContext<'_>) {
                 match self {
                     ($($ty),* ,) => {
                         $($ty.trace(ctx);)*
                     }
                 }
             }
+
+            #[expect(non_snake_case)]
+            fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+                match self {
+                    ($($ty),* ,) => {
+                        $($ty.trace_edges(ctx);)*
+                    }
+                }
+            }
         }
     };
 }
@@ -185,7 +690,9 @@ macro_rules! fn_trace {
     ($($arg:ident)* => $ret:ident) => {
         // SAFETY: function pointers store nothing
         unsafe impl<$ret: ?Sized, $($arg: ?Sized),*> Trace for fn($($arg),*) -> $ret {
-            fn trace(&self, _: &TraceContext<'_>) {}
+            const NEEDS_TRACE: bool = false;
+
+            fn trace(&self, _: &mut TraceContext<'_>) {}
         }
     };
 }
@@ -226,52 +733,452 @@ fns_trace! {
     (A B C D E F G H I J K L M N O P Q R S T U V W X Y Z => R1)
 }
 
+#[cfg(feature = "std")]
+mod std_impls {
+    use super::{EdgeContext, Trace, TraceContext};
+
+    /// SAFETY: `OnceLock::get` synchronizes with the initializing thread, so the traced value is always fully
+    /// initialized, and an initialized `OnceLock` is never written to again through a shared reference.
+    ///
+    /// # Concurrency
+    /// A cell that is initialized while the GC is tracing the containing object may or may not have its value traced.
+    /// Values stored after the object is reachable by the GC must also be reachable some other way (e.g. rooted)
+    /// until the next collection begins.
+    unsafe impl<T: Trace> Trace for std::sync::OnceLock<T> {
+        const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+        fn trace(&self, ctx: &mut TraceContext<'_>) {
+            if let Some(v) = self.get() {
+                v.trace(ctx);
+            }
+        }
+
+        fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+            if let Some(v) = self.get_mut() {
+                v.trace_edges(ctx);
+            }
+        }
+    }
+
+    /// SAFETY: the value is only traced once the lock has been forced. The initializer is never run by tracing.
+    ///
+    /// # Concurrency
+    /// The same caveats as for [`std::sync::OnceLock`] apply.
+    unsafe impl<T: Trace, F: FnOnce() -> T> Trace for std::sync::LazyLock<T, F> {
+        const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+        fn trace(&self, ctx: &mut TraceContext<'_>) {
+            if let Some(v) = std::sync::LazyLock::get(self) {
+                v.trace(ctx);
+            }
+        }
+
+        fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+            if let Some(v) = std::sync::LazyLock::get_mut(self) {
+                v.trace_edges(ctx);
+            }
+        }
+    }
+}
+
 #[cfg(feature = "alloc")]
 mod alloc_impls {
-    use super::{Trace, TraceContext};
+    use super::{EdgeContext, Trace, TraceContext};
 
     /// SAFETY: The referent's trace method is safe to call, and Box imposes no extra requirements.
     unsafe impl<T: Trace + ?Sized> Trace for alloc::boxed::Box<T> {
-        fn trace(&self, ctx: &TraceContext<'_>) {
+        const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+        fn trace(&self, ctx: &mut TraceContext<'_>) {
             (**self).trace(ctx);
         }
+
+        fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+            (**self).trace_edges(ctx);
+        }
     }
 
     /// SAFETY: We don't touch the reference counts and only invoke T's trace method.
     unsafe impl<T: Trace + ?Sized> Trace for alloc::rc::Rc<T> {
-        fn trace(&self, ctx: &TraceContext<'_>) {
+        const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+        fn trace(&self, ctx: &mut TraceContext<'_>) {
             (**self).trace(ctx);
         }
     }
 
     /// SAFETY: We only invoke T's trace method and do not touch any of Arc's state.
     unsafe impl<T: Trace + ?Sized> Trace for alloc::sync::Arc<T> {
-        fn trace(&self, ctx: &TraceContext<'_>) {
+        const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+        fn trace(&self, ctx: &mut TraceContext<'_>) {
             (**self).trace(ctx);
         }
     }
+
+    /// SAFETY: each form traces the value it holds, so an owned value is traced with its own `Trace` implementation
+    /// rather than through its `Borrow` view, which may not cover all of it.
+    unsafe impl<T> Trace for alloc::borrow::Cow<'_, T>
+    where
+        T: Trace + alloc::borrow::ToOwned + ?Sized,
+        T::Owned: Trace,
+    {
+        const NEEDS_TRACE: bool = T::NEEDS_TRACE || T::Owned::NEEDS_TRACE;
+
+        fn trace(&self, ctx: &mut TraceContext<'_>) {
+            match self {
+                alloc::borrow::Cow::Borrowed(borrowed) => borrowed.trace(ctx),
+                alloc::borrow::Cow::Owned(owned) => owned.trace(ctx),
+            }
+        }
+
+        fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+            match self {
+                alloc::borrow::Cow::Borrowed(borrowed) => borrowed.trace(ctx.fixed()),
+                alloc::borrow::Cow::Owned(owned) => owned.trace_edges(ctx),
+            }
+        }
+    }
+
     /// SAFETY: vec imposes no additional requirements for accessing elements.
     unsafe impl<T: Trace> Trace for alloc::vec::Vec<T> {
-        fn trace(&self, ctx: &TraceContext<'_>) {
+        const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+        fn trace(&self, ctx: &mut TraceContext<'_>) {
             for elem in self {
                 elem.trace(ctx);
             }
         }
+
+        fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+            for elem in self {
+                elem.trace_edges(ctx);
+            }
+        }
     }
 
     unsafe impl<T: Trace> Trace for alloc::collections::VecDeque<T> {
-        fn trace(&self, ctx: &TraceContext<'_>) {
+        const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+        fn trace(&self, ctx: &mut TraceContext<'_>) {
             for elem in self {
                 elem.trace(ctx);
             }
         }
+
+        fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+            for elem in self {
+                elem.trace_edges(ctx);
+            }
+        }
     }
 
     unsafe impl<T: Trace> Trace for alloc::collections::LinkedList<T> {
-        fn trace(&self, ctx: &TraceContext<'_>) {
+        const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+        fn trace(&self, ctx: &mut TraceContext<'_>) {
             for elem in self {
                 elem.trace(ctx);
             }
         }
+
+        fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+            for elem in self {
+                elem.trace_edges(ctx);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "nightly-simd")]
+mod simd_impls {
+    use super::{Trace, TraceContext};
+    use core::simd::{Mask, MaskElement, Simd, SimdElement};
+
+    /// SAFETY: SIMD vectors only contain primitive numbers and pointers, which have nothing to trace.
+    unsafe impl<T: SimdElement, const N: usize> Trace for Simd<T, N> {
+        const NEEDS_TRACE: bool = false;
+
+        fn trace(&self, _: &mut TraceContext<'_>) {}
+    }
+
+    /// SAFETY: there is nothing to trace
+    unsafe impl<T: MaskElement, const N: usize> Trace for Mask<T, N> {
+        const NEEDS_TRACE: bool = false;
+
+        fn trace(&self, _: &mut TraceContext<'_>) {}
+    }
+}
+
+#[cfg(feature = "smallvec")]
+mod smallvec_impls {
+    use super::{EdgeContext, Trace, TraceContext};
+
+    /// SAFETY: smallvec imposes no additional requirements for accessing elements.
+    unsafe impl<A: smallvec::Array> Trace for smallvec::SmallVec<A>
+    where
+        A::Item: Trace,
+    {
+        const NEEDS_TRACE: bool = A::Item::NEEDS_TRACE;
+
+        fn trace(&self, ctx: &mut TraceContext<'_>) {
+            for elem in self {
+                elem.trace(ctx);
+            }
+        }
+
+        fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+            for elem in self {
+                elem.trace_edges(ctx);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "arrayvec")]
+mod arrayvec_impls {
+    use super::{EdgeContext, Trace, TraceContext};
+
+    /// SAFETY: arrayvec imposes no additional requirements for accessing elements.
+    unsafe impl<T: Trace, const CAP: usize> Trace for arrayvec::ArrayVec<T, CAP> {
+        const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+        fn trace(&self, ctx: &mut TraceContext<'_>) {
+            for elem in self {
+                elem.trace(ctx);
+            }
+        }
+
+        fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+            for elem in self {
+                elem.trace_edges(ctx);
+            }
+        }
+    }
+
+    /// SAFETY: there is nothing to trace
+    unsafe impl<const CAP: usize> Trace for arrayvec::ArrayString<CAP> {
+        const NEEDS_TRACE: bool = false;
+
+        fn trace(&self, _: &mut TraceContext<'_>) {}
+    }
+}
+
+#[cfg(feature = "indexmap")]
+mod indexmap_impls {
+    use super::{EdgeContext, Trace, TraceContext};
+
+    /// SAFETY: indexmap imposes no additional requirements for accessing entries.
+    unsafe impl<K: Trace, V: Trace, S> Trace for indexmap::IndexMap<K, V, S> {
+        const NEEDS_TRACE: bool = K::NEEDS_TRACE || V::NEEDS_TRACE;
+
+        fn trace(&self, ctx: &mut TraceContext<'_>) {
+            for (k, v) in self {
+                k.trace(ctx);
+                v.trace(ctx);
+            }
+        }
+        /// Keys cannot be mutated in place, so edges reachable from keys are fixed.
+        fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+            for (k, v) in self {
+                k.trace(ctx.fixed());
+                v.trace_edges(ctx);
+            }
+        }
+    }
+
+    unsafe impl<T: Trace, S> Trace for indexmap::IndexSet<T, S> {
+        const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+        fn trace(&self, ctx: &mut TraceContext<'_>) {
+            for elem in self {
+                elem.trace(ctx);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "hashbrown")]
+mod hashbrown_impls {
+    use super::{EdgeContext, Trace, TraceContext};
+
+    /// SAFETY: hashbrown imposes no additional requirements for accessing entries.
+    unsafe impl<K: Trace, V: Trace, S> Trace for hashbrown::HashMap<K, V, S> {
+        const NEEDS_TRACE: bool = K::NEEDS_TRACE || V::NEEDS_TRACE;
+
+        fn trace(&self, ctx: &mut TraceContext<'_>) {
+            for (k, v) in self {
+                k.trace(ctx);
+                v.trace(ctx);
+            }
+        }
+        /// Keys cannot be mutated in place, so edges reachable from keys are fixed.
+        fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+            for (k, v) in self {
+                k.trace(ctx.fixed());
+                v.trace_edges(ctx);
+            }
+        }
+    }
+
+    unsafe impl<T: Trace, S> Trace for hashbrown::HashSet<T, S> {
+        const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+        fn trace(&self, ctx: &mut TraceContext<'_>) {
+            for elem in self {
+                elem.trace(ctx);
+            }
+        }
+    }
+
+    unsafe impl<T: Trace> Trace for hashbrown::HashTable<T> {
+        const NEEDS_TRACE: bool = T::NEEDS_TRACE;
+
+        fn trace(&self, ctx: &mut TraceContext<'_>) {
+            for elem in self {
+                elem.trace(ctx);
+            }
+        }
+
+        fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+            for elem in self {
+                elem.trace_edges(ctx);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "bytes")]
+unsafe_empty_trace! { bytes::Bytes bytes::BytesMut }
+
+#[cfg(feature = "uuid")]
+unsafe_empty_trace! { uuid::Uuid }
+
+#[cfg(feature = "chrono")]
+mod chrono_impls {
+    use super::{Trace, TraceContext};
+
+    unsafe_empty_trace! {
+        chrono::NaiveDate chrono::NaiveTime chrono::NaiveDateTime
+        chrono::Utc chrono::FixedOffset
+        chrono::TimeDelta chrono::Weekday chrono::Month
+    }
+
+    /// SAFETY: there is nothing to trace
+    unsafe impl<Tz: chrono::TimeZone> Trace for chrono::DateTime<Tz> {
+        const NEEDS_TRACE: bool = false;
+
+        fn trace(&self, _: &mut TraceContext<'_>) {}
+    }
+}
+
+#[cfg(feature = "serde_json")]
+unsafe_empty_trace! {
+    serde_json::Value serde_json::Number
+    serde_json::Map<alloc::string::String, serde_json::Value>
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{
+        num::{NonZeroU32, NonZeroUsize, Saturating, Wrapping},
+        time::Duration,
+    };
+
+    use super::*;
+
+    /// Counts the handles `value` visits when traced.
+    fn edges<T: ?Sized + Trace>(value: &T) -> usize {
+        let mut edges = 0;
+        value.trace(&mut TraceContext::new(&mut |_| edges += 1));
+        edges
+    }
+
+    #[test]
+    fn leaf_types_need_no_trace() {
+        fn check<T: Trace>(value: T) {
+            assert!(!T::NEEDS_TRACE);
+            assert_eq!(edges(&value), 0);
+        }
+
+        check(true);
+        check(usize::MAX);
+        check(isize::MIN);
+        check(NonZeroU32::MIN);
+        check(NonZeroUsize::MAX);
+        check(Wrapping(1u8));
+        check(Saturating(1i64));
+        check(Duration::from_secs(1));
+        check(core::cmp::Ordering::Less);
+        check(core::net::Ipv4Addr::LOCALHOST);
+        check(core::net::SocketAddr::from(([127, 0, 0, 1], 80)));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn numeric_wrappers_trace_their_contents() {
+        let heap = crate::GcHeap::new(crate::strategies::Leak::new());
+        let root = heap.alloc(1u32);
+        const { assert!(<Wrapping<Gc<u32>> as Trace>::NEEDS_TRACE) };
+        assert_eq!(edges(&Wrapping(root.gc())), 1);
+        const { assert!(<Saturating<Gc<u32>> as Trace>::NEEDS_TRACE) };
+        assert_eq!(edges(&Saturating(root.gc())), 1);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn cow_traces_the_owned_value() {
+        use alloc::borrow::{Borrow, Cow, ToOwned};
+
+        /// A view of [`Owned`] that leaves out its second handle.
+        struct View(Gc<u32>);
+
+        struct Owned(View, Gc<u32>);
+
+        impl Borrow<View> for Owned {
+            fn borrow(&self) -> &View {
+                &self.0
+            }
+        }
+
+        impl ToOwned for View {
+            type Owned = Owned;
+
+            fn to_owned(&self) -> Owned {
+                Owned(View(self.0), self.0)
+            }
+        }
+
+        // SAFETY: traces the handle
+        unsafe impl Trace for View {
+            fn trace(&self, ctx: &mut TraceContext<'_>) {
+                self.0.trace(ctx);
+            }
+        }
+
+        // SAFETY: traces both handles
+        unsafe impl Trace for Owned {
+            fn trace(&self, ctx: &mut TraceContext<'_>) {
+                self.0.trace(ctx);
+                self.1.trace(ctx);
+            }
+        }
+
+        let heap = crate::GcHeap::new(crate::strategies::Leak::new());
+        let view = View(heap.alloc(1u32).gc());
+        assert_eq!(edges(&Cow::Borrowed(&view)), 1);
+        assert_eq!(edges(&Cow::<View>::Owned(view.to_owned())), 2);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn cow_strings_need_no_tracing() {
+        use alloc::{borrow::Cow, string::String};
+
+        const { assert!(!<Cow<'static, str> as Trace>::NEEDS_TRACE) };
+        let heap = crate::GcHeap::new(crate::strategies::Leak::new());
+        let borrowed = heap.alloc(Cow::<'static, str>::Borrowed("borrowed"));
+        let owned = heap.alloc(Cow::<'static, str>::Owned(String::from("owned")));
+        assert_eq!((&**borrowed, &**owned), ("borrowed", "owned"));
     }
 }
@@ -0,0 +1,430 @@
+//! Weak references to GC objects.
+//!
+//! A weak reference does not keep its target alive. Weak references are implemented with weak cells, which are GC
+//! objects managed by the strategy (see [`Collector::allocate_weak`](crate::heap::Collector::allocate_weak)). A
+//! [`WeakGc`] is a handle to a weak cell, and is stored in GC objects in the same way as a [`Gc`]: the weak cell is
+//! kept alive by tracing the `WeakGc`, while the target is kept alive only by strong references.
+//!
+//! An [`Ephemeron`] generalizes a weak reference to a key-value pair, where the value is kept alive only for as long
+//! as the key is. Ephemerons are the building block of weak-key maps such as [`GcWeakMap`], which attach data to
+//! objects without keeping them alive.
+
+use core::{fmt, marker::PhantomData, sync::atomic::AtomicPtr};
+
+use crate::{
+    heap::{GcStrategy, Handle, StrategyHandle},
+    ptr::GcPointee,
+    trace::{EdgeContext, Trace, TraceContext},
+    Gc, GcHeap, Root,
+};
+
+/// A weak reference to a GC object. This is the weak counterpart of [`Gc`], and like [`Gc`] it is not rooted.
+#[repr(C)]
+pub struct WeakGc<T: ?Sized + GcPointee> {
+    /// Handle to the weak cell.
+    cell: Handle,
+    metadata: T::Metadata,
+    _ph: PhantomData<fn() -> T>,
+}
+
+impl<T: ?Sized + GcPointee> WeakGc<T> {
+    /// Returns a root to the target of this weak reference, if it is still alive.
+    ///
+    /// # Safety
+    /// This weak reference must refer to a live weak cell allocated by `heap`, for example because it was read from
+    /// a rooted object.
+    pub unsafe fn upgrade<'heap, S: ?Sized + GcStrategy>(
+        self,
+        heap: &'heap GcHeap<'_, S>,
+    ) -> Option<Root<'heap, S, T>> {
+        // SAFETY: caller
+        upgrade(
+            self,
+            unsafe { heap.strategy.upgrade_weak(S::Handle::from_raw(self.cell)) },
+            &heap.strategy,
+        )
+    }
+
+    /// Returns whether two weak references use the same weak cell.
+    pub fn ptr_eq(this: Self, other: Self) -> bool {
+        this.cell == other.cell
+    }
+}
+
+/// Creates a root for a handle that has already been rooted with the strategy.
+fn adopt_root<S: ?Sized + GcStrategy, T: ?Sized + GcPointee>(
+    gc: &S,
+    handle: S::Handle,
+    metadata: T::Metadata,
+) -> Root<'_, S, T> {
+    Root {
+        handle: Gc {
+            handle: handle.into_raw(),
+            metadata,
+            _ph: PhantomData,
+        },
+        gc,
+        pinned: AtomicPtr::new(core::ptr::null_mut()),
+    }
+}
+
+fn upgrade<S: ?Sized + GcStrategy, T: ?Sized + GcPointee>(
+    weak: WeakGc<T>,
+    target: Option<S::Handle>,
+    gc: &S,
+) -> Option<Root<'_, S, T>> {
+    target.map(|handle| adopt_root(gc, handle, weak.metadata))
+}
+
+impl<T: ?Sized + GcPointee> Clone for WeakGc<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized + GcPointee> Copy for WeakGc<T> {}
+
+// Safety: WeakGc<T> can be upgraded to a shared reference, the same as Gc<T>
+unsafe impl<T: ?Sized + GcPointee + Sync> Send for WeakGc<T> {}
+unsafe impl<T: ?Sized + GcPointee + Sync> Sync for WeakGc<T> {}
+
+impl<T: ?Sized + GcPointee> fmt::Debug for WeakGc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "WeakGc<{}>({:?})",
+            core::any::type_name::<T>(),
+            self.cell
+        )
+    }
+}
+
+/// SAFETY: only the weak cell is visited, as the target must not be kept alive.
+unsafe impl<T: ?Sized + GcPointee> Trace for WeakGc<T> {
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        ctx.accept_handle(self.cell);
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        ctx.update_handle(&mut self.cell);
+    }
+}
+
+/// A rooted weak reference, created by [`Root::downgrade`]. The weak cell is kept alive for as long as the
+/// `WeakRoot` exists, but the target is not.
+pub struct WeakRoot<'root, S: ?Sized + GcStrategy, T: ?Sized + GcPointee> {
+    weak: WeakGc<T>,
+    gc: &'root S,
+}
+
+impl<'root, S: ?Sized + GcStrategy, T: ?Sized + GcPointee> WeakRoot<'root, S, T> {
+    /// Returns a root to the target, if it is still alive.
+    pub fn upgrade(&self) -> Option<Root<'root, S, T>> {
+        // SAFETY: the weak cell is rooted
+        upgrade(
+            self.weak,
+            unsafe { self.gc.upgrade_weak(S::Handle::from_raw(self.weak.cell)) },
+            self.gc,
+        )
+    }
+
+    /// The unrooted weak reference, e.g. to store in a GC object.
+    pub fn weak(&self) -> WeakGc<T> {
+        self.weak
+    }
+}
+
+impl<'root, S: ?Sized + GcStrategy, T: ?Sized + GcPointee> Root<'root, S, T> {
+    /// Creates a weak reference to the rooted object.
+    ///
+    /// # Panics
+    /// Panics if the strategy does not have enough heap space for the weak cell.
+    pub fn downgrade(&self) -> WeakRoot<'root, S, T> {
+        let cell = self
+            .gc
+            .allocate_weak(self.handle.strategy_handle())
+            .expect("out of memory");
+        WeakRoot {
+            weak: WeakGc {
+                cell: cell.into_raw(),
+                metadata: self.handle.metadata,
+                _ph: PhantomData,
+            },
+            gc: self.gc,
+        }
+    }
+}
+
+impl<S: ?Sized + GcStrategy, T: ?Sized + GcPointee> Clone for WeakRoot<'_, S, T> {
+    fn clone(&self) -> Self {
+        self.gc.root(S::Handle::from_raw(self.weak.cell));
+        Self {
+            weak: self.weak,
+            gc: self.gc,
+        }
+    }
+}
+
+impl<S: ?Sized + GcStrategy, T: ?Sized + GcPointee> fmt::Debug for WeakRoot<'_, S, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("WeakRoot").field(&self.weak).finish()
+    }
+}
+
+/// Unroots the weak cell when going out of scope.
+impl<S: ?Sized + GcStrategy, T: ?Sized + GcPointee> Drop for WeakRoot<'_, S, T> {
+    fn drop(&mut self) {
+        self.gc.unroot(S::Handle::from_raw(self.weak.cell));
+    }
+}
+
+/// A weak-key pair of GC objects: the value is kept alive only while the key is alive, and both are cleared once the
+/// key is no longer reachable. See [`Collector::allocate_ephemeron`](crate::heap::Collector::allocate_ephemeron).
+///
+/// Like [`WeakGc`], an `Ephemeron` is a handle to a cell, and is not rooted.
+pub struct Ephemeron<K: ?Sized + GcPointee, V: ?Sized + GcPointee> {
+    /// Handle to the ephemeron cell.
+    cell: Handle,
+    key_metadata: K::Metadata,
+    value_metadata: V::Metadata,
+    _key: PhantomData<fn() -> K>,
+    _value: PhantomData<fn() -> V>,
+}
+
+impl<K: ?Sized + GcPointee, V: ?Sized + GcPointee> Ephemeron<K, V> {
+    /// Returns roots to the key and value, if the key is still alive.
+    ///
+    /// # Safety
+    /// This ephemeron must refer to a live ephemeron cell allocated by `heap`, for example because it was read from
+    /// a rooted object.
+    pub unsafe fn upgrade<'heap, S: ?Sized + GcStrategy>(
+        self,
+        heap: &'heap GcHeap<'_, S>,
+    ) -> Option<(Root<'heap, S, K>, Root<'heap, S, V>)> {
+        // SAFETY: caller
+        upgrade_ephemeron(self, &heap.strategy)
+    }
+}
+
+/// # Safety
+/// The ephemeron must refer to a live ephemeron cell of the strategy.
+unsafe fn upgrade_ephemeron<S, K, V>(
+    ephemeron: Ephemeron<K, V>,
+    gc: &S,
+) -> Option<(Root<'_, S, K>, Root<'_, S, V>)>
+where
+    S: ?Sized + GcStrategy,
+    K: ?Sized + GcPointee,
+    V: ?Sized + GcPointee,
+{
+    // SAFETY: caller
+    let (key, value) = unsafe { gc.upgrade_ephemeron(S::Handle::from_raw(ephemeron.cell)) }?;
+    Some((
+        adopt_root(gc, key, ephemeron.key_metadata),
+        adopt_root(gc, value, ephemeron.value_metadata),
+    ))
+}
+
+impl<K: ?Sized + GcPointee, V: ?Sized + GcPointee> Clone for Ephemeron<K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K: ?Sized + GcPointee, V: ?Sized + GcPointee> Copy for Ephemeron<K, V> {}
+
+impl<K: ?Sized + GcPointee, V: ?Sized + GcPointee> fmt::Debug for Ephemeron<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Ephemeron<{}, {}>({:?})",
+            core::any::type_name::<K>(),
+            core::any::type_name::<V>(),
+            self.cell
+        )
+    }
+}
+
+// Safety: Ephemeron<K, V> can be upgraded to shared references to K and V, the same as Gc<K> and Gc<V>
+unsafe impl<K: ?Sized + GcPointee + Sync, V: ?Sized + GcPointee + Sync> Send for Ephemeron<K, V> {}
+unsafe impl<K: ?Sized + GcPointee + Sync, V: ?Sized + GcPointee + Sync> Sync for Ephemeron<K, V> {}
+
+/// SAFETY: only the ephemeron cell is visited, as the strategy decides when to keep the key and value alive.
+unsafe impl<K: ?Sized + GcPointee, V: ?Sized + GcPointee> Trace for Ephemeron<K, V> {
+    fn trace(&self, ctx: &mut TraceContext<'_>) {
+        ctx.accept_handle(self.cell);
+    }
+
+    fn trace_edges(&mut self, ctx: &mut EdgeContext<'_>) {
+        ctx.update_handle(&mut self.cell);
+    }
+}
+
+/// A rooted ephemeron. The ephemeron cell is kept alive for as long as the
+/// `EphemeronRoot` exists.
+pub struct EphemeronRoot<
+    'root,
+    S: ?Sized + GcStrategy,
+    K: ?Sized + GcPointee,
+    V: ?Sized + GcPointee,
+> {
+    ephemeron: Ephemeron<K, V>,
+    gc: &'root S,
+}
+
+impl<'root, S, K, V> EphemeronRoot<'root, S, K, V>
+where
+    S: ?Sized + GcStrategy,
+    K: ?Sized + GcPointee,
+    V: ?Sized + GcPointee,
+{
+    /// Creates an ephemeron cell for the given key and value. The returned root keeps the cell alive.
+    ///
+    /// # Panics
+    /// Panics if `key` and `value` belong to different heaps, or if the strategy does not have enough heap space for
+    /// the cell.
+    pub fn new(key: &Root<'root, S, K>, value: &Root<'root, S, V>) -> Self {
+        assert!(
+            core::ptr::eq(key.gc, value.gc),
+            "key and value belong to different heaps"
+        );
+        let cell = key
+            .gc
+            .allocate_ephemeron(key.handle.strategy_handle(), value.handle.strategy_handle())
+            .expect("out of memory");
+        EphemeronRoot {
+            ephemeron: Ephemeron {
+                cell: cell.into_raw(),
+                key_metadata: key.handle.metadata,
+                value_metadata: value.handle.metadata,
+                _key: PhantomData,
+                _value: PhantomData,
+            },
+            gc: key.gc,
+        }
+    }
+
+    /// Returns roots to the key and value, if the key is still alive.
+    pub fn upgrade(&self) -> Option<(Root<'root, S, K>, Root<'root, S, V>)> {
+        // SAFETY: the ephemeron cell is rooted
+        unsafe { upgrade_ephemeron(self.ephemeron, self.gc) }
+    }
+
+    /// The unrooted ephemeron, e.g. to store in a GC object.
+    pub fn ephemeron(&self) -> Ephemeron<K, V> {
+        self.ephemeron
+    }
+}
+
+impl<S, K, V> fmt::Debug for EphemeronRoot<'_, S, K, V>
+where
+    S: ?Sized + GcStrategy,
+    K: ?Sized + GcPointee,
+    V: ?Sized + GcPointee,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("EphemeronRoot")
+            .field(&self.ephemeron)
+            .finish()
+    }
+}
+
+/// Unroots the ephemeron cell when going out of scope.
+impl<S, K, V> Drop for EphemeronRoot<'_, S, K, V>
+where
+    S: ?Sized + GcStrategy,
+    K: ?Sized + GcPointee,
+    V: ?Sized + GcPointee,
+{
+    fn drop(&mut self) {
+        self.gc.unroot(S::Handle::from_raw(self.ephemeron.cell));
+    }
+}
+
+/// A map from GC objects to GC objects that does not keep its keys alive. Keys are compared by identity, and the
+/// values are kept alive only by reachable keys.
+///
+/// Each entry is an [`Ephemeron`], so the map needs a strategy that supports ephemerons, such as
+/// [`MarkSweep`](crate::strategies::MarkSweep). Entries whose keys have died still occupy space until they are looked
+/// up or [`GcWeakMap::purge`] is called.
+#[cfg(feature = "alloc")]
+pub struct GcWeakMap<'heap, S: ?Sized + GcStrategy, K: ?Sized + GcPointee, V: ?Sized + GcPointee> {
+    /// Ephemerons keyed by the handle of their key. Handles are stable, so they remain valid keys even if the
+    /// objects are moved.
+    entries: alloc::collections::BTreeMap<Handle, EphemeronRoot<'heap, S, K, V>>,
+    gc: &'heap S,
+}
+
+#[cfg(feature = "alloc")]
+impl<'heap, S, K, V> GcWeakMap<'heap, S, K, V>
+where
+    S: ?Sized + GcStrategy,
+    K: ?Sized + GcPointee,
+    V: ?Sized + GcPointee,
+{
+    pub fn new(heap: &'heap GcHeap<'_, S>) -> Self {
+        Self {
+            entries: alloc::collections::BTreeMap::new(),
+            gc: &heap.strategy,
+        }
+    }
+
+    /// Associates `value` with `key`, replacing any previous value.
+    ///
+    /// # Panics
+    /// Panics if `key` or `value` does not belong to the heap of this map, or if the strategy does not support
+    /// ephemerons.
+    pub fn insert(&mut self, key: &Root<'heap, S, K>, value: &Root<'heap, S, V>) {
+        assert!(
+            core::ptr::eq(key.gc, self.gc),
+            "key does not belong to this heap"
+        );
+        self.entries
+            .insert(key.handle.handle, EphemeronRoot::new(key, value));
+    }
+
+    /// Returns the value associated with `key`.
+    pub fn get(&mut self, key: &Root<'heap, S, K>) -> Option<Root<'heap, S, V>> {
+        let handle = key.handle.handle;
+        match self.entries.get(&handle)?.upgrade() {
+            Some((_, value)) => Some(value),
+            None => {
+                // the entry is for a dead object whose handle was reused
+                self.entries.remove(&handle);
+                None
+            }
+        }
+    }
+
+    /// Removes the value associated with `key`, returning it.
+    pub fn remove(&mut self, key: &Root<'heap, S, K>) -> Option<Root<'heap, S, V>> {
+        let (_, value) = self.entries.remove(&key.handle.handle)?.upgrade()?;
+        Some(value)
+    }
+
+    /// Removes all entries whose keys have died.
+    pub fn purge(&mut self) {
+        self.entries.retain(|_, entry| entry.upgrade().is_some());
+    }
+
+    /// The number of entries in the map, including entries whose keys have died but have not yet been purged.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S, K, V> fmt::Debug for GcWeakMap<'_, S, K, V>
+where
+    S: ?Sized + GcStrategy,
+    K: ?Sized + GcPointee,
+    V: ?Sized + GcPointee,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.entries.values()).finish()
+    }
+}